@@ -0,0 +1,38 @@
+#[cfg(unix)]
+extern crate wayland_scanner;
+
+use std::env;
+
+// Generates the bindings to the wayland protocols that wayland-protocols doesn't provide yet.
+fn main() {
+    // only the unix targets have a wayland backend
+    if env::var("CARGO_CFG_UNIX").is_err() {
+        return;
+    }
+    generate_protocols();
+}
+
+#[cfg(unix)]
+fn generate_protocols() {
+    use std::path::Path;
+    use wayland_scanner::{Side, generate_code, generate_interfaces};
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let out_dir = Path::new(&out_dir);
+
+    let protocols = [
+        ("text_input_v3", "wayland-protocols/text-input-unstable-v3.xml"),
+        ("primary_selection_v1", "wayland-protocols/primary-selection-unstable-v1.xml"),
+    ];
+    for &(name, file) in protocols.iter() {
+        println!("cargo:rerun-if-changed={}", file);
+        generate_interfaces(file, out_dir.join(format!("{}_interfaces.rs", name)));
+        generate_code(file, out_dir.join(format!("{}_client_api.rs", name)), Side::Client);
+    }
+}
+
+// wayland-scanner is only a build dependency on unix hosts
+#[cfg(not(unix))]
+fn generate_protocols() {
+    panic!("the wayland protocols can only be generated on a unix host");
+}
@@ -0,0 +1,37 @@
+extern crate winit;
+
+use winit::{DragAction, DragData, ElementState, MouseButton, MouseInput, WindowEvent};
+
+fn main() {
+    let events_loop = winit::EventsLoop::new();
+
+    let window = winit::WindowBuilder::new()
+        .with_title("Press the left button to drag some text, drop files to print them")
+        .build(&events_loop)
+        .unwrap();
+
+    events_loop.run_forever(|event| {
+        match event {
+            winit::Event::WindowEvent { event, .. } => {
+                match event {
+                    // the drag must be started while the button is pressed
+                    WindowEvent::MouseInput(MouseInput { state: ElementState::Pressed,
+                                                         button: MouseButton::Left, .. }) => {
+                        let data = DragData::from_text("Dragged out of a winit window");
+                        if let Err(err) = window.start_drag(data, &[DragAction::Copy, DragAction::Move]) {
+                            println!("Failed to start the drag: {}", err);
+                        }
+                    },
+
+                    WindowEvent::DragFinished(result) => println!("Drag finished: {:?}", result),
+
+                    WindowEvent::DroppedFile(path) => println!("Dropped file: {}", path.display()),
+
+                    WindowEvent::Closed => events_loop.interrupt(),
+
+                    _ => (),
+                }
+            },
+        }
+    });
+}
@@ -24,10 +24,48 @@ macro_rules! gen_api_transition {
                 self.interrupted.store(true, ::std::sync::atomic::Ordering::Relaxed);
             }
 
+            pub fn key_repeat_info(&self) -> Option<::KeyRepeatInfo> {
+                key_repeat_info()
+            }
+
+            pub fn keyboard_layouts(&self) -> Option<::KeyboardLayouts> {
+                None
+            }
+
+            // the list of windows is not locked while the window is used, as the callback of
+            // the events loop can use the clipboard
+            fn first_window(&self) -> Option<::std::sync::Arc<Window>> {
+                self.windows.lock().unwrap().first().cloned()
+            }
+
+            pub fn set_clipboard(&self, kind: ::ClipboardKind, contents: ::ClipboardContents)
+                                 -> Result<(), String>
+            {
+                match self.first_window() {
+                    Some(window) => window.set_clipboard(kind, contents),
+                    None => Err("The clipboard can't be used before a window is created.".to_string()),
+                }
+            }
+
+            pub fn clipboard_mime_types(&self, kind: ::ClipboardKind) -> Vec<String> {
+                match self.first_window() {
+                    Some(window) => window.clipboard_mime_types(kind),
+                    None => Vec::new(),
+                }
+            }
+
+            pub fn get_clipboard(&self, kind: ::ClipboardKind, mime_type: &str) -> Option<Vec<u8>> {
+                match self.first_window() {
+                    Some(window) => window.get_clipboard(kind, mime_type),
+                    None => None,
+                }
+            }
+
             pub fn poll_events<F>(&self, mut callback: F)
                 where F: FnMut(::Event)
             {
-                let mut windows = self.windows.lock().unwrap();
+                // the callback may use the events loop, which needs the list of windows
+                let windows = self.windows.lock().unwrap().clone();
                 for window in windows.iter() {
                     for event in window.poll_events() {
                         callback(::Event::WindowEvent {
@@ -0,0 +1,73 @@
+/// The MIME type of UTF-8 text.
+pub const TEXT_UTF8_MIME_TYPE: &'static str = "text/plain;charset=utf-8";
+
+/// The MIME type of text whose encoding isn't specified. Most applications use UTF-8.
+pub const TEXT_MIME_TYPE: &'static str = "text/plain";
+
+/// One of the clipboards of the system.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ClipboardKind {
+    /// The clipboard used by copy and paste.
+    Clipboard,
+
+    /// The primary selection, which holds the text that was selected last and is usually
+    /// pasted with a middle click. Only exists on X11 and Wayland.
+    Primary,
+}
+
+/// Contents to put in a clipboard.
+///
+/// The same contents can be provided in several formats, identified by their MIME type, like
+/// `text/plain;charset=utf-8`, `text/html` or `image/png`, so that the application that pastes
+/// them can pick the one it understands best.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClipboardContents {
+    formats: Vec<(String, Vec<u8>)>,
+}
+
+impl ClipboardContents {
+    /// Builds empty contents.
+    #[inline]
+    pub fn new() -> ClipboardContents {
+        ClipboardContents { formats: Vec::new() }
+    }
+
+    /// Builds contents holding some text, as `text/plain;charset=utf-8` and `text/plain`.
+    pub fn from_text(text: &str) -> ClipboardContents {
+        ClipboardContents::new()
+            .with_format(TEXT_UTF8_MIME_TYPE, text.as_bytes().to_vec())
+            .with_format(TEXT_MIME_TYPE, text.as_bytes().to_vec())
+    }
+
+    /// Adds a format to the contents. If there is already a format with the same MIME type,
+    /// its data is replaced.
+    pub fn add_format(&mut self, mime_type: &str, data: Vec<u8>) {
+        match self.formats.iter().position(|&(ref mime, _)| mime == mime_type) {
+            Some(index) => self.formats[index].1 = data,
+            None => self.formats.push((mime_type.to_owned(), data)),
+        }
+    }
+
+    /// Same as `add_format`, but consumes and returns the contents.
+    #[inline]
+    pub fn with_format(mut self, mime_type: &str, data: Vec<u8>) -> ClipboardContents {
+        self.add_format(mime_type, data);
+        self
+    }
+
+    /// Returns the MIME types of the formats, in the order they were added.
+    pub fn mime_types(&self) -> Vec<&str> {
+        self.formats.iter().map(|&(ref mime, _)| &mime[..]).collect()
+    }
+
+    /// Returns the data of a format.
+    pub fn get(&self, mime_type: &str) -> Option<&[u8]> {
+        self.formats.iter().find(|&&(ref mime, _)| mime == mime_type).map(|&(_, ref data)| &data[..])
+    }
+
+    /// Returns whether the contents have no format.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.formats.is_empty()
+    }
+}
@@ -0,0 +1,314 @@
+use std::slice;
+#[cfg(feature = "icon_loading")]
+use std::fs::File;
+#[cfg(feature = "icon_loading")]
+use std::io::Read;
+#[cfg(feature = "icon_loading")]
+use std::path::Path;
+
+use {BadIcon, IconImage};
+#[cfg(feature = "icon_loading")]
+use image_loading::{self, DecodedImage, Format, LoadIconError};
+
+/// A cursor made of custom images, as opposed to one of the system cursors of `MouseCursor`.
+///
+/// A cursor can hold several images of different sizes, so that the platform can pick the one
+/// that fits best the cursor size of the user. Each size can be animated, with several frames
+/// displayed one after the other in a loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomCursor {
+    // the frames of every size, sorted by increasing size of their first frame
+    sizes: Vec<Vec<CursorImage>>,
+}
+
+/// One of the images of a `CustomCursor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorImage {
+    image: IconImage,
+    hotspot: (u32, u32),
+    delay: u32,
+}
+
+impl CustomCursor {
+    /// Builds a cursor from a single image.
+    ///
+    /// The buffer must contain `width * height` pixels in RGBA order, row by row starting from
+    /// the top-left corner, with one byte per channel. The hotspot is the pixel of the image
+    /// that points at the position of the mouse.
+    ///
+    /// `delay` is how long the image stays displayed once frames are added after it with
+    /// `add_frame`, in milliseconds. It is ignored if the cursor is not animated.
+    #[inline]
+    pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32, hotspot_x: u32, hotspot_y: u32,
+                     delay: u32) -> Result<CustomCursor, BadIcon>
+    {
+        let image = try!(CursorImage::new(rgba, width, height, (hotspot_x, hotspot_y), delay));
+        Ok(CustomCursor { sizes: vec![vec![image]] })
+    }
+
+    /// Loads a cursor from an XCursor file, as found in cursor themes, or from a CUR file. Both
+    /// formats can contain several sizes.
+    ///
+    /// Only available with the `icon_loading` feature.
+    #[cfg(feature = "icon_loading")]
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<CustomCursor, LoadIconError> {
+        let mut bytes = Vec::new();
+        try!(try!(File::open(path)).read_to_end(&mut bytes));
+        match image_loading::guess_format(&bytes) {
+            Some(Format::Xcursor) => CustomCursor::from_xcursor_bytes(&bytes),
+            Some(Format::Ico) => CustomCursor::from_cur_bytes(&bytes),
+            _ => Err(LoadIconError::InvalidData("not an XCursor or CUR file".to_owned())),
+        }
+    }
+
+    /// Decodes a cursor from the content of an XCursor file, with every size it contains and
+    /// their animations.
+    ///
+    /// Only available with the `icon_loading` feature.
+    #[cfg(feature = "icon_loading")]
+    pub fn from_xcursor_bytes(bytes: &[u8]) -> Result<CustomCursor, LoadIconError> {
+        let images = try!(image_loading::decode_xcursor(bytes));
+
+        // the frames of a size are not necessarily contiguous in the file
+        let mut sizes: Vec<(u32, Vec<DecodedImage>)> = Vec::new();
+        for (nominal_size, image) in images {
+            match sizes.iter().position(|&(size, _)| size == nominal_size) {
+                Some(index) => sizes[index].1.push(image),
+                None => sizes.push((nominal_size, vec![image])),
+            }
+        }
+
+        let mut cursor: Option<CustomCursor> = None;
+        for (_, frames) in sizes {
+            let mut frames = frames.into_iter();
+            let first = frames.next().unwrap();
+            match cursor {
+                Some(ref mut cursor) => try!(cursor.add_rgba(first.rgba, first.width, first.height,
+                                                             first.hotspot.0, first.hotspot.1,
+                                                             first.delay)),
+                None => cursor = Some(try!(CustomCursor::from_rgba(first.rgba, first.width, first.height,
+                                                                   first.hotspot.0, first.hotspot.1,
+                                                                   first.delay))),
+            }
+            let cursor = cursor.as_mut().unwrap();
+            for frame in frames {
+                try!(cursor.add_frame(frame.rgba, frame.width, frame.height,
+                                      frame.hotspot.0, frame.hotspot.1, frame.delay));
+            }
+        }
+        Ok(cursor.unwrap())
+    }
+
+    /// Decodes a cursor from the content of a CUR file, with every size it contains.
+    ///
+    /// Only available with the `icon_loading` feature.
+    #[cfg(feature = "icon_loading")]
+    pub fn from_cur_bytes(bytes: &[u8]) -> Result<CustomCursor, LoadIconError> {
+        let images = try!(image_loading::decode_ico(bytes));
+        CustomCursor::from_decoded(images.into_iter())
+    }
+
+    #[cfg(feature = "icon_loading")]
+    fn from_decoded<I>(mut images: I) -> Result<CustomCursor, LoadIconError>
+        where I: Iterator<Item = DecodedImage>
+    {
+        let first = images.next().unwrap();
+        let mut cursor = try!(CustomCursor::from_rgba(first.rgba, first.width, first.height,
+                                                      first.hotspot.0, first.hotspot.1, first.delay));
+        for image in images {
+            try!(cursor.add_rgba(image.rgba, image.width, image.height,
+                                 image.hotspot.0, image.hotspot.1, image.delay));
+        }
+        Ok(cursor)
+    }
+
+    /// Adds another size to the cursor. See `from_rgba` for the format of the buffer and the
+    /// delay.
+    ///
+    /// If the cursor already has an image with the same dimensions, it is replaced along with
+    /// its animation.
+    pub fn add_rgba(&mut self, rgba: Vec<u8>, width: u32, height: u32, hotspot_x: u32, hotspot_y: u32,
+                    delay: u32) -> Result<(), BadIcon>
+    {
+        let image = try!(CursorImage::new(rgba, width, height, (hotspot_x, hotspot_y), delay));
+        match self.sizes.binary_search_by_key(&image.sort_key(), |frames| frames[0].sort_key()) {
+            Ok(index) => self.sizes[index] = vec![image],
+            Err(index) => self.sizes.insert(index, vec![image]),
+        }
+        Ok(())
+    }
+
+    /// Appends a frame to the animation of the image with the same dimensions, or adds a new
+    /// size if there is none. See `from_rgba` for the format of the buffer.
+    ///
+    /// `delay` is how long the frame stays displayed, in milliseconds. The animation loops
+    /// forever.
+    pub fn add_frame(&mut self, rgba: Vec<u8>, width: u32, height: u32, hotspot_x: u32, hotspot_y: u32,
+                     delay: u32) -> Result<(), BadIcon>
+    {
+        let image = try!(CursorImage::new(rgba, width, height, (hotspot_x, hotspot_y), delay));
+        match self.sizes.binary_search_by_key(&image.sort_key(), |frames| frames[0].sort_key()) {
+            Ok(index) => self.sizes[index].push(image),
+            Err(index) => self.sizes.insert(index, vec![image]),
+        }
+        Ok(())
+    }
+
+    /// Returns the images of the cursor, sorted by increasing size. Only the first frame of
+    /// the animated sizes is included, see `frames_for_size` for the others.
+    #[inline]
+    pub fn images<'a>(&'a self) -> CursorImages<'a> {
+        CursorImages { sizes: self.sizes.iter() }
+    }
+
+    /// Returns whether the cursor has at least one animated size.
+    #[inline]
+    pub fn is_animated(&self) -> bool {
+        self.sizes.iter().any(|frames| frames.len() > 1)
+    }
+
+    /// Returns the frames of the size that fits best a cursor of the given size. There is only
+    /// one frame if that size is not animated.
+    ///
+    /// This is the smallest size that is at least as large, or the largest size if none is.
+    /// The size is in physical pixels, so sizes in points must be multiplied by
+    /// `Window::hidpi_factor()` first.
+    pub fn frames_for_size(&self, size: u32) -> &[CursorImage] {
+        self.sizes.iter()
+            .find(|frames| frames[0].image.width() >= size && frames[0].image.height() >= size)
+            .unwrap_or_else(|| self.sizes.last().unwrap())
+    }
+
+    /// Returns the first frame of the size that fits best a cursor of the given size. See
+    /// `frames_for_size`.
+    #[inline]
+    pub fn image_for_size(&self, size: u32) -> &CursorImage {
+        &self.frames_for_size(size)[0]
+    }
+}
+
+/// Iterator over the images of a `CustomCursor`, returned by `CustomCursor::images`.
+#[derive(Debug, Clone)]
+pub struct CursorImages<'a> {
+    sizes: slice::Iter<'a, Vec<CursorImage>>,
+}
+
+impl<'a> Iterator for CursorImages<'a> {
+    type Item = &'a CursorImage;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a CursorImage> {
+        self.sizes.next().map(|frames| &frames[0])
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.sizes.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for CursorImages<'a> {}
+
+impl<'a> DoubleEndedIterator for CursorImages<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a CursorImage> {
+        self.sizes.next_back().map(|frames| &frames[0])
+    }
+}
+
+impl CursorImage {
+    fn new(rgba: Vec<u8>, width: u32, height: u32, hotspot: (u32, u32), delay: u32)
+           -> Result<CursorImage, BadIcon>
+    {
+        let image = try!(IconImage::from_rgba(rgba, width, height));
+        if hotspot.0 >= width || hotspot.1 >= height {
+            return Err(BadIcon::HotspotOutOfBounds {
+                width: width,
+                height: height,
+                hotspot: hotspot,
+            });
+        }
+
+        Ok(CursorImage {
+            image: image,
+            hotspot: hotspot,
+            delay: delay,
+        })
+    }
+
+    #[inline]
+    fn sort_key(&self) -> (u64, u32) {
+        (self.image.width() as u64 * self.image.height() as u64, self.image.width())
+    }
+
+    /// Returns the pixels of the cursor.
+    #[inline]
+    pub fn image(&self) -> &IconImage {
+        &self.image
+    }
+
+    /// Returns the position of the hotspot in the image.
+    #[inline]
+    pub fn hotspot(&self) -> (u32, u32) {
+        self.hotspot
+    }
+
+    /// Returns how long the frame stays displayed in an animation, in milliseconds.
+    #[inline]
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use BadIcon;
+    use super::CustomCursor;
+
+    fn image(size: u32) -> Vec<u8> {
+        vec![0xff; size as usize * size as usize * 4]
+    }
+
+    #[test]
+    fn rejects_hotspot_out_of_bounds() {
+        assert_eq!(CustomCursor::from_rgba(image(16), 16, 16, 16, 0, 0),
+                   Err(BadIcon::HotspotOutOfBounds { width: 16, height: 16, hotspot: (16, 0) }));
+        let mut cursor = CustomCursor::from_rgba(image(16), 16, 16, 15, 15, 0).unwrap();
+        assert!(cursor.add_rgba(image(32), 32, 32, 0, 32, 0).is_err());
+        assert!(cursor.add_frame(image(16), 16, 16, 0, 16, 10).is_err());
+    }
+
+    #[test]
+    fn sorts_sizes() {
+        let mut cursor = CustomCursor::from_rgba(image(32), 32, 32, 0, 0, 0).unwrap();
+        cursor.add_rgba(image(16), 16, 16, 0, 0, 0).unwrap();
+        cursor.add_rgba(image(48), 48, 48, 0, 0, 0).unwrap();
+        cursor.add_rgba(image(16), 16, 16, 1, 1, 0).unwrap();
+
+        let sizes: Vec<_> = cursor.images().map(|image| image.image().width()).collect();
+        assert_eq!(sizes, vec![16, 32, 48]);
+        assert_eq!(cursor.images().next().unwrap().hotspot(), (1, 1));
+        assert_eq!(cursor.image_for_size(24).image().width(), 32);
+        assert_eq!(cursor.image_for_size(64).image().width(), 48);
+        assert!(!cursor.is_animated());
+    }
+
+    #[test]
+    fn animates_sizes() {
+        let mut cursor = CustomCursor::from_rgba(image(16), 16, 16, 0, 0, 30).unwrap();
+        cursor.add_frame(image(16), 16, 16, 1, 1, 40).unwrap();
+        cursor.add_frame(image(32), 32, 32, 0, 0, 50).unwrap();
+        assert!(cursor.is_animated());
+
+        let delays: Vec<_> = cursor.frames_for_size(16).iter().map(|frame| frame.delay()).collect();
+        assert_eq!(delays, vec![30, 40]);
+        assert_eq!(cursor.frames_for_size(32).len(), 1);
+        let first_frames: Vec<_> = cursor.images().map(|image| image.delay()).collect();
+        assert_eq!(first_frames, vec![30, 50]);
+
+        // replacing a size drops its animation
+        cursor.add_rgba(image(16), 16, 16, 0, 0, 0).unwrap();
+        assert_eq!(cursor.frames_for_size(16).len(), 1);
+        assert!(!cursor.is_animated());
+    }
+}
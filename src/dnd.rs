@@ -0,0 +1,122 @@
+use std::path::Path;
+
+use ClipboardContents;
+
+/// The MIME type of lists of URIs, used to drag files.
+pub const URI_LIST_MIME_TYPE: &'static str = "text/uri-list";
+
+/// What the application that receives a drop does with the dragged data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DragAction {
+    /// The data is copied.
+    Copy,
+    /// The data is moved, the source should delete it.
+    Move,
+    /// A link to the data is created.
+    Link,
+}
+
+/// The outcome of a drag started with `Window::start_drag`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DragResult {
+    /// The data was dropped and accepted with this action.
+    Completed(DragAction),
+    /// The drag was cancelled, or the data was dropped where it isn't accepted.
+    Cancelled,
+}
+
+/// Data dragged out of a window with `Window::start_drag`.
+///
+/// Like the contents of the clipboard, the data can be provided in several formats identified
+/// by their MIME type, among which the application receiving the drop picks one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DragData {
+    contents: ClipboardContents,
+}
+
+impl DragData {
+    /// Builds data holding a list of files, as `text/uri-list`.
+    pub fn from_files<P: AsRef<Path>>(paths: &[P]) -> DragData {
+        let mut uri_list = String::new();
+        for path in paths {
+            uri_list.push_str(&file_uri(path.as_ref()));
+            uri_list.push_str("\r\n");
+        }
+        DragData::from_contents(ClipboardContents::new()
+            .with_format(URI_LIST_MIME_TYPE, uri_list.into_bytes()))
+    }
+
+    /// Builds data holding some text, as `text/plain;charset=utf-8` and `text/plain`.
+    #[inline]
+    pub fn from_text(text: &str) -> DragData {
+        DragData::from_contents(ClipboardContents::from_text(text))
+    }
+
+    /// Builds data holding any formats.
+    #[inline]
+    pub fn from_contents(contents: ClipboardContents) -> DragData {
+        DragData { contents: contents }
+    }
+
+    /// Returns the formats of the data.
+    #[inline]
+    pub fn contents(&self) -> &ClipboardContents {
+        &self.contents
+    }
+}
+
+/// Returns the `file://` URI of a path, with the reserved characters percent-encoded.
+fn file_uri(path: &Path) -> String {
+    let bytes = path_bytes(path);
+    let mut uri = String::from("file://");
+    // windows paths start with a drive letter
+    if !bytes.starts_with(b"/") {
+        uri.push('/');
+    }
+    for &b in bytes.iter() {
+        match b {
+            b'a' ... b'z' | b'A' ... b'Z' | b'0' ... b'9' |
+            b'/' | b'-' | b'_' | b'.' | b'~' | b':' => uri.push(b as char),
+            // windows paths use backslashes
+            b'\\' if cfg!(windows) => uri.push('/'),
+            b => uri.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    uri
+}
+
+// the paths of unix are bytes in any encoding, which are percent-encoded as they are
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+// the paths of windows are UTF-16, which is percent-encoded as UTF-8
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::file_uri;
+
+    #[test]
+    fn encodes_file_uris() {
+        assert_eq!(file_uri(Path::new("/tmp/a file.txt")), "file:///tmp/a%20file.txt");
+        assert_eq!(file_uri(Path::new("/tmp/caf\u{e9}")), "file:///tmp/caf%C3%A9");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn encodes_non_utf8_paths() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"/tmp/\xe9t\xe9"));
+        assert_eq!(file_uri(path), "file:///tmp/%E9t%E9");
+    }
+}
@@ -1,5 +1,6 @@
 use std::path::PathBuf;
-use WindowId;
+use std::time::Duration;
+use {DragResult, WindowId};
 
 #[derive(Clone, Debug)]
 pub enum Event {
@@ -24,24 +25,58 @@ pub enum WindowEvent {
     Closed,
 
     /// A file has been dropped into the window.
+    ///
+    /// When several files are dropped, one event is emitted for each of them.
     DroppedFile(PathBuf),
 
+    /// A file is being dragged over the window.
+    ///
+    /// When several files are dragged, one event is emitted for each of them. They are followed
+    /// by either `DroppedFile` or `HoveredFileCancelled` events. Only emitted on X11 and Wayland.
+    HoveredFile(PathBuf),
+
+    /// The files that were dragged over the window left it, or the drag was cancelled.
+    HoveredFileCancelled,
+
+    /// A drag started with `Window::start_drag` ended.
+    DragFinished(DragResult),
+
     /// The window received a unicode character.
     ReceivedCharacter(char),
 
+    /// An event from the input method, while it is allowed with `Window::set_ime_allowed`.
+    Ime(Ime),
+
     /// The window gained or lost focus.
     ///
     /// The parameter is true if the window has gained focus, and false if it has lost focus.
     Focused(bool),
 
     /// An event from the keyboard has been received.
-    KeyboardInput(ElementState, ScanCode, Option<VirtualKeyCode>, ModifiersState),
+    KeyboardInput(KeyboardInput),
+
+    /// The state of the keyboard modifiers has changed.
+    ///
+    /// Also emitted when the window gains the focus, with the modifiers active at that time.
+    ModifiersChanged(ModifiersState),
+
+    /// The active keyboard layout, or the list of layouts, has changed.
+    ///
+    /// Also emitted the first time the layouts are known while the window has the focus. Only
+    /// emitted on X11 and Wayland.
+    KeyboardLayoutChanged(KeyboardLayouts),
 
     /// The cursor has moved on the window.
     ///
     /// The parameter are the (x,y) coords in pixels relative to the top-left corner of the window.
     MouseMoved(i32, i32),
 
+    /// The mouse has moved while the cursor is locked with `CursorState::Locked`.
+    ///
+    /// The parameters are the (x,y) motion in pixels, without the acceleration of the pointer
+    /// when the platform reports it.
+    MouseMotion(f64, f64),
+
     /// The cursor has entered the window.
     MouseEntered,
 
@@ -49,10 +84,12 @@ pub enum WindowEvent {
     MouseLeft,
 
     /// A mouse wheel movement or touchpad scroll occurred.
-    MouseWheel(MouseScrollDelta, TouchPhase),
+    ///
+    /// The last parameter is the state of the keyboard modifiers during the scroll.
+    MouseWheel(MouseScrollDelta, TouchPhase, ModifiersState),
 
     /// An event from the mouse has been received.
-    MouseInput(ElementState, MouseButton),
+    MouseInput(MouseInput),
 
     /// Touchpad pressure event.
     ///
@@ -73,6 +110,32 @@ pub enum WindowEvent {
     Touch(Touch)
 }
 
+/// The composition of text with an input method.
+///
+/// Once the input method is enabled, the text being composed is reported with `Preedit` events,
+/// and the text to insert with `Commit` events, instead of `ReceivedCharacter` events.
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+pub enum Ime {
+    /// The input method was enabled for the window.
+    Enabled,
+
+    /// The text being composed changed, an empty text means that the composition ended.
+    ///
+    /// The cursor range is a range of bytes in the text, and `None` when the cursor should be
+    /// hidden. The application should display the text at the cursor position of its editor
+    /// until a `Commit` event replaces it.
+    Preedit {
+        text: String,
+        cursor_range: Option<(usize, usize)>,
+    },
+
+    /// The text to insert at the cursor position.
+    Commit(String),
+
+    /// The input method was disabled for the window.
+    Disabled,
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
 pub enum TouchPhase {
     Started,
@@ -104,7 +167,82 @@ pub struct Touch {
     pub id: u64
 }
 
-pub type ScanCode = u8;
+/// The native code of a key, as given by the platform.
+///
+/// This is the evdev code on Wayland, the keycode on X11, the set 1 scan code on Windows (with
+/// `0xE000` added for the extended keys) and the virtual key code on macOS.
+pub type ScanCode = u32;
+
+/// A key of the keyboard was pressed or released.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyboardInput {
+    pub state: ElementState,
+    pub scancode: ScanCode,
+    /// The position of the key on the keyboard, whatever the layout is.
+    pub physical_key: Option<PhysicalKey>,
+    pub virtual_keycode: Option<VirtualKeyCode>,
+    /// What the key means under the active layout and modifiers.
+    ///
+    /// ## Platform-specific
+    ///
+    /// Only reported on X11 and Wayland for now, `None` on the other platforms.
+    pub logical_key: Option<LogicalKey>,
+    pub modifiers: ModifiersState,
+    /// Whether the key is pressed again because it is held down.
+    ///
+    /// Only true for `ElementState::Pressed`, no release is emitted between the repeats.
+    pub is_repeat: bool,
+    /// Whether winit generated the event to keep the state of the keys in sync, instead of
+    /// reporting a key that was just pressed or released.
+    ///
+    /// When the window gains the focus, the keys that are already held down are reported with
+    /// synthetic presses, and when it loses the focus, the keys that are still held down are
+    /// reported with synthetic releases. Only emitted on X11 and Wayland.
+    pub is_synthetic: bool,
+}
+
+/// A mouse button was pressed or released.
+#[derive(Debug, Clone, Copy)]
+pub struct MouseInput {
+    pub state: ElementState,
+    pub button: MouseButton,
+    /// The state of the keyboard modifiers when the button was pressed or released.
+    pub modifiers: ModifiersState,
+    /// Whether winit generated the event to keep the state of the buttons in sync, instead of
+    /// reporting a button that was just pressed or released.
+    ///
+    /// When the cursor leaves the window or the window loses the focus, the buttons that are
+    /// still held down are reported with synthetic releases. Only emitted on X11 and Wayland.
+    ///
+    /// On X11, the real release of such a button is still reported if the window receives it.
+    pub is_synthetic: bool,
+}
+
+/// How the keys repeat while they are held down, as configured by the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRepeatInfo {
+    /// The delay between the press of a key and its first repeat.
+    pub delay: Duration,
+    /// The number of repeats per second.
+    pub rate: u32,
+}
+
+/// The keyboard layouts configured by the user, between which they switch while typing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyboardLayouts {
+    /// The names of the layouts, such as "us" or "de(nodeadkeys)".
+    pub names: Vec<String>,
+    /// The index of the active layout in `names`.
+    pub active: usize,
+}
+
+impl KeyboardLayouts {
+    /// Returns the name of the active layout.
+    #[inline]
+    pub fn active_name(&self) -> Option<&str> {
+        self.names.get(self.active).map(|name| &name[..])
+    }
+}
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
 pub enum ElementState {
@@ -117,7 +255,15 @@ pub enum MouseButton {
     Left,
     Right,
     Middle,
-    Other(u8),
+    /// The side button used to go back, usually under the thumb.
+    Back,
+    /// The side button used to go forward, usually under the thumb.
+    Forward,
+    /// Another button, numbered like X11 does on every platform.
+    ///
+    /// Buttons 1 to 3 are the left, middle and right buttons, 4 to 7 are the wheel and 8 and 9
+    /// are the back and forward buttons, so the other buttons are numbered from 10.
+    Other(u16),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -137,6 +283,263 @@ pub enum MouseScrollDelta {
 	PixelDelta(f32, f32)
 }
 
+/// The position of a key on the keyboard, independent of the layout.
+///
+/// The keys are named after the character they type on a US QWERTY keyboard, following the
+/// usages of the USB HID keyboard page. For example `KeyW` is the key at the right of the `Tab`
+/// key, which types `z` on an AZERTY keyboard and `,` with Dvorak.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum PhysicalKey {
+    KeyA,
+    KeyB,
+    KeyC,
+    KeyD,
+    KeyE,
+    KeyF,
+    KeyG,
+    KeyH,
+    KeyI,
+    KeyJ,
+    KeyK,
+    KeyL,
+    KeyM,
+    KeyN,
+    KeyO,
+    KeyP,
+    KeyQ,
+    KeyR,
+    KeyS,
+    KeyT,
+    KeyU,
+    KeyV,
+    KeyW,
+    KeyX,
+    KeyY,
+    KeyZ,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    Digit0,
+    Enter,
+    Escape,
+    Backspace,
+    Tab,
+    Space,
+    Minus,
+    Equal,
+    BracketLeft,
+    BracketRight,
+    Backslash,
+    Semicolon,
+    Quote,
+    Backquote,
+    Comma,
+    Period,
+    Slash,
+    CapsLock,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    PrintScreen,
+    ScrollLock,
+    Pause,
+    Insert,
+    Home,
+    PageUp,
+    Delete,
+    End,
+    PageDown,
+    ArrowRight,
+    ArrowLeft,
+    ArrowDown,
+    ArrowUp,
+    NumLock,
+    NumpadDivide,
+    NumpadMultiply,
+    NumpadSubtract,
+    NumpadAdd,
+    NumpadEnter,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    Numpad0,
+    NumpadDecimal,
+    NumpadEqual,
+    NumpadComma,
+    /// The key between the left shift and `Z` on ISO keyboards.
+    IntlBackslash,
+    /// The key at the left of the right shift on Japanese and Brazilian keyboards.
+    IntlRo,
+    /// The key at the left of backspace on Japanese keyboards.
+    IntlYen,
+    ContextMenu,
+    Power,
+    Help,
+    Convert,
+    NonConvert,
+    KanaMode,
+    /// Hangul/English toggle on Korean keyboards.
+    Lang1,
+    /// Hanja conversion on Korean keyboards.
+    Lang2,
+    ControlLeft,
+    ShiftLeft,
+    AltLeft,
+    SuperLeft,
+    ControlRight,
+    ShiftRight,
+    AltRight,
+    SuperRight,
+    AudioVolumeMute,
+    AudioVolumeDown,
+    AudioVolumeUp,
+    MediaPlayPause,
+    MediaStop,
+    MediaTrackNext,
+    MediaTrackPrevious,
+    MediaSelect,
+    LaunchMail,
+    LaunchApp1,
+    LaunchApp2,
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    BrowserStop,
+    BrowserSearch,
+    BrowserFavorites,
+    BrowserHome,
+    Sleep,
+    WakeUp,
+    Eject,
+}
+
+/// The meaning of a key under the active layout and modifiers.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum LogicalKey {
+    /// A key that doesn't type a character.
+    Named(NamedKey),
+    /// The character typed by the key, ignoring the control modifier.
+    ///
+    /// For example `'A'` for shift + A with a US layout, and `'ф'` for the same key without
+    /// shift with a Russian layout.
+    Character(char),
+    /// A dead key, which changes the character typed by the next key.
+    Dead,
+}
+
+/// The keys that don't type a character, named after the values of the `key` attribute of DOM
+/// keyboard events.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum NamedKey {
+    Alt,
+    AltGraph,
+    CapsLock,
+    Control,
+    NumLock,
+    ScrollLock,
+    Shift,
+    Super,
+    Enter,
+    Tab,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    ArrowUp,
+    End,
+    Home,
+    PageDown,
+    PageUp,
+    Backspace,
+    Clear,
+    Delete,
+    Insert,
+    Escape,
+    Pause,
+    PrintScreen,
+    ContextMenu,
+    Help,
+    Compose,
+    Convert,
+    NonConvert,
+    KanaMode,
+    HangulMode,
+    HanjaMode,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    AudioVolumeDown,
+    AudioVolumeMute,
+    AudioVolumeUp,
+    MediaPlayPause,
+    MediaStop,
+    MediaTrackNext,
+    MediaTrackPrevious,
+    BrowserBack,
+    BrowserForward,
+    BrowserHome,
+    BrowserRefresh,
+    BrowserSearch,
+    LaunchMail,
+    Power,
+    Sleep,
+    WakeUp,
+    Eject,
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
 #[repr(u32)]
 pub enum VirtualKeyCode {
@@ -325,7 +728,12 @@ pub enum VirtualKeyCode {
 /// Represents the current state of the keyboard modifiers
 ///
 /// Each field of this struct represents a modifier and is `true` if this modifier is active.
-#[derive(Default, Debug, Clone, Copy)]
+///
+/// ## Platform-specific
+///
+/// On X11 and Wayland, the sides only account for the keys pressed while the window has the
+/// focus and the ones held when it gained it.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ModifiersState {
     /// The "shift" key
     pub shift: bool,
@@ -336,5 +744,31 @@ pub struct ModifiersState {
     /// The "logo" key
     ///
     /// This is the "windows" key on PC and "command" key on Mac.
-    pub logo: bool
+    pub logo: bool,
+    /// The "AltGr" key, which selects the third symbol of the keys on some layouts.
+    ///
+    /// Always `false` on macOS, where the "option" key is reported as `alt`.
+    pub alt_graph: bool,
+    /// Whether caps lock is on.
+    pub caps_lock: bool,
+    /// Whether num lock is on.
+    ///
+    /// Always `false` on macOS, which has no num lock.
+    pub num_lock: bool,
+    /// The left "shift" key is held down.
+    pub left_shift: bool,
+    /// The right "shift" key is held down.
+    pub right_shift: bool,
+    /// The left "control" key is held down.
+    pub left_ctrl: bool,
+    /// The right "control" key is held down.
+    pub right_ctrl: bool,
+    /// The left "alt" key is held down.
+    pub left_alt: bool,
+    /// The right "alt" key is held down.
+    pub right_alt: bool,
+    /// The left "logo" key is held down.
+    pub left_logo: bool,
+    /// The right "logo" key is held down.
+    pub right_logo: bool,
 }
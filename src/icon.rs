@@ -0,0 +1,242 @@
+use std::error::Error;
+use std::fmt;
+#[cfg(feature = "icon_loading")]
+use std::fs::File;
+#[cfg(feature = "icon_loading")]
+use std::io::Read;
+#[cfg(feature = "icon_loading")]
+use std::path::Path;
+
+#[cfg(feature = "icon_loading")]
+use image_loading::{self, Format, LoadIconError};
+
+/// An icon that can be displayed in the titlebar, the taskbar or the task switcher.
+///
+/// An icon can hold several images of different sizes, so that the platform can pick the one
+/// that fits best where the icon is displayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icon {
+    // sorted by increasing size, at most one image per size
+    images: Vec<IconImage>,
+}
+
+/// One of the images of an `Icon`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconImage {
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+/// An error produced when building an `Icon` from invalid data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BadIcon {
+    /// The width or the height of the image is zero.
+    ZeroSized,
+    /// The length of the buffer doesn't match the dimensions of the image. The buffer must hold
+    /// exactly four bytes for each pixel.
+    ByteCountMismatch {
+        width: u32,
+        height: u32,
+        byte_count: usize,
+    },
+    /// The hotspot of a cursor is outside of its image.
+    HotspotOutOfBounds {
+        width: u32,
+        height: u32,
+        hotspot: (u32, u32),
+    },
+}
+
+impl Icon {
+    /// Builds an icon from a single image.
+    ///
+    /// The buffer must contain `width * height` pixels in RGBA order, row by row starting from
+    /// the top-left corner, with one byte per channel.
+    #[inline]
+    pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Icon, BadIcon> {
+        let image = try!(IconImage::from_rgba(rgba, width, height));
+        Ok(Icon { images: vec![image] })
+    }
+
+    /// Loads an icon from a PNG or ICO file. ICO files can contain several sizes.
+    ///
+    /// Only available with the `icon_loading` feature.
+    #[cfg(feature = "icon_loading")]
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Icon, LoadIconError> {
+        let mut bytes = Vec::new();
+        try!(try!(File::open(path)).read_to_end(&mut bytes));
+        match image_loading::guess_format(&bytes) {
+            Some(Format::Png) => Icon::from_png_bytes(&bytes),
+            Some(Format::Ico) => Icon::from_ico_bytes(&bytes),
+            _ => Err(LoadIconError::InvalidData("not a PNG or ICO file".to_owned())),
+        }
+    }
+
+    /// Decodes an icon from the content of a PNG file.
+    ///
+    /// Only available with the `icon_loading` feature.
+    #[cfg(feature = "icon_loading")]
+    pub fn from_png_bytes(bytes: &[u8]) -> Result<Icon, LoadIconError> {
+        let image = try!(image_loading::decode_png(bytes));
+        Ok(try!(Icon::from_rgba(image.rgba, image.width, image.height)))
+    }
+
+    /// Decodes an icon from the content of an ICO file, with every size it contains.
+    ///
+    /// Only available with the `icon_loading` feature.
+    #[cfg(feature = "icon_loading")]
+    pub fn from_ico_bytes(bytes: &[u8]) -> Result<Icon, LoadIconError> {
+        let mut images = try!(image_loading::decode_ico(bytes)).into_iter();
+        let first = images.next().unwrap();
+        let mut icon = try!(Icon::from_rgba(first.rgba, first.width, first.height));
+        for image in images {
+            try!(icon.add_rgba(image.rgba, image.width, image.height));
+        }
+        Ok(icon)
+    }
+
+    /// Adds another size to the icon. See `from_rgba` for the format of the buffer.
+    ///
+    /// If the icon already has an image with the same dimensions, it is replaced.
+    pub fn add_rgba(&mut self, rgba: Vec<u8>, width: u32, height: u32) -> Result<(), BadIcon> {
+        let image = try!(IconImage::from_rgba(rgba, width, height));
+        match self.images.binary_search_by_key(&image.sort_key(), IconImage::sort_key) {
+            Ok(index) => self.images[index] = image,
+            Err(index) => self.images.insert(index, image),
+        }
+        Ok(())
+    }
+
+    /// Returns the images of the icon, sorted by increasing size.
+    #[inline]
+    pub fn images(&self) -> &[IconImage] {
+        &self.images
+    }
+
+    /// Returns the image that fits best in an area of the given size.
+    ///
+    /// This is the smallest image that covers the area, or the largest image if none does.
+    /// The size is in physical pixels, so sizes in points must be multiplied by
+    /// `Window::hidpi_factor()` first.
+    pub fn image_for_size(&self, width: u32, height: u32) -> &IconImage {
+        self.images.iter()
+            .find(|image| image.width >= width && image.height >= height)
+            .unwrap_or_else(|| self.images.last().unwrap())
+    }
+}
+
+impl IconImage {
+    /// Builds an image from a buffer of `width * height` pixels in RGBA order, row by row
+    /// starting from the top-left corner, with one byte per channel.
+    pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<IconImage, BadIcon> {
+        if width == 0 || height == 0 {
+            return Err(BadIcon::ZeroSized);
+        }
+
+        if rgba.len() as u64 != width as u64 * height as u64 * 4 {
+            return Err(BadIcon::ByteCountMismatch {
+                width: width,
+                height: height,
+                byte_count: rgba.len(),
+            });
+        }
+
+        Ok(IconImage {
+            rgba: rgba,
+            width: width,
+            height: height,
+        })
+    }
+
+    #[inline]
+    fn sort_key(&self) -> (u64, u32) {
+        (self.width as u64 * self.height as u64, self.width)
+    }
+
+    /// Returns the pixels of the image, in RGBA order.
+    #[inline]
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl fmt::Display for BadIcon {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            BadIcon::ByteCountMismatch { width, height, byte_count } => {
+                write!(formatter, "{} (expected {} bytes for a {}x{} image, got {})",
+                       self.description(), width as u64 * height as u64 * 4, width, height,
+                       byte_count)
+            },
+            BadIcon::HotspotOutOfBounds { width, height, hotspot: (x, y) } => {
+                write!(formatter, "{} (hotspot at ({}, {}) in a {}x{} image)",
+                       self.description(), x, y, width, height)
+            },
+            _ => formatter.write_str(self.description()),
+        }
+    }
+}
+
+impl Error for BadIcon {
+    fn description(&self) -> &str {
+        match *self {
+            BadIcon::ZeroSized => "The icon has a width or height of zero",
+            BadIcon::ByteCountMismatch { .. } => "The size of the buffer doesn't match the dimensions of the icon",
+            BadIcon::HotspotOutOfBounds { .. } => "The hotspot of the cursor is outside of its image",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BadIcon, Icon, IconImage};
+
+    fn image(width: u32, height: u32) -> Vec<u8> {
+        vec![0xff; width as usize * height as usize * 4]
+    }
+
+    #[test]
+    fn rejects_bad_buffers() {
+        assert_eq!(IconImage::from_rgba(vec![], 0, 4), Err(BadIcon::ZeroSized));
+        assert_eq!(IconImage::from_rgba(vec![], 4, 0), Err(BadIcon::ZeroSized));
+        assert_eq!(IconImage::from_rgba(vec![0; 15], 2, 2),
+                   Err(BadIcon::ByteCountMismatch { width: 2, height: 2, byte_count: 15 }));
+        // the expected length overflows a 32 bits product
+        assert_eq!(IconImage::from_rgba(vec![0; 4], 0x10000, 0x10000),
+                   Err(BadIcon::ByteCountMismatch { width: 0x10000, height: 0x10000, byte_count: 4 }));
+    }
+
+    #[test]
+    fn sorts_and_replaces_sizes() {
+        let mut icon = Icon::from_rgba(image(32, 32), 32, 32).unwrap();
+        icon.add_rgba(image(16, 16), 16, 16).unwrap();
+        icon.add_rgba(image(48, 48), 48, 48).unwrap();
+        icon.add_rgba(vec![0; 16 * 16 * 4], 16, 16).unwrap();
+        assert!(icon.add_rgba(vec![], 8, 8).is_err());
+
+        let sizes: Vec<_> = icon.images().iter().map(|image| (image.width(), image.height())).collect();
+        assert_eq!(sizes, vec![(16, 16), (32, 32), (48, 48)]);
+        assert_eq!(icon.images()[0].rgba()[0], 0);
+    }
+
+    #[test]
+    fn picks_image_for_size() {
+        let mut icon = Icon::from_rgba(image(16, 16), 16, 16).unwrap();
+        icon.add_rgba(image(32, 32), 32, 32).unwrap();
+        assert_eq!(icon.image_for_size(8, 8).width(), 16);
+        assert_eq!(icon.image_for_size(16, 16).width(), 16);
+        assert_eq!(icon.image_for_size(20, 16).width(), 32);
+        assert_eq!(icon.image_for_size(64, 64).width(), 32);
+    }
+}
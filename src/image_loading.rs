@@ -0,0 +1,558 @@
+//! Decoders for the image formats that icons and cursors are commonly shipped in.
+
+use std::cmp;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use png;
+
+use BadIcon;
+
+/// An error produced when loading an icon or a cursor from a file or from encoded bytes.
+#[derive(Debug)]
+pub enum LoadIconError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// The data is not in a supported format, or is corrupted.
+    InvalidData(String),
+    /// The decoded image can't be used as an icon.
+    BadIcon(BadIcon),
+}
+
+impl fmt::Display for LoadIconError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            LoadIconError::Io(ref err) => write!(formatter, "{}: {}", self.description(), err),
+            LoadIconError::InvalidData(ref msg) => write!(formatter, "{}: {}", self.description(), msg),
+            LoadIconError::BadIcon(ref err) => fmt::Display::fmt(err, formatter),
+        }
+    }
+}
+
+impl Error for LoadIconError {
+    fn description(&self) -> &str {
+        match *self {
+            LoadIconError::Io(_) => "Failed to read the image",
+            LoadIconError::InvalidData(_) => "Failed to decode the image",
+            LoadIconError::BadIcon(ref err) => err.description(),
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            LoadIconError::Io(ref err) => Some(err),
+            LoadIconError::BadIcon(ref err) => Some(err),
+            LoadIconError::InvalidData(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadIconError {
+    #[inline]
+    fn from(err: io::Error) -> LoadIconError {
+        LoadIconError::Io(err)
+    }
+}
+
+impl From<BadIcon> for LoadIconError {
+    #[inline]
+    fn from(err: BadIcon) -> LoadIconError {
+        LoadIconError::BadIcon(err)
+    }
+}
+
+#[inline]
+fn invalid<T>(msg: &str) -> Result<T, LoadIconError> {
+    Err(LoadIconError::InvalidData(msg.to_owned()))
+}
+
+/// An image decoded to RGBA, with the hotspot it has if it comes from a cursor file.
+pub struct DecodedImage {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub hotspot: (u32, u32),
+    /// How long the frame is displayed in an animated cursor, in milliseconds.
+    pub delay: u32,
+}
+
+/// The format of an encoded image, guessed from its first bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    /// Both icon (`.ico`) and cursor (`.cur`) files.
+    Ico,
+    Xcursor,
+}
+
+pub fn guess_format(bytes: &[u8]) -> Option<Format> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(Format::Png)
+    } else if bytes.starts_with(b"\0\0\x01\0") || bytes.starts_with(b"\0\0\x02\0") {
+        Some(Format::Ico)
+    } else if bytes.starts_with(b"Xcur") {
+        Some(Format::Xcursor)
+    } else {
+        None
+    }
+}
+
+// The largest width or height accepted for a decoded image, which keeps the size of the
+// buffers that are allocated for it reasonable.
+const MAX_DIMENSION: u32 = 0x7fff;
+
+// Returns the `len` bytes starting at `offset`, `None` if they are out of bounds.
+#[inline]
+fn get_bytes(bytes: &[u8], offset: usize, len: usize) -> Option<&[u8]> {
+    offset.checked_add(len).and_then(|end| bytes.get(offset..end))
+}
+
+#[inline]
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    get_bytes(bytes, offset, 2).map(|b| b[0] as u16 | (b[1] as u16) << 8)
+}
+
+#[inline]
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    get_bytes(bytes, offset, 4)
+        .map(|b| b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+}
+
+// Computes `a * b + c`, `None` on overflow.
+#[inline]
+fn mul_add(a: usize, b: usize, c: usize) -> Option<usize> {
+    a.checked_mul(b).and_then(|product| product.checked_add(c))
+}
+
+/// Decodes a PNG image.
+pub fn decode_png(bytes: &[u8]) -> Result<DecodedImage, LoadIconError> {
+    let mut decoder = png::Decoder::new(bytes);
+    // turns palettes into RGB(A) and low bit depths into 8 bits
+    decoder.set(png::Transformations::EXPAND);
+    let (info, mut reader) = try!(decoder.read_info()
+                                         .map_err(|err| LoadIconError::InvalidData(err.to_string())));
+    if info.width == 0 || info.height == 0 || info.width > MAX_DIMENSION || info.height > MAX_DIMENSION {
+        return invalid("unsupported PNG dimensions");
+    }
+    let mut buf = vec![0; info.buffer_size()];
+    try!(reader.next_frame(&mut buf).map_err(|err| LoadIconError::InvalidData(err.to_string())));
+
+    // 16 bits channels are truncated to their most significant byte
+    let channel_size = if info.bit_depth == png::BitDepth::Sixteen { 2 } else { 1 };
+    let channels: Vec<u8> = buf.chunks(channel_size).map(|c| c[0]).collect();
+
+    let rgba = match info.color_type {
+        png::ColorType::RGBA => channels,
+        png::ColorType::RGB => {
+            channels.chunks(3).flat_map(|c| vec![c[0], c[1], c[2], 0xff]).collect()
+        },
+        png::ColorType::GrayscaleAlpha => {
+            channels.chunks(2).flat_map(|c| vec![c[0], c[0], c[0], c[1]]).collect()
+        },
+        png::ColorType::Grayscale => {
+            channels.iter().flat_map(|&c| vec![c, c, c, 0xff]).collect()
+        },
+        png::ColorType::Indexed => return invalid("indexed PNG images are not supported"),
+    };
+
+    Ok(DecodedImage {
+        rgba: rgba,
+        width: info.width,
+        height: info.height,
+        hotspot: (0, 0),
+        delay: 0,
+    })
+}
+
+/// Decodes every image of an icon (`.ico`) or cursor (`.cur`) file.
+pub fn decode_ico(bytes: &[u8]) -> Result<Vec<DecodedImage>, LoadIconError> {
+    let is_cursor = match read_u16(bytes, 2) {
+        Some(1) => false,
+        Some(2) => true,
+        _ => return invalid("not an ICO or CUR file"),
+    };
+    let count = match read_u16(bytes, 4) {
+        Some(count) => count as usize,
+        None => return invalid("truncated ICO header"),
+    };
+
+    let mut images = Vec::with_capacity(count);
+    for i in 0..count {
+        let entry = 6 + i * 16;
+        let (size, offset) = match (read_u32(bytes, entry + 8), read_u32(bytes, entry + 12)) {
+            (Some(size), Some(offset)) => (size as usize, offset as usize),
+            _ => return invalid("truncated ICO directory"),
+        };
+        let data = match get_bytes(bytes, offset, size) {
+            Some(data) => data,
+            None => return invalid("ICO image out of bounds"),
+        };
+
+        let mut image = if guess_format(data) == Some(Format::Png) {
+            try!(decode_png(data))
+        } else {
+            try!(decode_dib(data))
+        };
+
+        // in cursor files, the planes and bit count fields hold the hotspot
+        if is_cursor {
+            image.hotspot = (read_u16(bytes, entry + 4).unwrap() as u32,
+                             read_u16(bytes, entry + 6).unwrap() as u32);
+        }
+        images.push(image);
+    }
+
+    if images.is_empty() {
+        return invalid("the ICO file contains no image");
+    }
+    Ok(images)
+}
+
+// Decodes the BMP image of an ICO file: a `BITMAPINFOHEADER`, the palette if any, the color
+// bits and the 1 bit transparency mask, both bottom-up with rows padded to 4 bytes.
+fn decode_dib(data: &[u8]) -> Result<DecodedImage, LoadIconError> {
+    let header = (read_u32(data, 0), read_u32(data, 4), read_u32(data, 8),
+                  read_u16(data, 14), read_u32(data, 16), read_u32(data, 32));
+    let (header_size, width, height, bit_count, compression, colors_used) = match header {
+        (Some(a), Some(b), Some(c), Some(d), Some(e), Some(f)) => (a as usize, b, c / 2, d, e, f),
+        _ => return invalid("truncated BMP header"),
+    };
+    if compression != 0 {
+        return invalid("compressed BMP images are not supported");
+    }
+    if width == 0 || height == 0 || width > 1024 || height > 1024 {
+        return invalid("unsupported BMP dimensions");
+    }
+    let (width_, height_) = (width as usize, height as usize);
+
+    let palette_len = match bit_count {
+        1 | 4 | 8 if colors_used == 0 => 1 << bit_count,
+        1 | 4 | 8 => colors_used as usize,
+        24 | 32 => 0,
+        _ => return invalid("unsupported BMP bit count"),
+    };
+    // the dimensions are small enough for the strides not to overflow
+    let color_stride = (width_ * bit_count as usize + 31) / 32 * 4;
+    let mask_stride = (width_ + 31) / 32 * 4;
+    let palette_offset = header_size;
+    let offsets = mul_add(palette_len, 4, palette_offset).and_then(|color_offset| {
+        mul_add(color_stride, height_, color_offset).map(|mask_offset| (color_offset, mask_offset))
+    });
+    let (color_offset, mask_offset) = match offsets {
+        Some(offsets) if offsets.1 <= data.len() => offsets,
+        _ => return invalid("truncated BMP data"),
+    };
+    // some 32 bits images omit the mask and rely on their alpha channel
+    let has_mask = mul_add(mask_stride, height_, mask_offset).map(|end| end <= data.len()) == Some(true);
+
+    let mut rgba = Vec::with_capacity(width_ * height_ * 4);
+    for y in 0..height_ {
+        let row = height_ - 1 - y;
+        let color_row = &data[color_offset + row * color_stride..];
+        for x in 0..width_ {
+            let (b, g, r, a) = match bit_count {
+                32 => (color_row[x * 4], color_row[x * 4 + 1], color_row[x * 4 + 2], color_row[x * 4 + 3]),
+                24 => (color_row[x * 3], color_row[x * 3 + 1], color_row[x * 3 + 2], 0xff),
+                _ => {
+                    let bit = x * bit_count as usize;
+                    let pixels_per_byte = 8 / bit_count as usize;
+                    let shift = 8 - bit_count as usize * (x % pixels_per_byte + 1);
+                    let index = (color_row[bit / 8] >> shift) as usize & ((1 << bit_count) - 1);
+                    if index >= palette_len {
+                        return invalid("BMP palette index out of bounds");
+                    }
+                    let entry = palette_offset + index * 4;
+                    (data[entry], data[entry + 1], data[entry + 2], 0xff)
+                },
+            };
+
+            let masked = has_mask && {
+                let mask_byte = data[mask_offset + row * mask_stride + x / 8];
+                mask_byte & (0x80 >> (x % 8)) != 0
+            };
+            // the mask is only meaningful for images that don't have an alpha channel
+            let a = if bit_count != 32 && masked { 0 } else { a };
+
+            rgba.extend_from_slice(&[r, g, b, a]);
+        }
+    }
+
+    // 32 bits images that only use the mask have an alpha channel full of zeros
+    if bit_count == 32 && rgba.chunks(4).all(|px| px[3] == 0) {
+        for (i, px) in rgba.chunks_mut(4).enumerate() {
+            let (x, row) = (i % width_, height_ - 1 - i / width_);
+            let masked = has_mask && data[mask_offset + row * mask_stride + x / 8] & (0x80 >> (x % 8)) != 0;
+            px[3] = if masked { 0 } else { 0xff };
+        }
+    }
+
+    Ok(DecodedImage {
+        rgba: rgba,
+        width: width,
+        height: height,
+        hotspot: (0, 0),
+        delay: 0,
+    })
+}
+
+// Type of the chunks of an XCursor file that contain an image.
+const XCURSOR_IMAGE_TYPE: u32 = 0xfffd0002;
+
+/// Decodes every image of an XCursor file, along with the nominal size of each of them.
+///
+/// Animated cursors have several images with the same nominal size, in the order they are
+/// displayed.
+pub fn decode_xcursor(bytes: &[u8]) -> Result<Vec<(u32, DecodedImage)>, LoadIconError> {
+    if !bytes.starts_with(b"Xcur") {
+        return invalid("not an XCursor file");
+    }
+    let count = match read_u32(bytes, 12) {
+        Some(count) => count as usize,
+        None => return invalid("truncated XCursor header"),
+    };
+    let toc_offset = match read_u32(bytes, 4) {
+        Some(header_size) => header_size as usize,
+        None => return invalid("truncated XCursor header"),
+    };
+
+    let mut images = Vec::new();
+    for i in 0..count {
+        let entry = match mul_add(i, 12, toc_offset) {
+            Some(entry) => entry,
+            None => return invalid("truncated XCursor table of contents"),
+        };
+        let position = entry.checked_add(8).and_then(|offset| read_u32(bytes, offset));
+        let (type_, position) = match (read_u32(bytes, entry), position) {
+            (Some(type_), Some(position)) => (type_, position as usize),
+            _ => return invalid("truncated XCursor table of contents"),
+        };
+        if type_ != XCURSOR_IMAGE_TYPE {
+            continue;
+        }
+
+        let fields: Vec<_> = (0..9).map(|field| {
+            position.checked_add(field * 4).and_then(|offset| read_u32(bytes, offset))
+        }).collect();
+        let fields: Vec<u32> = match fields.into_iter().collect::<Option<_>>() {
+            Some(fields) => fields,
+            None => return invalid("truncated XCursor image header"),
+        };
+        let (header_size, nominal_size) = (fields[0] as usize, fields[2]);
+        let (width, height, xhot, yhot, delay) = (fields[4], fields[5], fields[6], fields[7], fields[8]);
+        if width == 0 || height == 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+            return invalid("unsupported XCursor image dimensions");
+        }
+
+        let pixels_offset = position.checked_add(header_size);
+        let pixels_len = (width as usize).checked_mul(height as usize)
+                                         .and_then(|len| len.checked_mul(4));
+        let pixels = match (pixels_offset, pixels_len) {
+            (Some(offset), Some(len)) => get_bytes(bytes, offset, len),
+            _ => None,
+        };
+        let pixels = match pixels {
+            Some(pixels) => pixels,
+            None => return invalid("truncated XCursor image"),
+        };
+
+        // pixels are premultiplied ARGB words in little endian
+        let rgba = pixels.chunks(4).flat_map(|px| {
+            let (b, g, r, a) = (px[0] as u32, px[1] as u32, px[2] as u32, px[3] as u32);
+            let unpremultiply = |c: u32| if a == 0 { 0 } else { cmp::min(c * 255 / a, 255) as u8 };
+            vec![unpremultiply(r), unpremultiply(g), unpremultiply(b), a as u8]
+        }).collect();
+
+        images.push((nominal_size, DecodedImage {
+            rgba: rgba,
+            width: width,
+            height: height,
+            hotspot: (cmp::min(xhot, width - 1), cmp::min(yhot, height - 1)),
+            delay: delay,
+        }));
+    }
+
+    if images.is_empty() {
+        return invalid("the XCursor file contains no image");
+    }
+    Ok(images)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Format, decode_ico, decode_png, decode_xcursor, guess_format};
+
+    const PNG_2X2: &'static [u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x08, 0x06, 0x00, 0x00, 0x00, 0x72,
+        0xb6, 0x0d, 0x24, 0x00, 0x00, 0x00, 0x1d, 0x49, 0x44, 0x41, 0x54, 0x78, 0x01, 0x01, 0x12,
+        0x00, 0xed, 0xff, 0x00, 0xff, 0x00, 0x00, 0xff, 0x00, 0xff, 0x00, 0xff, 0x00, 0x00, 0x00,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x48, 0xc9, 0x08, 0xf8, 0x57, 0x15, 0xb0, 0x15, 0x00,
+        0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82
+    ];
+
+    const PNG_OVERSIZED: &'static [u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x9c, 0x40, 0x00, 0x00, 0x9c, 0x40, 0x08, 0x06, 0x00, 0x00, 0x00, 0x51,
+        0x0c, 0x0e, 0x05, 0x00, 0x00, 0x00, 0x13, 0x49, 0x44, 0x41, 0x54, 0x78, 0x01, 0x01, 0x08,
+        0x00, 0xf7, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x01,
+        0xa8, 0x04, 0x4f, 0xf5, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60,
+        0x82
+    ];
+
+    const ICO_DIB_32: &'static [u8] = &[
+        0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x02, 0x02, 0x00, 0x00, 0x01, 0x00, 0x20, 0x00, 0x40,
+        0x00, 0x00, 0x00, 0x16, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+        0x04, 0x00, 0x00, 0x00, 0x01, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0xff, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0xff, 0xff, 0x00,
+        0xff, 0x00, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+    ];
+
+    const CUR_DIB_24: &'static [u8] = &[
+        0x00, 0x00, 0x02, 0x00, 0x01, 0x00, 0x02, 0x02, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x40,
+        0x00, 0x00, 0x00, 0x16, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+        0x04, 0x00, 0x00, 0x00, 0x01, 0x00, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0xff, 0x00, 0x00, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0x00, 0xff,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00
+    ];
+
+    const ICO_PNG: &'static [u8] = &[
+        0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x02, 0x02, 0x00, 0x00, 0x01, 0x00, 0x20, 0x00, 0x56,
+        0x00, 0x00, 0x00, 0x16, 0x00, 0x00, 0x00, 0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a,
+        0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+        0x02, 0x08, 0x06, 0x00, 0x00, 0x00, 0x72, 0xb6, 0x0d, 0x24, 0x00, 0x00, 0x00, 0x1d, 0x49,
+        0x44, 0x41, 0x54, 0x78, 0x01, 0x01, 0x12, 0x00, 0xed, 0xff, 0x00, 0xff, 0x00, 0x00, 0xff,
+        0x00, 0xff, 0x00, 0xff, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x48, 0xc9,
+        0x08, 0xf8, 0x57, 0x15, 0xb0, 0x15, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae,
+        0x42, 0x60, 0x82
+    ];
+
+    const XCURSOR_ANIMATED: &'static [u8] = &[
+        0x58, 0x63, 0x75, 0x72, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00,
+        0x00, 0x02, 0x00, 0xfd, 0xff, 0x02, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x02, 0x00,
+        0xfd, 0xff, 0x02, 0x00, 0x00, 0x00, 0x5c, 0x00, 0x00, 0x00, 0x24, 0x00, 0x00, 0x00, 0x02,
+        0x00, 0xfd, 0xff, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+        0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x32, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0xff, 0xff, 0x00, 0xff, 0x00, 0xff, 0xff, 0x00, 0x00, 0xff, 0x00, 0x00,
+        0x00, 0x00, 0x24, 0x00, 0x00, 0x00, 0x02, 0x00, 0xfd, 0xff, 0x02, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x46, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00,
+        0xff, 0x00, 0xff, 0x00, 0xff, 0x00, 0x00, 0xff, 0xff
+    ];
+
+    // red, green, blue and a transparent pixel
+    const RGBA_2X2: [u8; 16] = [0xff, 0, 0, 0xff, 0, 0xff, 0, 0xff, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0];
+
+    // Returns a copy of `bytes` with the little endian `value` written at `offset`.
+    fn patch_u32(bytes: &[u8], offset: usize, value: u32) -> Vec<u8> {
+        let mut bytes = bytes.to_vec();
+        for i in 0..4 {
+            bytes[offset + i] = (value >> (i * 8)) as u8;
+        }
+        bytes
+    }
+
+    #[test]
+    fn guesses_format() {
+        assert_eq!(guess_format(PNG_2X2), Some(Format::Png));
+        assert_eq!(guess_format(ICO_DIB_32), Some(Format::Ico));
+        assert_eq!(guess_format(CUR_DIB_24), Some(Format::Ico));
+        assert_eq!(guess_format(XCURSOR_ANIMATED), Some(Format::Xcursor));
+        assert_eq!(guess_format(b"GIF89a"), None);
+        assert_eq!(guess_format(b""), None);
+    }
+
+    #[test]
+    fn decodes_png() {
+        let image = decode_png(PNG_2X2).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.rgba, RGBA_2X2.to_vec());
+    }
+
+    #[test]
+    fn rejects_invalid_png() {
+        assert!(decode_png(b"\x89PNG\r\n\x1a\r").is_err());
+        assert!(decode_png(&PNG_2X2[1..]).is_err());
+        assert!(decode_png(PNG_OVERSIZED).is_err());
+        // the pixels are stored without compression, every prefix that stops before the last
+        // pixel byte (followed by the zlib checksum, the IDAT CRC and the IEND chunk) misses some
+        for len in 0..PNG_2X2.len() - 20 {
+            assert!(decode_png(&PNG_2X2[..len]).is_err(), "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn decodes_ico() {
+        let images = decode_ico(ICO_DIB_32).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!((images[0].width, images[0].height), (2, 2));
+        assert_eq!(images[0].rgba, RGBA_2X2.to_vec());
+        assert_eq!(images[0].hotspot, (0, 0));
+
+        let images = decode_ico(ICO_PNG).unwrap();
+        assert_eq!(images[0].rgba, RGBA_2X2.to_vec());
+    }
+
+    #[test]
+    fn decodes_cur_with_mask() {
+        let images = decode_ico(CUR_DIB_24).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].hotspot, (1, 0));
+        // the top-left pixel is masked out, the 24 bits pixels are otherwise opaque
+        assert_eq!(images[0].rgba, vec![0xff, 0, 0, 0, 0, 0xff, 0, 0xff,
+                                         0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn rejects_invalid_ico() {
+        assert!(decode_ico(b"\0\0\x03\0\x01\0").is_err());
+        for fixture in &[ICO_DIB_32, CUR_DIB_24, ICO_PNG] {
+            for len in 0..fixture.len() {
+                assert!(decode_ico(&fixture[..len]).is_err(), "{} bytes", len);
+            }
+        }
+        // the size and the offset of the image in the directory
+        assert!(decode_ico(&patch_u32(ICO_DIB_32, 14, 0xffffffff)).is_err());
+        assert!(decode_ico(&patch_u32(ICO_DIB_32, 18, 0xffffffff)).is_err());
+        // the BMP header starts at offset 22
+        assert!(decode_ico(&patch_u32(ICO_DIB_32, 22 + 4, 0x10000)).is_err());
+        assert!(decode_ico(&patch_u32(ICO_DIB_32, 22 + 8, 0x20000)).is_err());
+        assert!(decode_ico(&patch_u32(ICO_DIB_32, 22, 0xffffffff)).is_err());
+        // an 8 bits image with a huge palette
+        let paletted = patch_u32(&patch_u32(ICO_DIB_32, 22 + 12, 0x00080001), 22 + 32, 0xffffffff);
+        assert!(decode_ico(&paletted).is_err());
+    }
+
+    #[test]
+    fn decodes_xcursor() {
+        let images = decode_xcursor(XCURSOR_ANIMATED).unwrap();
+        assert_eq!(images.len(), 2);
+        let (nominal_size, ref first) = images[0];
+        assert_eq!(nominal_size, 2);
+        assert_eq!((first.width, first.height), (2, 2));
+        assert_eq!(first.hotspot, (1, 0));
+        assert_eq!(first.delay, 50);
+        // the transparent pixel loses its color when it is premultiplied
+        assert_eq!(first.rgba, vec![0xff, 0, 0, 0xff, 0, 0xff, 0, 0xff, 0, 0, 0xff, 0xff, 0, 0, 0, 0]);
+        assert_eq!(images[1].1.delay, 70);
+    }
+
+    #[test]
+    fn rejects_invalid_xcursor() {
+        assert!(decode_xcursor(b"Xcus\x10\0\0\0").is_err());
+        for len in 0..XCURSOR_ANIMATED.len() {
+            assert!(decode_xcursor(&XCURSOR_ANIMATED[..len]).is_err(), "{} bytes", len);
+        }
+        // the header size of the file, the number of entries and the position of the first image
+        assert!(decode_xcursor(&patch_u32(XCURSOR_ANIMATED, 4, 0xffffffff)).is_err());
+        assert!(decode_xcursor(&patch_u32(XCURSOR_ANIMATED, 12, 0xffffffff)).is_err());
+        assert!(decode_xcursor(&patch_u32(XCURSOR_ANIMATED, 24, 0xffffffff)).is_err());
+        // the first image starts at offset 40, with its header size, width and height
+        assert!(decode_xcursor(&patch_u32(XCURSOR_ANIMATED, 40, 0xffffffff)).is_err());
+        assert!(decode_xcursor(&patch_u32(XCURSOR_ANIMATED, 40 + 16, 0x8000)).is_err());
+        assert!(decode_xcursor(&patch_u32(XCURSOR_ANIMATED, 40 + 20, 0xffffffff)).is_err());
+        assert!(decode_xcursor(&patch_u32(XCURSOR_ANIMATED, 40 + 16, 0)).is_err());
+    }
+}
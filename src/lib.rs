@@ -118,17 +118,37 @@ extern crate x11_dl;
 #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly", target_os = "openbsd"))]
 #[macro_use(wayland_env,declare_handler)]
 extern crate wayland_client;
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly", target_os = "openbsd"))]
+extern crate wayland_protocols;
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly", target_os = "openbsd"))]
+#[macro_use]
+extern crate wayland_sys;
+
+#[cfg(feature = "icon_loading")]
+extern crate png;
 
 use std::sync::Arc;
 
 pub use events::*;
 pub use window::{AvailableMonitorsIter, MonitorId, get_available_monitors, get_primary_monitor};
 pub use native_monitor::NativeMonitorId;
+pub use clipboard::{ClipboardContents, ClipboardKind, TEXT_MIME_TYPE, TEXT_UTF8_MIME_TYPE};
+pub use cursor::{CursorImage, CursorImages, CustomCursor};
+pub use dnd::{DragAction, DragData, DragResult, URI_LIST_MIME_TYPE};
+pub use icon::{BadIcon, Icon, IconImage};
+#[cfg(feature = "icon_loading")]
+pub use image_loading::LoadIconError;
 
 #[macro_use]
 mod api_transition;
 
 mod platform;
+mod clipboard;
+mod cursor;
+mod dnd;
+mod icon;
+#[cfg(feature = "icon_loading")]
+mod image_loading;
 mod events;
 mod window;
 
@@ -200,6 +220,78 @@ impl EventsLoop {
         self.events_loop.run_forever(callback)
     }
 
+    /// Returns how the keys repeat while they are held down, as configured by the user.
+    ///
+    /// Returns `None` if the keys don't repeat or if the settings are unknown.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On Wayland, the compositor only gives the settings and winit generates the repeats
+    ///   itself.
+    /// - Always returns `None` on Android and iOS.
+    #[inline]
+    pub fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+        self.events_loop.key_repeat_info()
+    }
+
+    /// Returns the keyboard layouts configured by the user and the active one.
+    ///
+    /// Returns `None` if they are not known yet.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On Wayland, the layouts are known once the compositor sent the keymap of the keyboard.
+    /// - On X11, requires libxkbcommon-x11.
+    /// - Always returns `None` on Windows, macOS, Android and iOS.
+    #[inline]
+    pub fn keyboard_layouts(&self) -> Option<KeyboardLayouts> {
+        self.events_loop.keyboard_layouts()
+    }
+
+    /// Takes the ownership of a clipboard and fills it with some contents, like
+    /// `Window::set_clipboard`.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On X11, Windows, Android and iOS, the clipboard is owned by a window of the events loop,
+    ///   so this returns an error until a window is created
+    /// - See also `Window::set_clipboard`
+    ///
+    #[inline]
+    pub fn set_clipboard(&self, kind: ClipboardKind, contents: ClipboardContents) -> Result<(), String> {
+        self.events_loop.set_clipboard(kind, contents)
+    }
+
+    /// Returns the MIME types in which the current contents of a clipboard are available.
+    ///
+    /// Returns an empty list if the clipboard is empty or can't be read, and on X11, Windows,
+    /// Android and iOS until a window of the events loop is created.
+    #[inline]
+    pub fn clipboard_mime_types(&self, kind: ClipboardKind) -> Vec<String> {
+        self.events_loop.clipboard_mime_types(kind)
+    }
+
+    /// Reads the current contents of a clipboard in the given format.
+    ///
+    /// Returns `None` if the clipboard is empty, if its contents aren't available in this format
+    /// or if the application owning the clipboard doesn't answer, and on X11, Windows, Android
+    /// and iOS until a window of the events loop is created.
+    ///
+    /// This function blocks until the data is transferred.
+    #[inline]
+    pub fn get_clipboard(&self, kind: ClipboardKind, mime_type: &str) -> Option<Vec<u8>> {
+        self.events_loop.get_clipboard(kind, mime_type)
+    }
+
+    /// Reads the current contents of a clipboard as text.
+    ///
+    /// Invalid UTF-8 sequences are replaced with `U+FFFD`.
+    pub fn get_clipboard_text(&self, kind: ClipboardKind) -> Option<String> {
+        self.get_clipboard(kind, TEXT_UTF8_MIME_TYPE)
+            .or_else(|| self.get_clipboard(kind, TEXT_MIME_TYPE))
+            .map(|data| String::from_utf8_lossy(&data).into_owned())
+    }
+
     /// If we called `run_forever()`, stops the process of waiting for events.
     // TODO: what if we're waiting from multiple threads?
     #[inline]
@@ -318,6 +410,19 @@ pub enum CursorState {
     ///
     /// This is useful for first-person cameras for example.
     Grab,
+
+    /// Locks the cursor in place and hides it. Instead of `MouseMoved` events, the window
+    /// receives `MouseMotion` events with the relative motion of the mouse, which is not
+    /// stopped by the edges of the screen.
+    ///
+    /// This is what first-person cameras usually want.
+    Locked,
+
+    /// Keeps the cursor inside the window, without hiding it. Unlike `Grab`, the window
+    /// doesn't get exclusive access to the mouse.
+    ///
+    /// This is useful for edge scrolling for example.
+    Confined,
 }
 
 /// Attributes to use when creating a window.
@@ -339,6 +444,11 @@ pub struct WindowAttributes {
     /// The default is `None`.
     pub max_dimensions: Option<(u32, u32)>,
 
+    /// Whether the window can be resized by the user.
+    ///
+    /// The default is `true`.
+    pub resizable: bool,
+
     /// If `Some`, the window will be in fullscreen mode with the given monitor.
     ///
     /// The default is `None`.
@@ -354,6 +464,27 @@ pub struct WindowAttributes {
     /// The default is `true`.
     pub visible: bool,
 
+    /// Whether the window should be maximized upon creation.
+    ///
+    /// The default is `false`. Ignored on Android and iOS.
+    pub maximized: bool,
+
+    /// Whether the window should always stay above other windows.
+    ///
+    /// The default is `false`.
+    pub always_on_top: bool,
+
+    /// Whether the window should always stay below other windows, for example for desktop
+    /// widgets.
+    ///
+    /// The default is `false`.
+    pub always_on_bottom: bool,
+
+    /// The icon of the window, displayed in the titlebar, the taskbar or the task switcher.
+    ///
+    /// The default is `None`.
+    pub window_icon: Option<Icon>,
+
     /// Whether the the window should be transparent. If this is true, writing colors
     /// with alpha values different than `1.0` will produce a transparent window.
     ///
@@ -377,9 +508,14 @@ impl Default for WindowAttributes {
             dimensions: None,
             min_dimensions: None,
             max_dimensions: None,
+            resizable: true,
             monitor: None,
             title: "glutin window".to_owned(),
             visible: true,
+            maximized: false,
+            always_on_top: false,
+            always_on_bottom: false,
+            window_icon: None,
             transparent: false,
             decorations: true,
             multitouch: false,
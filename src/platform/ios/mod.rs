@@ -166,6 +166,11 @@ impl MonitorId {
 
 gen_api_transition!();
 
+// there is no hardware keyboard to repeat the keys of
+fn key_repeat_info() -> Option<::KeyRepeatInfo> {
+    None
+}
+
 #[derive(Clone, Default)]
 pub struct PlatformSpecificWindowBuilderAttributes;
 
@@ -424,10 +429,94 @@ impl Window {
     pub fn set_window_resize_callback(&mut self, _: Option<fn(u32, u32)>) {
     }
 
+    #[inline]
+    pub fn set_resizable(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_maximized(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    pub fn set_minimized(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    pub fn set_always_on_top(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn set_always_on_bottom(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn set_window_icon(&self, _: Option<::Icon>) {
+    }
+
     #[inline]
     pub fn set_cursor(&self, _: MouseCursor) {
     }
 
+    #[inline]
+    pub fn set_custom_cursor(&self, _: &::CustomCursor) {
+    }
+
+    #[inline]
+    pub fn set_clipboard(&self, _: ::ClipboardKind, _: ::ClipboardContents) -> Result<(), String> {
+        Err("The clipboard is not supported yet".to_string())
+    }
+
+    #[inline]
+    pub fn clipboard_mime_types(&self, _: ::ClipboardKind) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[inline]
+    pub fn get_clipboard(&self, _: ::ClipboardKind, _: &str) -> Option<Vec<u8>> {
+        None
+    }
+
+    #[inline]
+    pub fn set_ime_position(&self, _x: i32, _y: i32) {
+    }
+
+    #[inline]
+    pub fn set_ime_allowed(&self, _allowed: bool) {
+    }
+
+    #[inline]
+    pub fn get_pressed_keys(&self) -> Vec<::ScanCode> {
+        Vec::new()
+    }
+
+    #[inline]
+    pub fn get_pressed_mouse_buttons(&self) -> Vec<::MouseButton> {
+        Vec::new()
+    }
+
+    #[inline]
+    pub fn start_drag(&self, _: ::DragData, _: &[::DragAction]) -> Result<(), String> {
+        Err("Dragging data out of a window is not supported yet".to_string())
+    }
+
     #[inline]
     pub fn set_cursor_state(&self, _: CursorState) -> Result<(), String> {
         Ok(())
@@ -0,0 +1,241 @@
+//! Physical positions of the keys from their evdev codes, which are the raw keys of Wayland and,
+//! shifted by 8, the keycodes of the X servers using the evdev or libinput drivers.
+//!
+//! The evdev codes of the mouse buttons are the buttons of Wayland.
+
+use {ModifiersState, MouseButton, PhysicalKey};
+
+/// Returns the position of the key with the given evdev code, from `linux/input-event-codes.h`.
+pub fn code_to_physical_key(code: u32) -> Option<PhysicalKey> {
+    Some(match code {
+        1 => PhysicalKey::Escape,
+        2 => PhysicalKey::Digit1,
+        3 => PhysicalKey::Digit2,
+        4 => PhysicalKey::Digit3,
+        5 => PhysicalKey::Digit4,
+        6 => PhysicalKey::Digit5,
+        7 => PhysicalKey::Digit6,
+        8 => PhysicalKey::Digit7,
+        9 => PhysicalKey::Digit8,
+        10 => PhysicalKey::Digit9,
+        11 => PhysicalKey::Digit0,
+        12 => PhysicalKey::Minus,
+        13 => PhysicalKey::Equal,
+        14 => PhysicalKey::Backspace,
+        15 => PhysicalKey::Tab,
+        16 => PhysicalKey::KeyQ,
+        17 => PhysicalKey::KeyW,
+        18 => PhysicalKey::KeyE,
+        19 => PhysicalKey::KeyR,
+        20 => PhysicalKey::KeyT,
+        21 => PhysicalKey::KeyY,
+        22 => PhysicalKey::KeyU,
+        23 => PhysicalKey::KeyI,
+        24 => PhysicalKey::KeyO,
+        25 => PhysicalKey::KeyP,
+        26 => PhysicalKey::BracketLeft,
+        27 => PhysicalKey::BracketRight,
+        28 => PhysicalKey::Enter,
+        29 => PhysicalKey::ControlLeft,
+        30 => PhysicalKey::KeyA,
+        31 => PhysicalKey::KeyS,
+        32 => PhysicalKey::KeyD,
+        33 => PhysicalKey::KeyF,
+        34 => PhysicalKey::KeyG,
+        35 => PhysicalKey::KeyH,
+        36 => PhysicalKey::KeyJ,
+        37 => PhysicalKey::KeyK,
+        38 => PhysicalKey::KeyL,
+        39 => PhysicalKey::Semicolon,
+        40 => PhysicalKey::Quote,
+        41 => PhysicalKey::Backquote,
+        42 => PhysicalKey::ShiftLeft,
+        43 => PhysicalKey::Backslash,
+        44 => PhysicalKey::KeyZ,
+        45 => PhysicalKey::KeyX,
+        46 => PhysicalKey::KeyC,
+        47 => PhysicalKey::KeyV,
+        48 => PhysicalKey::KeyB,
+        49 => PhysicalKey::KeyN,
+        50 => PhysicalKey::KeyM,
+        51 => PhysicalKey::Comma,
+        52 => PhysicalKey::Period,
+        53 => PhysicalKey::Slash,
+        54 => PhysicalKey::ShiftRight,
+        55 => PhysicalKey::NumpadMultiply,
+        56 => PhysicalKey::AltLeft,
+        57 => PhysicalKey::Space,
+        58 => PhysicalKey::CapsLock,
+        59 => PhysicalKey::F1,
+        60 => PhysicalKey::F2,
+        61 => PhysicalKey::F3,
+        62 => PhysicalKey::F4,
+        63 => PhysicalKey::F5,
+        64 => PhysicalKey::F6,
+        65 => PhysicalKey::F7,
+        66 => PhysicalKey::F8,
+        67 => PhysicalKey::F9,
+        68 => PhysicalKey::F10,
+        69 => PhysicalKey::NumLock,
+        70 => PhysicalKey::ScrollLock,
+        71 => PhysicalKey::Numpad7,
+        72 => PhysicalKey::Numpad8,
+        73 => PhysicalKey::Numpad9,
+        74 => PhysicalKey::NumpadSubtract,
+        75 => PhysicalKey::Numpad4,
+        76 => PhysicalKey::Numpad5,
+        77 => PhysicalKey::Numpad6,
+        78 => PhysicalKey::NumpadAdd,
+        79 => PhysicalKey::Numpad1,
+        80 => PhysicalKey::Numpad2,
+        81 => PhysicalKey::Numpad3,
+        82 => PhysicalKey::Numpad0,
+        83 => PhysicalKey::NumpadDecimal,
+        // 85: zenkaku/hankaku, which has no usage of its own
+        86 => PhysicalKey::IntlBackslash,
+        87 => PhysicalKey::F11,
+        88 => PhysicalKey::F12,
+        89 => PhysicalKey::IntlRo,
+        92 => PhysicalKey::Convert,
+        93 => PhysicalKey::KanaMode,
+        94 => PhysicalKey::NonConvert,
+        95 => PhysicalKey::NumpadComma,
+        96 => PhysicalKey::NumpadEnter,
+        97 => PhysicalKey::ControlRight,
+        98 => PhysicalKey::NumpadDivide,
+        99 => PhysicalKey::PrintScreen,
+        100 => PhysicalKey::AltRight,
+        102 => PhysicalKey::Home,
+        103 => PhysicalKey::ArrowUp,
+        104 => PhysicalKey::PageUp,
+        105 => PhysicalKey::ArrowLeft,
+        106 => PhysicalKey::ArrowRight,
+        107 => PhysicalKey::End,
+        108 => PhysicalKey::ArrowDown,
+        109 => PhysicalKey::PageDown,
+        110 => PhysicalKey::Insert,
+        111 => PhysicalKey::Delete,
+        113 => PhysicalKey::AudioVolumeMute,
+        114 => PhysicalKey::AudioVolumeDown,
+        115 => PhysicalKey::AudioVolumeUp,
+        116 => PhysicalKey::Power,
+        117 => PhysicalKey::NumpadEqual,
+        119 => PhysicalKey::Pause,
+        121 => PhysicalKey::NumpadComma,
+        122 => PhysicalKey::Lang1,
+        123 => PhysicalKey::Lang2,
+        124 => PhysicalKey::IntlYen,
+        125 => PhysicalKey::SuperLeft,
+        126 => PhysicalKey::SuperRight,
+        127 => PhysicalKey::ContextMenu,
+        128 => PhysicalKey::BrowserStop,
+        138 => PhysicalKey::Help,
+        140 => PhysicalKey::LaunchApp2,
+        142 => PhysicalKey::Sleep,
+        143 => PhysicalKey::WakeUp,
+        155 => PhysicalKey::LaunchMail,
+        156 => PhysicalKey::BrowserFavorites,
+        157 => PhysicalKey::LaunchApp1,
+        158 => PhysicalKey::BrowserBack,
+        159 => PhysicalKey::BrowserForward,
+        161 => PhysicalKey::Eject,
+        163 => PhysicalKey::MediaTrackNext,
+        164 => PhysicalKey::MediaPlayPause,
+        165 => PhysicalKey::MediaTrackPrevious,
+        166 => PhysicalKey::MediaStop,
+        172 => PhysicalKey::BrowserHome,
+        173 => PhysicalKey::BrowserRefresh,
+        183 => PhysicalKey::F13,
+        184 => PhysicalKey::F14,
+        185 => PhysicalKey::F15,
+        186 => PhysicalKey::F16,
+        187 => PhysicalKey::F17,
+        188 => PhysicalKey::F18,
+        189 => PhysicalKey::F19,
+        190 => PhysicalKey::F20,
+        191 => PhysicalKey::F21,
+        192 => PhysicalKey::F22,
+        193 => PhysicalKey::F23,
+        194 => PhysicalKey::F24,
+        217 => PhysicalKey::BrowserSearch,
+        226 => PhysicalKey::MediaSelect,
+        _ => return None,
+    })
+}
+
+/// Returns the position of the key with the given X11 keycode.
+pub fn keycode_to_physical_key(keycode: u32) -> Option<PhysicalKey> {
+    // the X servers reserve the keycodes below 8
+    if keycode < 8 {
+        return None;
+    }
+    code_to_physical_key(keycode - 8)
+}
+
+/// Marks the side of the modifier held by a key, if the key is one of them.
+pub fn add_modifier_side(mods: &mut ModifiersState, key: PhysicalKey) {
+    match key {
+        PhysicalKey::ShiftLeft => mods.left_shift = true,
+        PhysicalKey::ShiftRight => mods.right_shift = true,
+        PhysicalKey::ControlLeft => mods.left_ctrl = true,
+        PhysicalKey::ControlRight => mods.right_ctrl = true,
+        PhysicalKey::AltLeft => mods.left_alt = true,
+        PhysicalKey::AltRight => mods.right_alt = true,
+        PhysicalKey::SuperLeft => mods.left_logo = true,
+        PhysicalKey::SuperRight => mods.right_logo = true,
+        _ => (),
+    }
+}
+
+/// Returns the mouse button with the given evdev code, numbered like the X servers using the
+/// evdev or libinput drivers do.
+pub fn code_to_mouse_button(code: u32) -> Option<MouseButton> {
+    // BTN_LEFT, BTN_RIGHT, BTN_MIDDLE, BTN_SIDE and BTN_EXTRA
+    Some(match code {
+        0x110 => MouseButton::Left,
+        0x111 => MouseButton::Right,
+        0x112 => MouseButton::Middle,
+        0x113 => MouseButton::Back,
+        0x114 => MouseButton::Forward,
+        // the buttons after BTN_SIDE follow the eighth button of X11, up to the last mouse button
+        0x115...0x11f => MouseButton::Other((code - 0x113 + 8) as u16),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {MouseButton, PhysicalKey};
+
+    use super::{code_to_mouse_button, code_to_physical_key, keycode_to_physical_key};
+
+    #[test]
+    fn physical_keys() {
+        assert_eq!(code_to_physical_key(1), Some(PhysicalKey::Escape));
+        assert_eq!(code_to_physical_key(30), Some(PhysicalKey::KeyA));
+        assert_eq!(code_to_physical_key(42), Some(PhysicalKey::ShiftLeft));
+        assert_eq!(code_to_physical_key(57), Some(PhysicalKey::Space));
+        assert_eq!(code_to_physical_key(103), Some(PhysicalKey::ArrowUp));
+        assert_eq!(code_to_physical_key(0), None);
+    }
+
+    #[test]
+    fn x11_keycodes() {
+        assert_eq!(keycode_to_physical_key(9), Some(PhysicalKey::Escape));
+        assert_eq!(keycode_to_physical_key(38), Some(PhysicalKey::KeyA));
+        assert_eq!(keycode_to_physical_key(7), None);
+    }
+
+    #[test]
+    fn mouse_buttons() {
+        assert_eq!(code_to_mouse_button(0x110), Some(MouseButton::Left));
+        assert_eq!(code_to_mouse_button(0x111), Some(MouseButton::Right));
+        assert_eq!(code_to_mouse_button(0x112), Some(MouseButton::Middle));
+        assert_eq!(code_to_mouse_button(0x113), Some(MouseButton::Back));
+        assert_eq!(code_to_mouse_button(0x114), Some(MouseButton::Forward));
+        assert_eq!(code_to_mouse_button(0x115), Some(MouseButton::Other(10)));
+        assert_eq!(code_to_mouse_button(0x11f), Some(MouseButton::Other(20)));
+        assert_eq!(code_to_mouse_button(0x120), None);
+        assert_eq!(code_to_mouse_button(0x10f), None);
+    }
+}
@@ -0,0 +1,245 @@
+//! Interpretation of the X keysyms, which both X11 and the XKB keymaps of Wayland use to tell what
+//! a key means under the active layout.
+//!
+//! The values come from `X11/keysymdef.h` and `X11/XF86keysym.h`.
+
+use {LogicalKey, NamedKey};
+
+/// Returns the meaning of a keysym, `None` for the keysyms that are neither a known key nor a
+/// character.
+pub fn keysym_to_logical_key(keysym: u32) -> Option<LogicalKey> {
+    if let Some(key) = keysym_to_named_key(keysym) {
+        return Some(LogicalKey::Named(key));
+    }
+    // dead_grave to dead_greek, and dead_lowline to dead_longsolidusoverlay
+    if (keysym >= 0xfe50 && keysym <= 0xfe8c) || (keysym >= 0xfe90 && keysym <= 0xfe93) {
+        return Some(LogicalKey::Dead);
+    }
+    keysym_to_char(keysym).map(LogicalKey::Character)
+}
+
+fn keysym_to_named_key(keysym: u32) -> Option<NamedKey> {
+    // F1 to F24
+    if keysym >= 0xffbe && keysym <= 0xffd5 {
+        const FUNCTION_KEYS: [NamedKey; 24] = [
+            NamedKey::F1, NamedKey::F2, NamedKey::F3, NamedKey::F4, NamedKey::F5, NamedKey::F6,
+            NamedKey::F7, NamedKey::F8, NamedKey::F9, NamedKey::F10, NamedKey::F11, NamedKey::F12,
+            NamedKey::F13, NamedKey::F14, NamedKey::F15, NamedKey::F16, NamedKey::F17,
+            NamedKey::F18, NamedKey::F19, NamedKey::F20, NamedKey::F21, NamedKey::F22,
+            NamedKey::F23, NamedKey::F24,
+        ];
+        return Some(FUNCTION_KEYS[(keysym - 0xffbe) as usize]);
+    }
+
+    Some(match keysym {
+        0xff08 => NamedKey::Backspace,
+        // Tab, ISO_Left_Tab (shift + tab) and KP_Tab
+        0xff09 | 0xfe20 | 0xff89 => NamedKey::Tab,
+        0xff0b => NamedKey::Clear,
+        // Return and KP_Enter
+        0xff0d | 0xff8d => NamedKey::Enter,
+        0xff13 => NamedKey::Pause,
+        0xff14 => NamedKey::ScrollLock,
+        0xff15 => NamedKey::PrintScreen,
+        0xff1b => NamedKey::Escape,
+        0xff20 => NamedKey::Compose,
+        0xff22 => NamedKey::NonConvert,
+        0xff23 => NamedKey::Convert,
+        0xff27 => NamedKey::KanaMode,
+        0xff31 => NamedKey::HangulMode,
+        0xff34 => NamedKey::HanjaMode,
+        // the keypad keys when num lock is off
+        0xff50 | 0xff95 => NamedKey::Home,
+        0xff51 | 0xff96 => NamedKey::ArrowLeft,
+        0xff52 | 0xff97 => NamedKey::ArrowUp,
+        0xff53 | 0xff98 => NamedKey::ArrowRight,
+        0xff54 | 0xff99 => NamedKey::ArrowDown,
+        0xff55 | 0xff9a => NamedKey::PageUp,
+        0xff56 | 0xff9b => NamedKey::PageDown,
+        0xff57 | 0xff9c => NamedKey::End,
+        0xff9d => NamedKey::Clear,
+        0xff61 => NamedKey::PrintScreen,
+        0xff63 | 0xff9e => NamedKey::Insert,
+        0xffff | 0xff9f => NamedKey::Delete,
+        0xff67 => NamedKey::ContextMenu,
+        0xff6a => NamedKey::Help,
+        0xff6b => NamedKey::Pause,
+        0xff7f => NamedKey::NumLock,
+        // Shift_L and Shift_R
+        0xffe1 | 0xffe2 => NamedKey::Shift,
+        0xffe3 | 0xffe4 => NamedKey::Control,
+        // Caps_Lock and Shift_Lock
+        0xffe5 | 0xffe6 => NamedKey::CapsLock,
+        // Meta_L, Meta_R, Alt_L and Alt_R
+        0xffe7 ... 0xffea => NamedKey::Alt,
+        // Super_L, Super_R, Hyper_L and Hyper_R
+        0xffeb ... 0xffee => NamedKey::Super,
+        // ISO_Level3_Shift and Mode_switch
+        0xfe03 | 0xff7e => NamedKey::AltGraph,
+        0x1008ff11 => NamedKey::AudioVolumeDown,
+        0x1008ff12 => NamedKey::AudioVolumeMute,
+        0x1008ff13 => NamedKey::AudioVolumeUp,
+        // XF86AudioPlay and XF86AudioPause
+        0x1008ff14 | 0x1008ff31 => NamedKey::MediaPlayPause,
+        0x1008ff15 => NamedKey::MediaStop,
+        0x1008ff16 => NamedKey::MediaTrackPrevious,
+        0x1008ff17 => NamedKey::MediaTrackNext,
+        0x1008ff18 => NamedKey::BrowserHome,
+        0x1008ff19 => NamedKey::LaunchMail,
+        0x1008ff1b => NamedKey::BrowserSearch,
+        0x1008ff26 => NamedKey::BrowserBack,
+        0x1008ff27 => NamedKey::BrowserForward,
+        0x1008ff29 => NamedKey::BrowserRefresh,
+        0x1008ff2a => NamedKey::Power,
+        0x1008ff2b => NamedKey::WakeUp,
+        0x1008ff2c => NamedKey::Eject,
+        0x1008ff2f => NamedKey::Sleep,
+        _ => return None,
+    })
+}
+
+/// Returns the character of a keysym.
+///
+/// Covers the Unicode keysyms and the legacy ones of Latin-1, Latin-2, Cyrillic, Greek, Arabic,
+/// Hebrew and Thai, which are the ones used by the layouts of xkeyboard-config.
+pub fn keysym_to_char(keysym: u32) -> Option<char> {
+    let code = match keysym {
+        // Latin-1 matches Unicode
+        0x0020 ... 0x007e | 0x00a0 ... 0x00ff => keysym,
+        // the keysyms of the characters without a legacy keysym
+        0x01000100 ... 0x0110ffff => keysym - 0x01000000,
+        0x01a1 ... 0x01ff => latin2_to_unicode(keysym),
+        // ISO 8859-6, the Arabic keysyms only cover its letters and punctuation
+        0x05ac | 0x05bb | 0x05bf | 0x05c1 ... 0x05da | 0x05e0 ... 0x05f2 => (keysym & 0xff) + 0x0560,
+        0x06a1 ... 0x06ff => CYRILLIC[(keysym - 0x06a1) as usize] as u32,
+        0x07a1 ... 0x07f9 => greek_to_unicode(keysym),
+        // hebrew_doublelowline, then hebrew_aleph to hebrew_taw
+        0x0cdf => 0x2017,
+        0x0ce0 ... 0x0cfa => keysym - 0x0ce0 + 0x05d0,
+        // TIS-620
+        0x0da1 ... 0x0df9 => (keysym & 0xff) + 0x0d60,
+        0x13bc => 0x0152,
+        0x13bd => 0x0153,
+        0x13be => 0x0178,
+        0x20ac => 0x20ac,
+        // the keypad keys when num lock is on
+        0xff80 => ' ' as u32,
+        0xffaa => '*' as u32,
+        0xffab => '+' as u32,
+        0xffac => ',' as u32,
+        0xffad => '-' as u32,
+        0xffae => '.' as u32,
+        0xffaf => '/' as u32,
+        0xffb0 ... 0xffb9 => keysym - 0xffb0 + '0' as u32,
+        0xffbd => '=' as u32,
+        _ => 0,
+    };
+    if code == 0 {
+        return None;
+    }
+    ::std::char::from_u32(code)
+}
+
+/// The characters of ISO 8859-2 that are not in Latin-1, the other ones use their Latin-1 keysym.
+fn latin2_to_unicode(keysym: u32) -> u32 {
+    match keysym {
+        0x01a1 => 0x0104, 0x01a2 => 0x02d8, 0x01a3 => 0x0141, 0x01a5 => 0x013d,
+        0x01a6 => 0x015a, 0x01a9 => 0x0160, 0x01aa => 0x015e, 0x01ab => 0x0164,
+        0x01ac => 0x0179, 0x01ae => 0x017d, 0x01af => 0x017b, 0x01b1 => 0x0105,
+        0x01b2 => 0x02db, 0x01b3 => 0x0142, 0x01b5 => 0x013e, 0x01b6 => 0x015b,
+        0x01b7 => 0x02c7, 0x01b9 => 0x0161, 0x01ba => 0x015f, 0x01bb => 0x0165,
+        0x01bc => 0x017a, 0x01bd => 0x02dd, 0x01be => 0x017e, 0x01bf => 0x017c,
+        0x01c0 => 0x0154, 0x01c3 => 0x0102, 0x01c5 => 0x0139, 0x01c6 => 0x0106,
+        0x01c8 => 0x010c, 0x01ca => 0x0118, 0x01cc => 0x011a, 0x01cf => 0x010e,
+        0x01d0 => 0x0110, 0x01d1 => 0x0143, 0x01d2 => 0x0147, 0x01d5 => 0x0150,
+        0x01d8 => 0x0158, 0x01d9 => 0x016e, 0x01db => 0x0170, 0x01de => 0x0162,
+        0x01e0 => 0x0155, 0x01e3 => 0x0103, 0x01e5 => 0x013a, 0x01e6 => 0x0107,
+        0x01e8 => 0x010d, 0x01ea => 0x0119, 0x01ec => 0x011b, 0x01ef => 0x010f,
+        0x01f0 => 0x0111, 0x01f1 => 0x0144, 0x01f2 => 0x0148, 0x01f5 => 0x0151,
+        0x01f8 => 0x0159, 0x01f9 => 0x016f, 0x01fb => 0x0171, 0x01fe => 0x0163,
+        0x01ff => 0x02d9,
+        _ => 0,
+    }
+}
+
+/// The Cyrillic keysyms from `0x6a1`, whose letters follow the order of KOI8.
+const CYRILLIC: [u16; 95] = [
+            0x0452, 0x0453, 0x0451, 0x0454, 0x0455, 0x0456, 0x0457,
+    0x0458, 0x0459, 0x045a, 0x045b, 0x045c, 0x0491, 0x045e, 0x045f,
+    0x2116, 0x0402, 0x0403, 0x0401, 0x0404, 0x0405, 0x0406, 0x0407,
+    0x0408, 0x0409, 0x040a, 0x040b, 0x040c, 0x0490, 0x040e, 0x040f,
+    0x044e, 0x0430, 0x0431, 0x0446, 0x0434, 0x0435, 0x0444, 0x0433,
+    0x0445, 0x0438, 0x0439, 0x043a, 0x043b, 0x043c, 0x043d, 0x043e,
+    0x043f, 0x044f, 0x0440, 0x0441, 0x0442, 0x0443, 0x0436, 0x0432,
+    0x044c, 0x044b, 0x0437, 0x0448, 0x044d, 0x0449, 0x0447, 0x044a,
+    0x042e, 0x0410, 0x0411, 0x0426, 0x0414, 0x0415, 0x0424, 0x0413,
+    0x0425, 0x0418, 0x0419, 0x041a, 0x041b, 0x041c, 0x041d, 0x041e,
+    0x041f, 0x042f, 0x0420, 0x0421, 0x0422, 0x0423, 0x0416, 0x0412,
+    0x042c, 0x042b, 0x0417, 0x0428, 0x042d, 0x0429, 0x0427, 0x042a,
+];
+
+fn greek_to_unicode(keysym: u32) -> u32 {
+    match keysym {
+        // the accented letters
+        0x07a1 => 0x0386, 0x07a2 => 0x0388, 0x07a3 => 0x0389, 0x07a4 => 0x038a,
+        0x07a5 => 0x03aa, 0x07a7 => 0x038c, 0x07a8 => 0x038e, 0x07a9 => 0x03ab,
+        0x07ab => 0x038f, 0x07ae => 0x0385, 0x07af => 0x2015,
+        0x07b1 => 0x03ac, 0x07b2 => 0x03ad, 0x07b3 => 0x03ae, 0x07b4 => 0x03af,
+        0x07b5 => 0x03ca, 0x07b6 => 0x0390, 0x07b7 => 0x03cc, 0x07b8 => 0x03cd,
+        0x07b9 => 0x03cb, 0x07ba => 0x03b0, 0x07bb => 0x03ce,
+        // the alphabet, where the final sigma is only lowercase
+        0x07c1 ... 0x07d1 => keysym - 0x07c1 + 0x0391,
+        0x07d2 => 0x03a3,
+        0x07d4 ... 0x07d9 => keysym - 0x07d4 + 0x03a4,
+        0x07e1 ... 0x07f1 => keysym - 0x07e1 + 0x03b1,
+        0x07f2 => 0x03c3,
+        0x07f3 => 0x03c2,
+        0x07f4 ... 0x07f9 => keysym - 0x07f4 + 0x03c4,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {LogicalKey, NamedKey};
+
+    use super::keysym_to_logical_key;
+
+    #[test]
+    fn named_keys() {
+        assert_eq!(keysym_to_logical_key(0xff0d), Some(LogicalKey::Named(NamedKey::Enter)));
+        assert_eq!(keysym_to_logical_key(0xff8d), Some(LogicalKey::Named(NamedKey::Enter)));
+        assert_eq!(keysym_to_logical_key(0xffbe), Some(LogicalKey::Named(NamedKey::F1)));
+        assert_eq!(keysym_to_logical_key(0xffd5), Some(LogicalKey::Named(NamedKey::F24)));
+        assert_eq!(keysym_to_logical_key(0xfe03), Some(LogicalKey::Named(NamedKey::AltGraph)));
+        assert_eq!(keysym_to_logical_key(0x1008ff13),
+                   Some(LogicalKey::Named(NamedKey::AudioVolumeUp)));
+    }
+
+    #[test]
+    fn dead_keys() {
+        // dead_grave and dead_acute
+        assert_eq!(keysym_to_logical_key(0xfe50), Some(LogicalKey::Dead));
+        assert_eq!(keysym_to_logical_key(0xfe51), Some(LogicalKey::Dead));
+    }
+
+    #[test]
+    fn characters() {
+        assert_eq!(keysym_to_logical_key(0x0061), Some(LogicalKey::Character('a')));
+        assert_eq!(keysym_to_logical_key(0x00e9), Some(LogicalKey::Character('é')));
+        // Lstroke, Cyrillic_a, Cyrillic_zhe and Greek_ALPHA
+        assert_eq!(keysym_to_logical_key(0x01a3), Some(LogicalKey::Character('Ł')));
+        assert_eq!(keysym_to_logical_key(0x06c1), Some(LogicalKey::Character('а')));
+        assert_eq!(keysym_to_logical_key(0x06d6), Some(LogicalKey::Character('ж')));
+        assert_eq!(keysym_to_logical_key(0x07c1), Some(LogicalKey::Character('Α')));
+        // the Unicode keysym of the euro sign, and KP_5 with num lock on
+        assert_eq!(keysym_to_logical_key(0x010020ac), Some(LogicalKey::Character('€')));
+        assert_eq!(keysym_to_logical_key(0xffb5), Some(LogicalKey::Character('5')));
+    }
+
+    #[test]
+    fn unknown_keysyms() {
+        assert_eq!(keysym_to_logical_key(0), None);
+        assert_eq!(keysym_to_logical_key(0xfe01), None);
+    }
+}
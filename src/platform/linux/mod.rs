@@ -3,8 +3,14 @@
 use std::collections::VecDeque;
 use std::sync::Arc;
 
+use ClipboardContents;
+use ClipboardKind;
 use CreationError;
 use CursorState;
+use CustomCursor;
+use DragAction;
+use DragData;
+use Icon;
 use MouseCursor;
 use libc;
 
@@ -14,6 +20,10 @@ use self::x11::XNotSupported;
 use self::x11::ffi::XVisualInfo;
 
 mod dlopen;
+mod evdev;
+mod keysym;
+mod xkbcommon;
+mod uri_list;
 pub mod wayland;
 pub mod x11;
 
@@ -220,6 +230,86 @@ impl Window2 {
         }
     }
 
+    #[inline]
+    pub fn set_resizable(&self, resizable: bool) {
+        match self {
+            &Window2::X(ref w) => w.set_resizable(resizable),
+            &Window2::Wayland(ref w) => w.set_resizable(resizable)
+        }
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        match self {
+            &Window2::X(ref w) => w.set_min_dimensions(dimensions),
+            &Window2::Wayland(ref w) => w.set_min_dimensions(dimensions)
+        }
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        match self {
+            &Window2::X(ref w) => w.set_max_dimensions(dimensions),
+            &Window2::Wayland(ref w) => w.set_max_dimensions(dimensions)
+        }
+    }
+
+    #[inline]
+    pub fn set_maximized(&self, maximized: bool) {
+        match self {
+            &Window2::X(ref w) => w.set_maximized(maximized),
+            &Window2::Wayland(ref w) => w.set_maximized(maximized)
+        }
+    }
+
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        match self {
+            &Window2::X(ref w) => w.is_maximized(),
+            &Window2::Wayland(ref w) => w.is_maximized()
+        }
+    }
+
+    #[inline]
+    pub fn set_minimized(&self, minimized: bool) {
+        match self {
+            &Window2::X(ref w) => w.set_minimized(minimized),
+            &Window2::Wayland(ref w) => w.set_minimized(minimized)
+        }
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        match self {
+            &Window2::X(ref w) => w.is_minimized(),
+            &Window2::Wayland(ref w) => w.is_minimized()
+        }
+    }
+
+    #[inline]
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        match self {
+            &Window2::X(ref w) => w.set_always_on_top(always_on_top),
+            &Window2::Wayland(ref w) => w.set_always_on_top(always_on_top)
+        }
+    }
+
+    #[inline]
+    pub fn set_always_on_bottom(&self, always_on_bottom: bool) {
+        match self {
+            &Window2::X(ref w) => w.set_always_on_bottom(always_on_bottom),
+            &Window2::Wayland(ref w) => w.set_always_on_bottom(always_on_bottom)
+        }
+    }
+
+    #[inline]
+    pub fn set_window_icon(&self, window_icon: Option<Icon>) {
+        match self {
+            &Window2::X(ref w) => w.set_window_icon(window_icon),
+            &Window2::Wayland(ref w) => w.set_window_icon(window_icon)
+        }
+    }
+
     #[inline]
     pub fn set_cursor(&self, cursor: MouseCursor) {
         match self {
@@ -228,6 +318,78 @@ impl Window2 {
         }
     }
 
+    #[inline]
+    pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+        match self {
+            &Window2::X(ref w) => w.set_custom_cursor(cursor),
+            &Window2::Wayland(ref w) => w.set_custom_cursor(cursor)
+        }
+    }
+
+    #[inline]
+    pub fn set_clipboard(&self, kind: ClipboardKind, contents: ClipboardContents) -> Result<(), String> {
+        match self {
+            &Window2::X(ref w) => w.set_clipboard(kind, contents),
+            &Window2::Wayland(ref w) => w.set_clipboard(kind, contents)
+        }
+    }
+
+    #[inline]
+    pub fn clipboard_mime_types(&self, kind: ClipboardKind) -> Vec<String> {
+        match self {
+            &Window2::X(ref w) => w.clipboard_mime_types(kind),
+            &Window2::Wayland(ref w) => w.clipboard_mime_types(kind)
+        }
+    }
+
+    #[inline]
+    pub fn get_clipboard(&self, kind: ClipboardKind, mime_type: &str) -> Option<Vec<u8>> {
+        match self {
+            &Window2::X(ref w) => w.get_clipboard(kind, mime_type),
+            &Window2::Wayland(ref w) => w.get_clipboard(kind, mime_type)
+        }
+    }
+
+    #[inline]
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        match self {
+            &Window2::X(ref w) => w.set_ime_position(x, y),
+            &Window2::Wayland(ref w) => w.set_ime_position(x, y)
+        }
+    }
+
+    #[inline]
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        match self {
+            &Window2::X(ref w) => w.set_ime_allowed(allowed),
+            &Window2::Wayland(ref w) => w.set_ime_allowed(allowed)
+        }
+    }
+
+    #[inline]
+    pub fn get_pressed_keys(&self) -> Vec<::ScanCode> {
+        match self {
+            &Window2::X(ref w) => w.get_pressed_keys(),
+            &Window2::Wayland(ref w) => w.get_pressed_keys()
+        }
+    }
+
+    #[inline]
+    pub fn get_pressed_mouse_buttons(&self) -> Vec<::MouseButton> {
+        match self {
+            &Window2::X(ref w) => w.get_pressed_mouse_buttons(),
+            &Window2::Wayland(ref w) => w.get_pressed_mouse_buttons()
+        }
+    }
+
+    #[inline]
+    pub fn start_drag(&self, data: DragData, allowed_actions: &[DragAction]) -> Result<(), String> {
+        match self {
+            &Window2::X(ref w) => w.start_drag(data, allowed_actions),
+            &Window2::Wayland(ref w) => w.start_drag(data, allowed_actions)
+        }
+    }
+
     #[inline]
     pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
         match self {
@@ -325,6 +487,49 @@ impl EventsLoop {
         }
     }
 
+    pub fn key_repeat_info(&self) -> Option<::KeyRepeatInfo> {
+        match *self {
+            EventsLoop::Wayland(ref evlp) => evlp.key_repeat_info(),
+            EventsLoop::X(_) => match *UNIX_BACKEND {
+                UnixBackend::X(ref connec) => x11::key_repeat_info(connec),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn keyboard_layouts(&self) -> Option<::KeyboardLayouts> {
+        match *self {
+            EventsLoop::Wayland(ref evlp) => evlp.keyboard_layouts(),
+            EventsLoop::X(_) => match *UNIX_BACKEND {
+                UnixBackend::X(ref connec) => {
+                    connec.keyboard.lock().unwrap().as_ref().map(|keyboard| keyboard.layouts())
+                },
+                _ => None,
+            },
+        }
+    }
+
+    pub fn set_clipboard(&self, kind: ClipboardKind, contents: ClipboardContents) -> Result<(), String> {
+        match *self {
+            EventsLoop::Wayland(ref evlp) => evlp.set_clipboard(kind, contents),
+            EventsLoop::X(ref evlp) => evlp.set_clipboard(kind, contents)
+        }
+    }
+
+    pub fn clipboard_mime_types(&self, kind: ClipboardKind) -> Vec<String> {
+        match *self {
+            EventsLoop::Wayland(ref evlp) => evlp.clipboard_mime_types(kind),
+            EventsLoop::X(ref evlp) => evlp.clipboard_mime_types(kind)
+        }
+    }
+
+    pub fn get_clipboard(&self, kind: ClipboardKind, mime_type: &str) -> Option<Vec<u8>> {
+        match *self {
+            EventsLoop::Wayland(ref evlp) => evlp.get_clipboard(kind, mime_type),
+            EventsLoop::X(ref evlp) => evlp.get_clipboard(kind, mime_type)
+        }
+    }
+
     pub fn poll_events<F>(&self, callback: F)
         where F: FnMut(::Event)
     {
@@ -0,0 +1,86 @@
+//! Decoding of the `text/uri-list` format, used by drag-and-drop on X11 and Wayland to transfer
+//! file paths.
+
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+/// Returns the paths of the local files in a list of URIs. Other URIs are ignored.
+pub fn parse_uri_list(data: &[u8]) -> Vec<PathBuf> {
+    data.split(|&b| b == b'\n')
+        .map(|line| if line.ends_with(b"\r") { &line[.. line.len() - 1] } else { line })
+        // lines starting with a `#` are comments
+        .filter(|line| !line.is_empty() && line[0] != b'#')
+        .filter_map(file_uri_path)
+        .collect()
+}
+
+/// Returns the path of a `file://` URI, if the file is on this machine.
+fn file_uri_path(uri: &[u8]) -> Option<PathBuf> {
+    const SCHEME: &'static [u8] = b"file://";
+    if !uri.starts_with(SCHEME) {
+        return None;
+    }
+
+    // the host is usually empty, or the name of this machine
+    let rest = &uri[SCHEME.len() ..];
+    let path = match rest.iter().position(|&b| b == b'/') {
+        Some(start) => &rest[start ..],
+        None => return None,
+    };
+    Some(PathBuf::from(OsStr::from_bytes(&percent_decode(path))))
+}
+
+fn percent_decode(data: &[u8]) -> Vec<u8> {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+
+    let mut result = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] == b'%' && i + 2 < data.len() {
+            if let (Some(high), Some(low)) = (hex(data[i + 1]), hex(data[i + 2])) {
+                result.push(high << 4 | low);
+                i += 3;
+                continue;
+            }
+        }
+        result.push(data[i]);
+        i += 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{parse_uri_list, percent_decode};
+
+    #[test]
+    fn local_files() {
+        let data = b"file:///tmp/a%20b.txt\r\n# a comment\r\nhttp://example.com/c\r\n\
+                     file://localhost/home/user/d\r\nfile://nohost\r\n\r\n";
+        assert_eq!(parse_uri_list(data),
+                   vec![PathBuf::from("/tmp/a b.txt"), PathBuf::from("/home/user/d")]);
+    }
+
+    #[test]
+    fn last_line_without_newline() {
+        assert_eq!(parse_uri_list(b"file:///tmp/a\nfile:///tmp/b"),
+                   vec![PathBuf::from("/tmp/a"), PathBuf::from("/tmp/b")]);
+    }
+
+    #[test]
+    fn percent_escapes() {
+        assert_eq!(percent_decode(b"/caf%C3%A9"), "/café".as_bytes());
+        assert_eq!(percent_decode(b"%2f%2F"), b"//");
+        assert_eq!(percent_decode(b"%41"), b"A");
+    }
+
+    #[test]
+    fn invalid_percent_escapes() {
+        assert_eq!(percent_decode(b"100%"), b"100%");
+        assert_eq!(percent_decode(b"%4"), b"%4");
+        assert_eq!(percent_decode(b"%zz%"), b"%zz%");
+    }
+}
@@ -0,0 +1,165 @@
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::thread;
+
+use libc;
+
+use wayland_client::protocol::wl_data_offer;
+
+use {TEXT_MIME_TYPE, TEXT_UTF8_MIME_TYPE};
+
+use super::protocols::primary_selection_v1::client::zwp_primary_selection_offer_v1;
+
+/// How long we wait for the source of a selection to write some data before giving up.
+const TRANSFER_TIMEOUT_MS: libc::c_int = 1000;
+
+/// The offers through which other clients give the clipboard, the primary selection and the
+/// data dragged over our windows.
+pub trait OfferProxy {
+    /// Asks the source to write the data in the given MIME type in a file descriptor.
+    fn receive_into(&self, mime_type: String, fd: RawFd);
+}
+
+impl OfferProxy for wl_data_offer::WlDataOffer {
+    fn receive_into(&self, mime_type: String, fd: RawFd) {
+        self.receive(mime_type, fd);
+    }
+}
+
+impl OfferProxy for zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1 {
+    fn receive_into(&self, mime_type: String, fd: RawFd) {
+        self.receive(mime_type, fd);
+    }
+}
+
+/// Data offered by another client, with the MIME types it is available in.
+pub struct DataOffer<O = wl_data_offer::WlDataOffer> {
+    pub offer: O,
+    pub mime_types: Vec<String>,
+}
+
+impl<O: OfferProxy> DataOffer<O> {
+    pub fn new(offer: O) -> DataOffer<O> {
+        DataOffer {
+            offer: offer,
+            mime_types: Vec::new(),
+        }
+    }
+
+    /// Returns the MIME types of the offer, with the X11 names of text formats that some
+    /// clients use translated.
+    pub fn normalized_mime_types(&self) -> Vec<String> {
+        let mut mime_types: Vec<String> = Vec::new();
+        for mime in &self.mime_types {
+            let mime = match &mime[..] {
+                "UTF8_STRING" => TEXT_UTF8_MIME_TYPE.to_owned(),
+                "STRING" | "TEXT" => TEXT_MIME_TYPE.to_owned(),
+                mime if mime.contains('/') => mime.to_owned(),
+                _ => continue,
+            };
+            if !mime_types.contains(&mime) {
+                mime_types.push(mime);
+            }
+        }
+        mime_types
+    }
+
+    /// Returns the MIME type of the offer to request to get data of the given MIME type.
+    pub fn offered_mime_type(&self, mime_type: &str) -> Option<String> {
+        let aliases: &[&str] = if mime_type == TEXT_UTF8_MIME_TYPE {
+            &["UTF8_STRING"]
+        } else if mime_type == TEXT_MIME_TYPE {
+            &["STRING", "TEXT"]
+        } else {
+            &[]
+        };
+        Some(mime_type).into_iter().chain(aliases.iter().cloned())
+                       .find(|mime| self.mime_types.iter().any(|offered| offered == mime))
+                       .map(|mime| mime.to_owned())
+    }
+
+    /// Creates the pipe through which the source writes the data, and returns its reading end.
+    pub fn receive(&self, mime_type: String) -> Option<RawFd> {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        self.offer.receive_into(mime_type, fds[1]);
+        // the compositor has its own copy of the writing end once the request is sent, and the
+        // end of file is only reached once all the copies are closed
+        unsafe { libc::close(fds[1]) };
+        Some(fds[0])
+    }
+}
+
+/// Reads everything that the source writes in a pipe, then closes it.
+pub fn read_pipe(fd: RawFd) -> Option<Vec<u8>> {
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    let mut data = Vec::new();
+    let mut buffer = [0; 4096];
+
+    loop {
+        // don't block forever on a source that never answers
+        let mut pollfd = libc::pollfd { fd: fd, events: libc::POLLIN, revents: 0 };
+        if unsafe { libc::poll(&mut pollfd, 1, TRANSFER_TIMEOUT_MS) } <= 0 {
+            return None;
+        }
+        match file.read(&mut buffer) {
+            Ok(0) => return Some(data),
+            Ok(count) => data.extend_from_slice(&buffer[..count]),
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Reads what a source writes in a pipe as it arrives, without blocking, so that the events loop
+/// can wait for it along with the wayland events.
+pub struct PipeReader {
+    file: File,
+    data: Vec<u8>,
+}
+
+impl PipeReader {
+    pub fn new(fd: RawFd) -> PipeReader {
+        unsafe {
+            let flags = libc::fcntl(fd, libc::F_GETFL);
+            libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+        }
+        PipeReader {
+            file: unsafe { File::from_raw_fd(fd) },
+            data: Vec::new(),
+        }
+    }
+
+    pub fn fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+
+    /// Reads what the source has written so far, and returns all the data once it closed the
+    /// pipe. The pipe is closed when the reader is dropped.
+    pub fn read_available(&mut self) -> Option<Vec<u8>> {
+        let mut buffer = [0; 4096];
+        loop {
+            match self.file.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(count) => self.data.extend_from_slice(&buffer[..count]),
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => return None,
+                // what was read before the error is all the source will give
+                Err(_) => break,
+            }
+        }
+        Some(mem::replace(&mut self.data, Vec::new()))
+    }
+}
+
+/// Writes some data in a pipe from a background thread, so that the reader can't block us, and
+/// closes it.
+pub fn write_pipe(fd: RawFd, data: Vec<u8>) {
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    thread::spawn(move || {
+        let _ = file.write_all(&data);
+    });
+}
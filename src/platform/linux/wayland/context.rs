@@ -1,11 +1,20 @@
+use std::cmp;
 use std::collections::VecDeque;
+use std::os::unix::io::RawFd;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use libc;
 
 use wayland_client::{EnvHandler, default_connect, EventQueue, EventQueueHandle, Init, Proxy};
-use wayland_client::protocol::{wl_compositor, wl_seat, wl_shell, wl_shm, wl_subcompositor,
+use wayland_client::cursor::is_available as cursor_theme_available;
+use wayland_client::protocol::{wl_compositor, wl_seat, wl_shell, wl_shm, wl_shm_pool, wl_subcompositor,
                                wl_display, wl_registry, wl_output, wl_surface};
 
+use wayland_sys::client::WAYLAND_CLIENT_HANDLE;
+
 use super::wayland_window;
+use super::CursorTheme;
 
 /*
  * Registry and globals handling
@@ -67,6 +76,17 @@ impl WaylandEnv {
         }
         None
     }
+
+    // returns the global with the version it was bound with
+    fn get_optional_global<P: Proxy>(&self) -> Option<(P, u32)> {
+        for &(name, ref interface, version) in self.inner.globals() {
+            if interface == P::interface_name() {
+                let version = cmp::min(version, P::supported_version());
+                return Some((self.registry.bind::<P>(version, name), version))
+            }
+        }
+        None
+    }
 }
 
 impl Init for WaylandEnv {
@@ -158,6 +178,8 @@ pub struct WaylandContext {
     pub display: wl_display::WlDisplay,
     evq: Mutex<EventQueue>,
     env_id: usize,
+    // loaded on first use, and again when the scale of the outputs changes
+    cursor_theme: Mutex<Option<Arc<CursorTheme>>>,
 }
 
 impl WaylandContext {
@@ -179,7 +201,8 @@ impl WaylandContext {
         Some(WaylandContext {
             evq: Mutex::new(event_queue),
             display: display,
-            env_id: env_id
+            env_id: env_id,
+            cursor_theme: Mutex::new(None),
         })
     }
 
@@ -193,6 +216,41 @@ impl WaylandContext {
         guard.dispatch().expect("Wayland connection unexpectedly lost");
     }
 
+    /// Same as `dispatch`, but also stops waiting for events when one of the file descriptors
+    /// can be read, and gives up after the timeout if there is one.
+    pub fn dispatch_timeout(&self, timeout: Option<Duration>, fds: &[RawFd]) {
+        if timeout.is_none() && fds.is_empty() {
+            return self.dispatch();
+        }
+        let mut guard = self.evq.lock().unwrap();
+        if guard.dispatch_pending().expect("Wayland connection unexpectedly lost") > 0 {
+            return;
+        }
+        if let Some(read_guard) = guard.prepare_read() {
+            self.flush();
+            let display_fd = unsafe {
+                ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_get_fd, self.display.ptr() as *mut _)
+            };
+            let mut pollfds: Vec<libc::pollfd> = Some(display_fd).into_iter().chain(fds.iter().cloned())
+                .map(|fd| libc::pollfd { fd: fd, events: libc::POLLIN, revents: 0 })
+                .collect();
+            // rounded up, so that the deadline has passed when the poll times out
+            let millis = match timeout {
+                Some(timeout) => {
+                    let millis = timeout.as_secs() * 1000 + (timeout.subsec_nanos() as u64 + 999_999) / 1_000_000;
+                    cmp::min(millis, libc::c_int::max_value() as u64) as libc::c_int
+                },
+                None => -1,
+            };
+            let ready = unsafe { libc::poll(pollfds.as_mut_ptr(), pollfds.len() as libc::nfds_t, millis) };
+            if ready > 0 && pollfds[0].revents != 0 {
+                read_guard.read_events().expect("Wayland connection unexpectedly lost");
+            }
+            // otherwise dropping the guard cancels the read
+        }
+        guard.dispatch_pending().expect("Wayland connection unexpectedly lost");
+    }
+
     pub fn flush(&self) {
         let _ = self.display.flush();
     }
@@ -203,6 +261,19 @@ impl WaylandContext {
         state.get_handler::<WaylandEnv>(self.env_id).get_seat()
     }
 
+    /// Binds a global that the compositor may not provide, like the ones of unstable protocols.
+    pub fn get_optional_global<P: Proxy>(&self) -> Option<P> {
+        self.get_optional_global_with_version().map(|(global, _)| global)
+    }
+
+    /// Same as `get_optional_global`, but also returns the version of the global, which tells
+    /// which requests and events are available.
+    pub fn get_optional_global_with_version<P: Proxy>(&self) -> Option<(P, u32)> {
+        let mut guard = self.evq.lock().unwrap();
+        let state = guard.state();
+        state.get_handler::<WaylandEnv>(self.env_id).get_optional_global()
+    }
+
     pub fn with_output<F>(&self, id: MonitorId, f: F) where F: FnOnce(&wl_output::WlOutput) {
         let mut guard = self.evq.lock().unwrap();
         let state = guard.state();
@@ -213,6 +284,59 @@ impl WaylandContext {
         }
     }
 
+    pub fn create_surface(&self) -> wl_surface::WlSurface {
+        let mut guard = self.evq.lock().unwrap();
+        let state = guard.state();
+        let env = state.get_handler::<WaylandEnv>(self.env_id);
+        env.inner.compositor.create_surface()
+    }
+
+    pub fn create_shm_pool(&self, fd: RawFd, size: i32) -> wl_shm_pool::WlShmPool {
+        let mut guard = self.evq.lock().unwrap();
+        let state = guard.state();
+        let env = state.get_handler::<WaylandEnv>(self.env_id);
+        env.inner.shm.create_pool(fd, size)
+    }
+
+    /// Returns the largest scale factor of the outputs, which is used for the cursors since
+    /// the pointer can move to any of them.
+    pub fn max_output_scale(&self) -> i32 {
+        let mut guard = self.evq.lock().unwrap();
+        let state = guard.state();
+        let env = state.get_handler::<WaylandEnv>(self.env_id);
+        env.monitors.iter().map(|m| m.scale as i32).max().unwrap_or(1)
+    }
+
+    /// Returns the size of the first output in surface coordinates, `None` if its mode is not
+    /// known yet.
+    pub fn primary_output_size(&self) -> Option<(u32, u32)> {
+        let mut guard = self.evq.lock().unwrap();
+        let state = guard.state();
+        let env = state.get_handler::<WaylandEnv>(self.env_id);
+        env.monitors.iter().next()
+            .filter(|m| m.pix_size.0 > 0 && m.pix_size.1 > 0)
+            .map(|m| {
+                let scale = if m.scale > 0.0 { m.scale } else { 1.0 };
+                ((m.pix_size.0 as f32 / scale) as u32, (m.pix_size.1 as f32 / scale) as u32)
+            })
+    }
+
+    /// Returns the cursor theme of the user, or `None` if libwayland-cursor is not available.
+    pub fn cursor_theme(&self) -> Option<Arc<CursorTheme>> {
+        if !cursor_theme_available() {
+            return None;
+        }
+        let scale = self.max_output_scale();
+        let mut theme = self.cursor_theme.lock().unwrap();
+        if theme.as_ref().map(|theme| theme.scale()) != Some(scale) {
+            let mut guard = self.evq.lock().unwrap();
+            let state = guard.state();
+            let env = state.get_handler::<WaylandEnv>(self.env_id);
+            *theme = Some(Arc::new(CursorTheme::load(&env.inner.shm, scale)));
+        }
+        theme.clone()
+    }
+
     pub fn create_window<H: wayland_window::Handler>(&self)
         -> (Arc<wl_surface::WlSurface>, wayland_window::DecoratedSurface<H>)
     {
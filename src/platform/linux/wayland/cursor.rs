@@ -0,0 +1,261 @@
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::{cmp, mem};
+use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use wayland_client::Proxy;
+use wayland_client::cursor::{self as wl_cursor, load_theme};
+use wayland_client::protocol::{wl_buffer, wl_shm, wl_shm_pool, wl_surface};
+
+use {CursorImage, MouseCursor};
+
+use super::WaylandContext;
+use super::tempfile::tempfile;
+
+/// Returns the size of the cursors in pixels, as configured by `XCURSOR_SIZE`.
+pub fn cursor_size() -> u32 {
+    env::var("XCURSOR_SIZE").ok().and_then(|size| size.parse().ok()).unwrap_or(24)
+}
+
+/// Returns the names under which the cursor themes may provide a cursor, best looking first.
+pub fn cursor_names(cursor: MouseCursor) -> &'static [&'static str] {
+    match cursor {
+        MouseCursor::Alias => &["link", "alias"],
+        MouseCursor::Arrow => &["arrow", "left_ptr"],
+        MouseCursor::Cell => &["cell", "plus"],
+        MouseCursor::Copy => &["copy"],
+        MouseCursor::Crosshair => &["crosshair"],
+        MouseCursor::Default => &["left_ptr", "default"],
+        MouseCursor::Hand => &["hand2", "hand1", "pointer"],
+        MouseCursor::Help => &["question_arrow", "help"],
+        MouseCursor::Move => &["move", "fleur"],
+        MouseCursor::Grab => &["openhand", "grab"],
+        MouseCursor::Grabbing => &["closedhand", "grabbing"],
+        MouseCursor::Progress => &["left_ptr_watch", "progress"],
+        MouseCursor::AllScroll => &["all-scroll", "fleur"],
+        MouseCursor::ContextMenu => &["context-menu"],
+
+        MouseCursor::NoDrop => &["no-drop", "circle"],
+        MouseCursor::NotAllowed => &["crossed_circle", "not-allowed"],
+
+        MouseCursor::EResize => &["right_side", "e-resize"],
+        MouseCursor::NResize => &["top_side", "n-resize"],
+        MouseCursor::NeResize => &["top_right_corner", "ne-resize"],
+        MouseCursor::NwResize => &["top_left_corner", "nw-resize"],
+        MouseCursor::SResize => &["bottom_side", "s-resize"],
+        MouseCursor::SeResize => &["bottom_right_corner", "se-resize"],
+        MouseCursor::SwResize => &["bottom_left_corner", "sw-resize"],
+        MouseCursor::WResize => &["left_side", "w-resize"],
+        MouseCursor::EwResize => &["h_double_arrow", "ew-resize"],
+        MouseCursor::NsResize => &["v_double_arrow", "ns-resize"],
+        MouseCursor::NwseResize => &["bd_double_arrow", "size_bdiag", "nwse-resize"],
+        MouseCursor::NeswResize => &["fd_double_arrow", "size_fdiag", "nesw-resize"],
+        MouseCursor::ColResize => &["split_h", "h_double_arrow", "col-resize"],
+        MouseCursor::RowResize => &["split_v", "v_double_arrow", "row-resize"],
+
+        MouseCursor::Text => &["text", "xterm"],
+        MouseCursor::VerticalText => &["vertical-text"],
+
+        MouseCursor::Wait => &["watch", "wait"],
+
+        MouseCursor::ZoomIn => &["zoom-in"],
+        MouseCursor::ZoomOut => &["zoom-out"],
+
+        MouseCursor::NoneCursor => &[],
+    }
+}
+
+/// The XCursor theme of the user, as configured by `XCURSOR_THEME` and `XCURSOR_SIZE`.
+pub struct CursorTheme {
+    theme: Mutex<wl_cursor::CursorTheme>,
+    scale: i32,
+}
+
+impl CursorTheme {
+    /// Loads the theme with images large enough for outputs of the given scale.
+    pub fn load(shm: &wl_shm::WlShm, scale: i32) -> CursorTheme {
+        let name = env::var("XCURSOR_THEME").ok();
+        let theme = load_theme(name.as_ref().map(|name| &name[..]), cursor_size() * scale as u32, shm);
+        CursorTheme {
+            theme: Mutex::new(theme),
+            scale: scale,
+        }
+    }
+
+    #[inline]
+    pub fn scale(&self) -> i32 {
+        self.scale
+    }
+
+    /// Returns the frames of the first of these cursors that the theme provides.
+    pub fn get_cursor(theme: &Arc<CursorTheme>, names: &[&str]) -> Option<Vec<CursorBuffer>> {
+        let guard = theme.theme.lock().unwrap();
+        let cursor = match names.iter().filter_map(|name| guard.get_cursor(name)).next() {
+            Some(cursor) => cursor,
+            None => return None,
+        };
+
+        let mut frames = Vec::with_capacity(cursor.image_count());
+        for frame in 0..cursor.image_count() {
+            let (buffer, (width, height, hx, hy, delay)) =
+                match (cursor.frame_buffer(frame), cursor.frame_info(frame)) {
+                    (Some(buffer), Some(info)) => (buffer, info),
+                    _ => return None,
+                };
+            frames.push(CursorBuffer {
+                // the buffer belongs to the theme, which is kept alive as long as it is used
+                buffer: unsafe { wl_buffer::WlBuffer::from_ptr_initialized(buffer.ptr()) },
+                storage: BufferStorage::Theme(theme.clone()),
+                width: width as i32,
+                height: height as i32,
+                hotspot: (hx as i32, hy as i32),
+                delay: delay,
+                scale: theme.scale,
+            });
+        }
+        Some(frames)
+    }
+}
+
+/// The image of a cursor, stored in a buffer of the compositor.
+pub struct CursorBuffer {
+    buffer: wl_buffer::WlBuffer,
+    storage: BufferStorage,
+    width: i32,
+    height: i32,
+    hotspot: (i32, i32),
+    delay: u32,
+    scale: i32,
+}
+
+enum BufferStorage {
+    // a custom image, in shared memory we allocated
+    Shm { pool: wl_shm_pool::WlShmPool, _file: File },
+    // an image of the theme, which owns the buffer
+    Theme(Arc<CursorTheme>),
+}
+
+impl CursorBuffer {
+    /// Copies the image of a custom cursor, to be displayed on outputs of the given scale.
+    ///
+    /// The image is shrunk by the scale if its dimensions can be divided by it and if it
+    /// stays at least as large as the cursor size, and by a smaller scale otherwise.
+    pub fn new(ctxt: &WaylandContext, image: &CursorImage, scale: i32) -> Option<CursorBuffer> {
+        let (width, height) = (image.image().width(), image.image().height());
+        let scale = (1..scale as u32 + 1).rev()
+            .find(|&s| width % s == 0 && height % s == 0 && width / s >= cursor_size())
+            .unwrap_or(1) as i32;
+
+        // wl_shm only guarantees the support of ARGB, as premultiplied native endian words
+        let mut pixels = Vec::with_capacity(image.image().rgba().len());
+        for px in image.image().rgba().chunks(4) {
+            let a = px[3] as u32;
+            let premultiply = |c: u8| c as u32 * a / 255;
+            let argb = a << 24 | premultiply(px[0]) << 16 | premultiply(px[1]) << 8 | premultiply(px[2]);
+            let bytes: [u8; 4] = unsafe { mem::transmute(argb) };
+            pixels.extend_from_slice(&bytes);
+        }
+
+        let mut file = match tempfile() {
+            Ok(file) => file,
+            Err(_) => return None,
+        };
+        if file.write_all(&pixels).and_then(|_| file.flush()).is_err() {
+            return None;
+        }
+
+        let pool = ctxt.create_shm_pool(file.as_raw_fd(), pixels.len() as i32);
+        let buffer = pool.create_buffer(0, width as i32, height as i32, width as i32 * 4,
+                                        wl_shm::Format::Argb8888)
+                         .expect("Pool was destroyed!");
+
+        Some(CursorBuffer {
+            buffer: buffer,
+            storage: BufferStorage::Shm { pool: pool, _file: file },
+            width: width as i32,
+            height: height as i32,
+            hotspot: (image.hotspot().0 as i32, image.hotspot().1 as i32),
+            delay: image.delay(),
+            scale: scale,
+        })
+    }
+
+    /// Displays the image on a cursor surface. `(x, y)` is the position of the new image
+    /// relative to the previous one, in surface coordinates.
+    pub fn attach_to(&self, surface: &wl_surface::WlSurface, x: i32, y: i32) {
+        surface.set_buffer_scale(self.scale);
+        surface.attach(Some(&self.buffer), x, y);
+        surface.damage(0, 0, self.width / self.scale, self.height / self.scale);
+        surface.commit();
+    }
+
+    /// Returns the hotspot in surface coordinates.
+    #[inline]
+    pub fn hotspot(&self) -> (i32, i32) {
+        (self.hotspot.0 / self.scale, self.hotspot.1 / self.scale)
+    }
+
+    #[inline]
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+}
+
+impl Drop for CursorBuffer {
+    fn drop(&mut self) {
+        if let BufferStorage::Shm { ref pool, .. } = self.storage {
+            self.buffer.destroy();
+            pool.destroy();
+        }
+    }
+}
+
+/// Cycles through the frames of an animated cursor on a cursor surface, as the events loop
+/// reaches the deadline of each frame.
+pub struct CursorAnimation {
+    frames: Arc<Vec<CursorBuffer>>,
+    current: usize,
+    next: Instant,
+}
+
+impl CursorAnimation {
+    /// Starts animating a cursor surface, which must be displaying the first frame.
+    pub fn start(frames: Arc<Vec<CursorBuffer>>) -> CursorAnimation {
+        let next = Instant::now() + frame_delay(&frames[0]);
+        CursorAnimation {
+            frames: frames,
+            current: 0,
+            next: next,
+        }
+    }
+
+    /// Returns when the next frame is due.
+    #[inline]
+    pub fn deadline(&self) -> Instant {
+        self.next
+    }
+
+    /// Displays the next frame on the cursor surface, if it is due.
+    pub fn animate(&mut self, surface: &wl_surface::WlSurface) {
+        let now = Instant::now();
+        if self.next > now {
+            return;
+        }
+        let next = (self.current + 1) % self.frames.len();
+        // keep the hotspot of the new frame at the position of the pointer
+        let (hx, hy) = self.frames[self.current].hotspot();
+        let (next_hx, next_hy) = self.frames[next].hotspot();
+        self.frames[next].attach_to(surface, hx - next_hx, hy - next_hy);
+        self.current = next;
+        // don't try to catch up with the frames missed while the loop was busy
+        self.next = now + frame_delay(&self.frames[next]);
+    }
+}
+
+fn frame_delay(frame: &CursorBuffer) -> Duration {
+    // some cursor files have frames without delay, don't spin on them
+    Duration::from_millis(cmp::max(frame.delay(), 10) as u64)
+}
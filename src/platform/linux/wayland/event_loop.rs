@@ -1,38 +1,54 @@
-use {WindowEvent as Event, ElementState, MouseButton, MouseScrollDelta, TouchPhase, ModifiersState};
+use {WindowEvent as Event, ClipboardContents, ClipboardKind, CursorState, DragAction, DragResult, ElementState, Ime,
+     KeyboardInput, KeyboardLayouts, KeyRepeatInfo, MouseButton, MouseCursor, MouseInput, MouseScrollDelta,
+     Touch, TouchPhase, ModifiersState, ScanCode, URI_LIST_MIME_TYPE};
 
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{Arc, Mutex};
+use std::os::unix::io::RawFd;
+use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
 
-use super::{DecoratedHandler, WindowId, WaylandContext};
+use super::{CursorAnimation, CursorBuffer, CursorTheme, DataOffer, DecoratedHandler, WindowId, WaylandContext};
+use super::cursor::cursor_names;
 
 
 use wayland_client::{EventQueue, EventQueueHandle, Init, Proxy};
-use wayland_client::protocol::{wl_seat, wl_surface, wl_pointer, wl_keyboard};
+use wayland_client::protocol::{wl_seat, wl_surface, wl_pointer, wl_keyboard, wl_touch, wl_data_device,
+                               wl_data_device_manager, wl_data_offer, wl_data_source};
+use wayland_protocols::unstable::pointer_constraints::v1::client::{zwp_pointer_constraints_v1,
+                                                                   zwp_confined_pointer_v1,
+                                                                   zwp_locked_pointer_v1};
+use wayland_protocols::unstable::relative_pointer::v1::client::{zwp_relative_pointer_manager_v1,
+                                                                zwp_relative_pointer_v1};
 
 use super::make_wid;
+use super::protocols::text_input_v3::client::{zwp_text_input_manager_v3, zwp_text_input_v3};
+use super::protocols::primary_selection_v1::client::{zwp_primary_selection_device_manager_v1,
+                                                     zwp_primary_selection_device_v1,
+                                                     zwp_primary_selection_offer_v1,
+                                                     zwp_primary_selection_source_v1};
 use super::wayland_window::DecoratedSurface;
 use super::wayland_kbd::MappedKeyboard;
 use super::keyboard::KbdHandler;
+use super::clipboard::{read_pipe, write_pipe, PipeReader};
+use super::super::evdev::{code_to_mouse_button, code_to_physical_key};
+use super::super::uri_list::parse_uri_list;
 
-/// This struct is used as a holder for the callback
+/// This struct is used as a holder for the events produced
 /// during the dispatching of events.
 ///
-/// The proper ay to use it is:
-/// - set a callback in it (and retrieve the noop one it contains)
-/// - dispatch the EventQueue
-/// - put back the noop callback in it
-///
-/// Failure to do so is unsafe™
+/// They are only given to the callback of the events loop once
+/// the EventQueue is released, so that the callback can use the
+/// windows, which need the EventQueue too.
 pub struct EventsLoopSink {
-    callback: Box<FnMut(::Event)>
+    events: VecDeque<::Event>
 }
 
-unsafe impl Send for EventsLoopSink { }
-
 impl EventsLoopSink {
     pub fn new() -> EventsLoopSink {
         EventsLoopSink {
-            callback: Box::new(|_| {}),
+            events: VecDeque::new(),
         }
     }
 
@@ -41,20 +57,11 @@ impl EventsLoopSink {
             event: evt,
             window_id: ::WindowId(::platform::WindowId::Wayland(wid))
         };
-        (self.callback)(evt)
-    }
-
-    // This function is only safe of the set callback is unset before exclusive
-    // access to the wayland EventQueue is finished.
-    //
-    // The callback also cannot be used any longer as long as it has not been
-    // cleared from the Sink.
-    unsafe fn set_callback(&mut self, cb: Box<FnMut(::Event)>) -> Box<FnMut(::Event)> {
-        ::std::mem::replace(&mut self.callback, cb)
+        self.events.push_back(evt)
     }
 
-    fn with_callback<F: FnOnce(&mut FnMut(::Event))>(&mut self, f: F) {
-        f(&mut *self.callback)
+    fn take_events(&mut self) -> VecDeque<::Event> {
+        ::std::mem::replace(&mut self.events, VecDeque::new())
     }
 }
 
@@ -65,7 +72,7 @@ pub struct EventsLoop {
     evq: Arc<Mutex<EventQueue>>,
     // ids of the DecoratedHandlers of the surfaces we know
     decorated_ids: Mutex<Vec<(usize, Arc<wl_surface::WlSurface>)>>,
-    // our sink, receiver of callbacks, shared with some handlers
+    // our sink, receiver of the events of the handlers, shared with some of them
     sink: Arc<Mutex<EventsLoopSink>>,
     // trigger interruption of the run
     interrupted: AtomicBool,
@@ -91,122 +98,238 @@ impl EventsLoop {
     }
 
     // some internals that Window needs access to
-    pub fn get_window_init(&self) -> (Arc<Mutex<EventQueue>>, Arc<AtomicBool>) {
-        (self.evq.clone(), self.cleanup_needed.clone())
+    pub fn get_window_init(&self) -> (Arc<Mutex<EventQueue>>, Arc<AtomicBool>, usize) {
+        (self.evq.clone(), self.cleanup_needed.clone(), self.hid)
     }
 
-    pub fn register_window(&self, decorated_id: usize, surface: Arc<wl_surface::WlSurface>) {
+    pub fn register_window(&self, decorated_id: usize, surface: Arc<wl_surface::WlSurface>, multitouch: bool) {
         self.decorated_ids.lock().unwrap().push((decorated_id, surface.clone()));
         let mut guard = self.evq.lock().unwrap();
         let mut state = guard.state();
-        state.get_mut_handler::<InputHandler>(self.hid).windows.push(surface);
+        let handler = state.get_mut_handler::<InputHandler>(self.hid);
+        if multitouch {
+            handler.multitouch_windows.insert(make_wid(&surface));
+        }
+        handler.windows.push(surface);
     }
 
-    fn process_resize(evq: &mut EventQueue, ids: &[(usize, Arc<wl_surface::WlSurface>)], callback: &mut FnMut(::Event))
+    fn process_resize(evq: &mut EventQueue, ids: &[(usize, Arc<wl_surface::WlSurface>)], sink: &mut EventsLoopSink)
     {
         let mut state = evq.state();
         for &(decorated_id, ref window) in ids {
             let decorated = state.get_mut_handler::<DecoratedSurface<DecoratedHandler>>(decorated_id);
             if let Some((w, h)) = decorated.handler().as_mut().and_then(|h| h.take_newsize()) {
                 decorated.resize(w as i32, h as i32);
-                callback(
-                    ::Event::WindowEvent {
-                        window_id: ::WindowId(::platform::WindowId::Wayland(make_wid(&window))),
-                        event: ::WindowEvent::Resized(w,h)
-                    }
-                );
+                sink.send_event(::WindowEvent::Resized(w,h), make_wid(&window));
             }
         }
     }
 
+    fn process_deferred_events(evq: &mut EventQueue, hid: usize, sink: &mut EventsLoopSink) {
+        let mut state = evq.state();
+        let handler = state.get_mut_handler::<InputHandler>(hid);
+        handler.reload_theme_cursors();
+        handler.animate_cursor();
+        handler.read_dnd_offer();
+        if let KbdType::Mapped(ref mut h) = handler.kbd_handler {
+            handler.deferred_events.extend(h.handler().take_repeats());
+        }
+        for (event, wid) in handler.deferred_events.drain(..) {
+            sink.send_event(event, wid);
+        }
+    }
+
+    // dispatches the events read from the socket to our handlers, and queues the events they
+    // produce in the sink
+    fn dispatch(&self, evq_guard: &mut EventQueue) {
+        evq_guard.dispatch_pending().expect("Wayland connection unexpectedly lost");
+
+        let ids_guard = self.decorated_ids.lock().unwrap();
+        let mut sink_guard = self.sink.lock().unwrap();
+        Self::process_resize(evq_guard, &ids_guard, &mut sink_guard);
+        Self::process_deferred_events(evq_guard, self.hid, &mut sink_guard);
+    }
+
+    // gives the queued events to the callback, without holding any lock so that it can use the
+    // windows and the events loop
+    fn deliver_events<F>(&self, callback: &mut F)
+        where F: FnMut(::Event)
+    {
+        let events = self.sink.lock().unwrap().take_events();
+        for event in events {
+            callback(event);
+        }
+    }
+
+    // how long the dispatch may wait for events before a timer of the input handler is due, and
+    // the pipes it reads, whose data also ends the wait
+    fn dispatch_timeout(evq: &mut EventQueue, hid: usize) -> (Option<Duration>, Vec<RawFd>) {
+        let mut state = evq.state();
+        let handler = state.get_mut_handler::<InputHandler>(hid);
+        let timeout = handler.next_deadline().map(|deadline| {
+            let now = Instant::now();
+            if deadline > now { deadline - now } else { Duration::from_millis(0) }
+        });
+        (timeout, handler.pending_fds())
+    }
+
+    pub fn keyboard_layouts(&self) -> Option<KeyboardLayouts> {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        match state.get_mut_handler::<InputHandler>(self.hid).kbd_handler {
+            KbdType::Mapped(ref mut h) => h.handler().keyboard_layouts(),
+            KbdType::Plain(_) => None,
+        }
+    }
+
+    pub fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        match state.get_mut_handler::<InputHandler>(self.hid).kbd_handler {
+            KbdType::Mapped(ref mut h) => h.handler().key_repeat_info(),
+            KbdType::Plain(_) => None,
+        }
+    }
+
     pub fn interrupt(&self) {
         self.interrupted.store(true, ::std::sync::atomic::Ordering::Relaxed);
     }
 
+    pub fn set_clipboard(&self, kind: ClipboardKind, contents: ClipboardContents) -> Result<(), String> {
+        set_clipboard(&self.ctxt, &self.evq, self.hid, kind, contents)
+    }
+
+    pub fn clipboard_mime_types(&self, kind: ClipboardKind) -> Vec<String> {
+        clipboard_mime_types(&self.evq, self.hid, kind)
+    }
+
+    pub fn get_clipboard(&self, kind: ClipboardKind, mime_type: &str) -> Option<Vec<u8>> {
+        get_clipboard(&self.ctxt, &self.evq, self.hid, kind, mime_type)
+    }
+
     fn prune_dead_windows(&self) {
         self.decorated_ids.lock().unwrap().retain(|&(_, ref w)| w.is_alive());
         let mut evq_guard = self.evq.lock().unwrap();
         let mut state = evq_guard.state();
         let handler = state.get_mut_handler::<InputHandler>(self.hid);
         handler.windows.retain(|w| w.is_alive());
+        let alive: Vec<WindowId> = handler.windows.iter().map(|w| make_wid(w)).collect();
+        handler.cursors.retain(|wid, _| alive.contains(wid));
+        handler.theme_cursors.retain(|wid, _| alive.contains(wid));
+        handler.hidden_cursors.retain(|wid| alive.contains(wid));
+        handler.ime_allowed.retain(|wid| alive.contains(wid));
+        handler.ime_positions.retain(|wid, _| alive.contains(wid));
+        handler.multitouch_windows.retain(|wid| alive.contains(wid));
+        handler.touch_points.retain(|point| alive.contains(&point.window));
+        handler.touch_events.retain(|&(_, ref wid)| alive.contains(wid));
+        let dead: Vec<WindowId> = handler.constrained_pointers.keys().filter(|wid| !alive.contains(wid)).cloned().collect();
+        for wid in dead {
+            handler.constrained_pointers.remove(&wid).unwrap().destroy();
+        }
         if let Some(w) = handler.mouse_focus.take() {
             if w.is_alive() {
                 handler.mouse_focus = Some(w)
+            } else {
+                handler.cursor_animation = None;
             }
         }
     }
 
-    pub fn poll_events<F>(&self, callback: F)
+    pub fn poll_events<F>(&self, mut callback: F)
         where F: FnMut(::Event)
     {
         // send pending requests to the server...
         self.ctxt.flush();
 
-        // first of all, get exclusive access to this event queue
-        let mut evq_guard = self.evq.lock().unwrap();
-
-        // read some events from the socket if some are waiting & queue is empty
-        if let Some(guard) = evq_guard.prepare_read() {
-            guard.read_events().expect("Wayland connection unexpectedly lost");
-        }
-
-        // set the callback into the sink
-        // we extend the lifetime of the closure to 'static to be able to put it in
-        // the sink, but we'll explicitly drop it at the end of this function, so it's fine
-        let static_cb = unsafe { ::std::mem::transmute(Box::new(callback) as Box<FnMut(_)>) };
-        let old_cb = unsafe { self.sink.lock().unwrap().set_callback(static_cb) };
-
-        // then do the actual dispatching
-        self.ctxt.dispatch_pending();
-        evq_guard.dispatch_pending().expect("Wayland connection unexpectedly lost");
-
-        let mut sink_guard = self.sink.lock().unwrap();
+        {
+            // first of all, get exclusive access to this event queue
+            let mut evq_guard = self.evq.lock().unwrap();
 
-        // events where probably dispatched, process resize
-        let ids_guard = self.decorated_ids.lock().unwrap();
-        sink_guard.with_callback(
-            |cb| Self::process_resize(&mut evq_guard, &ids_guard, cb)
-        );
+            // read some events from the socket if some are waiting & queue is empty
+            if let Some(guard) = evq_guard.prepare_read() {
+                guard.read_events().expect("Wayland connection unexpectedly lost");
+            }
 
-        // replace the old noop callback
-        unsafe { self.sink.lock().unwrap().set_callback(old_cb) };
+            // then do the actual dispatching
+            self.ctxt.dispatch_pending();
+            self.dispatch(&mut evq_guard);
+        }
 
         if self.cleanup_needed.swap(false, ::std::sync::atomic::Ordering::Relaxed) {
             self.prune_dead_windows()
         }
+
+        self.deliver_events(&mut callback);
     }
 
-    pub fn run_forever<F>(&self, callback: F)
+    pub fn run_forever<F>(&self, mut callback: F)
         where F: FnMut(::Event)
     {
         // send pending requests to the server...
         self.ctxt.flush();
 
-        // first of all, get exclusive access to this event queue
-        let mut evq_guard = self.evq.lock().unwrap();
-
-        // set the callback into the sink
-        // we extend the lifetime of the closure to 'static to be able to put it in
-        // the sink, but we'll explicitly drop it at the end of this function, so it's fine
-        let static_cb = unsafe { ::std::mem::transmute(Box::new(callback) as Box<FnMut(_)>) };
-        let old_cb = unsafe { self.sink.lock().unwrap().set_callback(static_cb) };
-
         while !self.interrupted.load(::std::sync::atomic::Ordering::Relaxed) {
-            self.ctxt.dispatch();
-            evq_guard.dispatch_pending().expect("Wayland connection unexpectedly lost");
-            let ids_guard = self.decorated_ids.lock().unwrap();
-            self.sink.lock().unwrap().with_callback(
-                |cb| Self::process_resize(&mut evq_guard, &ids_guard, cb)
-            );
+            // the event queue is not held while waiting, the windows may use it meanwhile
+            let (timeout, fds) = Self::dispatch_timeout(&mut self.evq.lock().unwrap(), self.hid);
+            self.ctxt.dispatch_timeout(timeout, &fds);
+            self.dispatch(&mut self.evq.lock().unwrap());
             self.ctxt.flush();
 
             if self.cleanup_needed.swap(false, ::std::sync::atomic::Ordering::Relaxed) {
                 self.prune_dead_windows()
             }
+
+            self.deliver_events(&mut callback);
         }
+    }
+}
 
-        // replace the old noop callback
-        unsafe { self.sink.lock().unwrap().set_callback(old_cb) };
+// The clipboards are shared by the events loop and its windows, through its input handler.
+
+pub fn set_clipboard(ctxt: &WaylandContext, evq: &Mutex<EventQueue>, hid: usize, kind: ClipboardKind,
+                     contents: ClipboardContents) -> Result<(), String>
+{
+    {
+        let mut guard = evq.lock().unwrap();
+        match kind {
+            ClipboardKind::Clipboard => {
+                let source = try!(guard.state().get_handler::<InputHandler>(hid).create_data_source());
+                guard.register::<_, InputHandler>(&source, hid);
+                guard.state().get_mut_handler::<InputHandler>(hid).set_selection(source, contents);
+            },
+            ClipboardKind::Primary => {
+                let source = try!(guard.state().get_handler::<InputHandler>(hid).create_primary_source());
+                guard.register::<_, InputHandler>(&source, hid);
+                guard.state().get_mut_handler::<InputHandler>(hid).set_primary_selection(source, contents);
+            },
+        }
+    }
+    ctxt.flush();
+    Ok(())
+}
+
+pub fn clipboard_mime_types(evq: &Mutex<EventQueue>, hid: usize, kind: ClipboardKind) -> Vec<String> {
+    let mut guard = evq.lock().unwrap();
+    let state = guard.state();
+    state.get_handler::<InputHandler>(hid).selection_mime_types(kind)
+}
+
+pub fn get_clipboard(ctxt: &WaylandContext, evq: &Mutex<EventQueue>, hid: usize, kind: ClipboardKind,
+                     mime_type: &str) -> Option<Vec<u8>>
+{
+    let received = {
+        let mut guard = evq.lock().unwrap();
+        let state = guard.state();
+        state.get_handler::<InputHandler>(hid).receive_selection(kind, mime_type)
+    };
+    match received {
+        Some(Ok(data)) => Some(data),
+        Some(Err(fd)) => {
+            // the source writes the data once it gets the request
+            ctxt.flush();
+            read_pipe(fd)
+        },
+        None => None,
     }
 }
 
@@ -215,23 +338,149 @@ enum KbdType {
     Plain(Option<WindowId>)
 }
 
-struct InputHandler {
+// A constraint of the pointer while it is over a window
+enum PointerConstraint {
+    Locked(zwp_locked_pointer_v1::ZwpLockedPointerV1),
+    Confined(zwp_confined_pointer_v1::ZwpConfinedPointerV1),
+}
+
+impl PointerConstraint {
+    fn destroy(self) {
+        match self {
+            PointerConstraint::Locked(locked_pointer) => locked_pointer.destroy(),
+            PointerConstraint::Confined(confined_pointer) => confined_pointer.destroy(),
+        }
+    }
+}
+
+pub struct InputHandler {
     my_id: usize,
     windows: Vec<Arc<wl_surface::WlSurface>>,
     seat: Option<wl_seat::WlSeat>,
     mouse: Option<wl_pointer::WlPointer>,
     mouse_focus: Option<Arc<wl_surface::WlSurface>>,
     mouse_location: (i32, i32),
+    // the buttons held down since the pointer entered the focused window
+    pressed_buttons: Vec<MouseButton>,
+    // serial of the last pointer enter event, needed to change the cursor
+    mouse_enter_serial: u32,
+    // the surface displaying the cursor image
+    cursor_surface: Arc<wl_surface::WlSurface>,
+    // the frames of the cursors of the windows, an empty cursor is invisible
+    cursors: HashMap<WindowId, Arc<Vec<CursorBuffer>>>,
+    // the cursor of the windows that didn't set one
+    default_cursor: Option<Arc<Vec<CursorBuffer>>>,
+    // the windows that use a cursor of the theme, whose frames are in `cursors`
+    theme_cursors: HashMap<WindowId, MouseCursor>,
+    // the scale of the outputs when the cursors of the theme were loaded
+    cursor_scale: i32,
+    // the windows that hide the cursor with `CursorState::Hide`
+    hidden_cursors: HashSet<WindowId>,
+    pointer_constraints: Option<zwp_pointer_constraints_v1::ZwpPointerConstraintsV1>,
+    relative_pointer_manager: Option<zwp_relative_pointer_manager_v1::ZwpRelativePointerManagerV1>,
+    relative_pointer: Option<zwp_relative_pointer_v1::ZwpRelativePointerV1>,
+    // the windows that lock or confine the pointer
+    constrained_pointers: HashMap<WindowId, PointerConstraint>,
+    // the animation of the displayed cursor, if it has several frames
+    cursor_animation: Option<CursorAnimation>,
+    data_device_manager: Option<wl_data_device_manager::WlDataDeviceManager>,
+    data_device_manager_version: u32,
+    data_device: Option<wl_data_device::WlDataDevice>,
+    // the offers announced by the data device, until it tells what they are for
+    data_offers: Vec<DataOffer>,
+    // the offer of the current contents of the clipboard, if another client owns it
+    selection_offer: Option<DataOffer>,
+    // the source through which we serve our contents of the clipboard
+    selection_source: Option<(wl_data_source::WlDataSource, ClipboardContents)>,
+    primary_selection_manager: Option<zwp_primary_selection_device_manager_v1::ZwpPrimarySelectionDeviceManagerV1>,
+    primary_selection_device: Option<zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1>,
+    // the offers announced by the primary selection device, until it tells which one is current
+    primary_offers: Vec<DataOffer<zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1>>,
+    // the offer of the current contents of the primary selection, if another client owns it
+    primary_selection_offer: Option<DataOffer<zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1>>,
+    // the source through which we serve our contents of the primary selection
+    primary_selection_source: Option<(zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1,
+                                      ClipboardContents)>,
+    // serial of the last input event, needed to take the clipboard
+    last_input_serial: u32,
+    // the offer of the files dragged over one of our windows
+    dnd_offer: Option<DndOffer>,
+    // the source of the data dragged out of one of our windows
+    drag_source: Option<DragSource>,
+    text_input_manager: Option<zwp_text_input_manager_v3::ZwpTextInputManagerV3>,
+    text_input: Option<zwp_text_input_v3::ZwpTextInputV3>,
+    // the window that has the focus of the text input
+    text_input_focus: Option<WindowId>,
+    // the number of `commit` requests sent to the text input, which its `done` events echo
+    text_input_commits: u32,
+    // the windows that allow the input method
+    ime_allowed: HashSet<WindowId>,
+    // where the windows want the candidate window of the input method
+    ime_positions: HashMap<WindowId, (i32, i32)>,
+    // the changes of the composition announced by the text input until its `done` event
+    pending_preedit: Option<(String, Option<(usize, usize)>)>,
+    pending_commit: Option<String>,
+    // whether the application displays some text being composed
+    preedit_shown: bool,
+    // events caused by requests of the windows, emitted after the next dispatch
+    deferred_events: Vec<(Event, WindowId)>,
+    ctxt: Arc<WaylandContext>,
     axis_buffer: Option<(f32, f32)>,
     axis_discrete_buffer: Option<(i32, i32)>,
     axis_state: TouchPhase,
     kbd: Option<wl_keyboard::WlKeyboard>,
     kbd_handler: KbdType,
+    touch: Option<wl_touch::WlTouch>,
+    // the touch points held down, on the window they went down on until they go up
+    touch_points: Vec<TouchPoint>,
+    // the touch events until the `frame` event that ends their group
+    touch_events: Vec<(Event, WindowId)>,
+    // the windows created with `with_multitouch`, the other ones get a single touch point
+    multitouch_windows: HashSet<WindowId>,
     callback: Arc<Mutex<EventsLoopSink>>
 }
 
+// A touch point held down on a window
+struct TouchPoint {
+    id: i32,
+    window: WindowId,
+    location: (f64, f64),
+}
+
+// Files dragged over a window
+struct DndOffer {
+    offer: DataOffer,
+    window: WindowId,
+    // the list of the files while the source writes it, they are hovering the window once it
+    // is read
+    pipe: Option<PipeReader>,
+    paths: Vec<PathBuf>,
+    // whether the files were dropped, which is reported once their list is read
+    dropped: bool,
+}
+
+// Data dragged out of a window
+struct DragSource {
+    source: wl_data_source::WlDataSource,
+    contents: ClipboardContents,
+    window: WindowId,
+    // the action chosen by the target, with version 3 of the data device manager
+    action: DragAction,
+    // whether the target requested the data, the only sign of a drop before version 3
+    data_requested: bool,
+}
+
 impl InputHandler {
-    fn new(ctxt: &WaylandContext, sink: Arc<Mutex<EventsLoopSink>>) -> InputHandler {
+    fn new(ctxt: &Arc<WaylandContext>, sink: Arc<Mutex<EventsLoopSink>>) -> InputHandler {
+        let default_cursor = ctxt.cursor_theme().and_then(|theme| {
+            CursorTheme::get_cursor(&theme, &["left_ptr", "default"])
+        });
+        let cursor_scale = ctxt.max_output_scale();
+        let (data_device_manager, data_device_manager_version) =
+            match ctxt.get_optional_global_with_version() {
+                Some((manager, version)) => (Some(manager), version),
+                None => (None, 0),
+            };
         let kbd_handler = match MappedKeyboard::new(KbdHandler::new(sink.clone())) {
             Ok(h) => KbdType::Mapped(h),
             Err(_) => KbdType::Plain(None)
@@ -243,20 +492,577 @@ impl InputHandler {
             mouse: None,
             mouse_focus: None,
             mouse_location: (0,0),
+            pressed_buttons: Vec::new(),
+            mouse_enter_serial: 0,
+            cursor_surface: Arc::new(ctxt.create_surface()),
+            cursors: HashMap::new(),
+            default_cursor: default_cursor.map(Arc::new),
+            theme_cursors: HashMap::new(),
+            cursor_scale: cursor_scale,
+            hidden_cursors: HashSet::new(),
+            pointer_constraints: ctxt.get_optional_global(),
+            relative_pointer_manager: ctxt.get_optional_global(),
+            relative_pointer: None,
+            constrained_pointers: HashMap::new(),
+            cursor_animation: None,
+            data_device_manager: data_device_manager,
+            data_device_manager_version: data_device_manager_version,
+            data_device: None,
+            data_offers: Vec::new(),
+            selection_offer: None,
+            selection_source: None,
+            primary_selection_manager: ctxt.get_optional_global(),
+            primary_selection_device: None,
+            primary_offers: Vec::new(),
+            primary_selection_offer: None,
+            primary_selection_source: None,
+            last_input_serial: 0,
+            dnd_offer: None,
+            drag_source: None,
+            text_input_manager: ctxt.get_optional_global(),
+            text_input: None,
+            text_input_focus: None,
+            text_input_commits: 0,
+            ime_allowed: HashSet::new(),
+            ime_positions: HashMap::new(),
+            pending_preedit: None,
+            pending_commit: None,
+            preedit_shown: false,
+            deferred_events: Vec::new(),
+            ctxt: ctxt.clone(),
             axis_buffer: None,
             axis_discrete_buffer: None,
             axis_state: TouchPhase::Started,
             kbd: None,
             kbd_handler: kbd_handler,
+            touch: None,
+            touch_points: Vec::new(),
+            touch_events: Vec::new(),
+            multitouch_windows: HashSet::new(),
             callback: sink
         }
     }
 }
 
+impl InputHandler {
+    /// Returns the keys held down while the window has the keyboard focus.
+    pub fn get_pressed_keys(&mut self, wid: WindowId) -> Vec<ScanCode> {
+        match self.kbd_handler {
+            KbdType::Mapped(ref mut h) => h.handler().get_pressed_keys(wid),
+            KbdType::Plain(_) => Vec::new(),
+        }
+    }
+
+    /// Returns the modifiers of the keyboard, which are unknown while none of the windows has the
+    /// keyboard focus.
+    fn keyboard_modifiers(&mut self) -> ModifiersState {
+        if let KbdType::Mapped(ref mut h) = self.kbd_handler {
+            let h = h.handler();
+            if h.target.is_some() {
+                return h.current_modifiers();
+            }
+        }
+        ModifiersState::default()
+    }
+
+    /// Returns when the events loop has to wake up for the timers of the handler, if it has any.
+    fn next_deadline(&mut self) -> Option<Instant> {
+        let animation = self.cursor_animation.as_ref().map(|animation| animation.deadline());
+        let repeat = match self.kbd_handler {
+            KbdType::Mapped(ref mut h) => h.handler().next_repeat(),
+            KbdType::Plain(_) => None,
+        };
+        match (animation, repeat) {
+            (Some(a), Some(r)) => Some(::std::cmp::min(a, r)),
+            (a, r) => a.or(r),
+        }
+    }
+
+    /// Shows the next frame of the animated cursor, if it is due.
+    fn animate_cursor(&mut self) {
+        if let Some(ref mut animation) = self.cursor_animation {
+            animation.animate(&self.cursor_surface);
+        }
+    }
+
+    /// Sends the pending touch events, then cancels the touch points held down.
+    fn cancel_touch_points(&mut self) {
+        let mut guard = self.callback.lock().unwrap();
+        for (event, wid) in self.touch_events.drain(..) {
+            guard.send_event(event, wid);
+        }
+        for point in self.touch_points.drain(..) {
+            guard.send_event(Event::Touch(Touch {
+                phase: TouchPhase::Cancelled,
+                location: point.location,
+                id: point.id as u64,
+            }), point.window);
+        }
+    }
+
+    /// Returns the buttons held down while the pointer is over the window.
+    pub fn get_pressed_mouse_buttons(&self, wid: WindowId) -> Vec<MouseButton> {
+        if self.mouse_focus.as_ref().map(|w| make_wid(w)) != Some(wid) {
+            return Vec::new();
+        }
+        self.pressed_buttons.clone()
+    }
+
+    /// Sets the cursor of a window, with all the frames of its animation, or goes back to the
+    /// default cursor.
+    pub fn set_cursor(&mut self, wid: WindowId, cursor: Option<Vec<CursorBuffer>>) {
+        self.theme_cursors.remove(&wid);
+        self.set_cursor_frames(wid, cursor);
+    }
+
+    /// Sets one of the cursors of the theme on a window, which is loaded again when the scale
+    /// of the outputs changes.
+    pub fn set_theme_cursor(&mut self, wid: WindowId, cursor: MouseCursor) {
+        self.theme_cursors.insert(wid, cursor);
+        let frames = match cursor {
+            MouseCursor::NoneCursor => Some(Vec::new()),
+            cursor => self.load_theme_cursor(cursor_names(cursor)),
+        };
+        self.set_cursor_frames(wid, frames);
+    }
+
+    fn set_cursor_frames(&mut self, wid: WindowId, cursor: Option<Vec<CursorBuffer>>) {
+        match cursor {
+            Some(cursor) => { self.cursors.insert(wid, Arc::new(cursor)); },
+            None => { self.cursors.remove(&wid); },
+        }
+        if self.mouse_focus.as_ref().map(|w| make_wid(w)) == Some(wid) {
+            self.apply_cursor();
+        }
+    }
+
+    /// Returns the frames of the first of these cursors that the theme provides, at the scale
+    /// of the outputs.
+    fn load_theme_cursor(&self, names: &[&str]) -> Option<Vec<CursorBuffer>> {
+        self.ctxt.cursor_theme().and_then(|theme| CursorTheme::get_cursor(&theme, names))
+    }
+
+    /// Loads the cursors of the theme again if the scale of the outputs changed since they were
+    /// loaded, so that they keep their size and stay sharp.
+    fn reload_theme_cursors(&mut self) {
+        let scale = self.ctxt.max_output_scale();
+        if scale == self.cursor_scale {
+            return;
+        }
+        self.cursor_scale = scale;
+        self.default_cursor = self.load_theme_cursor(&["left_ptr", "default"]).map(Arc::new);
+        let theme_cursors: Vec<(WindowId, MouseCursor)> =
+            self.theme_cursors.iter().map(|(&wid, &cursor)| (wid, cursor)).collect();
+        for (wid, cursor) in theme_cursors {
+            if cursor == MouseCursor::NoneCursor {
+                continue;
+            }
+            match self.load_theme_cursor(cursor_names(cursor)) {
+                Some(frames) => { self.cursors.insert(wid, Arc::new(frames)); },
+                None => { self.cursors.remove(&wid); },
+            }
+        }
+        self.apply_cursor();
+    }
+
+    /// Hides or shows the cursor of a window.
+    pub fn set_cursor_hidden(&mut self, wid: WindowId, hidden: bool) {
+        if hidden {
+            self.hidden_cursors.insert(wid);
+        } else {
+            self.hidden_cursors.remove(&wid);
+        }
+        if self.mouse_focus.as_ref().map(|w| make_wid(w)) == Some(wid) {
+            self.apply_cursor();
+        }
+    }
+
+    /// Locks or confines the pointer on a window for the `Locked` and `Confined` cursor states,
+    /// and removes the constraint for the others. The constraint applies whenever the pointer
+    /// is over the window, until it is removed.
+    pub fn set_pointer_constraint(&mut self, wid: WindowId, state: CursorState) -> Result<(), String> {
+        let unchanged = match (self.constrained_pointers.get(&wid), state) {
+            (Some(&PointerConstraint::Locked(_)), CursorState::Locked) |
+            (Some(&PointerConstraint::Confined(_)), CursorState::Confined) => true,
+            _ => false,
+        };
+        if unchanged {
+            return Ok(());
+        }
+        // a window can only have one constraint at a time
+        if let Some(constraint) = self.constrained_pointers.remove(&wid) {
+            constraint.destroy();
+        }
+        if state != CursorState::Locked && state != CursorState::Confined {
+            return Ok(());
+        }
+
+        let (constraints, pointer) = match (self.pointer_constraints.as_ref(), self.mouse.as_ref()) {
+            (Some(constraints), Some(pointer)) => (constraints, pointer),
+            (None, _) => return Err("The compositor doesn't support pointer constraints.".to_string()),
+            (_, None) => return Err("There is no pointer to constrain.".to_string()),
+        };
+        if state == CursorState::Locked && self.relative_pointer.is_none() {
+            return Err("The compositor doesn't support relative pointer motion.".to_string());
+        }
+        let surface = match self.windows.iter().find(|w| make_wid(w) == wid) {
+            Some(surface) => surface,
+            None => return Err("The window is not alive.".to_string()),
+        };
+
+        let lifetime = zwp_pointer_constraints_v1::Lifetime::Persistent;
+        let constraint = if state == CursorState::Locked {
+            PointerConstraint::Locked(constraints.lock_pointer(surface, pointer, None, lifetime)
+                                                 .expect("Pointer constraints were destroyed!"))
+        } else {
+            PointerConstraint::Confined(constraints.confine_pointer(surface, pointer, None, lifetime)
+                                                   .expect("Pointer constraints were destroyed!"))
+        };
+        self.constrained_pointers.insert(wid, constraint);
+        Ok(())
+    }
+
+    /// Tells where the cursor should appear once a locked pointer is unlocked.
+    pub fn set_cursor_position_hint(&self, wid: WindowId, x: i32, y: i32) -> Result<(), ()> {
+        match self.constrained_pointers.get(&wid) {
+            Some(&PointerConstraint::Locked(ref locked_pointer)) => {
+                // applied on the next commit of the window
+                locked_pointer.set_cursor_position_hint(x as f64, y as f64);
+                Ok(())
+            },
+            _ => Err(())
+        }
+    }
+
+    /// Creates a data source to take the clipboard with `set_selection`. It must be registered
+    /// to this handler first.
+    pub fn create_data_source(&self) -> Result<wl_data_source::WlDataSource, String> {
+        match (self.data_device_manager.as_ref(), self.data_device.as_ref()) {
+            (Some(manager), Some(_)) => Ok(manager.create_data_source()),
+            _ => Err("The compositor doesn't support the clipboard.".to_string()),
+        }
+    }
+
+    /// Creates a source to take the primary selection with `set_primary_selection`. It must be
+    /// registered to this handler first.
+    pub fn create_primary_source(&self)
+        -> Result<zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1, String>
+    {
+        match (self.primary_selection_manager.as_ref(), self.primary_selection_device.as_ref()) {
+            (Some(manager), Some(_)) => {
+                Ok(manager.create_source().expect("Primary selection manager was destroyed!"))
+            },
+            _ => Err("The compositor doesn't support the primary selection.".to_string()),
+        }
+    }
+
+    /// Takes the primary selection, and serves its contents from the source.
+    pub fn set_primary_selection(&mut self, source: zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1,
+                                 contents: ClipboardContents)
+    {
+        for mime in contents.mime_types() {
+            source.offer(mime.to_owned());
+        }
+        if let Some(ref device) = self.primary_selection_device {
+            device.set_selection(Some(&source), self.last_input_serial);
+        }
+        if let Some((old_source, _)) = self.primary_selection_source.take() {
+            old_source.destroy();
+        }
+        self.primary_selection_source = Some((source, contents));
+    }
+
+    /// Takes the clipboard, and serves its contents from the source.
+    pub fn set_selection(&mut self, source: wl_data_source::WlDataSource, contents: ClipboardContents) {
+        for mime in contents.mime_types() {
+            source.offer(mime.to_owned());
+        }
+        if let Some(ref data_device) = self.data_device {
+            data_device.set_selection(Some(&source), self.last_input_serial);
+        }
+        if let Some((old_source, _)) = self.selection_source.take() {
+            old_source.destroy();
+        }
+        self.selection_source = Some((source, contents));
+    }
+
+    /// Starts dragging data out of a window, served from the source. The source must be
+    /// registered to this handler first.
+    pub fn start_drag(&mut self, source: wl_data_source::WlDataSource, surface: &wl_surface::WlSurface,
+                      contents: ClipboardContents, allowed_actions: &[DragAction])
+    {
+        for mime in contents.mime_types() {
+            source.offer(mime.to_owned());
+        }
+        if self.data_device_manager_version >= 3 {
+            let mut actions = wl_data_device_manager::DndAction::empty();
+            for &action in allowed_actions {
+                actions = actions | dnd_action(action);
+            }
+            source.set_actions(actions);
+        }
+        if let Some(ref data_device) = self.data_device {
+            data_device.start_drag(Some(&source), surface, None, self.last_input_serial);
+        }
+        if let Some(old_drag) = self.drag_source.take() {
+            old_drag.source.destroy();
+            let result = self.unfinished_drag_result(&old_drag);
+            self.deferred_events.push((Event::DragFinished(result), old_drag.window));
+        }
+        self.drag_source = Some(DragSource {
+            source: source,
+            contents: contents,
+            window: make_wid(surface),
+            action: allowed_actions[0],
+            data_requested: false,
+        });
+    }
+
+    // The result of a drag whose source is cancelled or replaced before the target tells that
+    // the drop is done, which it can only do from version 3
+    fn unfinished_drag_result(&self, drag: &DragSource) -> DragResult {
+        if self.data_device_manager_version < 3 && drag.data_requested {
+            DragResult::Completed(DragAction::Copy)
+        } else {
+            DragResult::Cancelled
+        }
+    }
+
+    // Ends the drag of the given source, if it is still ours
+    fn finish_drag(&mut self, proxy: &wl_data_source::WlDataSource, result: DragResult) -> bool {
+        let finished = match self.drag_source {
+            Some(ref drag) => drag.source.equals(proxy),
+            None => false,
+        };
+        if finished {
+            let drag = self.drag_source.take().unwrap();
+            drag.source.destroy();
+            self.callback.lock().unwrap().send_event(Event::DragFinished(result), drag.window);
+        }
+        finished
+    }
+
+    /// Allows or forbids the input method in a window.
+    pub fn set_ime_allowed(&mut self, wid: WindowId, allowed: bool) {
+        let changed = if allowed {
+            self.ime_allowed.insert(wid)
+        } else {
+            self.ime_allowed.remove(&wid)
+        };
+        if changed && self.text_input_focus == Some(wid) {
+            let events = if allowed {
+                self.enable_text_input(wid)
+            } else {
+                self.disable_text_input()
+            };
+            self.deferred_events.extend(events.into_iter().map(|event| (event, wid)));
+        }
+    }
+
+    /// Tells where a window wants the candidate window of the input method.
+    pub fn set_ime_position(&mut self, wid: WindowId, x: i32, y: i32) {
+        self.ime_positions.insert(wid, (x, y));
+        if self.text_input_focus == Some(wid) && self.ime_allowed.contains(&wid) {
+            if let Some(ref text_input) = self.text_input {
+                text_input.set_cursor_rectangle(x, y, 0, 0);
+            }
+            self.commit_text_input();
+        }
+    }
+
+    // Applies the pending state of the text input, counting the commits to recognize the `done`
+    // events that answer the last one
+    fn commit_text_input(&mut self) {
+        if let Some(ref text_input) = self.text_input {
+            text_input.commit();
+            self.text_input_commits = self.text_input_commits.wrapping_add(1);
+        }
+    }
+
+    // Enables the input method for the window that has the focus of the text input, which shows
+    // the virtual keyboard on touch devices
+    fn enable_text_input(&mut self, wid: WindowId) -> Vec<Event> {
+        {
+            let text_input = match self.text_input {
+                Some(ref text_input) => text_input,
+                None => return Vec::new(),
+            };
+            text_input.enable();
+            if let Some(&(x, y)) = self.ime_positions.get(&wid) {
+                text_input.set_cursor_rectangle(x, y, 0, 0);
+            }
+        }
+        self.commit_text_input();
+        vec![Event::Ime(Ime::Enabled)]
+    }
+
+    fn disable_text_input(&mut self) -> Vec<Event> {
+        {
+            let text_input = match self.text_input {
+                Some(ref text_input) => text_input,
+                None => return Vec::new(),
+            };
+            text_input.disable();
+        }
+        self.commit_text_input();
+
+        let mut events = Vec::new();
+        if self.preedit_shown {
+            events.push(Event::Ime(Ime::Preedit { text: String::new(), cursor_range: None }));
+            self.preedit_shown = false;
+        }
+        self.pending_preedit = None;
+        self.pending_commit = None;
+        events.push(Event::Ime(Ime::Disabled));
+        events
+    }
+
+    // Returns our own contents of a clipboard, if we own it
+    fn own_selection(&self, kind: ClipboardKind) -> Option<&ClipboardContents> {
+        match kind {
+            ClipboardKind::Clipboard => self.selection_source.as_ref().map(|&(_, ref contents)| contents),
+            ClipboardKind::Primary => self.primary_selection_source.as_ref().map(|&(_, ref contents)| contents),
+        }
+    }
+
+    /// Returns the MIME types of the contents of a clipboard.
+    pub fn selection_mime_types(&self, kind: ClipboardKind) -> Vec<String> {
+        if let Some(contents) = self.own_selection(kind) {
+            return contents.mime_types().into_iter().map(|mime| mime.to_owned()).collect();
+        }
+        let mime_types = match kind {
+            ClipboardKind::Clipboard => self.selection_offer.as_ref().map(|offer| offer.normalized_mime_types()),
+            ClipboardKind::Primary => {
+                self.primary_selection_offer.as_ref().map(|offer| offer.normalized_mime_types())
+            },
+        };
+        mime_types.unwrap_or(Vec::new())
+    }
+
+    /// Returns our own contents of a clipboard if we own it, or asks the owner to write its
+    /// contents in a pipe, whose reading end is returned.
+    pub fn receive_selection(&self, kind: ClipboardKind, mime_type: &str) -> Option<Result<Vec<u8>, RawFd>> {
+        if let Some(contents) = self.own_selection(kind) {
+            return contents.get(mime_type).map(|data| Ok(data.to_vec()));
+        }
+        let fd = match kind {
+            ClipboardKind::Clipboard => self.selection_offer.as_ref().and_then(|offer| {
+                offer.offered_mime_type(mime_type).and_then(|mime| offer.receive(mime))
+            }),
+            ClipboardKind::Primary => self.primary_selection_offer.as_ref().and_then(|offer| {
+                offer.offered_mime_type(mime_type).and_then(|mime| offer.receive(mime))
+            }),
+        };
+        fd.map(Err)
+    }
+
+    // The pipes being read, which the events loop waits for along with the wayland events
+    fn pending_fds(&self) -> Vec<RawFd> {
+        self.dnd_offer.as_ref().and_then(|dnd_offer| dnd_offer.pipe.as_ref()).map(|pipe| pipe.fd())
+                      .into_iter().collect()
+    }
+
+    // Reads the list of the files dragged over a window as it arrives, and reports them once it
+    // is complete
+    fn read_dnd_offer(&mut self) {
+        let data = match self.dnd_offer.as_mut().and_then(|dnd_offer| dnd_offer.pipe.as_mut()) {
+            Some(pipe) => match pipe.read_available() {
+                Some(data) => data,
+                None => return,
+            },
+            None => return,
+        };
+        let dropped = {
+            let dnd_offer = self.dnd_offer.as_mut().unwrap();
+            dnd_offer.pipe = None;
+            dnd_offer.paths = parse_uri_list(&data);
+            for path in &dnd_offer.paths {
+                self.deferred_events.push((Event::HoveredFile(path.clone()), dnd_offer.window));
+            }
+            dnd_offer.dropped
+        };
+        if dropped {
+            self.finish_dnd_offer();
+        }
+    }
+
+    // Reports the files dropped on a window, and tells the source that the drop is done
+    fn finish_dnd_offer(&mut self) {
+        if let Some(dnd_offer) = self.dnd_offer.take() {
+            for path in &dnd_offer.paths {
+                self.deferred_events.push((Event::DroppedFile(path.clone()), dnd_offer.window));
+            }
+            if self.data_device_manager_version >= 3 {
+                dnd_offer.offer.offer.finish();
+            }
+            dnd_offer.offer.offer.destroy();
+        }
+    }
+
+    // Takes an offer announced by the data device. The other ones will never be used.
+    fn take_data_offer(&mut self, id: &wl_data_offer::WlDataOffer) -> Option<DataOffer> {
+        let mut result = None;
+        for offer in self.data_offers.drain(..) {
+            if offer.offer.equals(id) {
+                result = Some(offer);
+            } else {
+                offer.offer.destroy();
+            }
+        }
+        result
+    }
+
+    // Displays the cursor of the window under the pointer
+    fn apply_cursor(&mut self) {
+        self.cursor_animation = None;
+        let (pointer, window) = match (self.mouse.as_ref(), self.mouse_focus.as_ref()) {
+            (Some(pointer), Some(window)) => (pointer, window),
+            _ => return
+        };
+        let wid = make_wid(window);
+        let frames = match self.cursors.get(&wid).or(self.default_cursor.as_ref()) {
+            Some(frames) if !self.hidden_cursors.contains(&wid) => frames,
+            // no cursor theme, keep the cursor of the compositor
+            None if !self.hidden_cursors.contains(&wid) => return,
+            _ => {
+                // a null surface hides the cursor
+                pointer.set_cursor(self.mouse_enter_serial, None, 0, 0);
+                return;
+            }
+        };
+        if frames.is_empty() {
+            pointer.set_cursor(self.mouse_enter_serial, None, 0, 0);
+        } else {
+            let (hx, hy) = frames[0].hotspot();
+            frames[0].attach_to(&self.cursor_surface, 0, 0);
+            pointer.set_cursor(self.mouse_enter_serial, Some(&self.cursor_surface), hx, hy);
+            if frames.len() > 1 {
+                self.cursor_animation = Some(CursorAnimation::start(frames.clone()));
+            }
+        }
+    }
+}
+
 impl Init for InputHandler {
     fn init(&mut self, evqh: &mut EventQueueHandle, index: usize) {
         if let Some(ref seat) = self.seat {
             evqh.register::<_, InputHandler>(seat, index);
+            if let Some(ref manager) = self.data_device_manager {
+                let data_device = manager.get_data_device(seat);
+                evqh.register::<_, InputHandler>(&data_device, index);
+                self.data_device = Some(data_device);
+            }
+            if let Some(ref manager) = self.primary_selection_manager {
+                let device = manager.get_device(seat).expect("Primary selection manager was destroyed!");
+                evqh.register::<_, InputHandler>(&device, index);
+                self.primary_selection_device = Some(device);
+            }
+            if let Some(ref manager) = self.text_input_manager {
+                let text_input = manager.get_text_input(seat).expect("Text input manager was destroyed!");
+                evqh.register::<_, InputHandler>(&text_input, index);
+                self.text_input = Some(text_input);
+                self.text_input_commits = 0;
+            }
         }
         self.my_id = index;
     }
@@ -272,10 +1078,22 @@ impl wl_seat::Handler for InputHandler {
         if capabilities.contains(wl_seat::Pointer) && self.mouse.is_none() {
             let pointer = seat.get_pointer().expect("Seat is not dead");
             evqh.register::<_, InputHandler>(&pointer, self.my_id);
+            if let Some(ref manager) = self.relative_pointer_manager {
+                let relative_pointer = manager.get_relative_pointer(&pointer)
+                                              .expect("Relative pointer manager was destroyed!");
+                evqh.register::<_, InputHandler>(&relative_pointer, self.my_id);
+                self.relative_pointer = Some(relative_pointer);
+            }
             self.mouse = Some(pointer);
         }
         // destroy pointer if applicable
         if !capabilities.contains(wl_seat::Pointer) {
+            for (_, constraint) in self.constrained_pointers.drain() {
+                constraint.destroy();
+            }
+            if let Some(relative_pointer) = self.relative_pointer.take() {
+                relative_pointer.destroy();
+            }
             if let Some(pointer) = self.mouse.take() {
                 pointer.release();
             }
@@ -292,6 +1110,19 @@ impl wl_seat::Handler for InputHandler {
                 kbd.release();
             }
         }
+        // create touch if applicable
+        if capabilities.contains(wl_seat::Touch) && self.touch.is_none() {
+            let touch = seat.get_touch().expect("Seat is not dead");
+            evqh.register::<_, InputHandler>(&touch, self.my_id);
+            self.touch = Some(touch);
+        }
+        // destroy touch if applicable
+        if !capabilities.contains(wl_seat::Touch) {
+            if let Some(touch) = self.touch.take() {
+                self.cancel_touch_points();
+                touch.release();
+            }
+        }
     }
 }
 
@@ -305,12 +1136,13 @@ impl wl_pointer::Handler for InputHandler {
     fn enter(&mut self,
              _evqh: &mut EventQueueHandle,
              _proxy: &wl_pointer::WlPointer,
-             _serial: u32,
+             serial: u32,
              surface: &wl_surface::WlSurface,
              surface_x: f64,
              surface_y: f64)
     {
         self.mouse_location = (surface_x as i32, surface_y as i32);
+        self.mouse_enter_serial = serial;
         for window in &self.windows {
             if window.equals(surface) {
                 self.mouse_focus = Some(window.clone());
@@ -321,6 +1153,7 @@ impl wl_pointer::Handler for InputHandler {
                 break;
             }
         }
+        self.apply_cursor();
     }
 
     fn leave(&mut self,
@@ -330,11 +1163,25 @@ impl wl_pointer::Handler for InputHandler {
              surface: &wl_surface::WlSurface)
     {
         self.mouse_focus = None;
+        self.cursor_animation = None;
+        let modifiers = self.keyboard_modifiers();
         for window in &self.windows {
             if window.equals(surface) {
-                self.callback.lock().unwrap().send_event(Event::MouseLeft, make_wid(window));
+                let mut guard = self.callback.lock().unwrap();
+                // the compositor doesn't send the releases of the buttons held down when the
+                // pointer leaves the surface
+                for &button in &self.pressed_buttons {
+                    guard.send_event(Event::MouseInput(MouseInput {
+                        state: ElementState::Released,
+                        button: button,
+                        modifiers: modifiers,
+                        is_synthetic: true,
+                    }), make_wid(window));
+                }
+                guard.send_event(Event::MouseLeft, make_wid(window));
             }
         }
+        self.pressed_buttons.clear();
     }
 
     fn motion(&mut self,
@@ -354,24 +1201,34 @@ impl wl_pointer::Handler for InputHandler {
     fn button(&mut self,
               _evqh: &mut EventQueueHandle,
               _proxy: &wl_pointer::WlPointer,
-              _serial: u32,
+              serial: u32,
               _time: u32,
               button: u32,
               state: wl_pointer::ButtonState)
     {
+        self.last_input_serial = serial;
+        let modifiers = self.keyboard_modifiers();
         if let Some(ref window) = self.mouse_focus {
             let state = match state {
                 wl_pointer::ButtonState::Pressed => ElementState::Pressed,
                 wl_pointer::ButtonState::Released => ElementState::Released
             };
-            let button = match button {
-                0x110 => MouseButton::Left,
-                0x111 => MouseButton::Right,
-                0x112 => MouseButton::Middle,
-                // TODO figure out the translation ?
-                _ => return
+            let button = match code_to_mouse_button(button) {
+                Some(button) => button,
+                None => return
             };
-            self.callback.lock().unwrap().send_event(Event::MouseInput(state, button), make_wid(window));
+            match state {
+                ElementState::Pressed => if !self.pressed_buttons.contains(&button) {
+                    self.pressed_buttons.push(button);
+                },
+                ElementState::Released => self.pressed_buttons.retain(|&b| b != button),
+            }
+            self.callback.lock().unwrap().send_event(Event::MouseInput(MouseInput {
+                state: state,
+                button: button,
+                modifiers: modifiers,
+                is_synthetic: false,
+            }), make_wid(window));
         }
     }
 
@@ -400,12 +1257,14 @@ impl wl_pointer::Handler for InputHandler {
     {
         let axis_buffer = self.axis_buffer.take();
         let axis_discrete_buffer = self.axis_discrete_buffer.take();
+        let modifiers = self.keyboard_modifiers();
         if let Some(ref window) = self.mouse_focus {
             if let Some((x, y)) = axis_discrete_buffer {
                 self.callback.lock().unwrap().send_event(
                     Event::MouseWheel(
                         MouseScrollDelta::LineDelta(x as f32, y as f32),
-                        self.axis_state
+                        self.axis_state,
+                        modifiers
                     ),
                     make_wid(window)
                 );
@@ -413,7 +1272,8 @@ impl wl_pointer::Handler for InputHandler {
                 self.callback.lock().unwrap().send_event(
                     Event::MouseWheel(
                         MouseScrollDelta::PixelDelta(x as f32, y as f32),
-                        self.axis_state
+                        self.axis_state,
+                        modifiers
                     ),
                     make_wid(window)
                 );
@@ -458,6 +1318,29 @@ impl wl_pointer::Handler for InputHandler {
 
 declare_handler!(InputHandler, wl_pointer::Handler, wl_pointer::WlPointer);
 
+impl zwp_relative_pointer_v1::Handler for InputHandler {
+    fn relative_motion(&mut self,
+                       _evqh: &mut EventQueueHandle,
+                       _proxy: &zwp_relative_pointer_v1::ZwpRelativePointerV1,
+                       _utime_hi: u32,
+                       _utime_lo: u32,
+                       _dx: f64,
+                       _dy: f64,
+                       dx_unaccel: f64,
+                       dy_unaccel: f64)
+    {
+        // the motion is only reported while the pointer is locked
+        if let Some(ref window) = self.mouse_focus {
+            let wid = make_wid(window);
+            if let Some(&PointerConstraint::Locked(_)) = self.constrained_pointers.get(&wid) {
+                self.callback.lock().unwrap().send_event(Event::MouseMotion(dx_unaccel, dy_unaccel), wid);
+            }
+        }
+    }
+}
+
+declare_handler!(InputHandler, zwp_relative_pointer_v1::Handler, zwp_relative_pointer_v1::ZwpRelativePointerV1);
+
 /*
  * Keyboard Handling
  */
@@ -472,7 +1355,10 @@ impl wl_keyboard::Handler for InputHandler {
               size: u32)
     {
         match self.kbd_handler {
-            KbdType::Mapped(ref mut h) => h.keymap(evqh, proxy, format, fd, size),
+            KbdType::Mapped(ref mut h) => {
+                h.handler().set_keymap(fd, size);
+                h.keymap(evqh, proxy, format, fd, size)
+            },
             _ => ()
         }
     }
@@ -484,12 +1370,14 @@ impl wl_keyboard::Handler for InputHandler {
              surface: &wl_surface::WlSurface,
              keys: Vec<u8>)
     {
+        self.last_input_serial = serial;
         for window in &self.windows {
             if window.equals(surface) {
                 self.callback.lock().unwrap().send_event(Event::Focused(true), make_wid(window));
                 match self.kbd_handler {
                     KbdType::Mapped(ref mut h) => {
                         h.handler().target = Some(make_wid(window));
+                        h.handler().set_pressed_keys(&keys);
                         h.enter(evqh, proxy, serial, surface, keys);
                     },
                     KbdType::Plain(ref mut target) => {
@@ -509,10 +1397,15 @@ impl wl_keyboard::Handler for InputHandler {
     {
         for window in &self.windows {
             if window.equals(surface) {
+                // the keys held down are released before the focus is lost
+                if let KbdType::Mapped(ref mut h) = self.kbd_handler {
+                    h.handler().release_pressed_keys();
+                }
                 self.callback.lock().unwrap().send_event(Event::Focused(false), make_wid(window));
                 match self.kbd_handler {
                     KbdType::Mapped(ref mut h) => {
                         h.handler().target = None;
+                        h.handler().reset();
                         h.leave(evqh, proxy, serial, surface);
                     },
                     KbdType::Plain(ref mut target) => {
@@ -532,6 +1425,7 @@ impl wl_keyboard::Handler for InputHandler {
            key: u32,
            state: wl_keyboard::KeyState)
     {
+        self.last_input_serial = serial;
         match self.kbd_handler {
             KbdType::Mapped(ref mut h) => h.key(evqh, proxy, serial, time, key, state),
             KbdType::Plain(Some(wid)) => {
@@ -547,12 +1441,16 @@ impl wl_keyboard::Handler for InputHandler {
                 // anyway, as we need libxkbcommon to interpret it (it is
                 // supposed to be serialized by the compositor using libxkbcommon)
                 self.callback.lock().unwrap().send_event(
-                    Event::KeyboardInput(
-                        state,
-                        key as u8,
-                        None,
-                        ModifiersState::default()
-                    ),
+                    Event::KeyboardInput(KeyboardInput {
+                        state: state,
+                        scancode: key,
+                        physical_key: code_to_physical_key(key),
+                        virtual_keycode: None,
+                        logical_key: None,
+                        modifiers: ModifiersState::default(),
+                        is_repeat: false,
+                        is_synthetic: false,
+                    }),
                     wid
                 );
             },
@@ -570,8 +1468,10 @@ impl wl_keyboard::Handler for InputHandler {
                  group: u32)
     {
         match self.kbd_handler {
-            KbdType::Mapped(ref mut h) => h.modifiers(evqh, proxy, serial, mods_depressed,
-                                                      mods_latched, mods_locked, group),
+            KbdType::Mapped(ref mut h) => {
+                h.modifiers(evqh, proxy, serial, mods_depressed, mods_latched, mods_locked, group);
+                h.handler().set_modifiers(mods_depressed, mods_latched, mods_locked, group);
+            },
             _ => ()
         }
     }
@@ -583,10 +1483,521 @@ impl wl_keyboard::Handler for InputHandler {
                    delay: i32)
     {
         match self.kbd_handler {
-            KbdType::Mapped(ref mut h) => h.repeat_info(evqh, proxy, rate, delay),
+            KbdType::Mapped(ref mut h) => {
+                h.handler().set_repeat_info(rate, delay);
+                h.repeat_info(evqh, proxy, rate, delay)
+            },
             _ => ()
         }
     }
 }
 
 declare_handler!(InputHandler, wl_keyboard::Handler, wl_keyboard::WlKeyboard);
+
+/*
+ * Touch Handling
+ */
+
+impl wl_touch::Handler for InputHandler {
+    fn down(&mut self,
+            _evqh: &mut EventQueueHandle,
+            _proxy: &wl_touch::WlTouch,
+            serial: u32,
+            _time: u32,
+            surface: &wl_surface::WlSurface,
+            id: i32,
+            x: f64,
+            y: f64)
+    {
+        self.last_input_serial = serial;
+        let wid = match self.windows.iter().find(|window| window.equals(surface)) {
+            Some(window) => make_wid(window),
+            None => return
+        };
+        // without multitouch, the touch points that go down after the first one are ignored
+        if !self.multitouch_windows.contains(&wid) && self.touch_points.iter().any(|p| p.window == wid) {
+            return;
+        }
+        self.touch_points.push(TouchPoint {
+            id: id,
+            window: wid,
+            location: (x, y),
+        });
+        self.touch_events.push((Event::Touch(Touch {
+            phase: TouchPhase::Started,
+            location: (x, y),
+            id: id as u64,
+        }), wid));
+    }
+
+    fn up(&mut self,
+          _evqh: &mut EventQueueHandle,
+          _proxy: &wl_touch::WlTouch,
+          serial: u32,
+          _time: u32,
+          id: i32)
+    {
+        self.last_input_serial = serial;
+        if let Some(index) = self.touch_points.iter().position(|p| p.id == id) {
+            let point = self.touch_points.remove(index);
+            self.touch_events.push((Event::Touch(Touch {
+                phase: TouchPhase::Ended,
+                location: point.location,
+                id: id as u64,
+            }), point.window));
+        }
+    }
+
+    fn motion(&mut self,
+              _evqh: &mut EventQueueHandle,
+              _proxy: &wl_touch::WlTouch,
+              _time: u32,
+              id: i32,
+              x: f64,
+              y: f64)
+    {
+        if let Some(point) = self.touch_points.iter_mut().find(|p| p.id == id) {
+            point.location = (x, y);
+            self.touch_events.push((Event::Touch(Touch {
+                phase: TouchPhase::Moved,
+                location: (x, y),
+                id: id as u64,
+            }), point.window));
+        }
+    }
+
+    fn frame(&mut self,
+             _evqh: &mut EventQueueHandle,
+             _proxy: &wl_touch::WlTouch)
+    {
+        let mut guard = self.callback.lock().unwrap();
+        for (event, wid) in self.touch_events.drain(..) {
+            guard.send_event(event, wid);
+        }
+    }
+
+    fn cancel(&mut self,
+              _evqh: &mut EventQueueHandle,
+              _proxy: &wl_touch::WlTouch)
+    {
+        // the compositor took over the touch points, for a gesture of its own
+        self.cancel_touch_points();
+    }
+}
+
+declare_handler!(InputHandler, wl_touch::Handler, wl_touch::WlTouch);
+
+/*
+ * Clipboard Handling
+ */
+
+impl wl_data_device::Handler for InputHandler {
+    fn data_offer(&mut self,
+                  evqh: &mut EventQueueHandle,
+                  _proxy: &wl_data_device::WlDataDevice,
+                  id: wl_data_offer::WlDataOffer)
+    {
+        // the MIME types of the offer are announced right after it
+        evqh.register::<_, InputHandler>(&id, self.my_id);
+        self.data_offers.push(DataOffer::new(id));
+    }
+
+    fn selection(&mut self,
+                 _evqh: &mut EventQueueHandle,
+                 _proxy: &wl_data_device::WlDataDevice,
+                 id: Option<&wl_data_offer::WlDataOffer>)
+    {
+        if let Some(old_offer) = self.selection_offer.take() {
+            old_offer.offer.destroy();
+        }
+        self.selection_offer = id.and_then(|id| self.take_data_offer(id));
+    }
+
+    fn enter(&mut self,
+             _evqh: &mut EventQueueHandle,
+             _proxy: &wl_data_device::WlDataDevice,
+             serial: u32,
+             surface: &wl_surface::WlSurface,
+             _x: f64,
+             _y: f64,
+             id: Option<&wl_data_offer::WlDataOffer>)
+    {
+        let offer = match id.and_then(|id| self.take_data_offer(id)) {
+            Some(offer) => offer,
+            None => return,
+        };
+        let window = match self.windows.iter().find(|w| w.equals(surface)) {
+            Some(window) => make_wid(window),
+            None => {
+                offer.offer.destroy();
+                return;
+            }
+        };
+
+        // only lists of files are accepted
+        let mut paths = Vec::new();
+        let mut pipe = None;
+        if offer.mime_types.iter().any(|mime| mime == URI_LIST_MIME_TYPE) {
+            offer.offer.accept(serial, Some(URI_LIST_MIME_TYPE.to_owned()));
+            if self.data_device_manager_version >= 3 {
+                offer.offer.set_actions(wl_data_device_manager::Copy, wl_data_device_manager::Copy);
+            }
+            // the data can be received during the drag, so that we know which files hover us,
+            // and is read from the events loop as it arrives. Our own drags are known already.
+            let own_data = self.drag_source.as_ref().map(|drag| {
+                drag.contents.get(URI_LIST_MIME_TYPE).map(parse_uri_list).unwrap_or(Vec::new())
+            });
+            if let Some(own_paths) = own_data {
+                paths = own_paths;
+            } else {
+                pipe = offer.receive(URI_LIST_MIME_TYPE.to_owned()).map(PipeReader::new);
+            }
+        } else {
+            offer.offer.accept(serial, None);
+        }
+
+        {
+            let mut guard = self.callback.lock().unwrap();
+            for path in &paths {
+                guard.send_event(Event::HoveredFile(path.clone()), window);
+            }
+        }
+        self.dnd_offer = Some(DndOffer {
+            offer: offer,
+            window: window,
+            pipe: pipe,
+            paths: paths,
+            dropped: false,
+        });
+    }
+
+    fn leave(&mut self,
+             _evqh: &mut EventQueueHandle,
+             _proxy: &wl_data_device::WlDataDevice)
+    {
+        // dropped files are reported once their list is read
+        if self.dnd_offer.as_ref().map(|dnd_offer| dnd_offer.dropped).unwrap_or(false) {
+            return;
+        }
+        if let Some(dnd_offer) = self.dnd_offer.take() {
+            if !dnd_offer.paths.is_empty() {
+                self.callback.lock().unwrap().send_event(Event::HoveredFileCancelled, dnd_offer.window);
+            }
+            dnd_offer.offer.offer.destroy();
+        }
+    }
+
+    fn drop(&mut self,
+            _evqh: &mut EventQueueHandle,
+            _proxy: &wl_data_device::WlDataDevice)
+    {
+        if let Some(ref mut dnd_offer) = self.dnd_offer {
+            dnd_offer.dropped = true;
+            // the drop is reported once the list of the files is read
+            if dnd_offer.pipe.is_some() {
+                return;
+            }
+        }
+        self.finish_dnd_offer();
+    }
+}
+
+declare_handler!(InputHandler, wl_data_device::Handler, wl_data_device::WlDataDevice);
+
+impl wl_data_offer::Handler for InputHandler {
+    fn offer(&mut self,
+             _evqh: &mut EventQueueHandle,
+             proxy: &wl_data_offer::WlDataOffer,
+             mime_type: String)
+    {
+        if let Some(offer) = self.data_offers.iter_mut().find(|o| o.offer.equals(proxy)) {
+            offer.mime_types.push(mime_type);
+        }
+    }
+}
+
+declare_handler!(InputHandler, wl_data_offer::Handler, wl_data_offer::WlDataOffer);
+
+impl zwp_primary_selection_device_v1::Handler for InputHandler {
+    fn data_offer(&mut self,
+                  evqh: &mut EventQueueHandle,
+                  _proxy: &zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1,
+                  offer: zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1)
+    {
+        // the MIME types of the offer are announced right after it
+        evqh.register::<_, InputHandler>(&offer, self.my_id);
+        self.primary_offers.push(DataOffer::new(offer));
+    }
+
+    fn selection(&mut self,
+                 _evqh: &mut EventQueueHandle,
+                 _proxy: &zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1,
+                 id: Option<&zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1>)
+    {
+        if let Some(old_offer) = self.primary_selection_offer.take() {
+            old_offer.offer.destroy();
+        }
+        for offer in self.primary_offers.drain(..) {
+            if id.map(|id| offer.offer.equals(id)).unwrap_or(false) {
+                self.primary_selection_offer = Some(offer);
+            } else {
+                offer.offer.destroy();
+            }
+        }
+    }
+}
+
+declare_handler!(InputHandler, zwp_primary_selection_device_v1::Handler,
+                 zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1);
+
+impl zwp_primary_selection_offer_v1::Handler for InputHandler {
+    fn offer(&mut self,
+             _evqh: &mut EventQueueHandle,
+             proxy: &zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1,
+             mime_type: String)
+    {
+        if let Some(offer) = self.primary_offers.iter_mut().find(|o| o.offer.equals(proxy)) {
+            offer.mime_types.push(mime_type);
+        }
+    }
+}
+
+declare_handler!(InputHandler, zwp_primary_selection_offer_v1::Handler,
+                 zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1);
+
+impl zwp_primary_selection_source_v1::Handler for InputHandler {
+    fn send(&mut self,
+            _evqh: &mut EventQueueHandle,
+            proxy: &zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1,
+            mime_type: String,
+            fd: RawFd)
+    {
+        let data = match self.primary_selection_source {
+            Some((ref source, ref contents)) if source.equals(proxy) => {
+                contents.get(&mime_type).map(|data| data.to_vec())
+            },
+            _ => None,
+        };
+        // closing the pipe without writing anything tells that we have no data
+        write_pipe(fd, data.unwrap_or(Vec::new()));
+    }
+
+    fn cancelled(&mut self,
+                 _evqh: &mut EventQueueHandle,
+                 proxy: &zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1)
+    {
+        // another client took the primary selection
+        let cancelled = match self.primary_selection_source {
+            Some((ref source, _)) => source.equals(proxy),
+            None => false,
+        };
+        if cancelled {
+            let (source, _) = self.primary_selection_source.take().unwrap();
+            source.destroy();
+        }
+    }
+}
+
+declare_handler!(InputHandler, zwp_primary_selection_source_v1::Handler,
+                 zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1);
+
+impl wl_data_source::Handler for InputHandler {
+    fn send(&mut self,
+            _evqh: &mut EventQueueHandle,
+            proxy: &wl_data_source::WlDataSource,
+            mime_type: String,
+            fd: RawFd)
+    {
+        let data = {
+            let contents = match (self.selection_source.as_ref(), self.drag_source.as_ref()) {
+                (Some(&(ref source, ref contents)), _) if source.equals(proxy) => Some(contents),
+                (_, Some(drag)) if drag.source.equals(proxy) => Some(&drag.contents),
+                _ => None,
+            };
+            contents.and_then(|contents| contents.get(&mime_type))
+                    .map(|data| data.to_vec()).unwrap_or(Vec::new())
+        };
+        // closing the pipe without writing anything tells that we have no data
+        write_pipe(fd, data);
+
+        // before version 3, the target may also request the data while it's dragged over it, so
+        // the drop is only reported once the source is cancelled
+        if let Some(ref mut drag) = self.drag_source {
+            if drag.source.equals(proxy) {
+                drag.data_requested = true;
+            }
+        }
+    }
+
+    fn cancelled(&mut self,
+                 _evqh: &mut EventQueueHandle,
+                 proxy: &wl_data_source::WlDataSource)
+    {
+        // the drag was not accepted by the target, or was cancelled
+        let result = match self.drag_source {
+            Some(ref drag) => self.unfinished_drag_result(drag),
+            None => DragResult::Cancelled,
+        };
+        if self.finish_drag(proxy, result) {
+            return;
+        }
+        // another client took the clipboard
+        let cancelled = match self.selection_source {
+            Some((ref source, _)) => source.equals(proxy),
+            None => false,
+        };
+        if cancelled {
+            let (source, _) = self.selection_source.take().unwrap();
+            source.destroy();
+        }
+    }
+
+    fn action(&mut self,
+              _evqh: &mut EventQueueHandle,
+              proxy: &wl_data_source::WlDataSource,
+              dnd_action: wl_data_device_manager::DndAction)
+    {
+        if let Some(ref mut drag) = self.drag_source {
+            if drag.source.equals(proxy) {
+                if dnd_action.contains(wl_data_device_manager::Move) {
+                    drag.action = DragAction::Move;
+                } else if dnd_action.contains(wl_data_device_manager::Copy) {
+                    drag.action = DragAction::Copy;
+                } else if dnd_action.contains(wl_data_device_manager::Ask) {
+                    // the user picks the action once the data is dropped
+                    drag.action = DragAction::Copy;
+                }
+            }
+        }
+    }
+
+    fn dnd_finished(&mut self,
+                    _evqh: &mut EventQueueHandle,
+                    proxy: &wl_data_source::WlDataSource)
+    {
+        let action = match self.drag_source {
+            Some(ref drag) => drag.action,
+            None => return,
+        };
+        self.finish_drag(proxy, DragResult::Completed(action));
+    }
+}
+
+declare_handler!(InputHandler, wl_data_source::Handler, wl_data_source::WlDataSource);
+
+impl zwp_text_input_v3::Handler for InputHandler {
+    fn enter(&mut self,
+             _evqh: &mut EventQueueHandle,
+             _proxy: &zwp_text_input_v3::ZwpTextInputV3,
+             surface: &wl_surface::WlSurface)
+    {
+        let wid = match self.windows.iter().find(|w| w.equals(surface)) {
+            Some(window) => make_wid(window),
+            None => return,
+        };
+        self.text_input_focus = Some(wid);
+        if self.ime_allowed.contains(&wid) {
+            let events = self.enable_text_input(wid);
+            let mut guard = self.callback.lock().unwrap();
+            for event in events {
+                guard.send_event(event, wid);
+            }
+        }
+    }
+
+    fn leave(&mut self,
+             _evqh: &mut EventQueueHandle,
+             _proxy: &zwp_text_input_v3::ZwpTextInputV3,
+             _surface: &wl_surface::WlSurface)
+    {
+        if let Some(wid) = self.text_input_focus.take() {
+            if self.ime_allowed.contains(&wid) {
+                let events = self.disable_text_input();
+                let mut guard = self.callback.lock().unwrap();
+                for event in events {
+                    guard.send_event(event, wid);
+                }
+            }
+        }
+    }
+
+    fn preedit_string(&mut self,
+                      _evqh: &mut EventQueueHandle,
+                      _proxy: &zwp_text_input_v3::ZwpTextInputV3,
+                      text: Option<String>,
+                      cursor_begin: i32,
+                      cursor_end: i32)
+    {
+        let text = text.unwrap_or(String::new());
+        // the cursor is hidden when its position is -1
+        let cursor_range = if cursor_begin < 0 || cursor_end < 0 {
+            None
+        } else {
+            Some((cursor_begin as usize, cursor_end as usize))
+        };
+        self.pending_preedit = Some((text, cursor_range));
+    }
+
+    fn commit_string(&mut self,
+                     _evqh: &mut EventQueueHandle,
+                     _proxy: &zwp_text_input_v3::ZwpTextInputV3,
+                     text: Option<String>)
+    {
+        self.pending_commit = text;
+    }
+
+    fn delete_surrounding_text(&mut self,
+                               _evqh: &mut EventQueueHandle,
+                               _proxy: &zwp_text_input_v3::ZwpTextInputV3,
+                               _before_length: u32,
+                               _after_length: u32)
+    {
+        // we never send the surrounding text, so the input method can't ask to delete it
+    }
+
+    fn done(&mut self,
+            _evqh: &mut EventQueueHandle,
+            _proxy: &zwp_text_input_v3::ZwpTextInputV3,
+            serial: u32)
+    {
+        // the changes are outdated if we committed another state since the compositor sent them
+        if serial != self.text_input_commits {
+            self.pending_preedit = None;
+            self.pending_commit = None;
+            return;
+        }
+        let wid = match self.text_input_focus {
+            Some(wid) if self.ime_allowed.contains(&wid) => wid,
+            _ => return,
+        };
+        // the preedit string is replaced, then the committed text is inserted before the new
+        // preedit string
+        let commit = self.pending_commit.take();
+        let preedit = self.pending_preedit.take().and_then(|(text, cursor_range)| {
+            if text.is_empty() { None } else { Some((text, cursor_range)) }
+        });
+
+        let mut guard = self.callback.lock().unwrap();
+        if self.preedit_shown && (commit.is_some() || preedit.is_none()) {
+            guard.send_event(Event::Ime(Ime::Preedit { text: String::new(), cursor_range: None }), wid);
+            self.preedit_shown = false;
+        }
+        if let Some(text) = commit {
+            guard.send_event(Event::Ime(Ime::Commit(text)), wid);
+        }
+        if let Some((text, cursor_range)) = preedit {
+            guard.send_event(Event::Ime(Ime::Preedit { text: text, cursor_range: cursor_range }), wid);
+            self.preedit_shown = true;
+        }
+    }
+}
+
+declare_handler!(InputHandler, zwp_text_input_v3::Handler, zwp_text_input_v3::ZwpTextInputV3);
+
+// The wayland data device has no link action, the targets copy the data instead
+fn dnd_action(action: DragAction) -> wl_data_device_manager::DndAction {
+    match action {
+        DragAction::Copy | DragAction::Link => wl_data_device_manager::Copy,
+        DragAction::Move => wl_data_device_manager::Move,
+    }
+}
@@ -1,21 +1,297 @@
+use std::collections::HashSet;
+use std::os::unix::io::RawFd;
+use std::{cmp, ptr};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use {VirtualKeyCode, ElementState, WindowEvent as Event};
+use libc;
+
+use {VirtualKeyCode, ElementState, KeyboardInput, KeyboardLayouts, KeyRepeatInfo, ScanCode,
+     WindowEvent as Event};
 
 use events::ModifiersState;
 
 use super::{wayland_kbd, EventsLoopSink, WindowId};
+use super::super::evdev::{add_modifier_side, code_to_physical_key};
+use super::super::keysym::keysym_to_logical_key;
+use super::super::xkbcommon::{Compose, ComposeResult, Keymap, ModIndices};
 use wayland_client::EventQueueHandle;
 use wayland_client::protocol::wl_keyboard;
 
 pub struct KbdHandler {
     sink: Arc<Mutex<EventsLoopSink>>,
-    pub target: Option<WindowId>
+    pub target: Option<WindowId>,
+    // characters per second, 0 disables the repeat
+    rate: i32,
+    // milliseconds
+    delay: i32,
+    // whether the compositor told the rate and the delay, which are guessed until then
+    repeat_info_known: bool,
+    repeat: Option<KeyRepeat>,
+    // wayland_kbd leaves the compose sequences and the dead keys to us
+    compose: Option<Compose>,
+    // the raw keys held down while the window has the focus
+    pressed_keys: HashSet<u32>,
+    // the modifiers given by the compositor, without their sides
+    mods: ModifiersState,
+    // the modifiers last reported to the window, `None` until they are reported after the focus
+    reported_mods: Option<ModifiersState>,
+    // our own copy of the keymap, for what `wayland_kbd` doesn't tell
+    keymap: Option<Keymap>,
+    layouts: Vec<String>,
+    // the effective layout, given with the modifiers
+    group: u32,
+    reported_layouts: Option<KeyboardLayouts>,
+}
+
+/// The key currently being repeated.
+struct KeyRepeat {
+    wid: WindowId,
+    rawkey: u32,
+    keysym: u32,
+    utf8: Option<String>,
+    mods: ModifiersState,
+    interval: Duration,
+    next: Instant,
 }
 
 impl KbdHandler {
     pub fn new(sink: Arc<Mutex<EventsLoopSink>>) -> KbdHandler {
-        KbdHandler { sink: sink, target: None }
+        // the values used by weston and most compositors until they tell otherwise
+        KbdHandler {
+            sink: sink,
+            target: None,
+            rate: 25,
+            delay: 600,
+            repeat_info_known: false,
+            repeat: None,
+            compose: Compose::new(),
+            pressed_keys: HashSet::new(),
+            mods: ModifiersState::default(),
+            reported_mods: None,
+            keymap: None,
+            layouts: Vec::new(),
+            group: 0,
+            reported_layouts: None,
+        }
+    }
+
+    pub fn set_repeat_info(&mut self, rate: i32, delay: i32) {
+        self.rate = rate;
+        self.delay = delay;
+        self.repeat_info_known = true;
+        self.repeat = None;
+    }
+
+    pub fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+        if !self.repeat_info_known || self.rate <= 0 {
+            return None;
+        }
+        Some(KeyRepeatInfo {
+            delay: Duration::from_millis(self.delay as u64),
+            rate: self.rate as u32,
+        })
+    }
+
+    /// Sets the keys held down when the window gains the focus, from the array of the enter event,
+    /// and reports them with synthetic presses.
+    pub fn set_pressed_keys(&mut self, keys: &[u8]) {
+        self.pressed_keys = keys.chunks(4)
+            .filter(|key| key.len() == 4)
+            .map(|key| key[0] as u32 | (key[1] as u32) << 8 | (key[2] as u32) << 16 |
+                       (key[3] as u32) << 24)
+            .collect();
+        // the compositor sends the modifiers right after, which are then reported
+        self.reported_mods = None;
+
+        if let Some(wid) = self.target {
+            let modifiers = self.current_modifiers();
+            let mut guard = self.sink.lock().unwrap();
+            for &rawkey in &self.pressed_keys {
+                let keysym = match self.keymap {
+                    Some(ref keymap) => keymap.keysym(rawkey + 8),
+                    None => 0,
+                };
+                guard.send_event(
+                    Event::KeyboardInput(KeyboardInput {
+                        state: ElementState::Pressed,
+                        scancode: rawkey,
+                        physical_key: code_to_physical_key(rawkey),
+                        virtual_keycode: key_to_vkey(rawkey, keysym),
+                        logical_key: keysym_to_logical_key(keysym),
+                        modifiers: modifiers,
+                        is_repeat: false,
+                        is_synthetic: true,
+                    }),
+                    wid
+                );
+            }
+        }
+    }
+
+    /// Reports the keys held down with synthetic releases, when the window loses the focus and
+    /// would not receive their releases.
+    pub fn release_pressed_keys(&mut self) {
+        if let Some(wid) = self.target {
+            let modifiers = self.current_modifiers();
+            let mut rawkeys: Vec<_> = self.pressed_keys.drain().collect();
+            rawkeys.sort();
+            let mut guard = self.sink.lock().unwrap();
+            for rawkey in rawkeys {
+                let keysym = match self.keymap {
+                    Some(ref keymap) => keymap.keysym(rawkey + 8),
+                    None => 0,
+                };
+                guard.send_event(
+                    Event::KeyboardInput(KeyboardInput {
+                        state: ElementState::Released,
+                        scancode: rawkey,
+                        physical_key: code_to_physical_key(rawkey),
+                        virtual_keycode: key_to_vkey(rawkey, keysym),
+                        logical_key: keysym_to_logical_key(keysym),
+                        modifiers: modifiers,
+                        is_repeat: false,
+                        is_synthetic: true,
+                    }),
+                    wid
+                );
+            }
+        }
+    }
+
+    /// Returns the keys held down while the window has the focus.
+    pub fn get_pressed_keys(&self, wid: WindowId) -> Vec<ScanCode> {
+        if self.target != Some(wid) {
+            return Vec::new();
+        }
+        self.pressed_keys.iter().cloned().collect()
+    }
+
+    /// Loads our copy of the keymap sent by the compositor, with the names of its layouts.
+    ///
+    /// `wayland_kbd` takes the file descriptor afterwards, it is left open.
+    pub fn set_keymap(&mut self, fd: RawFd, size: u32) {
+        let size = size as usize;
+        unsafe {
+            let map = libc::mmap(ptr::null_mut(), size, libc::PROT_READ, libc::MAP_PRIVATE, fd, 0);
+            if map == libc::MAP_FAILED {
+                return;
+            }
+            // the keymap ends with a nul byte
+            self.keymap = Keymap::from_text(map as *const _);
+            libc::munmap(map, size);
+        }
+        self.layouts = match self.keymap {
+            Some(ref keymap) => keymap.layout_names(),
+            None => Vec::new(),
+        };
+        self.report_layouts();
+    }
+
+    pub fn keyboard_layouts(&self) -> Option<KeyboardLayouts> {
+        if self.layouts.is_empty() {
+            return None;
+        }
+        Some(KeyboardLayouts {
+            names: self.layouts.clone(),
+            active: self.group as usize,
+        })
+    }
+
+    /// Sends a `KeyboardLayoutChanged` event if they changed since they were last reported.
+    fn report_layouts(&mut self) {
+        if let (Some(wid), Some(layouts)) = (self.target, self.keyboard_layouts()) {
+            if self.reported_layouts.as_ref() != Some(&layouts) {
+                self.reported_layouts = Some(layouts.clone());
+                self.sink.lock().unwrap().send_event(Event::KeyboardLayoutChanged(layouts), wid);
+            }
+        }
+    }
+
+    /// Updates the modifiers and the active layout from the masks of the compositor.
+    pub fn set_modifiers(&mut self, depressed: u32, latched: u32, locked: u32, group: u32) {
+        self.mods = match self.keymap {
+            Some(ref keymap) => keymap.mask_to_modifiers(depressed | latched | locked),
+            None => ModIndices::core().to_modifiers(depressed | latched | locked),
+        };
+        self.group = group;
+        if let Some(ref mut keymap) = self.keymap {
+            keymap.update_mask(depressed, latched, locked, group);
+        }
+        self.report_layouts();
+        self.report_modifiers();
+    }
+
+    /// Returns the modifiers, with the sides of the ones held down.
+    pub fn current_modifiers(&self) -> ModifiersState {
+        let mut mods = self.mods;
+        for &key in &self.pressed_keys {
+            if let Some(key) = code_to_physical_key(key) {
+                add_modifier_side(&mut mods, key);
+            }
+        }
+        mods
+    }
+
+    /// Sends a `ModifiersChanged` event if they changed since they were last reported.
+    fn report_modifiers(&mut self) {
+        if let Some(wid) = self.target {
+            let mods = self.current_modifiers();
+            if self.reported_mods != Some(mods) {
+                self.reported_mods = Some(mods);
+                self.sink.lock().unwrap().send_event(Event::ModifiersChanged(mods), wid);
+            }
+        }
+    }
+
+    /// Stops the repeat of the held key and drops the unfinished compose sequence.
+    pub fn reset(&mut self) {
+        self.pressed_keys.clear();
+        self.reported_mods = None;
+        self.repeat = None;
+        if let Some(ref mut compose) = self.compose {
+            compose.reset();
+        }
+    }
+
+    /// Returns when the next repeat of the held key is due, if a key is repeating.
+    pub fn next_repeat(&self) -> Option<Instant> {
+        self.repeat.as_ref().map(|repeat| repeat.next)
+    }
+
+    /// Returns the repeats of the held key that are due.
+    ///
+    /// The events loop waits until `next_repeat` at most, the events are generated here so that
+    /// they are delivered in order with the other ones.
+    pub fn take_repeats(&mut self) -> Vec<(Event, WindowId)> {
+        let mut events = Vec::new();
+        if let Some(ref mut repeat) = self.repeat {
+            let now = Instant::now();
+            if repeat.next <= now {
+                let vkcode = key_to_vkey(repeat.rawkey, repeat.keysym);
+                events.push((Event::KeyboardInput(KeyboardInput {
+                    state: ElementState::Pressed,
+                    scancode: repeat.rawkey,
+                    physical_key: code_to_physical_key(repeat.rawkey),
+                    virtual_keycode: vkcode,
+                    logical_key: keysym_to_logical_key(repeat.keysym),
+                    modifiers: repeat.mods,
+                    is_repeat: true,
+                    is_synthetic: false,
+                }), repeat.wid));
+                if let Some(ref txt) = repeat.utf8 {
+                    for chr in txt.chars() {
+                        events.push((Event::ReceivedCharacter(chr), repeat.wid));
+                    }
+                }
+                // don't try to catch up with the repeats missed while the loop was busy
+                repeat.next += repeat.interval;
+                if repeat.next < now {
+                    repeat.next = now + repeat.interval;
+                }
+            }
+        }
+        events
     }
 }
 
@@ -25,7 +301,7 @@ impl wayland_kbd::Handler for KbdHandler {
            _proxy: &wl_keyboard::WlKeyboard,
            _serial: u32,
            _time: u32,
-           mods: &wayland_kbd::ModifiersState,
+           _mods: &wayland_kbd::ModifiersState,
            rawkey: u32,
            keysym: u32,
            state: wl_keyboard::KeyState,
@@ -36,33 +312,93 @@ impl wayland_kbd::Handler for KbdHandler {
                 wl_keyboard::KeyState::Pressed => ElementState::Pressed,
                 wl_keyboard::KeyState::Released => ElementState::Released,
             };
+            let utf8 = match (state, self.compose.as_mut()) {
+                (ElementState::Pressed, Some(compose)) => match compose.feed(keysym) {
+                    ComposeResult::Nothing => utf8,
+                    ComposeResult::Composing | ComposeResult::Cancelled => None,
+                    ComposeResult::Composed(text) => Some(text),
+                },
+                _ => utf8,
+            };
             let vkcode = key_to_vkey(rawkey, keysym);
-            let mut guard = self.sink.lock().unwrap();
-            guard.send_event(
-                Event::KeyboardInput(
-                    state,
-                    rawkey as u8,
-                    vkcode,
-                    ModifiersState {
-                        shift: mods.shift,
-                        ctrl: mods.ctrl,
-                        alt: mods.alt,
-                        logo: mods.logo
+            // the modifiers before the key, the compositor sends their update after it
+            let modifiers = self.current_modifiers();
+            match state {
+                ElementState::Pressed => self.pressed_keys.insert(rawkey),
+                ElementState::Released => self.pressed_keys.remove(&rawkey),
+            };
+            {
+                let mut guard = self.sink.lock().unwrap();
+                guard.send_event(
+                    Event::KeyboardInput(KeyboardInput {
+                        state: state,
+                        scancode: rawkey,
+                        physical_key: code_to_physical_key(rawkey),
+                        virtual_keycode: vkcode,
+                        logical_key: keysym_to_logical_key(keysym),
+                        modifiers: modifiers,
+                        is_repeat: false,
+                        is_synthetic: false,
+                    }),
+                    wid
+                );
+                // send char event only on key press, not release
+                if let ElementState::Pressed = state {
+                    if let Some(ref txt) = utf8 {
+                        for chr in txt.chars() {
+                            guard.send_event(Event::ReceivedCharacter(chr), wid);
+                        }
+                    }
+                }
+            }
+            self.report_modifiers();
+
+            // the compositor leaves the repeat to the clients, the last pressed key repeats
+            // until it is released
+            match state {
+                ElementState::Pressed => {
+                    if self.rate > 0 && is_repeatable(keysym) {
+                        // faster rates than a repeat per millisecond would flood the window
+                        let rate = cmp::min(self.rate, 1000) as u32;
+                        let interval = Duration::new(0, 1_000_000_000 / rate);
+                        self.repeat = Some(KeyRepeat {
+                            wid: wid,
+                            rawkey: rawkey,
+                            keysym: keysym,
+                            utf8: utf8,
+                            mods: modifiers,
+                            interval: interval,
+                            next: Instant::now() + Duration::from_millis(self.delay as u64),
+                        });
+                    } else if !is_modifier(keysym) {
+                        self.repeat = None;
+                    }
+                },
+                ElementState::Released => {
+                    if self.repeat.as_ref().map(|r| r.rawkey == rawkey).unwrap_or(false) {
+                        self.repeat = None;
                     }
-                ),
-                wid
-            );
-            // send char event only on key press, not release
-            if let ElementState::Released = state { return }
-            if let Some(txt) = utf8 {
-                for chr in txt.chars() {
-                    guard.send_event(Event::ReceivedCharacter(chr), wid);
                 }
             }
         }
     }
 }
 
+fn is_modifier(keysym: u32) -> bool {
+    use super::wayland_kbd::keysyms;
+    // Shift_L to Hyper_R, and the ISO group and level keys
+    (keysym >= keysyms::XKB_KEY_Shift_L && keysym <= keysyms::XKB_KEY_Hyper_R) ||
+        (keysym >= 0xfe01 && keysym <= 0xfe0f)
+}
+
+fn is_repeatable(keysym: u32) -> bool {
+    use super::wayland_kbd::keysyms;
+    !is_modifier(keysym) && match keysym {
+        keysyms::XKB_KEY_Num_Lock | keysyms::XKB_KEY_Scroll_Lock => false,
+        _ => true,
+    }
+}
+
 fn key_to_vkey(rawkey: u32, keysym: u32) -> Option<VirtualKeyCode> {
     match rawkey {
          1 => Some(VirtualKeyCode::Escape),
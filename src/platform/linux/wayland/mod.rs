@@ -6,12 +6,18 @@ pub use self::context::{WaylandContext, MonitorId, get_available_monitors,
                         get_primary_monitor};
 
 use self::window::{make_wid, DecoratedHandler};
-use self::event_loop::EventsLoopSink;
+use self::event_loop::{EventsLoopSink, InputHandler};
+use self::clipboard::DataOffer;
+use self::cursor::{CursorAnimation, CursorBuffer, CursorTheme};
 
+extern crate tempfile;
 extern crate wayland_kbd;
 extern crate wayland_window;
 
+mod clipboard;
 mod context;
+mod cursor;
 mod event_loop;
 mod keyboard;
+mod protocols;
 mod window;
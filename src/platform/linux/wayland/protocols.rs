@@ -0,0 +1,45 @@
+//! Bindings to the unstable protocols that wayland-protocols doesn't provide yet, generated from
+//! the files of the `wayland-protocols` directory by the build script.
+
+#![allow(dead_code, non_camel_case_types, unused_unsafe, unused_variables)]
+#![allow(non_upper_case_globals, non_snake_case, unused_imports)]
+
+pub mod text_input_v3 {
+    pub mod interfaces {
+        #[doc(hidden)]
+        pub use wayland_sys::common::*;
+        #[doc(hidden)]
+        pub use wayland_client::protocol_interfaces::{wl_seat_interface, wl_surface_interface};
+        include!(concat!(env!("OUT_DIR"), "/text_input_v3_interfaces.rs"));
+    }
+
+    pub mod client {
+        #[doc(hidden)]
+        pub use wayland_client::{EventQueueHandle, Handler, Liveness, Proxy, RequestResult};
+        #[doc(hidden)]
+        pub use wayland_client::protocol::{wl_seat, wl_surface};
+        #[doc(hidden)]
+        pub use super::interfaces;
+        include!(concat!(env!("OUT_DIR"), "/text_input_v3_client_api.rs"));
+    }
+}
+
+pub mod primary_selection_v1 {
+    pub mod interfaces {
+        #[doc(hidden)]
+        pub use wayland_sys::common::*;
+        #[doc(hidden)]
+        pub use wayland_client::protocol_interfaces::wl_seat_interface;
+        include!(concat!(env!("OUT_DIR"), "/primary_selection_v1_interfaces.rs"));
+    }
+
+    pub mod client {
+        #[doc(hidden)]
+        pub use wayland_client::{EventQueueHandle, Handler, Liveness, Proxy, RequestResult};
+        #[doc(hidden)]
+        pub use wayland_client::protocol::wl_seat;
+        #[doc(hidden)]
+        pub use super::interfaces;
+        include!(concat!(env!("OUT_DIR"), "/primary_selection_v1_client_api.rs"));
+    }
+}
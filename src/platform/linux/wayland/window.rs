@@ -4,10 +4,13 @@ use std::sync::atomic::AtomicBool;
 use wayland_client::{EventQueue, EventQueueHandle, Proxy};
 use wayland_client::protocol::{wl_display,wl_surface,wl_shell_surface};
 
-use {CreationError, MouseCursor, CursorState, WindowAttributes};
+use {ClipboardContents, ClipboardKind, CreationError, MouseButton, MouseCursor, CursorState, CustomCursor,
+     DragAction, DragData, Icon, ScanCode, WindowAttributes};
 use platform::MonitorId as PlatformMonitorId;
 
-use super::{WaylandContext, EventsLoop};
+use super::{CursorBuffer, WaylandContext, EventsLoop, InputHandler};
+use super::event_loop;
+use super::cursor::cursor_size;
 use super::wayland_window;
 use super::wayland_window::DecoratedSurface;
 
@@ -23,7 +26,9 @@ pub struct Window {
     // our current inner dimensions
     size: Mutex<(u32, u32)>,
     // the id of our DecoratedHandler in the EventQueue
-    decorated_id: usize
+    decorated_id: usize,
+    // the id of the InputHandler of our EventsLoop in the EventQueue
+    input_handler_id: usize
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -37,12 +42,13 @@ pub fn make_wid(s: &wl_surface::WlSurface) -> WindowId {
 impl Window {
     pub fn new(evlp: &EventsLoop, ctxt: Arc<WaylandContext>, attributes: &WindowAttributes)  -> Result<Window, CreationError>
     {
-        let (width, height) = attributes.dimensions.unwrap_or((800,600));
+        let (width, height) = clamp_size(attributes.dimensions.unwrap_or((800,600)),
+                                         attributes.min_dimensions, attributes.max_dimensions);
 
         let (surface, decorated) = ctxt.create_window::<DecoratedHandler>();
 
         // init DecoratedSurface
-        let (evq, cleanup_signal) = evlp.get_window_init();
+        let (evq, cleanup_signal, input_handler_id) = evlp.get_window_init();
         let decorated_id = {
             let mut evq_guard = evq.lock().unwrap();
             let decorated_id = evq_guard.add_handler_with_init(decorated);
@@ -50,7 +56,7 @@ impl Window {
                 // initialize the DecoratedHandler
                 let mut state = evq_guard.state();
                 let decorated = state.get_mut_handler::<DecoratedSurface<DecoratedHandler>>(decorated_id);
-                *(decorated.handler()) = Some(DecoratedHandler::new());
+                *(decorated.handler()) = Some(DecoratedHandler::new(attributes, (width, height)));
 
                 // set fullscreen if necessary
                 if let Some(PlatformMonitorId::Wayland(ref monitor_id)) = attributes.monitor {
@@ -75,11 +81,16 @@ impl Window {
             cleanup_signal: cleanup_signal,
             surface: surface,
             size: Mutex::new((width, height)),
-            decorated_id: decorated_id
+            decorated_id: decorated_id,
+            input_handler_id: input_handler_id
         };
 
         // register ourselves to the EventsLoop
-        evlp.register_window(me.decorated_id, me.surface.clone());
+        evlp.register_window(me.decorated_id, me.surface.clone(), attributes.multitouch);
+
+        if attributes.maximized && attributes.monitor.is_none() {
+            me.set_maximized(true);
+        }
 
         Ok(me)
     }
@@ -135,22 +146,190 @@ impl Window {
         let mut state = guard.state();
         let mut decorated = state.get_mut_handler::<DecoratedSurface<DecoratedHandler>>(self.decorated_id);
         decorated.resize(x as i32, y as i32);
+        if let Some(handler) = decorated.handler().as_mut() {
+            handler.size = (x, y);
+        }
     }
 
     #[inline]
-    pub fn set_cursor(&self, _cursor: MouseCursor) {
-        // TODO
+    pub fn set_resizable(&self, resizable: bool) {
+        self.with_handler(|handler| handler.resizable = resizable);
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.with_handler(|handler| handler.min_size = dimensions);
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.with_handler(|handler| handler.max_size = dimensions);
+    }
+
+    // Updates the DecoratedHandler, then schedules a resize if the current size
+    // no longer fits in its constraints
+    fn with_handler<F: FnOnce(&mut DecoratedHandler)>(&self, f: F) {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        let decorated = state.get_mut_handler::<DecoratedSurface<DecoratedHandler>>(self.decorated_id);
+        if let Some(handler) = decorated.handler().as_mut() {
+            f(handler);
+            let size = handler.size;
+            let clamped = handler.clamp(size);
+            if clamped != size {
+                handler.size = clamped;
+                handler.newsize = Some(clamped);
+            }
+        }
+    }
+
+    // wayland_window doesn't give access to the shell surface, so the window is maximized by
+    // resizing it to the size of the output
+    pub fn set_maximized(&self, maximized: bool) {
+        let output_size = if maximized {
+            match self.ctxt.primary_output_size() {
+                Some(size) => Some(size),
+                None => return,
+            }
+        } else {
+            None
+        };
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        let decorated = state.get_mut_handler::<DecoratedSurface<DecoratedHandler>>(self.decorated_id);
+        if let Some(handler) = decorated.handler().as_mut() {
+            match output_size {
+                Some(size) => handler.maximize(size),
+                None => handler.unmaximize(),
+            }
+        }
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        let decorated = state.get_mut_handler::<DecoratedSurface<DecoratedHandler>>(self.decorated_id);
+        decorated.handler().as_ref().map(|handler| handler.restore_size.is_some()).unwrap_or(false)
     }
 
     #[inline]
+    pub fn set_minimized(&self, _minimized: bool) {
+        // Not possible with wl_shell
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    pub fn set_always_on_top(&self, _always_on_top: bool) {
+        // Not possible with wl_shell
+    }
+
+    #[inline]
+    pub fn set_always_on_bottom(&self, _always_on_bottom: bool) {
+        // Not possible with wl_shell
+    }
+
+    #[inline]
+    pub fn set_window_icon(&self, _window_icon: Option<Icon>) {
+        // TODO: needs the xdg-toplevel-icon protocol, which is not available with wl_shell
+    }
+
+    pub fn set_cursor(&self, cursor: MouseCursor) {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        state.get_mut_handler::<InputHandler>(self.input_handler_id).set_theme_cursor(self.id(), cursor);
+    }
+
+    pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+        // pick the images for the largest scale, the compositor downscales them on the others
+        let scale = self.ctxt.max_output_scale();
+        let frames = cursor.frames_for_size(cursor_size() * scale as u32).iter()
+                           .map(|frame| CursorBuffer::new(&self.ctxt, frame, scale))
+                           .collect::<Option<Vec<_>>>();
+        self.set_cursor_buffer(frames);
+    }
+
+    fn set_cursor_buffer(&self, frames: Option<Vec<CursorBuffer>>) {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        state.get_mut_handler::<InputHandler>(self.input_handler_id).set_cursor(self.id(), frames);
+    }
+
     pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
-        use CursorState::{Grab, Normal, Hide};
+        use CursorState::{Grab, Normal, Hide, Locked, Confined};
         // TODO : not yet possible on wayland to grab cursor
-        match state {
-            Grab => Err("Cursor cannot be grabbed on wayland yet.".to_string()),
-            Hide => Err("Cursor cannot be hidden on wayland yet.".to_string()),
-            Normal => Ok(())
+        let hidden = match state {
+            Grab => return Err("Cursor cannot be grabbed on wayland yet.".to_string()),
+            Hide | Locked => true,
+            Normal | Confined => false
+        };
+        let mut guard = self.evq.lock().unwrap();
+        let mut evq_state = guard.state();
+        let handler = evq_state.get_mut_handler::<InputHandler>(self.input_handler_id);
+        try!(handler.set_pointer_constraint(self.id(), state));
+        handler.set_cursor_hidden(self.id(), hidden);
+        Ok(())
+    }
+
+    pub fn set_clipboard(&self, kind: ClipboardKind, contents: ClipboardContents) -> Result<(), String> {
+        event_loop::set_clipboard(&self.ctxt, &self.evq, self.input_handler_id, kind, contents)
+    }
+
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        {
+            let mut guard = self.evq.lock().unwrap();
+            let mut state = guard.state();
+            state.get_mut_handler::<InputHandler>(self.input_handler_id)
+                 .set_ime_position(make_wid(&self.surface), x, y);
+        }
+        self.ctxt.flush();
+    }
+
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        {
+            let mut guard = self.evq.lock().unwrap();
+            let mut state = guard.state();
+            state.get_mut_handler::<InputHandler>(self.input_handler_id)
+                 .set_ime_allowed(make_wid(&self.surface), allowed);
+        }
+        self.ctxt.flush();
+    }
+
+    pub fn get_pressed_keys(&self) -> Vec<ScanCode> {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        state.get_mut_handler::<InputHandler>(self.input_handler_id).get_pressed_keys(self.id())
+    }
+
+    pub fn get_pressed_mouse_buttons(&self) -> Vec<MouseButton> {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        state.get_mut_handler::<InputHandler>(self.input_handler_id)
+             .get_pressed_mouse_buttons(self.id())
+    }
+
+    pub fn start_drag(&self, data: DragData, allowed_actions: &[DragAction]) -> Result<(), String> {
+        {
+            let mut guard = self.evq.lock().unwrap();
+            let source = try!(guard.state().get_handler::<InputHandler>(self.input_handler_id).create_data_source());
+            guard.register::<_, InputHandler>(&source, self.input_handler_id);
+            let contents = data.contents().clone();
+            guard.state().get_mut_handler::<InputHandler>(self.input_handler_id)
+                 .start_drag(source, &self.surface, contents, allowed_actions);
         }
+        self.ctxt.flush();
+        Ok(())
+    }
+
+    pub fn clipboard_mime_types(&self, kind: ClipboardKind) -> Vec<String> {
+        event_loop::clipboard_mime_types(&self.evq, self.input_handler_id, kind)
+    }
+
+    pub fn get_clipboard(&self, kind: ClipboardKind, mime_type: &str) -> Option<Vec<u8>> {
+        event_loop::get_clipboard(&self.ctxt, &self.evq, self.input_handler_id, kind, mime_type)
     }
 
     #[inline]
@@ -159,10 +338,11 @@ impl Window {
         1.0
     }
 
-    #[inline]
-    pub fn set_cursor_position(&self, _x: i32, _y: i32) -> Result<(), ()> {
-        // TODO: not yet possible on wayland
-        Err(())
+    pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
+        // the cursor can't be moved on wayland, only its position after an unlock can be hinted
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        state.get_mut_handler::<InputHandler>(self.input_handler_id).set_cursor_position_hint(self.id(), x, y)
     }
     
     pub fn get_display(&self) -> &wl_display::WlDisplay {
@@ -182,15 +362,77 @@ impl Drop for Window {
 }
 
 pub struct DecoratedHandler {
-    newsize: Option<(u32, u32)>
+    newsize: Option<(u32, u32)>,
+    // the last size we have been configured with
+    size: (u32, u32),
+    min_size: Option<(u32, u32)>,
+    max_size: Option<(u32, u32)>,
+    resizable: bool,
+    // whether the borders are drawn around the surface
+    decorated: bool,
+    // the size to restore when the window is unmaximized, `Some` while it is maximized
+    restore_size: Option<(u32, u32)>,
 }
 
 impl DecoratedHandler {
-    fn new() -> DecoratedHandler { DecoratedHandler { newsize: None }}
+    fn new(attributes: &WindowAttributes, size: (u32, u32)) -> DecoratedHandler {
+        DecoratedHandler {
+            newsize: None,
+            size: size,
+            min_size: attributes.min_dimensions,
+            max_size: attributes.max_dimensions,
+            resizable: attributes.resizable,
+            decorated: attributes.decorations && attributes.monitor.is_none(),
+            restore_size: None,
+        }
+    }
+
+    // Resizes the window so that it fills the output, borders included
+    fn maximize(&mut self, (width, height): (u32, u32)) {
+        use std::cmp::max;
+        let (width, height) = if self.decorated {
+            let (w, h) = wayland_window::substract_borders(width as i32, height as i32);
+            (max(w, 1) as u32, max(h, 1) as u32)
+        } else {
+            (width, height)
+        };
+        if self.restore_size.is_none() {
+            self.restore_size = Some(self.size);
+        }
+        let size = self.clamp((width, height));
+        self.size = size;
+        self.newsize = Some(size);
+    }
+
+    fn unmaximize(&mut self) {
+        if let Some(size) = self.restore_size.take() {
+            self.size = size;
+            self.newsize = Some(size);
+        }
+    }
 
     pub fn take_newsize(&mut self) -> Option<(u32, u32)> {
         self.newsize.take()
     }
+
+    fn clamp(&self, size: (u32, u32)) -> (u32, u32) {
+        clamp_size(size, self.min_size, self.max_size)
+    }
+}
+
+fn clamp_size((mut w, mut h): (u32, u32), min_size: Option<(u32, u32)>, max_size: Option<(u32, u32)>)
+    -> (u32, u32)
+{
+    use std::cmp::{min, max};
+    if let Some((max_w, max_h)) = max_size {
+        w = min(w, max_w);
+        h = min(h, max_h);
+    }
+    if let Some((min_w, min_h)) = min_size {
+        w = max(w, min_w);
+        h = max(h, min_h);
+    }
+    (w, h)
 }
 
 impl wayland_window::Handler for DecoratedHandler {
@@ -200,6 +442,14 @@ impl wayland_window::Handler for DecoratedHandler {
                  width: i32, height: i32)
     {
         use std::cmp::max;
-        self.newsize = Some((max(width,1) as u32, max(height,1) as u32));
+        if !self.resizable {
+            // keep our current size, the compositor is not allowed to change it
+            return;
+        }
+        // the user resized the window through its borders
+        self.restore_size = None;
+        let size = self.clamp((max(width,1) as u32, max(height,1) as u32));
+        self.size = size;
+        self.newsize = Some(size);
     }
 }
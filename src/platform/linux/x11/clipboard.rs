@@ -0,0 +1,627 @@
+//! Implementation of the `CLIPBOARD` and `PRIMARY` selections, as described by the ICCCM.
+//!
+//! The contents of the selections we own are stored in the `XConnection`, so that they can be
+//! served whichever window's events iterator receives the requests of other clients.
+
+use std::collections::HashMap;
+use std::{cmp, mem, ptr};
+use std::os::raw::c_ulong;
+use std::time::{Duration, Instant};
+
+use libc;
+
+use {ClipboardContents, ClipboardKind, TEXT_MIME_TYPE, TEXT_UTF8_MIME_TYPE};
+
+use super::ffi;
+use super::XConnection;
+
+/// Data bigger than this is sent with an `INCR` transfer, in chunks of this size.
+const INCR_CHUNK_SIZE: usize = 64 * 1024;
+
+/// How long we wait for the owner of a selection to answer before giving up.
+const TRANSFER_TIMEOUT_MS: u64 = 1000;
+
+/// The selections owned by the windows of a connection.
+#[derive(Default)]
+pub struct ClipboardState {
+    // the selections we own, by selection atom
+    owned: HashMap<ffi::Atom, OwnedSelection>,
+    // the `INCR` transfers that wait for the requestor to delete the previous chunk
+    transfers: Vec<IncrTransfer>,
+    // the timestamp of the last event received from the server, `CurrentTime` until there is one
+    event_time: ffi::Time,
+}
+
+struct OwnedSelection {
+    window: ffi::Window,
+    contents: ClipboardContents,
+    // when the ownership was taken, as answered to the `TIMESTAMP` target
+    time: ffi::Time,
+}
+
+struct IncrTransfer {
+    requestor: ffi::Window,
+    property: ffi::Atom,
+    type_: ffi::Atom,
+    data: Vec<u8>,
+    offset: usize,
+}
+
+fn selection_atom(display: &XConnection, kind: ClipboardKind) -> ffi::Atom {
+    match kind {
+        ClipboardKind::Clipboard => display.get_atom("CLIPBOARD"),
+        ClipboardKind::Primary => ffi::XA_PRIMARY,
+    }
+}
+
+// `STRING` is encoded in Latin-1, the characters it can't encode are replaced with `?`.
+fn utf8_to_latin1(data: &[u8]) -> Vec<u8> {
+    String::from_utf8_lossy(data).chars()
+                                 .map(|c| if (c as u32) < 0x100 { c as u8 } else { b'?' })
+                                 .collect()
+}
+
+fn latin1_to_utf8(data: &[u8]) -> Vec<u8> {
+    data.iter().map(|&byte| byte as char).collect::<String>().into_bytes()
+}
+
+/// Remembers the timestamp of an event of the core protocol, if it has one.
+///
+/// The ICCCM requires the timestamp of the event that caused it to take the ownership of a
+/// selection and to convert one.
+pub fn update_event_time(display: &XConnection, xev: &ffi::XEvent) {
+    let time = unsafe {
+        match xev.get_type() {
+            ffi::KeyPress | ffi::KeyRelease => mem::transmute::<_, &ffi::XKeyEvent>(xev).time,
+            ffi::ButtonPress | ffi::ButtonRelease => mem::transmute::<_, &ffi::XButtonEvent>(xev).time,
+            ffi::MotionNotify => mem::transmute::<_, &ffi::XMotionEvent>(xev).time,
+            ffi::EnterNotify | ffi::LeaveNotify => mem::transmute::<_, &ffi::XCrossingEvent>(xev).time,
+            ffi::PropertyNotify => mem::transmute::<_, &ffi::XPropertyEvent>(xev).time,
+            _ => return,
+        }
+    };
+    set_event_time(display, time);
+}
+
+/// Same as `update_event_time`, for the input events of XInput2.
+pub fn update_xi_event_time(display: &XConnection, cookie: &ffi::XGenericEventCookie) {
+    match cookie.evtype {
+        ffi::XI_KeyPress | ffi::XI_KeyRelease | ffi::XI_ButtonPress | ffi::XI_ButtonRelease |
+        ffi::XI_Motion | ffi::XI_TouchBegin | ffi::XI_TouchUpdate | ffi::XI_TouchEnd => {
+            let event: &ffi::XIDeviceEvent = unsafe { mem::transmute(cookie.data) };
+            set_event_time(display, event.time);
+        },
+        _ => (),
+    }
+}
+
+fn set_event_time(display: &XConnection, time: ffi::Time) {
+    if time != ffi::CurrentTime {
+        display.clipboard.lock().unwrap().event_time = time;
+    }
+}
+
+/// Returns the timestamp of the last event, `CurrentTime` if there was none.
+pub fn last_event_time(display: &XConnection) -> ffi::Time {
+    display.clipboard.lock().unwrap().event_time
+}
+
+// Returns the timestamp of the last event, or the current time of the server if there was no
+// event yet, which is read from the notification of a property change of our window.
+fn ownership_time(display: &XConnection, window: ffi::Window) -> ffi::Time {
+    let time = last_event_time(display);
+    if time != ffi::CurrentTime {
+        return time;
+    }
+
+    let property = display.get_atom("_WINIT_TIMESTAMP");
+    unsafe {
+        (display.xlib.XChangeProperty)(display.display, window, property, ffi::XA_INTEGER, 8,
+                                       ffi::PropModeAppend, ptr::null(), 0);
+    }
+    let event = wait_for_event(display, window, ffi::PropertyNotify, |xev| {
+        let event: &ffi::XPropertyEvent = unsafe { mem::transmute(xev) };
+        event.atom == property
+    });
+    match event {
+        Some(xev) => {
+            let event: &ffi::XPropertyEvent = unsafe { mem::transmute(&xev) };
+            set_event_time(display, event.time);
+            event.time
+        },
+        None => ffi::CurrentTime,
+    }
+}
+
+/// Returns the targets under which a MIME type is served, preferred first.
+pub fn mime_targets(display: &XConnection, mime_type: &str) -> Vec<ffi::Atom> {
+    if mime_type == TEXT_UTF8_MIME_TYPE {
+        vec![display.get_atom("UTF8_STRING"), display.get_atom(mime_type)]
+    } else if mime_type == TEXT_MIME_TYPE {
+        vec![display.get_atom(mime_type), ffi::XA_STRING, display.get_atom("TEXT")]
+    } else {
+        vec![display.get_atom(mime_type)]
+    }
+}
+
+/// Returns the MIME type corresponding to a target, if it is one.
+fn target_mime(display: &XConnection, target: ffi::Atom) -> Option<String> {
+    if target == display.get_atom("UTF8_STRING") {
+        return Some(TEXT_UTF8_MIME_TYPE.to_owned());
+    }
+    if target == ffi::XA_STRING || target == display.get_atom("TEXT") {
+        return Some(TEXT_MIME_TYPE.to_owned());
+    }
+
+    // targets like `TARGETS` or `TIMESTAMP` aren't formats of the contents
+    display.get_atom_name(target).and_then(|name| if name.contains('/') { Some(name) } else { None })
+}
+
+/// Returns the contents of a selection if one of our windows still owns it.
+fn owned_contents(display: &XConnection, selection: ffi::Atom) -> Option<ClipboardContents> {
+    let owner = unsafe { (display.xlib.XGetSelectionOwner)(display.display, selection) };
+    let state = display.clipboard.lock().unwrap();
+    match state.owned.get(&selection) {
+        Some(owned) if owned.window == owner => Some(owned.contents.clone()),
+        _ => None,
+    }
+}
+
+pub fn set_clipboard(display: &XConnection, window: ffi::Window, kind: ClipboardKind,
+                     contents: ClipboardContents) -> Result<(), String>
+{
+    own_selection(display, window, selection_atom(display, kind), contents)
+}
+
+/// Takes the ownership of a selection and serves its contents until another client takes it or
+/// `disown_selection` is called.
+pub fn own_selection(display: &XConnection, window: ffi::Window, selection: ffi::Atom,
+                     contents: ClipboardContents) -> Result<(), String>
+{
+    let time = ownership_time(display, window);
+    let owner = unsafe {
+        (display.xlib.XSetSelectionOwner)(display.display, selection, window, time);
+        (display.xlib.XGetSelectionOwner)(display.display, selection)
+    };
+    if let Err(err) = display.check_errors() {
+        return Err(format!("Failed to take the selection: {}", err));
+    }
+    if owner != window {
+        return Err("Failed to take the selection".to_owned());
+    }
+
+    display.clipboard.lock().unwrap().owned.insert(selection, OwnedSelection {
+        window: window,
+        contents: contents,
+        time: time,
+    });
+    Ok(())
+}
+
+/// Gives up the ownership of a selection taken with `own_selection`.
+pub fn disown_selection(display: &XConnection, window: ffi::Window, selection: ffi::Atom) {
+    let mut state = display.clipboard.lock().unwrap();
+    let owned = match state.owned.get(&selection) {
+        Some(owned) => owned.window == window,
+        None => false,
+    };
+    if owned {
+        state.owned.remove(&selection);
+        let time = state.event_time;
+        unsafe { (display.xlib.XSetSelectionOwner)(display.display, selection, 0, time) };
+        display.ignore_error();
+    }
+}
+
+pub fn clipboard_mime_types(display: &XConnection, window: ffi::Window, kind: ClipboardKind)
+                            -> Vec<String>
+{
+    let selection = selection_atom(display, kind);
+    if let Some(contents) = owned_contents(display, selection) {
+        return contents.mime_types().into_iter().map(|mime| mime.to_owned()).collect();
+    }
+
+    let mut mime_types: Vec<String> = Vec::new();
+    let time = last_event_time(display);
+    for target in get_targets(display, window, selection, time).unwrap_or(Vec::new()) {
+        if let Some(mime) = target_mime(display, target) {
+            if !mime_types.contains(&mime) {
+                mime_types.push(mime);
+            }
+        }
+    }
+    mime_types
+}
+
+pub fn get_clipboard(display: &XConnection, window: ffi::Window, kind: ClipboardKind,
+                     mime_type: &str) -> Option<Vec<u8>>
+{
+    get_selection(display, window, selection_atom(display, kind), mime_type,
+                  last_event_time(display))
+}
+
+/// Returns the contents of a selection in a MIME type, asking its owner for them at the given
+/// time unless one of our windows owns it.
+pub fn get_selection(display: &XConnection, window: ffi::Window, selection: ffi::Atom,
+                     mime_type: &str, time: ffi::Time) -> Option<Vec<u8>>
+{
+    if let Some(contents) = owned_contents(display, selection) {
+        return contents.get(mime_type).map(|data| data.to_vec());
+    }
+
+    let mut targets = mime_targets(display, mime_type);
+    // old clients don't support `TARGETS`, in which case we try our preferred target blindly
+    if let Some(available) = get_targets(display, window, selection, time) {
+        targets.retain(|target| available.contains(target));
+    } else {
+        targets.truncate(1);
+    }
+
+    targets.into_iter()
+           .filter_map(|target| convert_selection(display, window, selection, target, time))
+           .next()
+           .map(|(type_, data)| if type_ == ffi::XA_STRING { latin1_to_utf8(&data) } else { data })
+}
+
+/// Asks the owner of a selection for the list of its targets.
+fn get_targets(display: &XConnection, window: ffi::Window, selection: ffi::Atom,
+               time: ffi::Time) -> Option<Vec<ffi::Atom>>
+{
+    let targets = display.get_atom("TARGETS");
+    let property = display.get_atom("_WINIT_SELECTION");
+    if !request_conversion(display, window, selection, targets, property, time) {
+        return None;
+    }
+
+    let atoms = display.get_property(window, property, ffi::XA_ATOM);
+    unsafe { (display.xlib.XDeleteProperty)(display.display, window, property) };
+    display.ignore_error();
+    atoms.map(|atoms| atoms.into_iter().map(|atom| atom as ffi::Atom).collect())
+}
+
+/// Asks the owner of a selection to convert it to a target, and returns the type and the data.
+fn convert_selection(display: &XConnection, window: ffi::Window, selection: ffi::Atom,
+                     target: ffi::Atom, time: ffi::Time) -> Option<(ffi::Atom, Vec<u8>)>
+{
+    let property = display.get_atom("_WINIT_SELECTION");
+    if !request_conversion(display, window, selection, target, property, time) {
+        return None;
+    }
+
+    let (type_, data) = match display.take_property_bytes(window, property) {
+        Some(property) => property,
+        None => return None,
+    };
+    if type_ != display.get_atom("INCR") {
+        return Some((type_, data));
+    }
+
+    // the data is too big for a single property, the owner now sends it in chunks and waits
+    // for us to delete each of them, which `take_property_bytes` did for the first one
+    let mut result = Vec::new();
+    let mut result_type = target;
+    loop {
+        let event = wait_for_event(display, window, ffi::PropertyNotify, |xev| {
+            let event: &ffi::XPropertyEvent = unsafe { mem::transmute(xev) };
+            event.atom == property && event.state == ffi::PropertyNewValue
+        });
+        if event.is_none() {
+            return None;
+        }
+
+        // events queued before the transfer started may refer to a property that was deleted
+        match display.take_property_bytes(window, property) {
+            Some((_, ref chunk)) if chunk.is_empty() => return Some((result_type, result)),
+            Some((chunk_type, chunk)) => {
+                result_type = chunk_type;
+                result.extend_from_slice(&chunk);
+            },
+            None => continue,
+        }
+    }
+}
+
+/// Sends a `ConvertSelection` request and waits for the answer of the owner. Returns `true` if
+/// the data was stored in the property.
+fn request_conversion(display: &XConnection, window: ffi::Window, selection: ffi::Atom,
+                      target: ffi::Atom, property: ffi::Atom, time: ffi::Time) -> bool
+{
+    unsafe {
+        (display.xlib.XConvertSelection)(display.display, selection, target, property, window,
+                                         time);
+    }
+    if display.check_errors().is_err() {
+        return false;
+    }
+
+    let event = wait_for_event(display, window, ffi::SelectionNotify, |xev| {
+        let event: &ffi::XSelectionEvent = unsafe { mem::transmute(xev) };
+        event.selection == selection && event.target == target
+    });
+    match event {
+        Some(xev) => {
+            let event: &ffi::XSelectionEvent = unsafe { mem::transmute(&xev) };
+            // a property of `None` means that the owner refused the conversion
+            event.property == property
+        },
+        None => false,
+    }
+}
+
+/// Waits for an event of the given type on our window. The other events of this type are left
+/// in the queue.
+fn wait_for_event<F>(display: &XConnection, window: ffi::Window, event_type: libc::c_int,
+                     mut filter: F) -> Option<ffi::XEvent>
+    where F: FnMut(&ffi::XEvent) -> bool
+{
+    let deadline = Instant::now() + Duration::from_millis(TRANSFER_TIMEOUT_MS);
+    let mut skipped = Vec::new();
+    let mut result = None;
+    unsafe { (display.xlib.XFlush)(display.display) };
+
+    loop {
+        // reads what has arrived on the connection without blocking
+        let mut xev = unsafe { mem::uninitialized() };
+        let found = unsafe {
+            (display.xlib.XCheckTypedWindowEvent)(display.display, window, event_type, &mut xev)
+        };
+        if found != 0 {
+            if filter(&xev) {
+                result = Some(xev);
+                break;
+            }
+            skipped.push(xev);
+            continue;
+        }
+
+        // waits until something else arrives on the connection, or the transfer times out
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        let remaining = deadline - now;
+        let millis = remaining.as_secs() as libc::c_int * 1000 +
+                     ((remaining.subsec_nanos() + 999_999) / 1_000_000) as libc::c_int;
+        let mut pollfd = libc::pollfd {
+            fd: unsafe { (display.xlib.XConnectionNumber)(display.display) },
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut pollfd, 1, millis) };
+    }
+
+    // `XPutBackEvent` pushes to the front of the queue
+    for mut xev in skipped.into_iter().rev() {
+        unsafe { (display.xlib.XPutBackEvent)(display.display, &mut xev) };
+    }
+    result
+}
+
+/// Drops the contents of the selections owned by a window that is destroyed.
+pub fn forget_window(display: &XConnection, window: ffi::Window) {
+    let mut state = display.clipboard.lock().unwrap();
+    let selections: Vec<_> = state.owned.iter().filter(|&(_, owned)| owned.window == window)
+                                  .map(|(&selection, _)| selection).collect();
+    for selection in selections {
+        state.owned.remove(&selection);
+    }
+}
+
+/// Processes the events related to the selections we own.
+pub fn handle_event(display: &XConnection, xev: &ffi::XEvent) {
+    match xev.get_type() {
+        ffi::SelectionRequest => {
+            let request: &ffi::XSelectionRequestEvent = unsafe { mem::transmute(xev) };
+            let property = answer_request(display, request);
+
+            let notify = ffi::XSelectionEvent {
+                type_: ffi::SelectionNotify,
+                serial: 0,
+                send_event: 1,
+                display: display.display,
+                requestor: request.requestor,
+                selection: request.selection,
+                target: request.target,
+                property: property,
+                time: request.time,
+            };
+            let mut notify = ffi::XEvent::from(notify);
+            unsafe {
+                (display.xlib.XSendEvent)(display.display, request.requestor, ffi::False, 0,
+                                          &mut notify);
+                (display.xlib.XFlush)(display.display);
+            }
+            // the requestor may have been destroyed in the meantime
+            display.ignore_error();
+        },
+
+        ffi::SelectionClear => {
+            let event: &ffi::XSelectionClearEvent = unsafe { mem::transmute(xev) };
+            let mut state = display.clipboard.lock().unwrap();
+            let cleared = match state.owned.get(&event.selection) {
+                Some(owned) => owned.window == event.window,
+                None => false,
+            };
+            if cleared {
+                state.owned.remove(&event.selection);
+            }
+        },
+
+        ffi::PropertyNotify => {
+            let event: &ffi::XPropertyEvent = unsafe { mem::transmute(xev) };
+            if event.state != ffi::PropertyDelete {
+                return;
+            }
+
+            let mut state = display.clipboard.lock().unwrap();
+            let index = match state.transfers.iter().position(|t| {
+                t.requestor == event.window && t.property == event.atom
+            }) {
+                Some(index) => index,
+                None => return,
+            };
+
+            // the requestor read the previous chunk, send the next one; the last chunk is empty
+            let finished = {
+                let transfer = &mut state.transfers[index];
+                let end = cmp::min(transfer.offset + INCR_CHUNK_SIZE, transfer.data.len());
+                change_property_bytes(display, transfer.requestor, transfer.property,
+                                      transfer.type_, &transfer.data[transfer.offset .. end]);
+                let finished = transfer.offset == end;
+                transfer.offset = end;
+                finished
+            };
+            if finished {
+                let transfer = state.transfers.remove(index);
+                if state.transfers.iter().all(|t| t.requestor != transfer.requestor) {
+                    unsafe {
+                        (display.xlib.XSelectInput)(display.display, transfer.requestor,
+                                                    ffi::NoEventMask);
+                    }
+                }
+            }
+            unsafe { (display.xlib.XFlush)(display.display) };
+            display.ignore_error();
+        },
+
+        _ => (),
+    }
+}
+
+/// Stores the data requested by another client in the requested property. Returns the
+/// property, or `0` if the request is refused.
+fn answer_request(display: &XConnection, request: &ffi::XSelectionRequestEvent) -> ffi::Atom {
+    // obsolete clients don't give a property
+    let property = if request.property == 0 { request.target } else { request.property };
+
+    let (contents, time) = match display.clipboard.lock().unwrap().owned.get(&request.selection) {
+        Some(owned) if owned.window == request.owner => (owned.contents.clone(), owned.time),
+        _ => return 0,
+    };
+    // the request was made before we took the selection, it is meant for the previous owner
+    if request.time != ffi::CurrentTime && request.time < time {
+        return 0;
+    }
+
+    if request.target == display.get_atom("MULTIPLE") {
+        // the property holds pairs of targets and properties, in which the properties of the
+        // conversions we refuse are replaced with `None`
+        let atom_pair = display.get_atom("ATOM_PAIR");
+        let mut pairs = match display.get_property(request.requestor, property, atom_pair) {
+            Some(pairs) => pairs,
+            None => return 0,
+        };
+        for pair in pairs.chunks_mut(2).filter(|pair| pair.len() == 2) {
+            let (target, target_property) = (pair[0] as ffi::Atom, pair[1] as ffi::Atom);
+            let converted = target != request.target && target_property != 0 &&
+                            convert_target(display, request.requestor, target, target_property,
+                                           &contents, time);
+            if !converted {
+                pair[1] = 0;
+            }
+        }
+        unsafe {
+            (display.xlib.XChangeProperty)(display.display, request.requestor, property, atom_pair,
+                                           32, ffi::PropModeReplace, pairs.as_ptr() as *const _,
+                                           pairs.len() as libc::c_int);
+        }
+        return property;
+    }
+
+    if convert_target(display, request.requestor, request.target, property, &contents, time) {
+        property
+    } else {
+        0
+    }
+}
+
+// Stores the contents of a selection we own, converted to a target, in a property of the
+// requestor. Returns `false` if the contents can't be converted.
+fn convert_target(display: &XConnection, requestor: ffi::Window, target: ffi::Atom,
+                  property: ffi::Atom, contents: &ClipboardContents, time: ffi::Time) -> bool
+{
+    if target == display.get_atom("TARGETS") {
+        let mut targets: Vec<c_ulong> = vec![display.get_atom("TARGETS"),
+                                             display.get_atom("TIMESTAMP"),
+                                             display.get_atom("MULTIPLE")];
+        for mime in contents.mime_types() {
+            for target in mime_targets(display, mime) {
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+        }
+        unsafe {
+            (display.xlib.XChangeProperty)(display.display, requestor, property,
+                                           ffi::XA_ATOM, 32, ffi::PropModeReplace,
+                                           targets.as_ptr() as *const _, targets.len() as libc::c_int);
+        }
+        return true;
+    }
+
+    if target == display.get_atom("TIMESTAMP") {
+        let time = time as c_ulong;
+        unsafe {
+            (display.xlib.XChangeProperty)(display.display, requestor, property,
+                                           ffi::XA_INTEGER, 32, ffi::PropModeReplace,
+                                           &time as *const c_ulong as *const _, 1);
+        }
+        return true;
+    }
+
+    // `STRING` is Latin-1, which we also answer for `TEXT` as it lets us pick the encoding
+    let (type_, data) = if target == ffi::XA_STRING || target == display.get_atom("TEXT") {
+        match contents.get(TEXT_MIME_TYPE).or_else(|| contents.get(TEXT_UTF8_MIME_TYPE)) {
+            Some(data) => (ffi::XA_STRING, utf8_to_latin1(data)),
+            None => return false,
+        }
+    } else {
+        match target_mime(display, target).and_then(|mime| contents.get(&mime)) {
+            Some(data) => (target, data.to_vec()),
+            None => return false,
+        }
+    };
+
+    if data.len() > INCR_CHUNK_SIZE {
+        // we are notified when the requestor deletes the property, to send the next chunk
+        let length = data.len() as c_ulong;
+        unsafe {
+            (display.xlib.XSelectInput)(display.display, requestor, ffi::PropertyChangeMask);
+            (display.xlib.XChangeProperty)(display.display, requestor, property,
+                                           display.get_atom("INCR"), 32, ffi::PropModeReplace,
+                                           &length as *const c_ulong as *const _, 1);
+        }
+        display.clipboard.lock().unwrap().transfers.push(IncrTransfer {
+            requestor: requestor,
+            property: property,
+            type_: type_,
+            data: data,
+            offset: 0,
+        });
+    } else {
+        change_property_bytes(display, requestor, property, type_, &data);
+    }
+
+    true
+}
+
+fn change_property_bytes(display: &XConnection, window: ffi::Window, property: ffi::Atom,
+                         type_: ffi::Atom, data: &[u8])
+{
+    unsafe {
+        (display.xlib.XChangeProperty)(display.display, window, property, type_, 8,
+                                       ffi::PropModeReplace, data.as_ptr(), data.len() as libc::c_int);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{latin1_to_utf8, utf8_to_latin1};
+
+    #[test]
+    fn transcodes_latin1() {
+        assert_eq!(utf8_to_latin1("caf\u{e9} \u{20ac}".as_bytes()), b"caf\xe9 ?".to_vec());
+        assert_eq!(latin1_to_utf8(b"caf\xe9"), "caf\u{e9}".as_bytes().to_vec());
+        assert_eq!(latin1_to_utf8(&utf8_to_latin1(b"plain")), b"plain".to_vec());
+    }
+}
@@ -0,0 +1,676 @@
+//! Drag-and-drop with the XDND protocol, as described on
+//! https://freedesktop.org/wiki/Specifications/XDND/.
+//!
+//! Only lists of files, transferred as `text/uri-list`, are accepted by our windows. The data
+//! dragged out of them is served through the `XdndSelection` selection.
+
+use std::{cmp, mem};
+use std::os::raw::{c_long, c_uint};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use libc;
+
+use {WindowEvent as Event, DragAction, DragData, DragResult, URI_LIST_MIME_TYPE};
+
+use super::clipboard;
+use super::ffi;
+use super::XConnection;
+use super::super::uri_list::parse_uri_list;
+
+/// The version of the protocol we support.
+const XDND_VERSION: c_long = 5;
+
+/// How long we wait for the target of a drop to tell that it is done with the data.
+const FINISH_TIMEOUT_MS: u64 = 5000;
+
+/// How long we wait for the target to answer the last position once the button is released.
+const STATUS_TIMEOUT_MS: u64 = 1000;
+
+struct DndAtoms {
+    aware: ffi::Atom,
+    enter: ffi::Atom,
+    position: ffi::Atom,
+    status: ffi::Atom,
+    leave: ffi::Atom,
+    drop: ffi::Atom,
+    finished: ffi::Atom,
+    selection: ffi::Atom,
+    type_list: ffi::Atom,
+    action_list: ffi::Atom,
+    action_description: ffi::Atom,
+    proxy: ffi::Atom,
+    action_copy: ffi::Atom,
+    action_move: ffi::Atom,
+    action_link: ffi::Atom,
+    uri_list: ffi::Atom,
+}
+
+#[derive(Default)]
+struct DndState {
+    // the window of the application that drags something over our window
+    source: Option<ffi::Window>,
+    // the version of the protocol used by the source
+    version: c_long,
+    // whether the source offers a list of files
+    accepted: bool,
+    // the hovering files, requested when the pointer first moves over the window
+    paths: Option<Vec<PathBuf>>,
+}
+
+impl DndState {
+    fn hovered(&self) -> bool {
+        self.paths.as_ref().map(|paths| !paths.is_empty()).unwrap_or(false)
+    }
+}
+
+// Data dragged out of our window
+struct DragState {
+    types: Vec<ffi::Atom>,
+    // the allowed actions, the preferred one first
+    actions: Vec<ffi::Atom>,
+    // the XdndAware window under the pointer
+    target: Option<DropTarget>,
+    // whether the target accepts the drop, and with which action
+    accepted: bool,
+    action: ffi::Atom,
+    // whether the target has yet to answer the last position we sent, in which case the latest
+    // position of the pointer and its time wait to be sent
+    waiting_status: bool,
+    pending_position: Option<(c_long, ffi::Time)>,
+    // the time of the button release, if the target had yet to answer the last position
+    released: Option<(ffi::Time, Instant)>,
+    // when the data was dropped, the target then tells when it is done with it
+    dropped: Option<Instant>,
+}
+
+#[derive(Copy, Clone)]
+struct DropTarget {
+    window: ffi::Window,
+    // the window that receives the messages in place of the target, the target itself if it
+    // has no `XdndProxy`
+    proxy: ffi::Window,
+    // the version of the protocol supported by the target
+    version: c_long,
+}
+
+/// The drag-and-drop state of a window.
+pub struct Dnd {
+    atoms: DndAtoms,
+    state: Mutex<DndState>,
+    drag: Mutex<Option<DragState>>,
+}
+
+impl Dnd {
+    pub fn new(display: &XConnection) -> Dnd {
+        Dnd {
+            atoms: DndAtoms {
+                aware: display.get_atom("XdndAware"),
+                enter: display.get_atom("XdndEnter"),
+                position: display.get_atom("XdndPosition"),
+                status: display.get_atom("XdndStatus"),
+                leave: display.get_atom("XdndLeave"),
+                drop: display.get_atom("XdndDrop"),
+                finished: display.get_atom("XdndFinished"),
+                selection: display.get_atom("XdndSelection"),
+                type_list: display.get_atom("XdndTypeList"),
+                action_list: display.get_atom("XdndActionList"),
+                action_description: display.get_atom("XdndActionDescription"),
+                proxy: display.get_atom("XdndProxy"),
+                action_copy: display.get_atom("XdndActionCopy"),
+                action_move: display.get_atom("XdndActionMove"),
+                action_link: display.get_atom("XdndActionLink"),
+                uri_list: display.get_atom(URI_LIST_MIME_TYPE),
+            },
+            state: Mutex::new(DndState::default()),
+            drag: Mutex::new(None),
+        }
+    }
+
+    /// Tells the other applications that the window accepts drops.
+    pub fn set_aware(&self, display: &XConnection, window: ffi::Window) {
+        let version = XDND_VERSION;
+        unsafe {
+            (display.xlib.XChangeProperty)(display.display, window, self.atoms.aware, ffi::XA_ATOM,
+                                           32, ffi::PropModeReplace,
+                                           &version as *const c_long as *const _, 1);
+        }
+        display.check_errors().expect("Failed to set XdndAware");
+    }
+
+    /// Processes a client message of the XDND protocol. Returns `None` if the message is not
+    /// part of the protocol.
+    pub fn handle_client_message(&self, display: &XConnection, message: &ffi::XClientMessageEvent)
+                                 -> Option<Vec<Event>>
+    {
+        // the window under the cursor
+        let window = message.window;
+        let source = message.data.get_long(0) as ffi::Window;
+        let mut state = self.state.lock().unwrap();
+
+        if message.message_type == self.atoms.enter {
+            // the first three types are in the message, the others in a property of the source
+            let types: Vec<ffi::Atom> = if message.data.get_long(1) & 1 != 0 {
+                display.get_property(source, self.atoms.type_list, ffi::XA_ATOM).unwrap_or(Vec::new())
+                       .into_iter().map(|atom| atom as ffi::Atom).collect()
+            } else {
+                (2 .. 5).map(|i| message.data.get_long(i) as ffi::Atom).filter(|&atom| atom != 0).collect()
+            };
+
+            *state = DndState::default();
+            state.source = Some(source);
+            state.version = message.data.get_long(1) >> 24;
+            state.accepted = types.contains(&self.atoms.uri_list);
+            Some(Vec::new())
+
+        } else if message.message_type == self.atoms.position {
+            if state.source != Some(source) {
+                return Some(Vec::new());
+            }
+            // the data can be requested during the drag, with the time of the message, so that
+            // we know which files hover us
+            let mut events = Vec::new();
+            if state.accepted && state.paths.is_none() {
+                // sources older than the version 1 don't give the time
+                let time = if state.version >= 1 {
+                    message.data.get_long(3) as ffi::Time
+                } else {
+                    ffi::CurrentTime
+                };
+                let paths = self.dragged_paths(display, window, time);
+                events.extend(paths.iter().cloned().map(Event::HoveredFile));
+                state.paths = Some(paths);
+            }
+            // an empty rectangle means that we want a position message for every motion
+            let action = if state.accepted { self.atoms.action_copy } else { 0 };
+            self.send_message(display, source, self.atoms.status,
+                              [window as c_long, state.accepted as c_long, 0, 0, action as c_long]);
+            Some(events)
+
+        } else if message.message_type == self.atoms.leave {
+            if state.source != Some(source) {
+                return Some(Vec::new());
+            }
+            let hovered = state.hovered();
+            *state = DndState::default();
+            Some(if hovered { vec![Event::HoveredFileCancelled] } else { Vec::new() })
+
+        } else if message.message_type == self.atoms.drop {
+            if state.source != Some(source) {
+                return Some(Vec::new());
+            }
+            if !state.accepted {
+                self.send_message(display, source, self.atoms.finished, [window as c_long, 0, 0, 0, 0]);
+                *state = DndState::default();
+                return Some(Vec::new());
+            }
+            // the data must be requested again with the time of the drop
+            let time = message.data.get_long(2) as ffi::Time;
+            let paths = self.dragged_paths(display, window, time);
+            self.finish(display, window, &mut state, !paths.is_empty());
+            Some(paths.into_iter().map(Event::DroppedFile).collect())
+
+        } else {
+            None
+        }
+    }
+
+    // Asks the source for the dragged files, which may come in several chunks
+    fn dragged_paths(&self, display: &XConnection, window: ffi::Window, time: ffi::Time)
+                     -> Vec<PathBuf>
+    {
+        clipboard::get_selection(display, window, self.atoms.selection, URI_LIST_MIME_TYPE, time)
+            .map(|data| parse_uri_list(&data))
+            .unwrap_or(Vec::new())
+    }
+
+    /// Starts dragging data out of a window. The drag follows the pointer until the button,
+    /// which is supposed to be pressed, is released, or until it is cancelled with the escape
+    /// key. It is advanced by the events given to `handle_drag_event`.
+    ///
+    /// Returns the result of the drag if it ended right away, because no button is pressed.
+    pub fn start_drag(&self, display: &XConnection, window: ffi::Window, data: DragData,
+                      allowed_actions: &[DragAction]) -> Result<Option<DragResult>, String>
+    {
+        let mut drag = self.drag.lock().unwrap();
+        if drag.is_some() {
+            return Err("Data is already dragged out of the window".to_owned());
+        }
+
+        let buttons = unsafe {
+            let (mut root, mut child) = (0, 0);
+            let (mut root_x, mut root_y, mut x, mut y) = (0, 0, 0, 0);
+            let mut mask = 0;
+            (display.xlib.XQueryPointer)(display.display, window, &mut root, &mut child, &mut root_x,
+                                         &mut root_y, &mut x, &mut y, &mut mask);
+            // the buttons 4 and 5 are the wheel, which is never held
+            mask & (ffi::Button1Mask | ffi::Button2Mask | ffi::Button3Mask)
+        };
+        if buttons == 0 {
+            return Ok(Some(DragResult::Cancelled));
+        }
+
+        let mut types: Vec<ffi::Atom> = Vec::new();
+        for mime in data.contents().mime_types() {
+            for target in clipboard::mime_targets(display, mime) {
+                if !types.contains(&target) {
+                    types.push(target);
+                }
+            }
+        }
+        if types.len() > 3 {
+            // the targets only read the first three types from the messages
+            let list: Vec<c_long> = types.iter().map(|&atom| atom as c_long).collect();
+            unsafe {
+                (display.xlib.XChangeProperty)(display.display, window, self.atoms.type_list,
+                                               ffi::XA_ATOM, 32, ffi::PropModeReplace,
+                                               list.as_ptr() as *const _, list.len() as libc::c_int);
+            }
+        }
+
+        let mut actions: Vec<ffi::Atom> = Vec::new();
+        for &action in allowed_actions {
+            let atom = self.action_atom(action);
+            if !actions.contains(&atom) {
+                actions.push(atom);
+            }
+        }
+        if actions.len() > 1 {
+            // the targets that let the user choose the action read the others from properties,
+            // the descriptions being separated by null characters
+            let list: Vec<c_long> = actions.iter().map(|&atom| atom as c_long).collect();
+            let mut descriptions = Vec::new();
+            for &action in allowed_actions {
+                let description: &[u8] = match action {
+                    DragAction::Copy => b"Copy",
+                    DragAction::Move => b"Move",
+                    DragAction::Link => b"Link",
+                };
+                descriptions.extend_from_slice(description);
+                descriptions.push(0);
+            }
+            unsafe {
+                (display.xlib.XChangeProperty)(display.display, window, self.atoms.action_list,
+                                               ffi::XA_ATOM, 32, ffi::PropModeReplace,
+                                               list.as_ptr() as *const _, list.len() as libc::c_int);
+                (display.xlib.XChangeProperty)(display.display, window,
+                                               self.atoms.action_description, ffi::XA_STRING, 8,
+                                               ffi::PropModeReplace, descriptions.as_ptr(),
+                                               descriptions.len() as libc::c_int);
+            }
+        }
+        try!(clipboard::own_selection(display, window, self.atoms.selection, data.contents().clone()));
+
+        // all the pointer events go to our window until the drop
+        let grab = unsafe {
+            (display.xlib.XGrabPointer)(display.display, window, ffi::False,
+                                        (ffi::ButtonReleaseMask | ffi::PointerMotionMask) as c_uint,
+                                        ffi::GrabModeAsync, ffi::GrabModeAsync, 0, 0,
+                                        ffi::CurrentTime)
+        };
+        if grab != ffi::GrabSuccess {
+            self.end_drag(display, window);
+            return Err("Failed to grab the pointer".to_owned());
+        }
+        unsafe {
+            (display.xlib.XGrabKeyboard)(display.display, window, ffi::False, ffi::GrabModeAsync,
+                                         ffi::GrabModeAsync, ffi::CurrentTime);
+            (display.xlib.XFlush)(display.display);
+        }
+        display.ignore_error();
+
+        let preferred_action = actions[0];
+        *drag = Some(DragState {
+            types: types,
+            actions: actions,
+            target: None,
+            accepted: false,
+            action: preferred_action,
+            waiting_status: false,
+            pending_position: None,
+            released: None,
+            dropped: None,
+        });
+        Ok(None)
+    }
+
+    /// Advances the drag of data out of the window with an event, and returns the
+    /// `DragFinished` event once it ends. Returns `None` if the event is unrelated to the drag.
+    pub fn handle_drag_event(&self, display: &XConnection, window: ffi::Window, xev: &mut ffi::XEvent)
+                             -> Option<Vec<Event>>
+    {
+        let mut drag_guard = self.drag.lock().unwrap();
+        let result = {
+            let drag = match *drag_guard {
+                Some(ref mut drag) => drag,
+                None => return None,
+            };
+
+            match xev.get_type() {
+                ffi::MotionNotify if drag.dropped.is_none() => {
+                    // the target under the pointer once the button is released gets the drop
+                    if drag.released.is_none() {
+                        let motion: &ffi::XMotionEvent = unsafe { mem::transmute(&*xev) };
+                        self.drag_motion(display, window, drag, motion);
+                    }
+                    None
+                },
+
+                ffi::ClientMessage => {
+                    let message: &ffi::XClientMessageEvent = unsafe { mem::transmute(&*xev) };
+                    let from_target = drag.target.map(|t| t.window as c_long) == Some(message.data.get_long(0));
+                    if !from_target {
+                        return None;
+                    }
+                    if message.message_type == self.atoms.status && drag.dropped.is_none() {
+                        // the drop is refused if the target picks an action we don't allow
+                        drag.action = message.data.get_long(4) as ffi::Atom;
+                        drag.accepted = message.data.get_long(1) & 1 != 0 &&
+                                        drag.actions.contains(&drag.action);
+                        drag.waiting_status = false;
+                        if let Some((time, _)) = drag.released.take() {
+                            self.drop_data(display, window, drag, time)
+                        } else {
+                            if let Some((position, time)) = drag.pending_position.take() {
+                                self.send_position(display, window, drag, position, time);
+                            }
+                            None
+                        }
+                    } else if message.message_type == self.atoms.finished && drag.dropped.is_some() {
+                        // targets older than the version 5 don't tell if the drop succeeded
+                        let (success, action) = match self.action(message.data.get_long(2) as ffi::Atom) {
+                            Some(action) => (message.data.get_long(1) & 1 != 0, Some(action)),
+                            None => (true, self.action(drag.action)),
+                        };
+                        Some(if success {
+                            DragResult::Completed(action.unwrap_or(DragAction::Copy))
+                        } else {
+                            DragResult::Cancelled
+                        })
+                    } else {
+                        return None;
+                    }
+                },
+
+                ffi::ButtonRelease if drag.dropped.is_none() && drag.released.is_none() => {
+                    let release: &ffi::XButtonEvent = unsafe { mem::transmute(&*xev) };
+                    if drag.waiting_status {
+                        // the target may still refuse the drop at the last position
+                        drag.released = Some((release.time, Instant::now()));
+                        None
+                    } else {
+                        self.drop_data(display, window, drag, release.time)
+                    }
+                },
+
+                ffi::KeyPress if drag.dropped.is_none() => {
+                    let key: &mut ffi::XKeyEvent = unsafe { mem::transmute(&mut *xev) };
+                    let keysym = unsafe { (display.xlib.XLookupKeysym)(key, 0) };
+                    if keysym == ffi::XK_Escape as ffi::KeySym {
+                        if let Some(target) = drag.target {
+                            self.notify_target(display, target, self.atoms.leave,
+                                               [window as c_long, 0, 0, 0, 0]);
+                        }
+                        Some(DragResult::Cancelled)
+                    } else {
+                        None
+                    }
+                },
+
+                ffi::KeyRelease | ffi::ButtonRelease if drag.dropped.is_none() => None,
+
+                _ => return None,
+            }
+        };
+
+        Some(match result {
+            Some(result) => {
+                *drag_guard = None;
+                self.end_drag(display, window);
+                vec![Event::DragFinished(result)]
+            },
+            None => Vec::new(),
+        })
+    }
+
+    /// Ends the drag if the target didn't answer in time, either to the last position once the
+    /// button is released, which cancels the drag, or to the drop, in which case we assume
+    /// that it took the data as it told. Returns the `DragFinished` event.
+    pub fn check_drag_timeout(&self, display: &XConnection, window: ffi::Window) -> Option<Event> {
+        let mut drag_guard = self.drag.lock().unwrap();
+        let result = match *drag_guard {
+            Some(DragState { dropped: Some(time), action, .. })
+                if time.elapsed() >= Duration::from_millis(FINISH_TIMEOUT_MS) =>
+            {
+                DragResult::Completed(self.action(action).unwrap_or(DragAction::Copy))
+            },
+            Some(DragState { released: Some((_, time)), target, .. })
+                if time.elapsed() >= Duration::from_millis(STATUS_TIMEOUT_MS) =>
+            {
+                if let Some(target) = target {
+                    self.notify_target(display, target, self.atoms.leave,
+                                       [window as c_long, 0, 0, 0, 0]);
+                }
+                DragResult::Cancelled
+            },
+            _ => return None,
+        };
+        *drag_guard = None;
+        self.end_drag(display, window);
+        Some(Event::DragFinished(result))
+    }
+
+    // Tells the targets under the pointer that data is dragged over them
+    fn drag_motion(&self, display: &XConnection, window: ffi::Window, drag: &mut DragState,
+                   motion: &ffi::XMotionEvent)
+    {
+        let new_target = self.find_target(display, window, motion.x_root, motion.y_root);
+
+        if new_target.map(|t| t.window) != drag.target.map(|t| t.window) {
+            if let Some(old_target) = drag.target {
+                self.notify_target(display, old_target, self.atoms.leave, [window as c_long, 0, 0, 0, 0]);
+            }
+            drag.target = new_target;
+            drag.accepted = false;
+            drag.waiting_status = false;
+            drag.pending_position = None;
+            if let Some(new_target) = drag.target {
+                let flags = cmp::min(new_target.version, XDND_VERSION) << 24 |
+                            (drag.types.len() > 3) as c_long;
+                let types = &drag.types;
+                let first_type = |i: usize| types.get(i).map(|&atom| atom as c_long).unwrap_or(0);
+                self.notify_target(display, new_target, self.atoms.enter,
+                                   [window as c_long, flags, first_type(0), first_type(1), first_type(2)]);
+            }
+        }
+
+        let position = (motion.x_root as c_long) << 16 | (motion.y_root as c_long & 0xffff);
+        if drag.waiting_status {
+            // the target must answer a position before it gets the next one
+            drag.pending_position = Some((position, motion.time));
+        } else {
+            self.send_position(display, window, drag, position, motion.time);
+        }
+    }
+
+    fn send_position(&self, display: &XConnection, window: ffi::Window, drag: &mut DragState,
+                     position: c_long, time: ffi::Time)
+    {
+        if let Some(target) = drag.target {
+            self.notify_target(display, target, self.atoms.position,
+                               [window as c_long, 0, position, time as c_long, drag.actions[0] as c_long]);
+            drag.waiting_status = true;
+        }
+    }
+
+    // Drops the data on the target under the pointer, or cancels the drag if there is none or if
+    // it refuses the data. Returns the result of the drag if it ended.
+    fn drop_data(&self, display: &XConnection, window: ffi::Window, drag: &mut DragState,
+                 time: ffi::Time) -> Option<DragResult>
+    {
+        match drag.target {
+            Some(target) if drag.accepted => {
+                self.notify_target(display, target, self.atoms.drop,
+                                   [window as c_long, 0, time as c_long, 0, 0]);
+                // the data is still served until the target is done with it
+                self.ungrab(display);
+                drag.dropped = Some(Instant::now());
+                None
+            },
+            Some(target) => {
+                self.notify_target(display, target, self.atoms.leave,
+                                   [window as c_long, 0, 0, 0, 0]);
+                Some(DragResult::Cancelled)
+            },
+            None => Some(DragResult::Cancelled),
+        }
+    }
+
+    fn ungrab(&self, display: &XConnection) {
+        unsafe {
+            (display.xlib.XUngrabPointer)(display.display, ffi::CurrentTime);
+            (display.xlib.XUngrabKeyboard)(display.display, ffi::CurrentTime);
+            (display.xlib.XFlush)(display.display);
+        }
+    }
+
+    // Stops serving the dragged data
+    fn end_drag(&self, display: &XConnection, window: ffi::Window) {
+        self.ungrab(display);
+        unsafe {
+            (display.xlib.XDeleteProperty)(display.display, window, self.atoms.type_list);
+            (display.xlib.XDeleteProperty)(display.display, window, self.atoms.action_list);
+            (display.xlib.XDeleteProperty)(display.display, window, self.atoms.action_description);
+        }
+        clipboard::disown_selection(display, window, self.atoms.selection);
+        display.ignore_error();
+    }
+
+    // Returns the deepest window that accepts drops under a position of the screen, except ours
+    fn find_target(&self, display: &XConnection, window: ffi::Window, x: libc::c_int,
+                   y: libc::c_int) -> Option<DropTarget>
+    {
+        let root = unsafe { (display.xlib.XDefaultRootWindow)(display.display) };
+        let mut current = root;
+        loop {
+            let (mut child, mut child_x, mut child_y) = (0, 0, 0);
+            let success = unsafe {
+                (display.xlib.XTranslateCoordinates)(display.display, root, current, x, y,
+                                                     &mut child_x, &mut child_y, &mut child)
+            };
+            if success == 0 {
+                display.ignore_error();
+                return None;
+            }
+
+            // the window managers reparent the windows, only the client windows are aware
+            if current != root {
+                let proxy = self.proxy(display, current);
+                let version = display.get_property(proxy, self.atoms.aware, ffi::XA_ATOM)
+                                     .and_then(|version| version.first().cloned());
+                if let Some(version) = version {
+                    return if current == window || version < 3 {
+                        None
+                    } else {
+                        Some(DropTarget { window: current, proxy: proxy, version: version as c_long })
+                    };
+                }
+            }
+
+            if child == 0 {
+                return None;
+            }
+            current = child;
+        }
+    }
+
+    // Returns the window named by the `XdndProxy` property of a window, or the window itself if
+    // there is none. A valid proxy names itself in the same property, the others are left over
+    // by crashed applications.
+    fn proxy(&self, display: &XConnection, window: ffi::Window) -> ffi::Window {
+        let read_proxy = |window| {
+            display.get_property(window, self.atoms.proxy, ffi::XA_WINDOW)
+                   .and_then(|proxy| proxy.first().cloned())
+                   .map(|proxy| proxy as ffi::Window)
+        };
+        match read_proxy(window) {
+            Some(proxy) if read_proxy(proxy) == Some(proxy) => proxy,
+            _ => window,
+        }
+    }
+
+    fn action_atom(&self, action: DragAction) -> ffi::Atom {
+        match action {
+            DragAction::Copy => self.atoms.action_copy,
+            DragAction::Move => self.atoms.action_move,
+            DragAction::Link => self.atoms.action_link,
+        }
+    }
+
+    fn action(&self, atom: ffi::Atom) -> Option<DragAction> {
+        if atom == self.atoms.action_copy {
+            Some(DragAction::Copy)
+        } else if atom == self.atoms.action_move {
+            Some(DragAction::Move)
+        } else if atom == self.atoms.action_link {
+            Some(DragAction::Link)
+        } else {
+            None
+        }
+    }
+
+    // Tells the source that we are done with the drop
+    fn finish(&self, display: &XConnection, window: ffi::Window, state: &mut DndState, success: bool) {
+        if let Some(source) = state.source {
+            let action = if success { self.atoms.action_copy } else { 0 };
+            self.send_message(display, source, self.atoms.finished,
+                              [window as c_long, success as c_long, action as c_long, 0, 0]);
+        }
+        *state = DndState::default();
+    }
+
+    // Sends a message to the target of the drag, through its proxy
+    fn notify_target(&self, display: &XConnection, target: DropTarget, message_type: ffi::Atom,
+                     data: [c_long; 5])
+    {
+        self.send_message_to(display, target.window, target.proxy, message_type, data);
+    }
+
+    fn send_message(&self, display: &XConnection, target: ffi::Window, message_type: ffi::Atom,
+                    data: [c_long; 5])
+    {
+        self.send_message_to(display, target, target, message_type, data);
+    }
+
+    // Sends a message for a window to another one, which is the window itself or its proxy
+    fn send_message_to(&self, display: &XConnection, target: ffi::Window, destination: ffi::Window,
+                       message_type: ffi::Atom, data: [c_long; 5])
+    {
+        let mut client_data = ffi::ClientMessageData::new();
+        for (i, &value) in data.iter().enumerate() {
+            client_data.set_long(i, value);
+        }
+
+        let client_message_event = ffi::XClientMessageEvent {
+            type_: ffi::ClientMessage,
+            serial: 0,
+            send_event: 1,
+            display: display.display,
+            window: target,
+            message_type: message_type,
+            format: 32,
+            data: client_data,
+        };
+        let mut x_event = ffi::XEvent::from(client_message_event);
+
+        unsafe {
+            (display.xlib.XSendEvent)(display.display, destination, ffi::False, ffi::NoEventMask,
+                                      &mut x_event as *mut _);
+            (display.xlib.XFlush)(display.display);
+        }
+        // the source may have been destroyed in the meantime
+        display.ignore_error();
+    }
+}
+
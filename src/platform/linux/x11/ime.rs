@@ -0,0 +1,414 @@
+//! Composition of text with the X input method.
+//!
+//! While the input method is allowed, the input context uses the on-the-spot style, in which the
+//! input method tells the text being composed through callbacks, so that the application can
+//! display it inside its editor. Otherwise the composition happens in a window of the input
+//! method, and only the committed text is received as key presses.
+
+use std::{char, cmp, ptr, slice};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_short, c_ulong, c_ushort, c_void};
+use std::sync::{Arc, Mutex, Once, ONCE_INIT};
+
+use libc;
+
+use {Ime as ImeEvent, WindowEvent as Event};
+
+use super::ffi;
+use super::XConnection;
+
+// The following definitions of Xlib.h are missing from x11-dl.
+
+#[repr(C)]
+struct XIMStyles {
+    count_styles: c_ushort,
+    supported_styles: *mut c_ulong,
+}
+
+#[repr(C)]
+struct XIMCallback {
+    client_data: ffi::XPointer,
+    callback: *mut c_void,
+}
+
+#[repr(C)]
+struct XIMText {
+    length: c_ushort,
+    feedback: *mut c_ulong,
+    encoding_is_wchar: ffi::Bool,
+    // a `wchar_t*` if `encoding_is_wchar` is true
+    string: *mut c_char,
+}
+
+#[repr(C)]
+struct XIMPreeditDrawCallbackStruct {
+    caret: c_int,
+    chg_first: c_int,
+    chg_length: c_int,
+    text: *mut XIMText,
+}
+
+#[repr(C)]
+struct XIMPreeditCaretCallbackStruct {
+    position: c_int,
+    direction: c_int,
+    style: c_int,
+}
+
+// values of `XIMCaretDirection`
+const XIM_FORWARD_CHAR: c_int = 0;
+const XIM_BACKWARD_CHAR: c_int = 1;
+const XIM_LINE_START: c_int = 8;
+const XIM_LINE_END: c_int = 9;
+const XIM_ABSOLUTE_POSITION: c_int = 10;
+
+// values of `XIMCaretStyle`
+const XIM_IS_INVISIBLE: c_int = 0;
+
+extern "C" {
+    // from stdlib.h, converts from the multibyte encoding of the `LC_CTYPE` locale
+    fn mbstowcs(dest: *mut libc::wchar_t, src: *const c_char, n: libc::size_t) -> libc::size_t;
+}
+
+static INIT_LOCALE: Once = ONCE_INIT;
+
+/// Opens the input method selected by the user with the `XMODIFIERS` environment variable.
+///
+/// The input methods only work in the locale of the user, so the `LC_CTYPE` category of the
+/// locale of the process is set from the environment the first time.
+pub fn open_im(display: &XConnection) -> Option<ffi::XIM> {
+    INIT_LOCALE.call_once(|| unsafe {
+        libc::setlocale(libc::LC_CTYPE, b"\0".as_ptr() as *const c_char);
+        (display.xlib.XSetLocaleModifiers)(b"\0".as_ptr() as *const c_char);
+    });
+
+    let im = unsafe {
+        (display.xlib.XOpenIM)(display.display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut())
+    };
+    if im.is_null() {
+        None
+    } else {
+        Some(im)
+    }
+}
+
+/// The state of the composition, shared with the callbacks of the input context.
+struct Preedit {
+    text: Vec<char>,
+    // the position of the cursor, in characters
+    cursor: usize,
+    cursor_visible: bool,
+    // the events reported by the callbacks, until the window retrieves them
+    events: Vec<Event>,
+}
+
+impl Preedit {
+    fn push_event(&mut self) {
+        let text: String = self.text.iter().cloned().collect();
+        let cursor_range = if self.cursor_visible {
+            let cursor = self.text[.. self.cursor].iter().map(|c| c.len_utf8()).sum();
+            Some((cursor, cursor))
+        } else {
+            None
+        };
+        self.events.push(Event::Ime(ImeEvent::Preedit { text: text, cursor_range: cursor_range }));
+    }
+}
+
+/// The input context of a window.
+pub struct Ime {
+    display: Arc<XConnection>,
+    im: ffi::XIM,
+    window: ffi::Window,
+    ic: ffi::XIC,
+    // the style of the input context, which tells how it uses the preedit attributes
+    style: c_ulong,
+    allowed: bool,
+    // where the input method places its candidate window, in window coordinates
+    spot: ffi::XPoint,
+    // boxed so that the callbacks can keep a pointer to it
+    preedit: Box<Mutex<Preedit>>,
+}
+
+impl Ime {
+    /// Creates the input context of a window, in which the input method is not allowed.
+    pub fn new(display: &Arc<XConnection>, im: ffi::XIM, window: ffi::Window) -> Result<Ime, String> {
+        let mut ime = Ime {
+            display: display.clone(),
+            im: im,
+            window: window,
+            ic: ptr::null_mut(),
+            style: 0,
+            allowed: false,
+            spot: ffi::XPoint { x: 0, y: 0 },
+            preedit: Box::new(Mutex::new(Preedit {
+                text: Vec::new(),
+                cursor: 0,
+                cursor_visible: false,
+                events: Vec::new(),
+            })),
+        };
+        try!(ime.create_ic((ffi::XIMPreeditNothing | ffi::XIMStatusNothing) as c_ulong));
+        Ok(ime)
+    }
+
+    #[inline]
+    pub fn ic(&self) -> ffi::XIC {
+        self.ic
+    }
+
+    /// Returns whether the composed text is reported with `Ime` events.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.style & ffi::XIMPreeditCallbacks as c_ulong != 0
+    }
+
+    /// Allows or forbids the input method to report the composition of text, by recreating the
+    /// input context with another style.
+    pub fn set_allowed(&mut self, allowed: bool) {
+        if allowed == self.allowed {
+            return;
+        }
+        let was_enabled = self.is_enabled();
+        self.allowed = allowed;
+
+        unsafe { (self.display.xlib.XDestroyIC)(self.ic) };
+        self.ic = ptr::null_mut();
+
+        let styles: Vec<c_ulong> = if allowed {
+            let supported = self.supported_styles();
+            [ffi::XIMPreeditCallbacks, ffi::XIMPreeditPosition, ffi::XIMPreeditNothing].iter()
+                .map(|&preedit| (preedit | ffi::XIMStatusNothing) as c_ulong)
+                .filter(|style| supported.contains(style))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let created = styles.into_iter().any(|style| self.create_ic(style).is_ok());
+        if !created {
+            self.create_ic((ffi::XIMPreeditNothing | ffi::XIMStatusNothing) as c_ulong)
+                .expect("Failed to call XCreateIC");
+        }
+
+        let mut preedit = self.preedit.lock().unwrap();
+        preedit.text.clear();
+        preedit.cursor = 0;
+        if self.is_enabled() && !was_enabled {
+            preedit.events.push(Event::Ime(ImeEvent::Enabled));
+        } else if was_enabled && !self.is_enabled() {
+            preedit.events.push(Event::Ime(ImeEvent::Disabled));
+        }
+    }
+
+    /// Moves the candidate window of the input method.
+    pub fn set_spot(&mut self, x: i32, y: i32) {
+        let clamp = |value: i32| cmp::max(cmp::min(value, c_short::max_value() as i32),
+                                          c_short::min_value() as i32) as c_short;
+        self.spot = ffi::XPoint { x: clamp(x), y: clamp(y) };
+        if self.style & (ffi::XIMPreeditCallbacks | ffi::XIMPreeditPosition) as c_ulong == 0 {
+            return;
+        }
+
+        let spot_location = CString::new(ffi::XNSpotLocation).unwrap();
+        let preedit_attributes = CString::new(ffi::XNPreeditAttributes).unwrap();
+        unsafe {
+            let attributes = (self.display.xlib.XVaCreateNestedList)(0, spot_location.as_ptr(),
+                                                                     &self.spot, ptr::null_mut::<c_void>());
+            (self.display.xlib.XSetICValues)(self.ic, preedit_attributes.as_ptr(), attributes,
+                                             ptr::null_mut::<c_void>());
+            (self.display.xlib.XFree)(attributes);
+        }
+    }
+
+    /// Tells the input method whether the window has the keyboard focus.
+    pub fn set_focus(&self, focused: bool) {
+        unsafe {
+            if focused {
+                (self.display.xlib.XSetICFocus)(self.ic);
+            } else {
+                (self.display.xlib.XUnsetICFocus)(self.ic);
+            }
+        }
+    }
+
+    /// Returns the events reported by the input method since the last call.
+    pub fn take_events(&self) -> Vec<Event> {
+        let mut preedit = self.preedit.lock().unwrap();
+        if preedit.events.is_empty() {
+            Vec::new()
+        } else {
+            ::std::mem::replace(&mut preedit.events, Vec::new())
+        }
+    }
+
+    /// Destroys the input context and closes the input method.
+    pub fn close(&mut self) {
+        unsafe {
+            (self.display.xlib.XDestroyIC)(self.ic);
+            (self.display.xlib.XCloseIM)(self.im);
+        }
+        self.ic = ptr::null_mut();
+    }
+
+    fn supported_styles(&self) -> Vec<c_ulong> {
+        let query_input_style = CString::new(ffi::XNQueryInputStyle).unwrap();
+        let mut styles: *mut XIMStyles = ptr::null_mut();
+        unsafe {
+            let failed = (self.display.xlib.XGetIMValues)(self.im, query_input_style.as_ptr(), &mut styles,
+                                                          ptr::null_mut::<c_void>());
+            if !failed.is_null() || styles.is_null() {
+                return Vec::new();
+            }
+            let supported = slice::from_raw_parts((*styles).supported_styles,
+                                                  (*styles).count_styles as usize).to_vec();
+            (self.display.xlib.XFree)(styles as *mut _);
+            supported
+        }
+    }
+
+    fn create_ic(&mut self, style: c_ulong) -> Result<(), String> {
+        let input_style = CString::new(ffi::XNInputStyle).unwrap();
+        let client_window = CString::new(ffi::XNClientWindow).unwrap();
+        let focus_window = CString::new(ffi::XNFocusWindow).unwrap();
+        let preedit_attributes = CString::new(ffi::XNPreeditAttributes).unwrap();
+        let spot_location = CString::new(ffi::XNSpotLocation).unwrap();
+
+        let client_data = &*self.preedit as *const Mutex<Preedit> as ffi::XPointer;
+        let callback = |callback: *mut c_void| XIMCallback { client_data: client_data, callback: callback };
+        let start = callback(preedit_start as *mut c_void);
+        let done = callback(preedit_done as *mut c_void);
+        let draw = callback(preedit_draw as *mut c_void);
+        let caret = callback(preedit_caret as *mut c_void);
+
+        let ic = unsafe {
+            let xlib = &self.display.xlib;
+            let attributes = if style & ffi::XIMPreeditCallbacks as c_ulong != 0 {
+                let start_callback = CString::new(ffi::XNPreeditStartCallback).unwrap();
+                let done_callback = CString::new(ffi::XNPreeditDoneCallback).unwrap();
+                let draw_callback = CString::new(ffi::XNPreeditDrawCallback).unwrap();
+                let caret_callback = CString::new(ffi::XNPreeditCaretCallback).unwrap();
+                (xlib.XVaCreateNestedList)(0, start_callback.as_ptr(), &start, done_callback.as_ptr(), &done,
+                                           draw_callback.as_ptr(), &draw, caret_callback.as_ptr(), &caret,
+                                           spot_location.as_ptr(), &self.spot, ptr::null_mut::<c_void>())
+            } else if style & ffi::XIMPreeditPosition as c_ulong != 0 {
+                (xlib.XVaCreateNestedList)(0, spot_location.as_ptr(), &self.spot, ptr::null_mut::<c_void>())
+            } else {
+                ptr::null_mut()
+            };
+
+            let ic = if attributes.is_null() {
+                (xlib.XCreateIC)(self.im, input_style.as_ptr(), style, client_window.as_ptr(), self.window,
+                                 focus_window.as_ptr(), self.window, ptr::null_mut::<c_void>())
+            } else {
+                let ic = (xlib.XCreateIC)(self.im, input_style.as_ptr(), style, client_window.as_ptr(),
+                                          self.window, focus_window.as_ptr(), self.window,
+                                          preedit_attributes.as_ptr(), attributes, ptr::null_mut::<c_void>());
+                (xlib.XFree)(attributes);
+                ic
+            };
+            ic
+        };
+
+        if ic.is_null() {
+            return Err(format!("XCreateIC failed"));
+        }
+        unsafe { (self.display.xlib.XSetICFocus)(ic) };
+        self.ic = ic;
+        self.style = style;
+        Ok(())
+    }
+}
+
+unsafe fn preedit_from_client_data<'a>(client_data: ffi::XPointer) -> &'a Mutex<Preedit> {
+    &*(client_data as *const Mutex<Preedit>)
+}
+
+// Reads the characters of a text of the input method
+unsafe fn xim_text(text: &XIMText) -> Vec<char> {
+    if text.encoding_is_wchar != 0 {
+        let wide = slice::from_raw_parts(text.string as *const libc::wchar_t, text.length as usize);
+        wide.iter().filter_map(|&c| char::from_u32(c as u32)).collect()
+    } else {
+        // the text is in the encoding of the locale, which isn't necessarily UTF-8
+        let length = mbstowcs(ptr::null_mut(), text.string, 0);
+        if length == !0 {
+            return CStr::from_ptr(text.string).to_string_lossy().chars().collect();
+        }
+        let mut wide: Vec<libc::wchar_t> = vec![0; length + 1];
+        mbstowcs(wide.as_mut_ptr(), text.string, wide.len());
+        wide[.. length].iter().filter_map(|&c| char::from_u32(c as u32)).collect()
+    }
+}
+
+unsafe extern "C" fn preedit_start(_: ffi::XIM, client_data: ffi::XPointer, _: ffi::XPointer) -> c_int {
+    let mut preedit = preedit_from_client_data(client_data).lock().unwrap();
+    preedit.text.clear();
+    preedit.cursor = 0;
+    preedit.cursor_visible = true;
+    // no limit on the length of the text
+    -1
+}
+
+unsafe extern "C" fn preedit_done(_: ffi::XIM, client_data: ffi::XPointer, _: ffi::XPointer) {
+    let mut preedit = preedit_from_client_data(client_data).lock().unwrap();
+    preedit.text.clear();
+    preedit.cursor = 0;
+    preedit.push_event();
+}
+
+unsafe extern "C" fn preedit_draw(_: ffi::XIM, client_data: ffi::XPointer, call_data: ffi::XPointer) {
+    let mut preedit = preedit_from_client_data(client_data).lock().unwrap();
+    let call_data = &*(call_data as *const XIMPreeditDrawCallbackStruct);
+
+    let len = preedit.text.len();
+    let first = clamp_position(call_data.chg_first, len);
+    let last = clamp_position(call_data.chg_first + call_data.chg_length, len);
+    // no text means that the characters are deleted, and a text without string that only
+    // their feedback changes
+    let new_text = if call_data.text.is_null() {
+        Some(Vec::new())
+    } else if (*call_data.text).string.is_null() {
+        None
+    } else {
+        Some(xim_text(&*call_data.text))
+    };
+    if let Some(new_text) = new_text {
+        let tail = preedit.text.split_off(last);
+        preedit.text.truncate(first);
+        preedit.text.extend(new_text);
+        preedit.text.extend(tail);
+    }
+
+    preedit.cursor = clamp_position(call_data.caret, preedit.text.len());
+    preedit.push_event();
+}
+
+unsafe extern "C" fn preedit_caret(_: ffi::XIM, client_data: ffi::XPointer, call_data: ffi::XPointer) {
+    let mut preedit = preedit_from_client_data(client_data).lock().unwrap();
+    let call_data = &mut *(call_data as *mut XIMPreeditCaretCallbackStruct);
+
+    let len = preedit.text.len();
+    preedit.cursor = match call_data.direction {
+        XIM_FORWARD_CHAR => clamp_position(preedit.cursor as c_int + 1, len),
+        XIM_BACKWARD_CHAR => preedit.cursor.saturating_sub(1),
+        XIM_LINE_START => 0,
+        XIM_LINE_END => len,
+        XIM_ABSOLUTE_POSITION => clamp_position(call_data.position, len),
+        _ => preedit.cursor,
+    };
+    preedit.cursor_visible = call_data.style != XIM_IS_INVISIBLE;
+    // the input method reads the new position back
+    call_data.position = preedit.cursor as c_int;
+    preedit.push_event();
+}
+
+// Clamps a position given by the input method to the length of the text
+fn clamp_position(position: c_int, len: usize) -> usize {
+    if position < 0 {
+        0
+    } else if position as usize > len {
+        len
+    } else {
+        position as usize
+    }
+}
@@ -1,17 +1,24 @@
+use std::collections::{HashSet, VecDeque};
 use std::sync::Arc;
 
 use libc;
-use std::{mem, ptr};
+use std::{cmp, mem, ptr};
 use std::ffi::CString;
 use std::slice::from_raw_parts;
+use std::time::Duration;
 
-use WindowAttributes;
+use {KeyboardLayouts, KeyRepeatInfo, MouseButton, VirtualKeyCode, WindowAttributes};
 
 use events::WindowEvent as Event;
-use events::ModifiersState;
+use events::{KeyboardInput, ModifiersState};
 
 use super::{events, ffi};
 use super::XConnection;
+use super::ime::Ime;
+use super::keyboard::KeyText;
+use super::super::evdev::{add_modifier_side, keycode_to_physical_key};
+use super::super::keysym::keysym_to_logical_key;
+use super::super::xkbcommon::ModIndices;
 
 #[derive(Debug)]
 enum AxisType {
@@ -46,15 +53,26 @@ struct InputState {
 
 pub struct XInputEventHandler {
     display: Arc<XConnection>,
-    window: ffi::Window,
-    ic: ffi::XIC,
     axis_list: Vec<Axis>,
     current_state: InputState,
     multitouch: bool,
+    // the window the events are translated for
+    window: ffi::Window,
+    // keys held down, a press of one of them is an auto-repeat
+    pressed_keys: HashSet<libc::c_uint>,
+    // buttons held down, released when the window loses the focus
+    pressed_buttons: Vec<MouseButton>,
+    focused: bool,
+    // the modifiers last reported to the window, `None` until they are reported after the focus
+    reported_modifiers: Option<ModifiersState>,
+    // the layouts last reported to the window
+    reported_layouts: Option<KeyboardLayouts>,
+    // the modifiers according to the core protocol, used without libxkbcommon-x11
+    core_modifiers: ModifiersState,
 }
 
 impl XInputEventHandler {
-    pub fn new(display: &Arc<XConnection>, window: ffi::Window, ic: ffi::XIC,
+    pub fn new(display: &Arc<XConnection>, window: ffi::Window,
                window_attrs: &WindowAttributes) -> XInputEventHandler {
         // query XInput support
         let mut opcode: libc::c_int = 0;
@@ -112,90 +130,347 @@ impl XInputEventHandler {
 
         XInputEventHandler {
             display: display.clone(),
-            window: window,
-            ic: ic,
             axis_list: read_input_axis_info(display),
             current_state: InputState {
                 cursor_pos: (0.0, 0.0),
                 axis_values: Vec::new()
             },
             multitouch: window_attrs.multitouch,
+            window: window,
+            pressed_keys: HashSet::new(),
+            pressed_buttons: Vec::new(),
+            focused: false,
+            reported_modifiers: None,
+            reported_layouts: None,
+            core_modifiers: ModifiersState::default(),
+        }
+    }
+
+    /// Starts or stops reporting the relative motion of the mouse with `MouseMotion` events.
+    pub fn set_relative_motion(&mut self, enabled: bool) {
+        let mut state = self.display.raw_motion.lock().unwrap();
+        let was_selected = !state.lockers.is_empty();
+        let window = self.window;
+        state.lockers.retain(|&locker| locker != window);
+        state.pending.retain(|&(locker, _)| locker != window);
+        if enabled {
+            state.lockers.push(window);
+        }
+
+        // raw events are only delivered to the root window, for every window of the connection
+        let selected = !state.lockers.is_empty();
+        if selected != was_selected {
+            select_raw_motion(&self.display, selected);
         }
     }
 
-    pub fn translate_key_event(&self, event: &mut ffi::XKeyEvent) -> Vec<Event> {
-        use events::WindowEvent::{KeyboardInput, ReceivedCharacter};
+    /// Returns the relative motion of the mouse received for this window by any window of the
+    /// connection.
+    pub fn take_relative_motion(&mut self) -> Vec<Event> {
+        use events::WindowEvent::MouseMotion;
+
+        let mut state = self.display.raw_motion.lock().unwrap();
+        if state.pending.is_empty() {
+            return Vec::new();
+        }
+        let window = self.window;
+        let (mine, others): (Vec<_>, VecDeque<_>) = state.pending.drain(..)
+                                                          .partition(|&(locker, _)| locker == window);
+        state.pending = others;
+        mine.into_iter().map(|(_, (x, y))| MouseMotion(x, y)).collect()
+    }
+
+    /// Translates a key event that the input method didn't filter, if there is one.
+    pub fn translate_key_event(&mut self, event: &mut ffi::XKeyEvent, ime: Option<&Ime>) -> Vec<Event> {
+        use events::WindowEvent::{Ime as ImeEvent, KeyboardInput as KeyboardInputEvent, ReceivedCharacter};
         use events::ElementState::{Pressed, Released};
+        use events::Ime::Commit;
 
         let mut translated_events = Vec::new();
 
-        let state;
-        if event.type_ == ffi::KeyPress {
-            let raw_ev: *mut ffi::XKeyEvent = event;
-            unsafe { (self.display.xlib.XFilterEvent)(mem::transmute(raw_ev), self.window) };
-            state = Pressed;
+        let state = if event.type_ == ffi::KeyPress {
+            Pressed
         } else {
-            state = Released;
+            Released
+        };
+
+        // the input method sends the text it commits with key presses without keycode
+        if event.keycode == 0 {
+            let written = self.lookup_string(event, ime);
+            if ime.map(|ime| ime.is_enabled()).unwrap_or(false) {
+                if !written.is_empty() {
+                    translated_events.push(ImeEvent(Commit(written)));
+                }
+            } else {
+                translated_events.extend(written.chars().map(ReceivedCharacter));
+            }
+            return translated_events;
         }
 
-        let mut kp_keysym = 0;
+        // the modifiers before the key, like the state of the event
+        let ev_mods = self.modifiers_with_sides(self.mask_to_modifiers(event.state));
+
+        // the keysym of the active group and shift level, and the text typed by the key
+        let (keysym, written) = match *self.display.keyboard.lock().unwrap() {
+            Some(ref mut keyboard) => {
+                let keysym = keyboard.keysym(event.keycode);
+                let written = match state {
+                    Pressed => match keyboard.key_text(event.keycode) {
+                        KeyText::Text(text) => text,
+                        KeyText::Composing => String::new(),
+                    },
+                    Released => String::new(),
+                };
+                (keysym, written)
+            },
+            None => {
+                let keysym = self.lookup_keysym(event.keycode, event.state);
+                let written = match state {
+                    Pressed => self.lookup_string(event, ime),
+                    Released => String::new(),
+                };
+                (keysym, written)
+            },
+        };
 
-        let mut ev_mods = ModifiersState::default();
+        for chr in written.chars() {
+            translated_events.push(ReceivedCharacter(chr));
+        }
 
-        let written = unsafe {
-            use std::str;
+        let vkey = self.keycode_to_vkey(event.keycode, keysym);
 
-            let mut buffer: [u8; 16] = [mem::uninitialized(); 16];
-            let raw_ev: *mut ffi::XKeyEvent = event;
-            let count = (self.display.xlib.Xutf8LookupString)(self.ic, mem::transmute(raw_ev),
-            mem::transmute(buffer.as_mut_ptr()),
-            buffer.len() as libc::c_int, &mut kp_keysym, ptr::null_mut());
+        // the auto-repeat is detectable, repeated keys are pressed again without being released
+        let is_repeat = match state {
+            Pressed => !self.pressed_keys.insert(event.keycode),
+            Released => { self.pressed_keys.remove(&event.keycode); false },
+        };
+        if self.display.keyboard.lock().unwrap().is_none() {
+            self.query_core_modifiers();
+        }
 
-            {
-                // Translate x event state to mods
-                let state = event.state;
-                if (state & ffi::Mod1Mask) != 0 {
-                    ev_mods.alt = true;
-                }
+        translated_events.push(KeyboardInputEvent(KeyboardInput {
+            state: state,
+            scancode: event.keycode,
+            physical_key: keycode_to_physical_key(event.keycode),
+            virtual_keycode: vkey,
+            logical_key: keysym_to_logical_key(keysym),
+            modifiers: ev_mods,
+            is_repeat: is_repeat,
+            is_synthetic: false,
+        }));
+        translated_events
+    }
 
-                if (state & ffi::ShiftMask) != 0 {
-                    ev_mods.shift = true;
-                }
+    /// Returns a `ModifiersChanged` event if the modifiers changed since they were last reported
+    /// to the window, while it has the focus.
+    pub fn modifiers_changed(&mut self) -> Option<Event> {
+        if !self.focused {
+            return None;
+        }
+        let mods = match *self.display.keyboard.lock().unwrap() {
+            Some(ref keyboard) => keyboard.modifiers(),
+            None => self.core_modifiers,
+        };
+        let mods = self.modifiers_with_sides(mods);
+        if self.reported_modifiers == Some(mods) {
+            return None;
+        }
+        self.reported_modifiers = Some(mods);
+        Some(Event::ModifiersChanged(mods))
+    }
 
-                if (state & ffi::ControlMask) != 0 {
-                    ev_mods.ctrl = true;
-                }
+    /// Returns a `KeyboardLayoutChanged` event if the layouts changed since they were last
+    /// reported to the window, while it has the focus.
+    pub fn layouts_changed(&mut self) -> Option<Event> {
+        if !self.focused {
+            return None;
+        }
+        let layouts = match *self.display.keyboard.lock().unwrap() {
+            Some(ref keyboard) => keyboard.layouts(),
+            None => return None,
+        };
+        if self.reported_layouts.as_ref() == Some(&layouts) {
+            return None;
+        }
+        self.reported_layouts = Some(layouts.clone());
+        Some(Event::KeyboardLayoutChanged(layouts))
+    }
 
-                if (state & ffi::Mod4Mask) != 0 {
-                    ev_mods.logo = true;
-                }
+    /// Returns the modifiers active in a mask of the core protocol, whose `Mod1` to `Mod5` the
+    /// keymap assigns.
+    fn mask_to_modifiers(&self, mask: u32) -> ModifiersState {
+        match *self.display.keyboard.lock().unwrap() {
+            Some(ref keyboard) => keyboard.mask_to_modifiers(mask),
+            None => ModIndices::core().to_modifiers(mask),
+        }
+    }
+
+    /// Adds the sides of the modifiers held down to a state.
+    fn modifiers_with_sides(&self, mut mods: ModifiersState) -> ModifiersState {
+        for &keycode in &self.pressed_keys {
+            if let Some(key) = keycode_to_physical_key(keycode) {
+                add_modifier_side(&mut mods, key);
             }
+        }
+        mods
+    }
+
+    /// Returns the modifiers of a pointer event, with the sides of the ones held down.
+    fn event_modifiers(&self, event: &ffi::XIDeviceEvent) -> ModifiersState {
+        let mods = self.mask_to_modifiers(event.mods.effective as u32);
+        self.modifiers_with_sides(mods)
+    }
+
+    /// Asks the server for the state of the modifiers, which is otherwise tracked by
+    /// libxkbcommon-x11.
+    fn query_core_modifiers(&mut self) {
+        self.core_modifiers = ModIndices::core().to_modifiers(query_pointer_mask(&self.display));
+    }
 
-            str::from_utf8(&buffer[..count as usize]).unwrap_or("").to_string()
+    /// Reports the keys held down that the window doesn't know of with synthetic presses, from
+    /// the state of the keyboard given by the server when the window gains the focus.
+    pub fn sync_pressed_keys(&mut self, keymap: &[libc::c_char; 32]) -> Vec<Event> {
+        use events::ElementState::Pressed;
+
+        if !self.focused {
+            return Vec::new();
+        }
+        let held = keymap_to_keycodes(keymap);
+        // the keys released while the window didn't have the focus
+        self.pressed_keys = self.pressed_keys.intersection(&held).cloned().collect();
+
+        let modifiers = match *self.display.keyboard.lock().unwrap() {
+            Some(ref keyboard) => keyboard.modifiers(),
+            None => self.core_modifiers,
         };
+        let mut events = Vec::new();
+        for keycode in held {
+            if !self.pressed_keys.insert(keycode) {
+                continue;
+            }
+            let keysym = match *self.display.keyboard.lock().unwrap() {
+                Some(ref keyboard) => keyboard.keysym(keycode),
+                None => self.lookup_keysym(keycode, 0),
+            };
+            events.push(Event::KeyboardInput(KeyboardInput {
+                state: Pressed,
+                scancode: keycode,
+                physical_key: keycode_to_physical_key(keycode),
+                virtual_keycode: self.keycode_to_vkey(keycode, keysym),
+                logical_key: keysym_to_logical_key(keysym),
+                modifiers: self.modifiers_with_sides(modifiers),
+                is_repeat: false,
+                is_synthetic: true,
+            }));
+        }
+        events
+    }
 
-        for chr in written.chars() {
-            translated_events.push(ReceivedCharacter(chr));
+    /// Reports the keys and buttons held down with synthetic releases, when the window loses the
+    /// focus and would not receive their releases.
+    pub fn release_pressed(&mut self) -> Vec<Event> {
+        use events::ElementState::Released;
+
+        let modifiers = match *self.display.keyboard.lock().unwrap() {
+            Some(ref keyboard) => keyboard.modifiers(),
+            None => self.core_modifiers,
+        };
+        let modifiers = self.modifiers_with_sides(modifiers);
+
+        let mut keycodes: Vec<_> = self.pressed_keys.drain().collect();
+        keycodes.sort();
+        let mut released = Vec::new();
+        for keycode in keycodes {
+            let keysym = match *self.display.keyboard.lock().unwrap() {
+                Some(ref keyboard) => keyboard.keysym(keycode),
+                None => self.lookup_keysym(keycode, 0),
+            };
+            released.push(Event::KeyboardInput(KeyboardInput {
+                state: Released,
+                scancode: keycode,
+                physical_key: keycode_to_physical_key(keycode),
+                virtual_keycode: self.keycode_to_vkey(keycode, keysym),
+                logical_key: keysym_to_logical_key(keysym),
+                modifiers: modifiers,
+                is_repeat: false,
+                is_synthetic: true,
+            }));
+        }
+        for button in self.pressed_buttons.drain(..) {
+            released.push(Event::MouseInput(::events::MouseInput {
+                state: Released,
+                button: button,
+                modifiers: modifiers,
+                is_synthetic: true,
+            }));
         }
+        released
+    }
 
-        let mut keysym = unsafe {
-            (self.display.xlib.XKeycodeToKeysym)(self.display.display, event.keycode as ffi::KeyCode, 0)
+    /// Returns the keysym of a key according to xlib, used without libxkbcommon-x11.
+    fn lookup_keysym(&self, keycode: libc::c_uint, state: libc::c_uint) -> u32 {
+        unsafe {
+            let mut mods_rtrn = 0;
+            let mut keysym = 0;
+            (self.display.xlib.XkbLookupKeySym)(self.display.display, keycode as u8, state,
+                                                &mut mods_rtrn, &mut keysym);
+            keysym as u32
+        }
+    }
+
+    /// Returns the virtual key code of a key, from the keysym of its first level in the first
+    /// group, apart from the keypad whose keysym depends on num lock.
+    fn keycode_to_vkey(&self, keycode: libc::c_uint, keysym: u32) -> Option<VirtualKeyCode> {
+        let mut vkey_keysym = unsafe {
+            (self.display.xlib.XkbKeycodeToKeysym)(self.display.display, keycode as u8, 0, 0)
         };
 
-        if (ffi::XK_KP_Space as libc::c_ulong <= keysym) && (keysym <= ffi::XK_KP_9 as libc::c_ulong) {
-            keysym = kp_keysym
+        if (ffi::XK_KP_Space as libc::c_ulong <= vkey_keysym) && (vkey_keysym <= ffi::XK_KP_9 as libc::c_ulong) {
+            vkey_keysym = keysym as libc::c_ulong
         };
 
-        let vkey = events::keycode_to_element(keysym as libc::c_uint);
+        events::keycode_to_element(vkey_keysym as libc::c_uint)
+    }
 
-        translated_events.push(KeyboardInput(state, event.keycode as u8, vkey, ev_mods));
-        translated_events
+    /// Returns the text of a key press according to xlib, and to the input method if there is
+    /// one.
+    fn lookup_string(&self, event: &mut ffi::XKeyEvent, ime: Option<&Ime>) -> String {
+        unsafe {
+            let mut buffer: Vec<u8> = vec![0; 16];
+            let mut keysym = 0;
+
+            let ic = match ime {
+                Some(ime) => ime.ic(),
+                None => {
+                    // no input method is running, xlib only knows the characters of Latin-1
+                    let count = (self.display.xlib.XLookupString)(event, buffer.as_mut_ptr() as *mut _,
+                                                                  buffer.len() as libc::c_int,
+                                                                  &mut keysym, ptr::null_mut());
+                    return buffer[..count as usize].iter().map(|&b| b as char).collect();
+                },
+            };
+
+            let mut status = 0;
+            let mut count = (self.display.xlib.Xutf8LookupString)(ic, event,
+                                                                  buffer.as_mut_ptr() as *mut _,
+                                                                  buffer.len() as libc::c_int,
+                                                                  &mut keysym, &mut status);
+            if status == ffi::XBufferOverflow {
+                // the text committed by the input method can be long
+                buffer = vec![0; count as usize];
+                count = (self.display.xlib.Xutf8LookupString)(ic, event,
+                                                              buffer.as_mut_ptr() as *mut _,
+                                                              buffer.len() as libc::c_int,
+                                                              &mut keysym, &mut status);
+            }
+            String::from_utf8_lossy(&buffer[..count as usize]).into_owned()
+        }
     }
 
     pub fn translate_event(&mut self, cookie: &ffi::XGenericEventCookie) -> Option<Event> {
         use events::WindowEvent::{Focused, MouseEntered, MouseInput, MouseLeft, MouseMoved, MouseWheel};
         use events::ElementState::{Pressed, Released};
-        use events::MouseButton::{Left, Right, Middle};
+        use events::MouseButton::{Left, Right, Middle, Back, Forward, Other};
         use events::MouseScrollDelta::LineDelta;
         use events::{Touch, TouchPhase};
 
@@ -211,10 +486,13 @@ impl XInputEventHandler {
                 } else {
                     Released
                 };
-                match event_data.detail as u32 {
-                    ffi::Button1 => Some(MouseInput(state, Left)),
-                    ffi::Button2 => Some(MouseInput(state, Middle)),
-                    ffi::Button3 => Some(MouseInput(state, Right)),
+                let modifiers = self.event_modifiers(event_data);
+                let button = match event_data.detail as u32 {
+                    ffi::Button1 => Left,
+                    ffi::Button2 => Middle,
+                    ffi::Button3 => Right,
+                    8 => Back,
+                    9 => Forward,
                     ffi::Button4 | ffi::Button5 => {
                         if event_data.flags & ffi::XIPointerEmulated == 0 {
                             // scroll event from a traditional wheel with
@@ -224,16 +502,32 @@ impl XInputEventHandler {
                             } else {
                                 -1.0
                             };
-                            Some(MouseWheel(LineDelta(0.0, delta), TouchPhase::Moved))
+                            return Some(MouseWheel(LineDelta(0.0, delta), TouchPhase::Moved, modifiers))
                         } else {
                             // emulated button event from a touch/smooth-scroll
                             // event. Ignore these events and handle scrolling
                             // via XI_Motion event handler instead
-                            None
+                            return None
                         }
                     }
-                    _ => None
+                    // the buttons of the horizontal scroll, reported with XI_Motion
+                    6 | 7 => return None,
+                    other => Other(other as u16),
+                };
+                // the buttons held down get a synthetic release when the focus leaves, the real
+                // releases are always reported, even if one of them was already sent
+                match state {
+                    Pressed => if !self.pressed_buttons.contains(&button) {
+                        self.pressed_buttons.push(button);
+                    },
+                    Released => self.pressed_buttons.retain(|&b| b != button),
                 }
+                Some(MouseInput(::events::MouseInput {
+                    state: state,
+                    button: button,
+                    modifiers: modifiers,
+                    is_synthetic: false,
+                }))
             },
             ffi::XI_Motion => {
                 let event_data: &ffi::XIDeviceEvent = unsafe{mem::transmute(cookie.data)};
@@ -259,7 +553,7 @@ impl XInputEventHandler {
 
                 if scroll_delta.0.abs() > 0.0 || scroll_delta.1.abs() > 0.0 {
                     Some(MouseWheel(LineDelta(scroll_delta.0 as f32, scroll_delta.1 as f32),
-                                    TouchPhase::Moved))
+                                    TouchPhase::Moved, self.event_modifiers(event_data)))
                 } else {
                     let new_cursor_pos = (event_data.event_x, event_data.event_y);
                     if new_cursor_pos != self.current_state.cursor_pos {
@@ -270,6 +564,33 @@ impl XInputEventHandler {
                     }
                 }
             },
+            ffi::XI_RawMotion => {
+                // the motion goes to the window that locked the cursor, which is not
+                // necessarily the one reading the event
+                let event_data: &ffi::XIRawEvent = unsafe{mem::transmute(cookie.data)};
+                let axis_state = event_data.valuators;
+                let mask = unsafe{ from_raw_parts(axis_state.mask, axis_state.mask_len as usize) };
+
+                // the first two axes of a pointer are its x and y motion, and the raw values
+                // are not accelerated
+                let mut delta = (0.0, 0.0);
+                let mut value_index = 0;
+                for axis_id in 0..cmp::min(2, axis_state.mask_len * 8) {
+                    if ffi::XIMaskIsSet(&mask, axis_id) {
+                        let value = unsafe{*event_data.raw_values.offset(value_index)};
+                        if axis_id == 0 { delta.0 = value } else { delta.1 = value }
+                        value_index += 1;
+                    }
+                }
+
+                if delta != (0.0, 0.0) {
+                    let mut state = self.display.raw_motion.lock().unwrap();
+                    if let Some(&locker) = state.lockers.last() {
+                        state.pending.push_back((locker, delta));
+                    }
+                }
+                None
+            },
             ffi::XI_Enter => {
                 // axis movements whilst the cursor is outside the window
                 // will alter the absolute value of the axes. We only want to
@@ -280,8 +601,25 @@ impl XInputEventHandler {
                 Some(MouseEntered)
             },
             ffi::XI_Leave => Some(MouseLeft),
-            ffi::XI_FocusIn => Some(Focused(true)),
-            ffi::XI_FocusOut => Some(Focused(false)),
+            ffi::XI_FocusIn => {
+                // the modifiers are reported again once the window has the focus
+                self.focused = true;
+                self.reported_modifiers = None;
+                // the keys held down are reported from the state of the keyboard afterwards
+                self.pressed_keys.clear();
+                if self.display.keyboard.lock().unwrap().is_none() {
+                    self.query_core_modifiers();
+                }
+                Some(Focused(true))
+            },
+            ffi::XI_FocusOut => {
+                // the keys and buttons held down are released with `release_pressed`
+                self.focused = false;
+                if let Some(ref mut keyboard) = *self.display.keyboard.lock().unwrap() {
+                    keyboard.reset_compose();
+                }
+                Some(Focused(false))
+            },
             ffi::XI_TouchBegin | ffi::XI_TouchUpdate | ffi::XI_TouchEnd => {
                 if !self.multitouch {
                     return None
@@ -304,6 +642,57 @@ impl XInputEventHandler {
     }
 }
 
+impl Drop for XInputEventHandler {
+    fn drop(&mut self) {
+        // other windows may still report the relative motion
+        self.set_relative_motion(false);
+    }
+}
+
+/// The windows of a connection that receive the relative motion of the mouse.
+#[derive(Debug, Default)]
+pub struct RawMotionState {
+    // the windows that locked the cursor, the motion goes to the last one as it holds the grab
+    lockers: Vec<ffi::Window>,
+    // the motion not read yet by the window it goes to
+    pending: VecDeque<(ffi::Window, (f64, f64))>,
+}
+
+// Adds or removes `XI_RawMotion` from the events selected on the root window, keeping the others.
+fn select_raw_motion(display: &XConnection, enabled: bool) {
+    let mut mask: Vec<libc::c_uchar> = vec![0; (ffi::XI_LASTEVENT as usize >> 3) + 1];
+
+    unsafe {
+        let root = (display.xlib.XDefaultRootWindow)(display.display);
+        let mut count = 0;
+        let selected = (display.xinput2.XIGetSelectedEvents)(display.display, root, &mut count);
+        if !selected.is_null() {
+            for event_mask in from_raw_parts(selected, count as usize) {
+                if event_mask.deviceid == ffi::XIAllMasterDevices {
+                    let bytes = from_raw_parts(event_mask.mask, event_mask.mask_len as usize);
+                    for (byte, &selected_byte) in mask.iter_mut().zip(bytes) {
+                        *byte = selected_byte;
+                    }
+                }
+            }
+            (display.xlib.XFree)(selected as *mut _);
+        }
+
+        if enabled {
+            ffi::XISetMask(&mut mask, ffi::XI_RawMotion);
+        } else {
+            ffi::XIClearMask(&mut mask, ffi::XI_RawMotion);
+        }
+        let mut input_event_mask = ffi::XIEventMask {
+            deviceid: ffi::XIAllMasterDevices,
+            mask_len: mask.len() as i32,
+            mask: mask.as_mut_ptr()
+        };
+        (display.xinput2.XISelectEvents)(display.display, root, &mut input_event_mask, 1);
+    }
+    display.check_errors().expect("Failed to call XISelectEvents");
+}
+
 fn read_input_axis_info(display: &Arc<XConnection>) -> Vec<Axis> {
     let mut axis_list = Vec::new();
     let mut device_count = 0;
@@ -389,3 +778,115 @@ fn calc_scroll_deltas(event: &ffi::XIDeviceEvent,
     scroll_delta
 }
 
+/// Returns the state of the keyboard, a bit per keycode set for the keys held down.
+pub fn query_keymap(display: &Arc<XConnection>) -> [libc::c_char; 32] {
+    let mut keymap = [0; 32];
+    unsafe { (display.xlib.XQueryKeymap)(display.display, keymap.as_mut_ptr()) };
+    keymap
+}
+
+/// Returns the keycodes of the keys held down in a state of the keyboard.
+pub fn keymap_to_keycodes(keymap: &[libc::c_char; 32]) -> HashSet<libc::c_uint> {
+    let mut keycodes = HashSet::new();
+    for (i, &byte) in keymap.iter().enumerate() {
+        for bit in 0..8 {
+            if (byte as u8) & (1 << bit) != 0 {
+                keycodes.insert((i * 8 + bit) as libc::c_uint);
+            }
+        }
+    }
+    keycodes
+}
+
+/// Returns the mouse buttons held down, the buttons of the wheel are left out.
+pub fn query_pointer_buttons(display: &Arc<XConnection>) -> Vec<MouseButton> {
+    use events::MouseButton::{Left, Right, Middle, Back, Forward, Other};
+
+    let mut buttons = Vec::new();
+    unsafe {
+        // the core protocol only knows the first five buttons, XInput2 has the mask of all of them
+        let mut device_id = 0;
+        if (display.xinput2.XIGetClientPointer)(display.display, 0, &mut device_id) == ffi::False {
+            return buttons;
+        }
+        let root = (display.xlib.XDefaultRootWindow)(display.display);
+        let (mut root_return, mut child) = (0, 0);
+        let (mut root_x, mut root_y, mut win_x, mut win_y) = (0.0, 0.0, 0.0, 0.0);
+        let mut button_state: ffi::XIButtonState = mem::zeroed();
+        let mut mods: ffi::XIModifierState = mem::zeroed();
+        let mut group: ffi::XIModifierState = mem::zeroed();
+        let ok = (display.xinput2.XIQueryPointer)(display.display, device_id, root,
+                                                  &mut root_return, &mut child,
+                                                  &mut root_x, &mut root_y, &mut win_x, &mut win_y,
+                                                  &mut button_state, &mut mods, &mut group);
+        if ok == ffi::False || button_state.mask.is_null() {
+            return buttons;
+        }
+
+        let mask = from_raw_parts(button_state.mask, button_state.mask_len as usize);
+        for (index, &byte) in mask.iter().enumerate() {
+            for bit in 0..8 {
+                if byte & (1 << bit) == 0 {
+                    continue;
+                }
+                // numbered like in the button events, where 4 to 7 are the scroll wheels
+                let button = match (index * 8 + bit) as u32 {
+                    ffi::Button1 => Left,
+                    ffi::Button2 => Middle,
+                    ffi::Button3 => Right,
+                    8 => Back,
+                    9 => Forward,
+                    0 | 4...7 => continue,
+                    other => Other(other as u16),
+                };
+                buttons.push(button);
+            }
+        }
+        (display.xlib.XFree)(button_state.mask as *mut _);
+    }
+    buttons
+}
+
+/// Returns the state of the modifiers and of the mouse buttons, according to the core protocol.
+fn query_pointer_mask(display: &Arc<XConnection>) -> libc::c_uint {
+    let mut mask = 0;
+    unsafe {
+        let root = (display.xlib.XDefaultRootWindow)(display.display);
+        let (mut root_return, mut child) = (0, 0);
+        let (mut root_x, mut root_y, mut win_x, mut win_y) = (0, 0, 0, 0);
+        (display.xlib.XQueryPointer)(display.display, root, &mut root_return, &mut child,
+                                     &mut root_x, &mut root_y, &mut win_x, &mut win_y, &mut mask);
+    }
+    mask
+}
+
+// from XKB.h, the device of the core keyboard
+const XKB_USE_CORE_KBD: libc::c_uint = 0x0100;
+
+/// Returns the auto-repeat settings of the core keyboard, `None` if the auto-repeat is off.
+pub fn key_repeat_info(display: &Arc<XConnection>) -> Option<KeyRepeatInfo> {
+    unsafe {
+        let mut keyboard_state: ffi::XKeyboardState = mem::uninitialized();
+        (display.xlib.XGetKeyboardControl)(display.display, &mut keyboard_state);
+        if keyboard_state.global_auto_repeat == ffi::AutoRepeatModeOff {
+            return None;
+        }
+
+        let mut delay = 0;
+        let mut interval = 0;
+        if (display.xlib.XkbGetAutoRepeatRate)(display.display, XKB_USE_CORE_KBD,
+                                               &mut delay, &mut interval) == ffi::False
+        {
+            return None;
+        }
+        display.check_errors().expect("Failed to call XkbGetAutoRepeatRate");
+
+        if interval == 0 {
+            return None;
+        }
+        Some(KeyRepeatInfo {
+            delay: Duration::from_millis(delay as u64),
+            rate: 1000 / interval as u32,
+        })
+    }
+}
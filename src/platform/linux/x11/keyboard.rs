@@ -0,0 +1,235 @@
+//! The keymap of the X server, interpreted by libxkbcommon like the ones of Wayland so that both
+//! backends translate the keys the same way.
+
+use std::mem;
+use std::os::raw::{c_int, c_void};
+
+use {KeyboardLayouts, ModifiersState};
+
+use super::ffi;
+use super::super::xkbcommon::{self, Compose, ComposeResult, ModIndices, XKBCOMMON, XKBCOMMON_X11};
+
+/// The keyboard of the X server, kept up to date with the XKB events.
+pub struct Keyboard {
+    connection: *mut c_void,
+    device_id: i32,
+    // the type of all the XKB events, which tell their kind in `xkb_type`
+    event_type: c_int,
+    context: *mut xkbcommon::xkb_context,
+    keymap: *mut xkbcommon::xkb_keymap,
+    state: *mut xkbcommon::xkb_state,
+    mod_indices: ModIndices,
+    // the effective modifiers
+    mods: u32,
+    layouts: Vec<String>,
+    // the effective layout
+    group: u32,
+    compose: Option<Compose>,
+}
+
+unsafe impl Send for Keyboard {}
+
+/// The text typed by a key press.
+pub enum KeyText {
+    /// The key types this text, which is empty for the keys without text.
+    Text(String),
+    /// The key types nothing as it is part of a compose sequence, like a dead key.
+    Composing,
+}
+
+impl Keyboard {
+    /// Loads the keymap of the core keyboard, returns `None` if libxkbcommon-x11 isn't available.
+    pub fn new(xlib: &ffi::Xlib, xlib_xcb: &ffi::Xlib_xcb, display: *mut ffi::Display)
+               -> Option<Keyboard>
+    {
+        let (xkb, xkb_x11) = match (XKBCOMMON.as_ref(), XKBCOMMON_X11.as_ref()) {
+            (Some(xkb), Some(xkb_x11)) => (xkb, xkb_x11),
+            _ => return None,
+        };
+
+        unsafe {
+            let connection = (xlib_xcb.XGetXCBConnection)(display) as *mut c_void;
+            let ok = (xkb_x11.xkb_x11_setup_xkb_extension)(
+                connection,
+                xkbcommon::XKB_X11_MIN_MAJOR_XKB_VERSION,
+                xkbcommon::XKB_X11_MIN_MINOR_XKB_VERSION,
+                xkbcommon::XKB_X11_SETUP_XKB_EXTENSION_NO_FLAGS,
+                &mut 0, &mut 0, &mut 0, &mut 0);
+            if ok == 0 {
+                return None;
+            }
+            let device_id = (xkb_x11.xkb_x11_get_core_keyboard_device_id)(connection);
+            if device_id < 0 {
+                return None;
+            }
+
+            // xlib delivers the XKB events once it knows the extension
+            let mut opcode = 0;
+            let mut event_type = 0;
+            let mut error_base = 0;
+            // XkbMajorVersion and XkbMinorVersion
+            let mut major = 1;
+            let mut minor = 0;
+            if (xlib.XkbQueryExtension)(display, &mut opcode, &mut event_type, &mut error_base,
+                                        &mut major, &mut minor) == ffi::False
+            {
+                return None;
+            }
+            let mask = (ffi::XkbNewKeyboardNotifyMask | ffi::XkbMapNotifyMask |
+                        ffi::XkbStateNotifyMask) as u32;
+            (xlib.XkbSelectEvents)(display, device_id as u32, mask, mask);
+
+            let context = (xkb.xkb_context_new)(xkbcommon::XKB_CONTEXT_NO_FLAGS);
+            if context.is_null() {
+                return None;
+            }
+            let mut keyboard = Keyboard {
+                connection: connection,
+                device_id: device_id,
+                event_type: event_type,
+                context: context,
+                keymap: ::std::ptr::null_mut(),
+                state: ::std::ptr::null_mut(),
+                mod_indices: ModIndices::core(),
+                mods: 0,
+                layouts: Vec::new(),
+                group: 0,
+                compose: Compose::new(),
+            };
+            if !keyboard.load_keymap() {
+                return None;
+            }
+            Some(keyboard)
+        }
+    }
+
+    /// Loads the keymap of the device and its current state, replacing the previous ones.
+    fn load_keymap(&mut self) -> bool {
+        let xkb = XKBCOMMON.as_ref().unwrap();
+        let xkb_x11 = XKBCOMMON_X11.as_ref().unwrap();
+        unsafe {
+            let keymap = (xkb_x11.xkb_x11_keymap_new_from_device)(
+                self.context, self.connection, self.device_id,
+                xkbcommon::XKB_KEYMAP_COMPILE_NO_FLAGS);
+            if keymap.is_null() {
+                return false;
+            }
+            let state = (xkb_x11.xkb_x11_state_new_from_device)(keymap, self.connection,
+                                                                self.device_id);
+            if state.is_null() {
+                (xkb.xkb_keymap_unref)(keymap);
+                return false;
+            }
+            self.free_keymap();
+            self.keymap = keymap;
+            self.state = state;
+            self.mod_indices = ModIndices::from_keymap(keymap);
+            self.mods = (xkb.xkb_state_serialize_mods)(state, xkbcommon::XKB_STATE_MODS_EFFECTIVE);
+            self.layouts = xkbcommon::layout_names(keymap);
+            self.group = (xkb.xkb_state_serialize_layout)(state,
+                                                          xkbcommon::XKB_STATE_LAYOUT_EFFECTIVE);
+            true
+        }
+    }
+
+    fn free_keymap(&mut self) {
+        let xkb = XKBCOMMON.as_ref().unwrap();
+        unsafe {
+            if !self.state.is_null() {
+                (xkb.xkb_state_unref)(self.state);
+            }
+            if !self.keymap.is_null() {
+                (xkb.xkb_keymap_unref)(self.keymap);
+            }
+        }
+    }
+
+    /// Handles the event if it is an XKB event, returns whether it was.
+    pub fn handle_event(&mut self, xev: &ffi::XEvent) -> bool {
+        if xev.get_type() != self.event_type {
+            return false;
+        }
+        let any: &ffi::XkbAnyEvent = unsafe { mem::transmute(xev) };
+        if any.device as i32 != self.device_id {
+            return true;
+        }
+        match any.xkb_type {
+            ffi::XkbNewKeyboardNotify | ffi::XkbMapNotify => {
+                // the old keymap stays in use if the new one can't be loaded
+                self.load_keymap();
+            },
+            ffi::XkbStateNotify => {
+                let state: &ffi::XkbStateNotifyEvent = unsafe { mem::transmute(xev) };
+                let xkb = XKBCOMMON.as_ref().unwrap();
+                unsafe {
+                    (xkb.xkb_state_update_mask)(self.state, state.base_mods, state.latched_mods,
+                                                state.locked_mods, state.base_group as u32,
+                                                state.latched_group as u32,
+                                                state.locked_group as u32);
+                }
+                self.mods = state.mods;
+                self.group = state.group as u32;
+            },
+            _ => (),
+        }
+        true
+    }
+
+    /// Returns the modifiers currently active, without their sides.
+    pub fn modifiers(&self) -> ModifiersState {
+        self.mod_indices.to_modifiers(self.mods)
+    }
+
+    /// Returns the modifiers active in a mask of the core protocol, like the state of the events.
+    pub fn mask_to_modifiers(&self, mask: u32) -> ModifiersState {
+        self.mod_indices.to_modifiers(mask)
+    }
+
+    /// Returns the layouts of the keymap and the active one.
+    pub fn layouts(&self) -> KeyboardLayouts {
+        KeyboardLayouts {
+            names: self.layouts.clone(),
+            active: self.group as usize,
+        }
+    }
+
+    /// Returns the keysym of a key under the current layout and modifiers.
+    pub fn keysym(&self, keycode: u32) -> u32 {
+        let xkb = XKBCOMMON.as_ref().unwrap();
+        unsafe { (xkb.xkb_state_key_get_one_sym)(self.state, keycode) }
+    }
+
+    /// Returns the text typed by the press of a key, going through the compose sequences.
+    pub fn key_text(&mut self, keycode: u32) -> KeyText {
+        let keysym = self.keysym(keycode);
+        if let Some(ref mut compose) = self.compose {
+            match compose.feed(keysym) {
+                ComposeResult::Nothing => (),
+                ComposeResult::Composing | ComposeResult::Cancelled => return KeyText::Composing,
+                ComposeResult::Composed(text) => return KeyText::Text(text),
+            }
+        }
+        let xkb = XKBCOMMON.as_ref().unwrap();
+        let state = self.state;
+        KeyText::Text(unsafe {
+            xkbcommon::get_utf8(|buffer, size| {
+                (xkb.xkb_state_key_get_utf8)(state, keycode, buffer, size)
+            })
+        })
+    }
+
+    /// Drops the unfinished compose sequence.
+    pub fn reset_compose(&mut self) {
+        if let Some(ref mut compose) = self.compose {
+            compose.reset();
+        }
+    }
+}
+
+impl Drop for Keyboard {
+    fn drop(&mut self) {
+        self.free_keymap();
+        let xkb = XKBCOMMON.as_ref().unwrap();
+        unsafe { (xkb.xkb_context_unref)(self.context) };
+    }
+}
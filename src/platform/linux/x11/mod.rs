@@ -3,6 +3,7 @@
 pub use self::monitor::{MonitorId, get_available_monitors, get_primary_monitor};
 pub use self::window::{Window, XWindow, PollEventsIterator, WaitEventsIterator, WindowProxy};
 pub use self::xdisplay::{XConnection, XNotSupported, XError};
+pub use self::input::key_repeat_info;
 
 pub mod ffi;
 
@@ -11,8 +12,12 @@ use CreationError;
 
 use std::sync::Arc;
 
+mod clipboard;
+mod dnd;
 mod events;
+mod ime;
 mod input;
+mod keyboard;
 mod monitor;
 mod window;
 mod xdisplay;
@@ -41,10 +46,40 @@ impl EventsLoop {
         self.interrupted.store(true, ::std::sync::atomic::Ordering::Relaxed);
     }
 
+    // the selections are owned and received by a window, any of ours does
+    //
+    // the list of windows is not locked while the window is used, as the callback of the
+    // events loop can use the clipboard
+    fn first_window(&self) -> Option<::std::sync::Arc<Window>> {
+        self.windows.lock().unwrap().first().cloned()
+    }
+
+    pub fn set_clipboard(&self, kind: ::ClipboardKind, contents: ::ClipboardContents) -> Result<(), String> {
+        match self.first_window() {
+            Some(window) => window.set_clipboard(kind, contents),
+            None => Err("The clipboard can't be used before a window is created.".to_string()),
+        }
+    }
+
+    pub fn clipboard_mime_types(&self, kind: ::ClipboardKind) -> Vec<String> {
+        match self.first_window() {
+            Some(window) => window.clipboard_mime_types(kind),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn get_clipboard(&self, kind: ::ClipboardKind, mime_type: &str) -> Option<Vec<u8>> {
+        match self.first_window() {
+            Some(window) => window.get_clipboard(kind, mime_type),
+            None => None,
+        }
+    }
+
     pub fn poll_events<F>(&self, mut callback: F)
         where F: FnMut(::Event)
     {
-        let windows = self.windows.lock().unwrap();
+        // the callback may use the events loop, which needs the list of windows
+        let windows = self.windows.lock().unwrap().clone();
         for window in windows.iter() {
             for event in window.poll_events() {
                 callback(::Event::WindowEvent {
@@ -3,7 +3,7 @@ use CreationError;
 use CreationError::OsError;
 use libc;
 use std::borrow::Borrow;
-use std::{mem, ptr, cmp};
+use std::{mem, ptr, cmp, slice};
 use std::cell::Cell;
 use std::sync::atomic::AtomicBool;
 use std::collections::VecDeque;
@@ -12,13 +12,17 @@ use std::os::raw::c_long;
 use std::thread;
 use std::time::Duration;
 
-use CursorState;
+use {ClipboardContents, ClipboardKind, CursorImage, CursorState, CustomCursor, DragAction, DragData};
+use {Icon, IconImage, MouseButton, ScanCode};
 use WindowAttributes;
 use platform::PlatformSpecificWindowBuilderAttributes;
 
 use platform::MonitorId as PlatformMonitorId;
 
-use super::input::XInputEventHandler;
+use super::clipboard;
+use super::dnd::Dnd;
+use super::ime::{self, Ime};
+use super::input::{self, XInputEventHandler};
 use super::{ffi};
 use super::{MonitorId, XConnection};
 
@@ -47,9 +51,14 @@ pub struct XWindow {
     is_fullscreen: bool,
     screen_id: libc::c_int,
     xf86_desk_mode: Option<ffi::XF86VidModeModeInfo>,
-    ic: ffi::XIC,
-    im: ffi::XIM,
+    // `None` when no input method is running, the keys are then translated without it
+    ime: Mutex<Option<Ime>>,
     window_proxy_data: Arc<Mutex<Option<WindowProxyData>>>,
+    // the pixmap and mask referenced by the icon in `WM_HINTS`
+    icon_pixmaps: Mutex<Option<(ffi::Pixmap, ffi::Pixmap)>>,
+    // the cursor set with `set_cursor` or `set_custom_cursor`, or 0 for the default one, which
+    // is kept to be defined again when the cursor stops being hidden
+    cursor: Mutex<ffi::Cursor>,
 }
 
 unsafe impl Send for XWindow {}
@@ -74,8 +83,21 @@ impl Drop for XWindow {
                 (self.display.xf86vmode.XF86VidModeSetViewPort)(self.display.display, self.screen_id, 0, 0);
             }
 
-            (self.display.xlib.XDestroyIC)(self.ic);
-            (self.display.xlib.XCloseIM)(self.im);
+            if let Some((pixmap, mask)) = self.icon_pixmaps.lock().unwrap().take() {
+                (self.display.xlib.XFreePixmap)(self.display.display, pixmap);
+                (self.display.xlib.XFreePixmap)(self.display.display, mask);
+            }
+
+            clipboard::forget_window(&self.display, self.window);
+
+            let cursor = *self.cursor.lock().unwrap();
+            if cursor != 0 {
+                (self.display.xlib.XFreeCursor)(self.display.display, cursor);
+            }
+
+            if let Some(ref mut ime) = *self.ime.lock().unwrap() {
+                ime.close();
+            }
             (self.display.xlib.XDestroyWindow)(self.display.display, self.window);
         }
     }
@@ -153,6 +175,21 @@ impl<'a> Iterator for PollEventsIterator<'a> {
         let xlib = &self.window.x.display.xlib;
 
         loop {
+            // the XKB events that change the modifiers and the layout may be read by any window
+            {
+                let mut input_handler = self.window.input_handler.lock().unwrap();
+                let changes = input_handler.layouts_changed().into_iter()
+                    .chain(input_handler.modifiers_changed());
+                self.window.pending_events.lock().unwrap().extend(changes);
+                // as is the relative motion of the mouse
+                let motion = input_handler.take_relative_motion();
+                self.window.pending_events.lock().unwrap().extend(motion);
+            }
+
+            if let Some(event) = self.window.dnd.check_drag_timeout(&self.window.x.display, self.window.x.window) {
+                self.window.pending_events.lock().unwrap().push_back(event);
+            }
+
             if let Some(ev) = self.window.pending_events.lock().unwrap().pop_front() {
                 return Some(ev);
             }
@@ -179,6 +216,33 @@ impl<'a> Iterator for PollEventsIterator<'a> {
                 assert!(res == 0);
             };
 
+            // the timestamps of the events are needed to take and convert the selections
+            clipboard::update_event_time(&self.window.x.display, &xev);
+
+            // the pointer and the keyboard are grabbed while data is dragged out of the window
+            if let Some(events) = self.window.dnd.handle_drag_event(&self.window.x.display,
+                                                                    self.window.x.window, &mut xev)
+            {
+                self.window.pending_events.lock().unwrap().extend(events);
+                continue;
+            }
+
+            // the input method communicates through events, and consumes the key presses that
+            // compose text, reporting the composition with the callbacks of the input context
+            let filtered = unsafe { (xlib.XFilterEvent)(&mut xev, 0) } == ffi::True;
+            if let Some(ref ime) = *self.window.x.ime.lock().unwrap() {
+                self.window.pending_events.lock().unwrap().extend(ime.take_events());
+            }
+            if filtered {
+                continue;
+            }
+
+            if let Some(ref mut keyboard) = *self.window.x.display.keyboard.lock().unwrap() {
+                if keyboard.handle_event(&xev) {
+                    continue;
+                }
+            }
+
             match xev.get_type() {
                 ffi::MappingNotify => {
                     unsafe { (xlib.XRefreshKeyboardMapping)(mem::transmute(&xev)); }
@@ -191,7 +255,9 @@ impl<'a> Iterator for PollEventsIterator<'a> {
 
                     let client_msg: &ffi::XClientMessageEvent = unsafe { mem::transmute(&xev) };
 
-                    if client_msg.data.get_long(0) == self.window.wm_delete_window as libc::c_long {
+                    if let Some(events) = self.window.dnd.handle_client_message(&self.window.x.display, client_msg) {
+                        self.window.pending_events.lock().unwrap().extend(events);
+                    } else if client_msg.data.get_long(0) == self.window.wm_delete_window as libc::c_long {
                         self.window.is_closed.store(true, Relaxed);
                         return Some(Closed);
                     } else {
@@ -214,9 +280,22 @@ impl<'a> Iterator for PollEventsIterator<'a> {
                     return Some(Refresh);
                 },
 
+                ffi::SelectionRequest | ffi::SelectionClear | ffi::PropertyNotify => {
+                    clipboard::handle_event(&self.window.x.display, &xev);
+                },
+
+                ffi::KeymapNotify => {
+                    // sent after the window gains the focus, the held keys are reported
+                    let keymap_event: &ffi::XKeymapEvent = unsafe { mem::transmute(&xev) };
+                    let events = self.window.input_handler.lock().unwrap()
+                        .sync_pressed_keys(&keymap_event.key_vector);
+                    self.window.pending_events.lock().unwrap().extend(events);
+                },
+
                 ffi::KeyPress | ffi::KeyRelease => {
                     let mut event: &mut ffi::XKeyEvent = unsafe { mem::transmute(&mut xev) };
-                    let events = self.window.input_handler.lock().unwrap().translate_key_event(&mut event);
+                    let ime = self.window.x.ime.lock().unwrap();
+                    let events = self.window.input_handler.lock().unwrap().translate_key_event(&mut event, ime.as_ref());
                     for event in events {
                         self.window.pending_events.lock().unwrap().push_back(event);
                     }
@@ -224,12 +303,31 @@ impl<'a> Iterator for PollEventsIterator<'a> {
 
                 ffi::GenericEvent => {
                     if let Some(cookie) = GenericEventCookie::from_event(self.window.x.display.borrow(), xev) {
+                        clipboard::update_xi_event_time(&self.window.x.display, &cookie.cookie);
                         match cookie.cookie.evtype {
                             ffi::XI_DeviceChanged...ffi::XI_LASTEVENT => {
                                 match self.window.input_handler.lock() {
                                     Ok(mut handler) => {
                                         match handler.translate_event(&cookie.cookie) {
-                                            Some(event) => self.window.pending_events.lock().unwrap().push_back(event),
+                                            Some(event) => {
+                                                let mut focused_in = false;
+                                                if let Event::Focused(focused) = event {
+                                                    if let Some(ref ime) = *self.window.x.ime.lock().unwrap() {
+                                                        ime.set_focus(focused);
+                                                    }
+                                                    focused_in = focused;
+                                                    if !focused {
+                                                        let events = handler.release_pressed();
+                                                        self.window.pending_events.lock().unwrap().extend(events);
+                                                    }
+                                                }
+                                                self.window.pending_events.lock().unwrap().push_back(event);
+                                                if focused_in {
+                                                    let keymap = input::query_keymap(&self.window.x.display);
+                                                    let events = handler.sync_pressed_keys(&keymap);
+                                                    self.window.pending_events.lock().unwrap().extend(events);
+                                                }
+                                            },
                                             None => {}
                                         }
                                     },
@@ -279,6 +377,47 @@ impl<'a> Iterator for WaitEventsIterator<'a> {
     }
 }
 
+/// The size constraints that are communicated to the window manager through `WM_NORMAL_HINTS`.
+struct SizeConstraints {
+    min_dimensions: Option<(u32, u32)>,
+    max_dimensions: Option<(u32, u32)>,
+    resizable: bool,
+}
+
+impl SizeConstraints {
+    fn set_normal_hints(&self, display: &XConnection, window: ffi::Window, dimensions: (u32, u32)) {
+        let mut size_hints: ffi::XSizeHints = unsafe { mem::zeroed() };
+        size_hints.flags = ffi::PSize;
+        size_hints.width = dimensions.0 as i32;
+        size_hints.height = dimensions.1 as i32;
+
+        // a window that can't be resized has its minimum and maximum sizes locked to its
+        // current size
+        let (min_dimensions, max_dimensions) = if self.resizable {
+            (self.min_dimensions, self.max_dimensions)
+        } else {
+            (Some(dimensions), Some(dimensions))
+        };
+
+        if let Some(dimensions) = min_dimensions {
+            size_hints.flags |= ffi::PMinSize;
+            size_hints.min_width = dimensions.0 as i32;
+            size_hints.min_height = dimensions.1 as i32;
+        }
+
+        if let Some(dimensions) = max_dimensions {
+            size_hints.flags |= ffi::PMaxSize;
+            size_hints.max_width = dimensions.0 as i32;
+            size_hints.max_height = dimensions.1 as i32;
+        }
+
+        unsafe {
+            (display.xlib.XSetNormalHints)(display.display, window, &mut size_hints);
+            display.check_errors().expect("Failed to call XSetNormalHints");
+        }
+    }
+}
+
 pub struct Window {
     pub x: Arc<XWindow>,
     is_closed: AtomicBool,
@@ -287,6 +426,10 @@ pub struct Window {
     /// Events that have been retreived with XLib but not dispatched with iterators yet
     pending_events: Mutex<VecDeque<Event>>,
     cursor_state: Mutex<CursorState>,
+    // the window to which the cursor is confined while it is locked
+    cursor_lock_window: Mutex<Option<ffi::Window>>,
+    size_constraints: Mutex<SizeConstraints>,
+    dnd: Dnd,
     input_handler: Mutex<XInputEventHandler>
 }
 
@@ -313,6 +456,12 @@ impl Window {
 
         };
 
+        let size_constraints = SizeConstraints {
+            min_dimensions: window_attrs.min_dimensions,
+            max_dimensions: window_attrs.max_dimensions,
+            resizable: window_attrs.resizable,
+        };
+
         let screen_id = match pl_attribs.screen_id {
             Some(id) => id,
             None => match window_attrs.monitor {
@@ -369,7 +518,7 @@ impl Window {
             swa.event_mask = ffi::ExposureMask | ffi::StructureNotifyMask |
                 ffi::VisibilityChangeMask | ffi::KeyPressMask | ffi::PointerMotionMask |
                 ffi::KeyReleaseMask | ffi::ButtonPressMask |
-                ffi::ButtonReleaseMask | ffi::KeymapStateMask;
+                ffi::ButtonReleaseMask | ffi::KeymapStateMask | ffi::PropertyChangeMask;
             swa.border_pixel = 0;
             if window_attrs.transparent {
                 swa.background_pixel = 0;
@@ -403,6 +552,33 @@ impl Window {
             win
         };
 
+        // set the initial EWMH state ; window managers read it when the window gets mapped,
+        // which avoids briefly showing the window in its normal state first
+        {
+            let mut net_wm_state = Vec::new();
+            if window_attrs.maximized {
+                net_wm_state.push(display.get_atom("_NET_WM_STATE_MAXIMIZED_VERT"));
+                net_wm_state.push(display.get_atom("_NET_WM_STATE_MAXIMIZED_HORZ"));
+            }
+
+            if window_attrs.always_on_top {
+                net_wm_state.push(display.get_atom("_NET_WM_STATE_ABOVE"));
+            } else if window_attrs.always_on_bottom {
+                net_wm_state.push(display.get_atom("_NET_WM_STATE_BELOW"));
+            }
+
+            if !net_wm_state.is_empty() {
+                let state_atom = display.get_atom("_NET_WM_STATE");
+                unsafe {
+                    (display.xlib.XChangeProperty)(display.display, window, state_atom,
+                                                   ffi::XA_ATOM, 32, ffi::PropModeReplace,
+                                                   net_wm_state.as_ptr() as *const u8,
+                                                   net_wm_state.len() as libc::c_int);
+                }
+                display.check_errors().expect("Failed to set the initial _NET_WM_STATE");
+            }
+        }
+
         // set visibility
         if window_attrs.visible {
             unsafe {
@@ -427,35 +603,20 @@ impl Window {
             wm_delete_window
         };
 
-        // creating IM
-        let im = unsafe {
+        // creating IM and input context, the window works without them if no input method runs
+        let ime = {
             let _lock = GLOBAL_XOPENIM_LOCK.lock().unwrap();
 
-            let im = (display.xlib.XOpenIM)(display.display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
-            if im.is_null() {
-                return Err(OsError(format!("XOpenIM failed")));
-            }
-            im
-        };
-
-        // creating input context
-        let ic = unsafe {
-            let ic = with_c_str("inputStyle", |input_style|
-                with_c_str("clientWindow", |client_window|
-                    (display.xlib.XCreateIC)(
-                        im, input_style,
-                        ffi::XIMPreeditNothing | ffi::XIMStatusNothing, client_window,
-                        window, ptr::null::<()>()
-                    )
-                )
-            );
-            if ic.is_null() {
-                return Err(OsError(format!("XCreateIC failed")));
-            }
-            (display.xlib.XSetICFocus)(ic);
-            display.check_errors().expect("Failed to call XSetICFocus");
-            ic
+            ime::open_im(display).and_then(|im| match Ime::new(display, im, window) {
+                Ok(ime) => Some(ime),
+                Err(_) => {
+                    unsafe { (display.xlib.XCloseIM)(im) };
+                    None
+                },
+            })
         };
+        // the failures of the input method aren't fatal
+        let _ = display.check_errors();
 
         // Attempt to make keyboard input repeat detectable
         unsafe {
@@ -481,48 +642,12 @@ impl Window {
         let is_fullscreen = window_attrs.monitor.is_some();
 
         if is_fullscreen {
-            let state_atom = unsafe {
-                with_c_str("_NET_WM_STATE", |state|
-                    (display.xlib.XInternAtom)(display.display, state, 0)
-                )
-            };
-            display.check_errors().expect("Failed to call XInternAtom");
-            let fullscreen_atom = unsafe {
-                with_c_str("_NET_WM_STATE_FULLSCREEN", |state_fullscreen|
-                    (display.xlib.XInternAtom)(display.display, state_fullscreen, 0)
-                )
-            };
-            display.check_errors().expect("Failed to call XInternAtom");
+            let state_atom = display.get_atom("_NET_WM_STATE");
+            let fullscreen_atom = display.get_atom("_NET_WM_STATE_FULLSCREEN");
 
-            let client_message_event = ffi::XClientMessageEvent {
-                type_: ffi::ClientMessage,
-                serial: 0,
-                send_event: 1,            // true because we are sending this through `XSendEvent`
-                display: display.display,
-                window: window,
-                message_type: state_atom, // the _NET_WM_STATE atom is sent to change the state of a window
-                format: 32,               // view `data` as `c_long`s
-                data: {
-                    let mut data = ffi::ClientMessageData::new();
-                    // This first `long` is the action; `1` means add/set following property.
-                    data.set_long(0, 1);
-                    // This second `long` is the property to set (fullscreen)
-                    data.set_long(1, fullscreen_atom as c_long);
-                    data
-                }
-            };
-            let mut x_event = ffi::XEvent::from(client_message_event);
-
-            unsafe {
-                (display.xlib.XSendEvent)(
-                    display.display,
-                    root,
-                    0,
-                    ffi::SubstructureRedirectMask | ffi::SubstructureNotifyMask,
-                    &mut x_event as *mut _
-                );
-                display.check_errors().expect("Failed to call XSendEvent");
-            }
+            // The first `long` is the action; `1` means add/set following property.
+            display.send_client_message(window, state_atom, &[1, fullscreen_atom as c_long])
+                   .expect("Failed to call XSendEvent");
 
             if let Some(mut mode_to_switch_to) = mode_to_switch_to {
                 unsafe {
@@ -545,27 +670,7 @@ impl Window {
         } else {
 
             // set size hints
-            let mut size_hints: ffi::XSizeHints = unsafe { mem::zeroed() };
-            size_hints.flags = ffi::PSize;
-            size_hints.width = dimensions.0 as i32;
-            size_hints.height = dimensions.1 as i32;
-
-            if let Some(dimensions) = window_attrs.min_dimensions {
-                size_hints.flags |= ffi::PMinSize;
-                size_hints.min_width = dimensions.0 as i32;
-                size_hints.min_height = dimensions.1 as i32;
-            }
-
-            if let Some(dimensions) = window_attrs.max_dimensions {
-                size_hints.flags |= ffi::PMaxSize;
-                size_hints.max_width = dimensions.0 as i32;
-                size_hints.max_height = dimensions.1 as i32;
-            }
-
-            unsafe {
-                (display.xlib.XSetNormalHints)(display.display, window, &mut size_hints);
-                display.check_errors().expect("Failed to call XSetNormalHints");
-            }
+            size_constraints.set_normal_hints(display, window, dimensions);
 
         }
 
@@ -580,24 +685,33 @@ impl Window {
             x: Arc::new(XWindow {
                 display: display.clone(),
                 window: window,
-                im: im,
-                ic: ic,
+                ime: Mutex::new(ime),
                 screen_id: screen_id,
                 is_fullscreen: is_fullscreen,
                 xf86_desk_mode: xf86_desk_mode,
                 window_proxy_data: window_proxy_data,
+                icon_pixmaps: Mutex::new(None),
+                cursor: Mutex::new(0),
             }),
             is_closed: AtomicBool::new(false),
             wm_delete_window: wm_delete_window,
             current_size: Cell::new((0, 0)),
             pending_events: Mutex::new(VecDeque::new()),
             cursor_state: Mutex::new(CursorState::Normal),
-            input_handler: Mutex::new(XInputEventHandler::new(display, window, ic, window_attrs))
+            cursor_lock_window: Mutex::new(None),
+            size_constraints: Mutex::new(size_constraints),
+            dnd: Dnd::new(display),
+            input_handler: Mutex::new(XInputEventHandler::new(display, window, window_attrs))
         };
 
         window.set_title(&window_attrs.title);
+        window.dnd.set_aware(display, window.x.window);
         window.set_decorations(window_attrs.decorations);
 
+        if window_attrs.window_icon.is_some() {
+            window.set_window_icon(window_attrs.window_icon.clone());
+        }
+
         if window_attrs.visible {
             unsafe {
                 let ref x_window: &XWindow = window.x.borrow();
@@ -712,6 +826,200 @@ impl Window {
         }
     }
 
+    /// Adds or removes up to two properties of the EWMH `_NET_WM_STATE` of the window.
+    fn change_net_wm_state(&self, add: bool, first: ffi::Atom, second: ffi::Atom) {
+        let state_atom = self.x.display.get_atom("_NET_WM_STATE");
+        // `_NET_WM_STATE_ADD` is 1, `_NET_WM_STATE_REMOVE` is 0 ; the last `long` indicates
+        // that the request comes from a normal application
+        let action = if add { 1 } else { 0 };
+        self.x.display.send_client_message(self.x.window, state_atom,
+                                           &[action, first as c_long, second as c_long, 1])
+                      .expect("Failed to change _NET_WM_STATE");
+    }
+
+    /// Returns the list of properties currently in the EWMH `_NET_WM_STATE` of the window.
+    fn get_net_wm_state(&self) -> Vec<ffi::Atom> {
+        let state_atom = self.x.display.get_atom("_NET_WM_STATE");
+        self.x.display.get_property(self.x.window, state_atom, ffi::XA_ATOM).unwrap_or(Vec::new())
+    }
+
+    pub fn set_maximized(&self, maximized: bool) {
+        let vert = self.x.display.get_atom("_NET_WM_STATE_MAXIMIZED_VERT");
+        let horz = self.x.display.get_atom("_NET_WM_STATE_MAXIMIZED_HORZ");
+        self.change_net_wm_state(maximized, vert, horz);
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        let vert = self.x.display.get_atom("_NET_WM_STATE_MAXIMIZED_VERT");
+        let horz = self.x.display.get_atom("_NET_WM_STATE_MAXIMIZED_HORZ");
+        let state = self.get_net_wm_state();
+        state.contains(&vert) && state.contains(&horz)
+    }
+
+    pub fn set_minimized(&self, minimized: bool) {
+        if minimized {
+            unsafe {
+                (self.x.display.xlib.XIconifyWindow)(self.x.display.display, self.x.window, self.x.screen_id);
+                (self.x.display.xlib.XFlush)(self.x.display.display);
+            }
+            self.x.display.check_errors().expect("Failed to call XIconifyWindow");
+        } else {
+            // ICCCM says that mapping an iconified window brings it back to the normal state,
+            // but some EWMH window managers only listen to `_NET_ACTIVE_WINDOW`
+            self.show();
+            let active_window = self.x.display.get_atom("_NET_ACTIVE_WINDOW");
+            self.x.display.send_client_message(self.x.window, active_window,
+                                               &[1, ffi::CurrentTime as c_long, 0])
+                          .expect("Failed to send _NET_ACTIVE_WINDOW");
+        }
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        // value of the `IconicState` constant of ICCCM
+        const ICONIC_STATE: libc::c_ulong = 3;
+
+        let wm_state = self.x.display.get_atom("WM_STATE");
+        match self.x.display.get_property(self.x.window, wm_state, wm_state) {
+            Some(ref state) if !state.is_empty() => state[0] == ICONIC_STATE,
+            _ => false,
+        }
+    }
+
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        let above = self.x.display.get_atom("_NET_WM_STATE_ABOVE");
+        if always_on_top {
+            let below = self.x.display.get_atom("_NET_WM_STATE_BELOW");
+            self.change_net_wm_state(false, below, 0);
+        }
+        self.change_net_wm_state(always_on_top, above, 0);
+    }
+
+    pub fn set_always_on_bottom(&self, always_on_bottom: bool) {
+        let below = self.x.display.get_atom("_NET_WM_STATE_BELOW");
+        if always_on_bottom {
+            let above = self.x.display.get_atom("_NET_WM_STATE_ABOVE");
+            self.change_net_wm_state(false, above, 0);
+        }
+        self.change_net_wm_state(always_on_bottom, below, 0);
+    }
+
+    pub fn set_window_icon(&self, window_icon: Option<Icon>) {
+        let net_wm_icon = self.x.display.get_atom("_NET_WM_ICON");
+
+        match window_icon {
+            Some(ref icon) => {
+                // every size is written one after the other, each one starting with its dimensions
+                let mut data: Vec<libc::c_ulong> = Vec::new();
+                for image in icon.images() {
+                    data.push(image.width() as libc::c_ulong);
+                    data.push(image.height() as libc::c_ulong);
+                    data.extend(image.rgba().chunks(4).map(|px| {
+                        (px[3] as libc::c_ulong) << 24 | (px[0] as libc::c_ulong) << 16 |
+                        (px[1] as libc::c_ulong) << 8 | px[2] as libc::c_ulong
+                    }));
+                }
+
+                unsafe {
+                    (self.x.display.xlib.XChangeProperty)(self.x.display.display, self.x.window,
+                                                          net_wm_icon, ffi::XA_CARDINAL, 32,
+                                                          ffi::PropModeReplace, data.as_ptr() as *const u8,
+                                                          data.len() as libc::c_int);
+                }
+            },
+            None => unsafe {
+                (self.x.display.xlib.XDeleteProperty)(self.x.display.display, self.x.window, net_wm_icon);
+            },
+        }
+        self.x.display.check_errors().expect("Failed to set _NET_WM_ICON");
+
+        // window managers that don't support `_NET_WM_ICON` use the pixmap of `WM_HINTS`
+        let pixmaps = window_icon.as_ref().and_then(|icon| unsafe {
+            let size = (32.0 * self.hidpi_factor()).round() as u32;
+            self.create_icon_pixmaps(icon.image_for_size(size, size))
+        });
+
+        unsafe {
+            let mut hints = (self.x.display.xlib.XGetWMHints)(self.x.display.display, self.x.window);
+            if hints.is_null() {
+                hints = (self.x.display.xlib.XAllocWMHints)();
+            }
+
+            if !hints.is_null() {
+                match pixmaps {
+                    Some((pixmap, mask)) => {
+                        (*hints).flags |= ffi::IconPixmapHint | ffi::IconMaskHint;
+                        (*hints).icon_pixmap = pixmap;
+                        (*hints).icon_mask = mask;
+                    },
+                    None => {
+                        (*hints).flags &= !(ffi::IconPixmapHint | ffi::IconMaskHint);
+                    },
+                }
+
+                (self.x.display.xlib.XSetWMHints)(self.x.display.display, self.x.window, hints);
+                (self.x.display.xlib.XFree)(hints as *mut _);
+            }
+
+            let old_pixmaps = mem::replace(&mut *self.x.icon_pixmaps.lock().unwrap(), pixmaps);
+            if let Some((pixmap, mask)) = old_pixmaps {
+                (self.x.display.xlib.XFreePixmap)(self.x.display.display, pixmap);
+                (self.x.display.xlib.XFreePixmap)(self.x.display.display, mask);
+            }
+
+            (self.x.display.xlib.XFlush)(self.x.display.display);
+        }
+        self.x.display.check_errors().expect("Failed to set the icon in WM_HINTS");
+    }
+
+    // Creates the pixmap and the mask of an icon for `WM_HINTS`, on the root window of our screen.
+    unsafe fn create_icon_pixmaps(&self, image: &IconImage) -> Option<(ffi::Pixmap, ffi::Pixmap)> {
+        let display = &self.x.display;
+
+        // we only know how to write the pixels of 24 and 32 bits TrueColor visuals
+        let depth = (display.xlib.XDefaultDepth)(display.display, self.x.screen_id);
+        if depth != 24 && depth != 32 {
+            return None;
+        }
+
+        let root = (display.xlib.XRootWindow)(display.display, self.x.screen_id);
+        let (width, height) = (image.width(), image.height());
+        let mask_stride = (width + 7) / 8;
+
+        let mut pixels = Vec::with_capacity(image.rgba().len());
+        let mut mask = vec![0u8; (mask_stride * height) as usize];
+        for (i, px) in image.rgba().chunks(4).enumerate() {
+            pixels.extend_from_slice(&[px[2], px[1], px[0], px[3]]);
+            if px[3] >= 128 {
+                let (x, y) = (i as u32 % width, i as u32 / width);
+                mask[(y * mask_stride + x / 8) as usize] |= 1 << (x % 8);
+            }
+        }
+
+        let visual = (display.xlib.XDefaultVisual)(display.display, self.x.screen_id);
+        let ximage = (display.xlib.XCreateImage)(display.display, visual, depth as libc::c_uint,
+                                                 ffi::ZPixmap, 0, pixels.as_mut_ptr() as *mut libc::c_char,
+                                                 width, height, 32, 0);
+        if ximage.is_null() {
+            return None;
+        }
+        (*ximage).byte_order = ffi::LSBFirst;
+
+        let pixmap = (display.xlib.XCreatePixmap)(display.display, root, width, height, depth as libc::c_uint);
+        let gc = (display.xlib.XCreateGC)(display.display, pixmap, 0, ptr::null_mut());
+        (display.xlib.XPutImage)(display.display, pixmap, gc, ximage, 0, 0, 0, 0, width, height);
+        (display.xlib.XFreeGC)(display.display, gc);
+
+        // the pixels belong to our buffer, Xlib must not free them
+        (*ximage).data = ptr::null_mut();
+        (display.xlib.XDestroyImage)(ximage);
+
+        let mask = (display.xlib.XCreateBitmapFromData)(display.display, root,
+                                                        mask.as_ptr() as *const libc::c_char,
+                                                        width, height);
+
+        Some((pixmap, mask))
+    }
+
     fn get_geometry(&self) -> Option<(i32, i32, u32, u32, u32)> {
         unsafe {
             use std::mem;
@@ -759,6 +1067,62 @@ impl Window {
     pub fn set_inner_size(&self, x: u32, y: u32) {
         unsafe { (self.x.display.xlib.XResizeWindow)(self.x.display.display, self.x.window, x as libc::c_uint, y as libc::c_uint); }
         self.x.display.check_errors().expect("Failed to call XResizeWindow");
+
+        // a non-resizable window has its size hints locked to its size, so they must follow
+        let size_constraints = self.size_constraints.lock().unwrap();
+        if !size_constraints.resizable {
+            size_constraints.set_normal_hints(&self.x.display, self.x.window, (x, y));
+        }
+    }
+
+    pub fn set_resizable(&self, resizable: bool) {
+        let mut size_constraints = self.size_constraints.lock().unwrap();
+        size_constraints.resizable = resizable;
+        self.update_normal_hints(&size_constraints);
+    }
+
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        let mut size_constraints = self.size_constraints.lock().unwrap();
+        size_constraints.min_dimensions = dimensions;
+        self.update_normal_hints(&size_constraints);
+    }
+
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        let mut size_constraints = self.size_constraints.lock().unwrap();
+        size_constraints.max_dimensions = dimensions;
+        self.update_normal_hints(&size_constraints);
+    }
+
+    fn update_normal_hints(&self, size_constraints: &SizeConstraints) {
+        if self.x.is_fullscreen {
+            return;
+        }
+
+        let mut dimensions = self.get_inner_size().unwrap_or((800, 600));
+
+        // x11 only applies constraints when the window is actively resized
+        // by the user, so we have to manually apply the new constraints
+        if size_constraints.resizable {
+            let mut clamped = dimensions;
+            if let Some(max) = size_constraints.max_dimensions {
+                clamped.0 = cmp::min(clamped.0, max.0);
+                clamped.1 = cmp::min(clamped.1, max.1);
+            }
+            if let Some(min) = size_constraints.min_dimensions {
+                clamped.0 = cmp::max(clamped.0, min.0);
+                clamped.1 = cmp::max(clamped.1, min.1);
+            }
+            if clamped != dimensions {
+                unsafe {
+                    (self.x.display.xlib.XResizeWindow)(self.x.display.display, self.x.window,
+                                                        clamped.0 as libc::c_uint, clamped.1 as libc::c_uint);
+                }
+                self.x.display.check_errors().expect("Failed to call XResizeWindow");
+                dimensions = clamped;
+            }
+        }
+
+        size_constraints.set_normal_hints(&self.x.display, self.x.window, dimensions);
     }
 
     #[inline]
@@ -883,14 +1247,96 @@ impl Window {
                 MouseCursor::NoneCursor => self.create_empty_cursor(),
             };
 
-            (self.x.display.xlib.XDefineCursor)(self.x.display.display, self.x.window, xcursor);
-            if xcursor != 0 {
-                (self.x.display.xlib.XFreeCursor)(self.x.display.display, xcursor);
+            self.replace_cursor(xcursor);
+        }
+    }
+
+    pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+        unsafe {
+            // the default size takes the `Xft.dpi` resource and `XCURSOR_SIZE` into account
+            let size = (self.x.display.xcursor.XcursorGetDefaultSize)(self.x.display.display);
+            let xcursor = self.create_custom_cursor(cursor.frames_for_size(size as u32));
+
+            self.replace_cursor(xcursor);
+        }
+    }
+
+    // Stores the new cursor of the window and frees the previous one. The cursor is only defined
+    // now if it isn't hidden, otherwise it will be when the cursor is shown again.
+    fn replace_cursor(&self, xcursor: ffi::Cursor) {
+        let previous = mem::replace(&mut *self.x.cursor.lock().unwrap(), xcursor);
+        match *self.cursor_state.lock().unwrap() {
+            CursorState::Hide | CursorState::Locked => {},
+            _ => unsafe {
+                (self.x.display.xlib.XDefineCursor)(self.x.display.display, self.x.window, xcursor);
+            },
+        }
+        unsafe {
+            if previous != 0 {
+                (self.x.display.xlib.XFreeCursor)(self.x.display.display, previous);
             }
             self.x.display.check_errors().expect("Failed to set or free the cursor");
         }
     }
 
+    // Defines the cursor set with `set_cursor` or `set_custom_cursor` again, after it was hidden.
+    fn restore_cursor(&self) {
+        unsafe {
+            (self.x.display.xlib.XDefineCursor)(self.x.display.display, self.x.window,
+                                                *self.x.cursor.lock().unwrap());
+        }
+    }
+
+    // the X server animates the cursor by itself when it is created from several images
+    fn create_custom_cursor(&self, frames: &[CursorImage]) -> ffi::Cursor {
+        unsafe {
+            let ximages = (self.x.display.xcursor.XcursorImagesCreate)(frames.len() as libc::c_int);
+            if ximages.is_null() {
+                return 0;
+            }
+
+            for frame in frames {
+                let ximage = self.create_xcursor_image(frame);
+                if ximage.is_null() {
+                    (self.x.display.xcursor.XcursorImagesDestroy)(ximages);
+                    return 0;
+                }
+                *(*ximages).images.offset((*ximages).nimage as isize) = ximage;
+                (*ximages).nimage += 1;
+            }
+
+            let cursor = (self.x.display.xcursor.XcursorImagesLoadCursor)(self.x.display.display, ximages);
+            // also destroys the images
+            (self.x.display.xcursor.XcursorImagesDestroy)(ximages);
+            cursor
+        }
+    }
+
+    fn create_xcursor_image(&self, cursor_image: &CursorImage) -> *mut ffi::XcursorImage {
+        let image = cursor_image.image();
+        unsafe {
+            let ximage = (self.x.display.xcursor.XcursorImageCreate)(image.width() as libc::c_int,
+                                                                     image.height() as libc::c_int);
+            if ximage.is_null() {
+                return ximage;
+            }
+
+            (*ximage).xhot = cursor_image.hotspot().0;
+            (*ximage).yhot = cursor_image.hotspot().1;
+            (*ximage).delay = cursor_image.delay();
+
+            // Xcursor pixels are premultiplied ARGB
+            let pixels = slice::from_raw_parts_mut((*ximage).pixels, (image.width() * image.height()) as usize);
+            for (pixel, px) in pixels.iter_mut().zip(image.rgba().chunks(4)) {
+                let a = px[3] as u32;
+                let premultiply = |c: u8| c as u32 * a / 255;
+                *pixel = a << 24 | premultiply(px[0]) << 16 | premultiply(px[1]) << 8 | premultiply(px[2]);
+            }
+
+            ximage
+        }
+    }
+
     fn load_cursor(&self, name: &str) -> ffi::Cursor {
         use std::ffi::CString;
         unsafe {
@@ -937,29 +1383,34 @@ impl Window {
     }
 
     pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
-        use CursorState::{ Grab, Normal, Hide };
+        use CursorState::{ Grab, Normal, Hide, Locked, Confined };
 
         let mut cursor_state = self.cursor_state.lock().unwrap();
         match (state, *cursor_state) {
-            (Normal, Normal) | (Hide, Hide) | (Grab, Grab) => return Ok(()),
+            (Normal, Normal) | (Hide, Hide) | (Grab, Grab) | (Locked, Locked) | (Confined, Confined) => return Ok(()),
             _ => {},
         }
 
         match *cursor_state {
-            Grab => {
+            Grab | Confined => {
                 unsafe {
                     (self.x.display.xlib.XUngrabPointer)(self.x.display.display, ffi::CurrentTime);
                     self.x.display.check_errors().expect("Failed to call XUngrabPointer");
                 }
             },
-            Normal => {},
-            Hide => {
-                // NB: Calling XDefineCursor with None (aka 0)
-                // as a value resets the cursor to the default.
+            Locked => {
                 unsafe {
-                    (self.x.display.xlib.XDefineCursor)(self.x.display.display, self.x.window, 0);
+                    (self.x.display.xlib.XUngrabPointer)(self.x.display.display, ffi::CurrentTime);
+                    if let Some(lock_window) = self.cursor_lock_window.lock().unwrap().take() {
+                        (self.x.display.xlib.XDestroyWindow)(self.x.display.display, lock_window);
+                    }
+                    self.restore_cursor();
+                    self.x.display.check_errors().expect("Failed to unlock the cursor");
                 }
+                self.input_handler.lock().unwrap().set_relative_motion(false);
             },
+            Normal => {},
+            Hide => self.restore_cursor(),
         }
 
         *cursor_state = state;
@@ -996,7 +1447,129 @@ impl Window {
                     }
                 }
             },
+            Locked => {
+                let result = self.lock_cursor();
+                if result.is_err() {
+                    *cursor_state = Normal;
+                }
+                result
+            },
+            Confined => {
+                // only the pointer events are grabbed, and they are still reported to the other
+                // windows of the application
+                let result = unsafe {
+                    (self.x.display.xlib.XGrabPointer)(
+                        self.x.display.display, self.x.window, ffi::True,
+                        (ffi::ButtonPressMask | ffi::ButtonReleaseMask | ffi::EnterWindowMask |
+                        ffi::LeaveWindowMask | ffi::PointerMotionMask) as libc::c_uint,
+                        ffi::GrabModeAsync, ffi::GrabModeAsync,
+                        self.x.window, 0, ffi::CurrentTime
+                    )
+                };
+                if result == ffi::GrabSuccess {
+                    Ok(())
+                } else {
+                    *cursor_state = Normal;
+                    Err("cursor could not be confined".to_string())
+                }
+            },
+        }
+    }
+
+    // The cursor is hidden and confined to a 1x1 child window so that it can't move at all,
+    // while the motion of the mouse is still reported by the raw XInput events
+    fn lock_cursor(&self) -> Result<(), String> {
+        let xlib = &self.x.display.xlib;
+        let display = self.x.display.display;
+        unsafe {
+            // keep the cursor where it is if it's over the window, otherwise in the middle
+            let (mut root, mut child) = (0, 0);
+            let (mut root_x, mut root_y, mut x, mut y) = (0, 0, 0, 0);
+            let mut buttons = 0;
+            (xlib.XQueryPointer)(display, self.x.window, &mut root, &mut child, &mut root_x, &mut root_y,
+                                 &mut x, &mut y, &mut buttons);
+            let (width, height) = self.current_size.get();
+            if x < 0 || y < 0 || x >= width || y >= height {
+                x = width / 2;
+                y = height / 2;
+            }
+
+            let lock_window = (xlib.XCreateWindow)(display, self.x.window, x, y, 1, 1, 0, 0,
+                                                   ffi::InputOnly as libc::c_uint, ptr::null_mut(),
+                                                   0, ptr::null_mut());
+            (xlib.XMapWindow)(display, lock_window);
+
+            let cursor = self.create_empty_cursor();
+            (xlib.XDefineCursor)(display, self.x.window, cursor);
+            let result = (xlib.XGrabPointer)(
+                display, self.x.window, ffi::True,
+                (ffi::ButtonPressMask | ffi::ButtonReleaseMask | ffi::PointerMotionMask) as libc::c_uint,
+                ffi::GrabModeAsync, ffi::GrabModeAsync,
+                lock_window, cursor, ffi::CurrentTime
+            );
+            if cursor != 0 {
+                (xlib.XFreeCursor)(display, cursor);
+            }
+            self.x.display.check_errors().expect("Failed to lock the cursor");
+
+            if result != ffi::GrabSuccess {
+                (xlib.XDestroyWindow)(display, lock_window);
+                self.restore_cursor();
+                self.x.display.check_errors().expect("Failed to destroy the lock window");
+                return Err("cursor could not be locked".to_string());
+            }
+            *self.cursor_lock_window.lock().unwrap() = Some(lock_window);
+        }
+
+        self.input_handler.lock().unwrap().set_relative_motion(true);
+        Ok(())
+    }
+
+    #[inline]
+    pub fn set_clipboard(&self, kind: ClipboardKind, contents: ClipboardContents) -> Result<(), String> {
+        clipboard::set_clipboard(&self.x.display, self.x.window, kind, contents)
+    }
+
+    #[inline]
+    pub fn clipboard_mime_types(&self, kind: ClipboardKind) -> Vec<String> {
+        clipboard::clipboard_mime_types(&self.x.display, self.x.window, kind)
+    }
+
+    #[inline]
+    pub fn get_clipboard(&self, kind: ClipboardKind, mime_type: &str) -> Option<Vec<u8>> {
+        clipboard::get_clipboard(&self.x.display, self.x.window, kind, mime_type)
+    }
+
+    #[inline]
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        if let Some(ref mut ime) = *self.x.ime.lock().unwrap() {
+            ime.set_spot(x, y);
+            self.x.display.check_errors().expect("Failed to call XSetICValues");
+        }
+    }
+
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        if let Some(ref mut ime) = *self.x.ime.lock().unwrap() {
+            ime.set_allowed(allowed);
+            self.pending_events.lock().unwrap().extend(ime.take_events());
+        }
+    }
+
+    pub fn get_pressed_keys(&self) -> Vec<ScanCode> {
+        let keymap = input::query_keymap(&self.x.display);
+        input::keymap_to_keycodes(&keymap).into_iter().collect()
+    }
+
+    pub fn get_pressed_mouse_buttons(&self) -> Vec<MouseButton> {
+        input::query_pointer_buttons(&self.x.display)
+    }
+
+    pub fn start_drag(&self, data: DragData, allowed_actions: &[DragAction]) -> Result<(), String> {
+        // the drag is advanced by the events, unless it ended right away
+        if let Some(result) = try!(self.dnd.start_drag(&self.x.display, self.x.window, data, allowed_actions)) {
+            self.pending_events.lock().unwrap().push_back(Event::DragFinished(result));
         }
+        Ok(())
     }
 
     pub fn hidpi_factor(&self) -> f32 {
@@ -1,11 +1,16 @@
-use std::ptr;
+use std::{mem, ptr, slice};
 use std::fmt;
 use std::error::Error;
+use std::ffi::{CStr, CString};
 use std::sync::Mutex;
+use std::os::raw::{c_long, c_short, c_ulong};
 
 use libc;
 
 use super::ffi;
+use super::clipboard::ClipboardState;
+use super::input::RawMotionState;
+use super::keyboard::Keyboard;
 
 /// A connection to an X server.
 pub struct XConnection {
@@ -16,6 +21,10 @@ pub struct XConnection {
     pub xlib_xcb: ffi::Xlib_xcb,
     pub display: *mut ffi::Display,
     pub latest_error: Mutex<Option<XError>>,
+    pub clipboard: Mutex<ClipboardState>,
+    pub raw_motion: Mutex<RawMotionState>,
+    // `None` if libxkbcommon-x11 isn't available, xlib translates the keys then
+    pub keyboard: Mutex<Option<Keyboard>>,
 }
 
 unsafe impl Send for XConnection {}
@@ -44,6 +53,8 @@ impl XConnection {
             display
         };
 
+        let keyboard = Keyboard::new(&xlib, &xlib_xcb, display);
+
         Ok(XConnection {
             xlib: xlib,
             xf86vmode: xf86vmode,
@@ -52,6 +63,9 @@ impl XConnection {
             xlib_xcb: xlib_xcb,
             display: display,
             latest_error: Mutex::new(None),
+            clipboard: Mutex::new(ClipboardState::default()),
+            raw_motion: Mutex::new(RawMotionState::default()),
+            keyboard: Mutex::new(keyboard),
         })
     }
 
@@ -72,6 +86,169 @@ impl XConnection {
     pub fn ignore_error(&self) {
         *self.latest_error.lock().unwrap() = None;
     }
+
+    /// Returns the atom corresponding to `name`, creating it if it doesn't exist yet.
+    pub fn get_atom(&self, name: &str) -> ffi::Atom {
+        let c_name = CString::new(name).unwrap();
+        let atom = unsafe { (self.xlib.XInternAtom)(self.display, c_name.as_ptr(), ffi::False) };
+        self.check_errors().expect("Failed to call XInternAtom");
+        atom
+    }
+
+    /// Reads a property of format 32 from a window.
+    ///
+    /// Returns `None` if the property doesn't exist or if it doesn't have the requested type.
+    /// Note that xlib always returns items of format 32 as `c_long`s, whatever their real size.
+    pub fn get_property(&self, window: ffi::Window, property: ffi::Atom, type_: ffi::Atom)
+                        -> Option<Vec<c_ulong>>
+    {
+        let mut result = Vec::new();
+        let mut offset = 0;
+
+        loop {
+            let mut actual_type = 0;
+            let mut actual_format = 0;
+            let mut item_count = 0;
+            let mut bytes_after = 0;
+            let mut data: *mut libc::c_uchar = ptr::null_mut();
+
+            let status = unsafe {
+                (self.xlib.XGetWindowProperty)(self.display, window, property, offset, 1024,
+                                               ffi::False, type_, &mut actual_type,
+                                               &mut actual_format, &mut item_count,
+                                               &mut bytes_after, &mut data)
+            };
+
+            if status != ffi::Success as libc::c_int || actual_type != type_ || actual_format != 32 {
+                if !data.is_null() {
+                    unsafe { (self.xlib.XFree)(data as *mut _) };
+                }
+                self.ignore_error();
+                return None;
+            }
+
+            unsafe {
+                let items = data as *const c_ulong;
+                result.extend((0 .. item_count as isize).map(|i| *items.offset(i)));
+                (self.xlib.XFree)(data as *mut _);
+            }
+
+            if bytes_after == 0 {
+                return Some(result);
+            }
+            offset += item_count as c_long;
+        }
+    }
+
+    /// Returns the name of an atom.
+    pub fn get_atom_name(&self, atom: ffi::Atom) -> Option<String> {
+        unsafe {
+            let name = (self.xlib.XGetAtomName)(self.display, atom);
+            if name.is_null() {
+                self.ignore_error();
+                return None;
+            }
+            let result = CStr::from_ptr(name).to_string_lossy().into_owned();
+            (self.xlib.XFree)(name as *mut _);
+            Some(result)
+        }
+    }
+
+    /// Reads a property of any type and format from a window as bytes, then deletes it.
+    ///
+    /// Returns `None` if the property doesn't exist, or its type and its data otherwise. Items of
+    /// format 16 and 32 are stored in native endianness.
+    pub fn take_property_bytes(&self, window: ffi::Window, property: ffi::Atom)
+                               -> Option<(ffi::Atom, Vec<u8>)>
+    {
+        let mut result = Vec::new();
+        let mut offset = 0;
+
+        loop {
+            let mut actual_type = 0;
+            let mut actual_format = 0;
+            let mut item_count = 0;
+            let mut bytes_after = 0;
+            let mut data: *mut libc::c_uchar = ptr::null_mut();
+
+            let status = unsafe {
+                (self.xlib.XGetWindowProperty)(self.display, window, property, offset, 1 << 16,
+                                               ffi::False, ffi::AnyPropertyType as ffi::Atom,
+                                               &mut actual_type, &mut actual_format,
+                                               &mut item_count, &mut bytes_after, &mut data)
+            };
+
+            if status != ffi::Success as libc::c_int || actual_type == 0 {
+                if !data.is_null() {
+                    unsafe { (self.xlib.XFree)(data as *mut _) };
+                }
+                self.ignore_error();
+                return None;
+            }
+
+            // xlib returns items of format 16 as `c_short`s and of format 32 as `c_long`s
+            unsafe {
+                let count = item_count as usize;
+                match actual_format {
+                    16 => for i in 0 .. count {
+                        let item = *(data as *const c_short).offset(i as isize) as u16;
+                        result.extend_from_slice(&mem::transmute::<u16, [u8; 2]>(item));
+                    },
+                    32 => for i in 0 .. count {
+                        let item = *(data as *const c_ulong).offset(i as isize) as u32;
+                        result.extend_from_slice(&mem::transmute::<u32, [u8; 4]>(item));
+                    },
+                    _ => result.extend_from_slice(slice::from_raw_parts(data, count)),
+                }
+                (self.xlib.XFree)(data as *mut _);
+            }
+
+            if bytes_after == 0 {
+                unsafe { (self.xlib.XDeleteProperty)(self.display, window, property) };
+                self.ignore_error();
+                return Some((actual_type, result));
+            }
+            // the offset is expressed in 32-bit units
+            offset += (item_count as c_long * actual_format as c_long / 8) / 4;
+        }
+    }
+
+    /// Sends a client message of format 32 to the root window on behalf of `window`, as
+    /// required by the EWMH specification to change the state of a mapped window.
+    pub fn send_client_message(&self, window: ffi::Window, message_type: ffi::Atom,
+                               data: &[c_long]) -> Result<(), XError>
+    {
+        let mut client_data = ffi::ClientMessageData::new();
+        for (i, &value) in data.iter().enumerate() {
+            client_data.set_long(i, value);
+        }
+
+        let client_message_event = ffi::XClientMessageEvent {
+            type_: ffi::ClientMessage,
+            serial: 0,
+            send_event: 1,            // true because we are sending this through `XSendEvent`
+            display: self.display,
+            window: window,
+            message_type: message_type,
+            format: 32,               // view `data` as `c_long`s
+            data: client_data,
+        };
+        let mut x_event = ffi::XEvent::from(client_message_event);
+
+        unsafe {
+            let root = (self.xlib.XDefaultRootWindow)(self.display);
+            (self.xlib.XSendEvent)(
+                self.display,
+                root,
+                ffi::False,
+                ffi::SubstructureRedirectMask | ffi::SubstructureNotifyMask,
+                &mut x_event as *mut _
+            );
+            (self.xlib.XFlush)(self.display);
+        }
+
+        self.check_errors()
+    }
 }
 
 impl Drop for XConnection {
@@ -0,0 +1,491 @@
+//! Bindings to libxkbcommon, loaded at runtime.
+//!
+//! Wayland translates the keys through `wayland_kbd`, these bindings cover the rest: the keymaps
+//! of the X server through libxkbcommon-x11, the layouts on both backends and the compose
+//! sequences.
+
+#![allow(dead_code, non_camel_case_types)]
+
+use std::env;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::Path;
+
+use libc;
+
+use ModifiersState;
+
+pub enum xkb_context {}
+pub enum xkb_keymap {}
+pub enum xkb_state {}
+pub enum xkb_compose_table {}
+pub enum xkb_compose_state {}
+
+pub type xkb_keycode_t = u32;
+pub type xkb_keysym_t = u32;
+pub type xkb_layout_index_t = u32;
+pub type xkb_mod_index_t = u32;
+pub type xkb_mod_mask_t = u32;
+
+pub const XKB_MOD_INVALID: xkb_mod_index_t = 0xffffffff;
+
+pub const XKB_CONTEXT_NO_FLAGS: c_int = 0;
+pub const XKB_KEYMAP_FORMAT_TEXT_V1: c_int = 1;
+pub const XKB_KEYMAP_COMPILE_NO_FLAGS: c_int = 0;
+pub const XKB_COMPOSE_COMPILE_NO_FLAGS: c_int = 0;
+pub const XKB_COMPOSE_STATE_NO_FLAGS: c_int = 0;
+pub const XKB_X11_SETUP_XKB_EXTENSION_NO_FLAGS: c_int = 0;
+
+// enum xkb_state_component
+pub const XKB_STATE_MODS_EFFECTIVE: c_int = 1 << 3;
+pub const XKB_STATE_LAYOUT_EFFECTIVE: c_int = 1 << 7;
+
+pub const XKB_X11_MIN_MAJOR_XKB_VERSION: u16 = 1;
+pub const XKB_X11_MIN_MINOR_XKB_VERSION: u16 = 0;
+
+// enum xkb_compose_feed_result
+pub const XKB_COMPOSE_FEED_IGNORED: c_int = 0;
+pub const XKB_COMPOSE_FEED_ACCEPTED: c_int = 1;
+
+// enum xkb_compose_status
+pub const XKB_COMPOSE_NOTHING: c_int = 0;
+pub const XKB_COMPOSE_COMPOSING: c_int = 1;
+pub const XKB_COMPOSE_COMPOSED: c_int = 2;
+pub const XKB_COMPOSE_CANCELLED: c_int = 3;
+
+shared_library!(XkbCommon,
+    fn xkb_context_new(flags: c_int) -> *mut xkb_context,
+    fn xkb_context_unref(context: *mut xkb_context),
+    fn xkb_keymap_new_from_string(context: *mut xkb_context, string: *const c_char, format: c_int,
+                                  flags: c_int) -> *mut xkb_keymap,
+    fn xkb_keymap_unref(keymap: *mut xkb_keymap),
+    fn xkb_keymap_get_as_string(keymap: *mut xkb_keymap, format: c_int) -> *mut c_char,
+    fn xkb_keymap_num_layouts(keymap: *mut xkb_keymap) -> xkb_layout_index_t,
+    fn xkb_keymap_layout_get_name(keymap: *mut xkb_keymap,
+                                  idx: xkb_layout_index_t) -> *const c_char,
+    fn xkb_keymap_mod_get_index(keymap: *mut xkb_keymap, name: *const c_char) -> xkb_mod_index_t,
+    fn xkb_state_new(keymap: *mut xkb_keymap) -> *mut xkb_state,
+    fn xkb_state_unref(state: *mut xkb_state),
+    fn xkb_state_update_mask(state: *mut xkb_state, depressed_mods: xkb_mod_mask_t,
+                             latched_mods: xkb_mod_mask_t, locked_mods: xkb_mod_mask_t,
+                             depressed_layout: xkb_layout_index_t,
+                             latched_layout: xkb_layout_index_t,
+                             locked_layout: xkb_layout_index_t) -> c_int,
+    fn xkb_state_serialize_mods(state: *mut xkb_state, components: c_int) -> xkb_mod_mask_t,
+    fn xkb_state_serialize_layout(state: *mut xkb_state,
+                                  components: c_int) -> xkb_layout_index_t,
+    fn xkb_state_key_get_one_sym(state: *mut xkb_state, key: xkb_keycode_t) -> xkb_keysym_t,
+    fn xkb_state_key_get_utf8(state: *mut xkb_state, key: xkb_keycode_t, buffer: *mut c_char,
+                              size: usize) -> c_int,
+    fn xkb_compose_table_new_from_locale(context: *mut xkb_context, locale: *const c_char,
+                                         flags: c_int) -> *mut xkb_compose_table,
+    fn xkb_compose_table_unref(table: *mut xkb_compose_table),
+    fn xkb_compose_state_new(table: *mut xkb_compose_table,
+                             flags: c_int) -> *mut xkb_compose_state,
+    fn xkb_compose_state_unref(state: *mut xkb_compose_state),
+    fn xkb_compose_state_feed(state: *mut xkb_compose_state, keysym: xkb_keysym_t) -> c_int,
+    fn xkb_compose_state_reset(state: *mut xkb_compose_state),
+    fn xkb_compose_state_get_status(state: *mut xkb_compose_state) -> c_int,
+    fn xkb_compose_state_get_utf8(state: *mut xkb_compose_state, buffer: *mut c_char,
+                                  size: usize) -> c_int,
+);
+
+shared_library!(XkbCommonX11,
+    fn xkb_x11_setup_xkb_extension(connection: *mut c_void, major_xkb_version: u16,
+                                   minor_xkb_version: u16, flags: c_int,
+                                   major_xkb_version_out: *mut u16,
+                                   minor_xkb_version_out: *mut u16, base_event_out: *mut u8,
+                                   base_error_out: *mut u8) -> c_int,
+    fn xkb_x11_get_core_keyboard_device_id(connection: *mut c_void) -> i32,
+    fn xkb_x11_keymap_new_from_device(context: *mut xkb_context, connection: *mut c_void,
+                                      device_id: i32, flags: c_int) -> *mut xkb_keymap,
+    fn xkb_x11_state_new_from_device(keymap: *mut xkb_keymap, connection: *mut c_void,
+                                     device_id: i32) -> *mut xkb_state,
+);
+
+lazy_static! {
+    pub static ref XKBCOMMON: Option<XkbCommon> = {
+        XkbCommon::open(Path::new("libxkbcommon.so.0")).ok()
+    };
+
+    pub static ref XKBCOMMON_X11: Option<XkbCommonX11> = {
+        XkbCommonX11::open(Path::new("libxkbcommon-x11.so.0")).ok()
+    };
+}
+
+/// Calls one of the functions of libxkbcommon that write UTF-8 in a buffer.
+pub unsafe fn get_utf8<F>(f: F) -> String where F: Fn(*mut c_char, usize) -> c_int {
+    let mut buffer = vec![0u8; 16];
+    let size = f(buffer.as_mut_ptr() as *mut c_char, buffer.len());
+    if size <= 0 {
+        return String::new();
+    }
+    // the size doesn't include the nul byte
+    if size as usize >= buffer.len() {
+        buffer = vec![0u8; size as usize + 1];
+        f(buffer.as_mut_ptr() as *mut c_char, buffer.len());
+    }
+    buffer.truncate(size as usize);
+    String::from_utf8(buffer).unwrap_or(String::new())
+}
+
+/// The indices of the modifiers in the masks of a keymap.
+///
+/// `Mod1` is alt, `Mod2` num lock and `Mod4` the logo key, as libxkbcommon names them. AltGr is
+/// the `LevelThree` modifier, which the keymaps of X11 map to `Mod5`.
+#[derive(Debug, Clone, Copy)]
+pub struct ModIndices {
+    shift: xkb_mod_index_t,
+    caps_lock: xkb_mod_index_t,
+    ctrl: xkb_mod_index_t,
+    alt: xkb_mod_index_t,
+    num_lock: xkb_mod_index_t,
+    logo: xkb_mod_index_t,
+    level_three: xkb_mod_index_t,
+    mod5: xkb_mod_index_t,
+}
+
+impl ModIndices {
+    /// The indices of the masks of the core protocol of X11, for when there is no keymap.
+    pub fn core() -> ModIndices {
+        ModIndices {
+            shift: 0,
+            caps_lock: 1,
+            ctrl: 2,
+            alt: 3,
+            num_lock: 4,
+            logo: 6,
+            level_three: XKB_MOD_INVALID,
+            mod5: 7,
+        }
+    }
+
+    /// Looks up the modifiers in a keymap, the missing ones are never active.
+    pub unsafe fn from_keymap(keymap: *mut xkb_keymap) -> ModIndices {
+        let xkb = XKBCOMMON.as_ref().unwrap();
+        let index = |name: &str| {
+            let name = CString::new(name).unwrap();
+            (xkb.xkb_keymap_mod_get_index)(keymap, name.as_ptr())
+        };
+        ModIndices {
+            shift: index("Shift"),
+            caps_lock: index("Lock"),
+            ctrl: index("Control"),
+            alt: index("Mod1"),
+            num_lock: index("Mod2"),
+            logo: index("Mod4"),
+            level_three: index("LevelThree"),
+            mod5: index("Mod5"),
+        }
+    }
+
+    /// Returns the modifiers active in a mask.
+    pub fn to_modifiers(&self, mask: xkb_mod_mask_t) -> ModifiersState {
+        let active = |index: xkb_mod_index_t| index < 32 && mask & (1 << index) != 0;
+        ModifiersState {
+            shift: active(self.shift),
+            caps_lock: active(self.caps_lock),
+            ctrl: active(self.ctrl),
+            alt: active(self.alt),
+            num_lock: active(self.num_lock),
+            logo: active(self.logo),
+            alt_graph: active(self.level_three) || active(self.mod5),
+            .. ModifiersState::default()
+        }
+    }
+}
+
+/// Returns the names of the layouts of a keymap, such as "us" or "de(nodeadkeys)".
+///
+/// These are the symbols the keymap was compiled from, the descriptions given by the keymap
+/// ("German (no dead keys)") are only used when the symbols can't be matched to the layouts.
+pub unsafe fn layout_names(keymap: *mut xkb_keymap) -> Vec<String> {
+    let xkb = XKBCOMMON.as_ref().unwrap();
+    let count = (xkb.xkb_keymap_num_layouts)(keymap) as usize;
+
+    let text = (xkb.xkb_keymap_get_as_string)(keymap, XKB_KEYMAP_FORMAT_TEXT_V1);
+    if !text.is_null() {
+        let names = symbols_layouts(&CStr::from_ptr(text).to_string_lossy());
+        libc::free(text as *mut _);
+        if names.len() == count {
+            return names;
+        }
+    }
+
+    (0..count).map(|idx| {
+        let name = (xkb.xkb_keymap_layout_get_name)(keymap, idx as xkb_layout_index_t);
+        if name.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(name).to_string_lossy().into_owned()
+        }
+    }).collect()
+}
+
+/// A keymap given as text, like the ones of Wayland, with its state.
+pub struct Keymap {
+    context: *mut xkb_context,
+    keymap: *mut xkb_keymap,
+    state: *mut xkb_state,
+    mod_indices: ModIndices,
+}
+
+impl Keymap {
+    /// Compiles the keymap, the text ends with a nul byte.
+    pub unsafe fn from_text(text: *const c_char) -> Option<Keymap> {
+        let xkb = match *XKBCOMMON {
+            Some(ref xkb) => xkb,
+            None => return None,
+        };
+        let context = (xkb.xkb_context_new)(XKB_CONTEXT_NO_FLAGS);
+        if context.is_null() {
+            return None;
+        }
+        let keymap = (xkb.xkb_keymap_new_from_string)(context, text, XKB_KEYMAP_FORMAT_TEXT_V1,
+                                                      XKB_KEYMAP_COMPILE_NO_FLAGS);
+        if keymap.is_null() {
+            (xkb.xkb_context_unref)(context);
+            return None;
+        }
+        let state = (xkb.xkb_state_new)(keymap);
+        if state.is_null() {
+            (xkb.xkb_keymap_unref)(keymap);
+            (xkb.xkb_context_unref)(context);
+            return None;
+        }
+        Some(Keymap {
+            context: context,
+            keymap: keymap,
+            state: state,
+            mod_indices: ModIndices::from_keymap(keymap),
+        })
+    }
+
+    /// Returns the names of the layouts of the keymap.
+    pub fn layout_names(&self) -> Vec<String> {
+        unsafe { layout_names(self.keymap) }
+    }
+
+    /// Returns the modifiers active in a mask of the compositor.
+    pub fn mask_to_modifiers(&self, mask: xkb_mod_mask_t) -> ModifiersState {
+        self.mod_indices.to_modifiers(mask)
+    }
+
+    /// Updates the state from the masks of the compositor.
+    pub fn update_mask(&mut self, depressed: u32, latched: u32, locked: u32, group: u32) {
+        let xkb = XKBCOMMON.as_ref().unwrap();
+        unsafe { (xkb.xkb_state_update_mask)(self.state, depressed, latched, locked, 0, 0, group) };
+    }
+
+    /// Returns the keysym of a key under the current layout and modifiers.
+    pub fn keysym(&self, keycode: xkb_keycode_t) -> xkb_keysym_t {
+        let xkb = XKBCOMMON.as_ref().unwrap();
+        unsafe { (xkb.xkb_state_key_get_one_sym)(self.state, keycode) }
+    }
+}
+
+unsafe impl Send for Keymap {}
+
+impl Drop for Keymap {
+    fn drop(&mut self) {
+        let xkb = XKBCOMMON.as_ref().unwrap();
+        unsafe {
+            (xkb.xkb_state_unref)(self.state);
+            (xkb.xkb_keymap_unref)(self.keymap);
+            (xkb.xkb_context_unref)(self.context);
+        }
+    }
+}
+
+/// Finds the layouts in the `xkb_symbols` section of a keymap, whose name lists what it includes,
+/// like `"pc+us+de(nodeadkeys):2+inet(evdev)"`.
+fn symbols_layouts(keymap: &str) -> Vec<String> {
+    // the symbols that are not layouts, but options and models
+    const NOT_LAYOUTS: &'static [&'static str] = &[
+        "pc", "inet", "group", "level3", "level5", "ctrl", "compose", "terminate", "keypad",
+        "kpdl", "altwin", "capslock", "shift", "lv3", "lv5", "eurosign", "rupeesign", "nbsp",
+        "japan", "korean", "srvr_ctrl", "caps", "numpad", "parens", "grp", "apple", "macintosh",
+    ];
+
+    let section = match keymap.find("xkb_symbols") {
+        Some(start) => &keymap[start..],
+        None => return Vec::new(),
+    };
+    let name = match section.split('"').nth(1) {
+        Some(name) => name,
+        None => return Vec::new(),
+    };
+
+    let mut layouts: Vec<(usize, String)> = Vec::new();
+    for part in name.split('+') {
+        // the first layout has no group index, the following ones have theirs after a colon
+        let (symbols, group) = match part.find(':') {
+            Some(colon) => match part[colon + 1..].parse::<usize>() {
+                Ok(group) => (&part[..colon], group),
+                Err(_) => continue,
+            },
+            None => (part, 1),
+        };
+        let base = symbols.split('(').next().unwrap_or(symbols);
+        if NOT_LAYOUTS.contains(&base) || layouts.iter().any(|&(g, _)| g == group) {
+            continue;
+        }
+        layouts.push((group, symbols.to_owned()));
+    }
+
+    layouts.sort_by_key(|&(group, _)| group);
+    // the groups must follow each other from the first one
+    if layouts.iter().enumerate().any(|(i, &(group, _))| group != i + 1) {
+        return Vec::new();
+    }
+    layouts.into_iter().map(|(_, name)| name).collect()
+}
+
+/// What a key press did to the current compose sequence.
+pub enum ComposeResult {
+    /// The key is not part of a sequence, it types its own text.
+    Nothing,
+    /// The key started or continued a sequence, and types nothing for now.
+    Composing,
+    /// The key finished a sequence, which types this text.
+    Composed(String),
+    /// The key doesn't continue the sequence, which is dropped with the key.
+    Cancelled,
+}
+
+/// The compose sequences of the locale of the user, which also implement the dead keys.
+pub struct Compose {
+    context: *mut xkb_context,
+    table: *mut xkb_compose_table,
+    state: *mut xkb_compose_state,
+}
+
+impl Compose {
+    /// Loads the compose table of the locale, as configured by `LC_ALL`, `LC_CTYPE` and `LANG`.
+    pub fn new() -> Option<Compose> {
+        let xkb = match *XKBCOMMON {
+            Some(ref xkb) => xkb,
+            None => return None,
+        };
+        let locale = env::var("LC_ALL").ok().into_iter()
+            .chain(env::var("LC_CTYPE").ok())
+            .chain(env::var("LANG").ok())
+            .find(|locale| !locale.is_empty())
+            .unwrap_or("C".to_owned());
+        let locale = match CString::new(locale) {
+            Ok(locale) => locale,
+            Err(_) => return None,
+        };
+
+        unsafe {
+            let context = (xkb.xkb_context_new)(XKB_CONTEXT_NO_FLAGS);
+            if context.is_null() {
+                return None;
+            }
+            let table = (xkb.xkb_compose_table_new_from_locale)(context, locale.as_ptr(),
+                                                                XKB_COMPOSE_COMPILE_NO_FLAGS);
+            if table.is_null() {
+                (xkb.xkb_context_unref)(context);
+                return None;
+            }
+            let state = (xkb.xkb_compose_state_new)(table, XKB_COMPOSE_STATE_NO_FLAGS);
+            if state.is_null() {
+                (xkb.xkb_compose_table_unref)(table);
+                (xkb.xkb_context_unref)(context);
+                return None;
+            }
+            Some(Compose { context: context, table: table, state: state })
+        }
+    }
+
+    /// Feeds the keysym of a pressed key to the current sequence.
+    pub fn feed(&mut self, keysym: xkb_keysym_t) -> ComposeResult {
+        let xkb = XKBCOMMON.as_ref().unwrap();
+        unsafe {
+            if (xkb.xkb_compose_state_feed)(self.state, keysym) == XKB_COMPOSE_FEED_IGNORED {
+                // the modifiers, which don't interrupt the sequences
+                return ComposeResult::Nothing;
+            }
+            match (xkb.xkb_compose_state_get_status)(self.state) {
+                XKB_COMPOSE_COMPOSING => ComposeResult::Composing,
+                XKB_COMPOSE_COMPOSED => {
+                    let state = self.state;
+                    let text = get_utf8(|buffer, size| {
+                        (xkb.xkb_compose_state_get_utf8)(state, buffer, size)
+                    });
+                    (xkb.xkb_compose_state_reset)(self.state);
+                    ComposeResult::Composed(text)
+                },
+                XKB_COMPOSE_CANCELLED => {
+                    (xkb.xkb_compose_state_reset)(self.state);
+                    ComposeResult::Cancelled
+                },
+                _ => ComposeResult::Nothing,
+            }
+        }
+    }
+
+    /// Whether a sequence has started.
+    pub fn is_composing(&self) -> bool {
+        let xkb = XKBCOMMON.as_ref().unwrap();
+        unsafe { (xkb.xkb_compose_state_get_status)(self.state) == XKB_COMPOSE_COMPOSING }
+    }
+
+    /// Drops the current sequence, for example when the window loses the focus.
+    pub fn reset(&mut self) {
+        let xkb = XKBCOMMON.as_ref().unwrap();
+        unsafe { (xkb.xkb_compose_state_reset)(self.state) };
+    }
+}
+
+unsafe impl Send for Compose {}
+
+impl Drop for Compose {
+    fn drop(&mut self) {
+        let xkb = XKBCOMMON.as_ref().unwrap();
+        unsafe {
+            (xkb.xkb_compose_state_unref)(self.state);
+            (xkb.xkb_compose_table_unref)(self.table);
+            (xkb.xkb_context_unref)(self.context);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::symbols_layouts;
+
+    fn layouts(symbols: &str) -> Vec<String> {
+        symbols_layouts(&format!("xkb_keymap {{\n\txkb_keycodes \"evdev+aliases(qwerty)\" {{ }};\n\
+                                  \txkb_symbols \"{}\" {{ }};\n}};", symbols))
+    }
+
+    #[test]
+    fn layouts_in_group_order() {
+        assert_eq!(layouts("pc+us+de:2+inet(evdev)"), vec!["us", "de"]);
+        assert_eq!(layouts("pc+fr(bepo)+us:3+ru:2+inet(evdev)"), vec!["fr(bepo)", "ru", "us"]);
+    }
+
+    #[test]
+    fn first_layout_of_each_group() {
+        assert_eq!(layouts("pc+us+us(intl):1+de:2"), vec!["us", "de"]);
+    }
+
+    #[test]
+    fn missing_groups() {
+        assert_eq!(layouts("pc+us+de:3+inet(evdev)"), Vec::<String>::new());
+        assert_eq!(layouts("pc+us+de:x"), vec!["us"]);
+    }
+
+    #[test]
+    fn options_are_not_layouts() {
+        assert_eq!(layouts("pc+us+inet(evdev)+group(alt_shift_toggle)+level3(ralt_switch)+ctrl(nocaps)"),
+                   vec!["us"]);
+        assert_eq!(layouts("pc+inet(evdev)+compose(ralt)"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn no_symbols_section() {
+        assert_eq!(symbols_layouts("xkb_keymap { };"), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,114 @@
+use std;
+use std::ffi::CStr;
+use std::os::raw::c_void;
+
+use objc::runtime::{Class, BOOL, NO};
+
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSInteger, NSString, NSUInteger};
+
+use {ClipboardContents, ClipboardKind, TEXT_MIME_TYPE, TEXT_UTF8_MIME_TYPE};
+
+use super::window::IdRef;
+
+pub fn set_clipboard(kind: ClipboardKind, contents: ClipboardContents) -> Result<(), String> {
+    if kind == ClipboardKind::Primary {
+        return Err("The primary selection doesn't exist on macOS".to_string());
+    }
+
+    unsafe {
+        let pasteboard: id = msg_send![Class::get("NSPasteboard").unwrap(), generalPasteboard];
+        let _: NSInteger = msg_send![pasteboard, clearContents];
+
+        let mut types = Vec::new();
+        for mime_type in contents.mime_types() {
+            // `text/plain` and `text/plain;charset=utf-8` share the same type
+            let pasteboard_type = mime_pasteboard_type(mime_type);
+            if types.contains(&pasteboard_type) {
+                continue;
+            }
+            types.push(pasteboard_type);
+
+            let data = contents.get(mime_type).unwrap();
+            let nsdata: id = msg_send![Class::get("NSData").unwrap(),
+                                       dataWithBytes:data.as_ptr() as *const c_void
+                                       length:data.len() as NSUInteger];
+            let pasteboard_type = IdRef::new(NSString::alloc(nil).init_str(pasteboard_type));
+            let success: BOOL = msg_send![pasteboard, setData:nsdata forType:*pasteboard_type];
+            if success == NO {
+                return Err(format!("Failed to put {} data in the pasteboard", mime_type));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn clipboard_mime_types(kind: ClipboardKind) -> Vec<String> {
+    if kind == ClipboardKind::Primary {
+        return Vec::new();
+    }
+
+    let mut mime_types: Vec<String> = Vec::new();
+    unsafe {
+        let pasteboard: id = msg_send![Class::get("NSPasteboard").unwrap(), generalPasteboard];
+        let types: id = msg_send![pasteboard, types];
+        if types == nil {
+            return mime_types;
+        }
+        let count: NSUInteger = msg_send![types, count];
+        for i in 0..count {
+            let pasteboard_type: id = msg_send![types, objectAtIndex:i];
+            let pasteboard_type = CStr::from_ptr(NSString::UTF8String(pasteboard_type));
+            for mime in pasteboard_type_mime_types(&pasteboard_type.to_string_lossy()) {
+                if !mime_types.contains(&mime) {
+                    mime_types.push(mime);
+                }
+            }
+        }
+    }
+    mime_types
+}
+
+pub fn get_clipboard(kind: ClipboardKind, mime_type: &str) -> Option<Vec<u8>> {
+    if kind == ClipboardKind::Primary {
+        return None;
+    }
+
+    unsafe {
+        let pasteboard: id = msg_send![Class::get("NSPasteboard").unwrap(), generalPasteboard];
+        let pasteboard_type = IdRef::new(NSString::alloc(nil).init_str(mime_pasteboard_type(mime_type)));
+        let nsdata: id = msg_send![pasteboard, dataForType:*pasteboard_type];
+        if nsdata == nil {
+            return None;
+        }
+        let bytes: *const u8 = msg_send![nsdata, bytes];
+        let length: NSUInteger = msg_send![nsdata, length];
+        if length == 0 {
+            return Some(Vec::new());
+        }
+        Some(std::slice::from_raw_parts(bytes, length as usize).to_vec())
+    }
+}
+
+/// Returns the pasteboard type in which data of a MIME type is stored. MIME types without a
+/// corresponding uniform type identifier are used as pasteboard types directly.
+fn mime_pasteboard_type(mime_type: &str) -> &str {
+    match mime_type {
+        TEXT_UTF8_MIME_TYPE | TEXT_MIME_TYPE => "public.utf8-plain-text",
+        "text/html" => "public.html",
+        "image/png" => "public.png",
+        mime_type => mime_type,
+    }
+}
+
+/// Returns the MIME types of the data stored in a pasteboard type.
+fn pasteboard_type_mime_types(pasteboard_type: &str) -> Vec<String> {
+    match pasteboard_type {
+        "public.utf8-plain-text" => vec![TEXT_UTF8_MIME_TYPE.to_owned(), TEXT_MIME_TYPE.to_owned()],
+        "public.html" => vec!["text/html".to_owned()],
+        "public.png" => vec!["image/png".to_owned()],
+        // other uniform type identifiers don't have a MIME type we know of
+        pasteboard_type if pasteboard_type.contains('/') => vec![pasteboard_type.to_owned()],
+        _ => Vec::new(),
+    }
+}
@@ -1,6 +1,8 @@
 use cocoa::{self, appkit, foundation};
 use cocoa::appkit::{NSApplication, NSEvent, NSView, NSWindow};
-use events::{self, ElementState, Event, MouseButton, TouchPhase, WindowEvent, ModifiersState};
+use events::{self, ElementState, Event, KeyboardInput, KeyRepeatInfo, MouseButton, TouchPhase, WindowEvent,
+             ModifiersState};
+use super::clipboard;
 use super::window::Window;
 use std;
 
@@ -194,6 +196,40 @@ impl EventsLoop {
         self.user_callback.drop();
     }
 
+    pub fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+        unsafe {
+            // both in seconds
+            let delay: f64 = msg_send![cocoa::base::class("NSEvent"), keyRepeatDelay];
+            let interval: f64 = msg_send![cocoa::base::class("NSEvent"), keyRepeatInterval];
+            if interval <= 0.0 {
+                return None;
+            }
+            Some(KeyRepeatInfo {
+                delay: std::time::Duration::from_millis((delay * 1000.0) as u64),
+                rate: (1.0 / interval).round() as u32,
+            })
+        }
+    }
+
+    pub fn keyboard_layouts(&self) -> Option<events::KeyboardLayouts> {
+        None
+    }
+
+    #[inline]
+    pub fn set_clipboard(&self, kind: ::ClipboardKind, contents: ::ClipboardContents) -> Result<(), String> {
+        clipboard::set_clipboard(kind, contents)
+    }
+
+    #[inline]
+    pub fn clipboard_mime_types(&self, kind: ::ClipboardKind) -> Vec<String> {
+        clipboard::clipboard_mime_types(kind)
+    }
+
+    #[inline]
+    pub fn get_clipboard(&self, kind: ::ClipboardKind, mime_type: &str) -> Option<Vec<u8>> {
+        clipboard::get_clipboard(kind, mime_type)
+    }
+
     pub fn interrupt(&self) {
         self.interrupted.store(true, std::sync::atomic::Ordering::Relaxed);
 
@@ -314,9 +350,17 @@ impl EventsLoop {
                 }
 
                 let vkey =  to_virtual_key_code(NSEvent::keyCode(ns_event));
-                let state = ElementState::Pressed;
-                let code = NSEvent::keyCode(ns_event) as u8;
-                let window_event = WindowEvent::KeyboardInput(state, code, vkey, event_mods(ns_event));
+                let is_repeat: cocoa::base::BOOL = msg_send![ns_event, isARepeat];
+                let window_event = WindowEvent::KeyboardInput(KeyboardInput {
+                    state: ElementState::Pressed,
+                    scancode: NSEvent::keyCode(ns_event) as u32,
+                    physical_key: to_physical_key(NSEvent::keyCode(ns_event)),
+                    virtual_keycode: vkey,
+                    logical_key: None,
+                    modifiers: event_mods(ns_event),
+                    is_repeat: is_repeat == cocoa::base::YES,
+                    is_synthetic: false,
+                });
                 events.push_back(into_event(window_event));
                 let event = events.pop_front();
                 self.pending_events.lock().unwrap().extend(events.into_iter());
@@ -326,9 +370,16 @@ impl EventsLoop {
             appkit::NSKeyUp => {
                 let vkey =  to_virtual_key_code(NSEvent::keyCode(ns_event));
 
-                let state = ElementState::Released;
-                let code = NSEvent::keyCode(ns_event) as u8;
-                let window_event = WindowEvent::KeyboardInput(state, code, vkey, event_mods(ns_event));
+                let window_event = WindowEvent::KeyboardInput(KeyboardInput {
+                    state: ElementState::Released,
+                    scancode: NSEvent::keyCode(ns_event) as u32,
+                    physical_key: to_physical_key(NSEvent::keyCode(ns_event)),
+                    virtual_keycode: vkey,
+                    logical_key: None,
+                    modifiers: event_mods(ns_event),
+                    is_repeat: false,
+                    is_synthetic: false,
+                });
                 Some(into_event(window_event))
             },
 
@@ -341,15 +392,29 @@ impl EventsLoop {
                                          key_pressed: bool) -> Option<WindowEvent>
                 {
                     if !key_pressed && NSEvent::modifierFlags(event).contains(keymask) {
-                        let state = ElementState::Pressed;
-                        let code = NSEvent::keyCode(event) as u8;
-                        let window_event = WindowEvent::KeyboardInput(state, code, Some(key), event_mods(event));
+                        let window_event = WindowEvent::KeyboardInput(KeyboardInput {
+                            state: ElementState::Pressed,
+                            scancode: NSEvent::keyCode(event) as u32,
+                            physical_key: to_physical_key(NSEvent::keyCode(event)),
+                            virtual_keycode: Some(key),
+                            logical_key: None,
+                            modifiers: event_mods(event),
+                            is_repeat: false,
+                            is_synthetic: false,
+                        });
                         Some(window_event)
 
                     } else if key_pressed && !NSEvent::modifierFlags(event).contains(keymask) {
-                        let state = ElementState::Released;
-                        let code = NSEvent::keyCode(event) as u8;
-                        let window_event = WindowEvent::KeyboardInput(state, code, Some(key), event_mods(event));
+                        let window_event = WindowEvent::KeyboardInput(KeyboardInput {
+                            state: ElementState::Released,
+                            scancode: NSEvent::keyCode(event) as u32,
+                            physical_key: to_physical_key(NSEvent::keyCode(event)),
+                            virtual_keycode: Some(key),
+                            logical_key: None,
+                            modifiers: event_mods(event),
+                            is_repeat: false,
+                            is_synthetic: false,
+                        });
                         Some(window_event)
 
                     } else {
@@ -394,17 +459,20 @@ impl EventsLoop {
                     events.push_back(into_event(window_event));
                 }
 
+                // also sent when caps lock is toggled, which presses no other modifier
+                events.push_back(into_event(WindowEvent::ModifiersChanged(event_mods(ns_event))));
+
                 let event = events.pop_front();
                 self.pending_events.lock().unwrap().extend(events.into_iter());
                 event
             },
 
-            appkit::NSLeftMouseDown => { Some(into_event(WindowEvent::MouseInput(ElementState::Pressed, MouseButton::Left))) },
-            appkit::NSLeftMouseUp => { Some(into_event(WindowEvent::MouseInput(ElementState::Released, MouseButton::Left))) },
-            appkit::NSRightMouseDown => { Some(into_event(WindowEvent::MouseInput(ElementState::Pressed, MouseButton::Right))) },
-            appkit::NSRightMouseUp => { Some(into_event(WindowEvent::MouseInput(ElementState::Released, MouseButton::Right))) },
-            appkit::NSOtherMouseDown => { Some(into_event(WindowEvent::MouseInput(ElementState::Pressed, MouseButton::Middle))) },
-            appkit::NSOtherMouseUp => { Some(into_event(WindowEvent::MouseInput(ElementState::Released, MouseButton::Middle))) },
+            appkit::NSLeftMouseDown => { Some(into_event(mouse_input(ns_event, ElementState::Pressed, MouseButton::Left))) },
+            appkit::NSLeftMouseUp => { Some(into_event(mouse_input(ns_event, ElementState::Released, MouseButton::Left))) },
+            appkit::NSRightMouseDown => { Some(into_event(mouse_input(ns_event, ElementState::Pressed, MouseButton::Right))) },
+            appkit::NSRightMouseUp => { Some(into_event(mouse_input(ns_event, ElementState::Released, MouseButton::Right))) },
+            appkit::NSOtherMouseDown => { Some(into_event(mouse_input(ns_event, ElementState::Pressed, other_button(ns_event)))) },
+            appkit::NSOtherMouseUp => { Some(into_event(mouse_input(ns_event, ElementState::Released, other_button(ns_event)))) },
 
             appkit::NSMouseEntered => { Some(into_event(WindowEvent::MouseEntered)) },
             appkit::NSMouseExited => { Some(into_event(WindowEvent::MouseLeft)) },
@@ -461,7 +529,7 @@ impl EventsLoop {
                     appkit::NSEventPhaseEnded => TouchPhase::Ended,
                     _ => TouchPhase::Moved,
                 };
-                let window_event = WindowEvent::MouseWheel(delta, phase);
+                let window_event = WindowEvent::MouseWheel(delta, phase, event_mods(ns_event));
                 Some(into_event(window_event))
             },
 
@@ -621,14 +689,202 @@ fn to_virtual_key_code(code: u16) -> Option<events::VirtualKeyCode> {
     })
 }
 
+fn to_physical_key(code: u16) -> Option<events::PhysicalKey> {
+    Some(match code {
+        0x00 => events::PhysicalKey::KeyA,
+        0x01 => events::PhysicalKey::KeyS,
+        0x02 => events::PhysicalKey::KeyD,
+        0x03 => events::PhysicalKey::KeyF,
+        0x04 => events::PhysicalKey::KeyH,
+        0x05 => events::PhysicalKey::KeyG,
+        0x06 => events::PhysicalKey::KeyZ,
+        0x07 => events::PhysicalKey::KeyX,
+        0x08 => events::PhysicalKey::KeyC,
+        0x09 => events::PhysicalKey::KeyV,
+        0x0a => events::PhysicalKey::IntlBackslash,
+        0x0b => events::PhysicalKey::KeyB,
+        0x0c => events::PhysicalKey::KeyQ,
+        0x0d => events::PhysicalKey::KeyW,
+        0x0e => events::PhysicalKey::KeyE,
+        0x0f => events::PhysicalKey::KeyR,
+        0x10 => events::PhysicalKey::KeyY,
+        0x11 => events::PhysicalKey::KeyT,
+        0x12 => events::PhysicalKey::Digit1,
+        0x13 => events::PhysicalKey::Digit2,
+        0x14 => events::PhysicalKey::Digit3,
+        0x15 => events::PhysicalKey::Digit4,
+        0x16 => events::PhysicalKey::Digit6,
+        0x17 => events::PhysicalKey::Digit5,
+        0x18 => events::PhysicalKey::Equal,
+        0x19 => events::PhysicalKey::Digit9,
+        0x1a => events::PhysicalKey::Digit7,
+        0x1b => events::PhysicalKey::Minus,
+        0x1c => events::PhysicalKey::Digit8,
+        0x1d => events::PhysicalKey::Digit0,
+        0x1e => events::PhysicalKey::BracketRight,
+        0x1f => events::PhysicalKey::KeyO,
+        0x20 => events::PhysicalKey::KeyU,
+        0x21 => events::PhysicalKey::BracketLeft,
+        0x22 => events::PhysicalKey::KeyI,
+        0x23 => events::PhysicalKey::KeyP,
+        0x24 => events::PhysicalKey::Enter,
+        0x25 => events::PhysicalKey::KeyL,
+        0x26 => events::PhysicalKey::KeyJ,
+        0x27 => events::PhysicalKey::Quote,
+        0x28 => events::PhysicalKey::KeyK,
+        0x29 => events::PhysicalKey::Semicolon,
+        0x2a => events::PhysicalKey::Backslash,
+        0x2b => events::PhysicalKey::Comma,
+        0x2c => events::PhysicalKey::Slash,
+        0x2d => events::PhysicalKey::KeyN,
+        0x2e => events::PhysicalKey::KeyM,
+        0x2f => events::PhysicalKey::Period,
+        0x30 => events::PhysicalKey::Tab,
+        0x31 => events::PhysicalKey::Space,
+        0x32 => events::PhysicalKey::Backquote,
+        0x33 => events::PhysicalKey::Backspace,
+        0x35 => events::PhysicalKey::Escape,
+        0x36 => events::PhysicalKey::SuperRight,
+        0x37 => events::PhysicalKey::SuperLeft,
+        0x38 => events::PhysicalKey::ShiftLeft,
+        0x39 => events::PhysicalKey::CapsLock,
+        0x3a => events::PhysicalKey::AltLeft,
+        0x3b => events::PhysicalKey::ControlLeft,
+        0x3c => events::PhysicalKey::ShiftRight,
+        0x3d => events::PhysicalKey::AltRight,
+        0x3e => events::PhysicalKey::ControlRight,
+        0x40 => events::PhysicalKey::F17,
+        0x41 => events::PhysicalKey::NumpadDecimal,
+        0x43 => events::PhysicalKey::NumpadMultiply,
+        0x45 => events::PhysicalKey::NumpadAdd,
+        // the clear key of the Apple keypads
+        0x47 => events::PhysicalKey::NumLock,
+        0x48 => events::PhysicalKey::AudioVolumeUp,
+        0x49 => events::PhysicalKey::AudioVolumeDown,
+        0x4a => events::PhysicalKey::AudioVolumeMute,
+        0x4b => events::PhysicalKey::NumpadDivide,
+        0x4c => events::PhysicalKey::NumpadEnter,
+        0x4e => events::PhysicalKey::NumpadSubtract,
+        0x4f => events::PhysicalKey::F18,
+        0x50 => events::PhysicalKey::F19,
+        0x51 => events::PhysicalKey::NumpadEqual,
+        0x52 => events::PhysicalKey::Numpad0,
+        0x53 => events::PhysicalKey::Numpad1,
+        0x54 => events::PhysicalKey::Numpad2,
+        0x55 => events::PhysicalKey::Numpad3,
+        0x56 => events::PhysicalKey::Numpad4,
+        0x57 => events::PhysicalKey::Numpad5,
+        0x58 => events::PhysicalKey::Numpad6,
+        0x59 => events::PhysicalKey::Numpad7,
+        0x5a => events::PhysicalKey::F20,
+        0x5b => events::PhysicalKey::Numpad8,
+        0x5c => events::PhysicalKey::Numpad9,
+        0x5d => events::PhysicalKey::IntlYen,
+        0x5e => events::PhysicalKey::IntlRo,
+        0x5f => events::PhysicalKey::NumpadComma,
+        0x60 => events::PhysicalKey::F5,
+        0x61 => events::PhysicalKey::F6,
+        0x62 => events::PhysicalKey::F7,
+        0x63 => events::PhysicalKey::F3,
+        0x64 => events::PhysicalKey::F8,
+        0x65 => events::PhysicalKey::F9,
+        0x66 => events::PhysicalKey::Lang2,
+        0x67 => events::PhysicalKey::F11,
+        0x68 => events::PhysicalKey::Lang1,
+        0x69 => events::PhysicalKey::F13,
+        0x6a => events::PhysicalKey::F16,
+        0x6b => events::PhysicalKey::F14,
+        0x6d => events::PhysicalKey::F10,
+        0x6e => events::PhysicalKey::ContextMenu,
+        0x6f => events::PhysicalKey::F12,
+        0x71 => events::PhysicalKey::F15,
+        // the help key of the older Apple keyboards
+        0x72 => events::PhysicalKey::Insert,
+        0x73 => events::PhysicalKey::Home,
+        0x74 => events::PhysicalKey::PageUp,
+        0x75 => events::PhysicalKey::Delete,
+        0x76 => events::PhysicalKey::F4,
+        0x77 => events::PhysicalKey::End,
+        0x78 => events::PhysicalKey::F2,
+        0x79 => events::PhysicalKey::PageDown,
+        0x7a => events::PhysicalKey::F1,
+        0x7b => events::PhysicalKey::ArrowLeft,
+        0x7c => events::PhysicalKey::ArrowRight,
+        0x7d => events::PhysicalKey::ArrowDown,
+        0x7e => events::PhysicalKey::ArrowUp,
+        _ => return None,
+    })
+}
+
+fn mouse_input(event: cocoa::base::id, state: ElementState, button: MouseButton) -> WindowEvent {
+    WindowEvent::MouseInput(events::MouseInput {
+        state: state,
+        button: button,
+        modifiers: event_mods(event),
+        is_synthetic: false,
+    })
+}
+
+/// Returns the button of an `NSOtherMouseDown` or `NSOtherMouseUp` event.
+fn other_button(event: cocoa::base::id) -> MouseButton {
+    let number: foundation::NSInteger = unsafe { msg_send![event, buttonNumber] };
+    button_number_to_mouse_button(number as usize)
+}
+
+/// Returns the button with a number of AppKit, where 0 and 1 are the left and right buttons.
+pub fn button_number_to_mouse_button(number: usize) -> MouseButton {
+    match number {
+        0 => MouseButton::Left,
+        1 => MouseButton::Right,
+        2 => MouseButton::Middle,
+        3 => MouseButton::Back,
+        4 => MouseButton::Forward,
+        // the sixth button of AppKit is the tenth button of X11
+        other => MouseButton::Other(other as u16 + 5),
+    }
+}
+
 fn event_mods(event: cocoa::base::id) -> ModifiersState {
-    let flags = unsafe {
-        NSEvent::modifierFlags(event)
+    // the raw flags, as the device-dependent ones telling the sides are not part of the bitflags
+    let flags: foundation::NSUInteger = unsafe { msg_send![event, modifierFlags] };
+    flags_to_mods(flags)
+}
+
+/// Returns the modifiers currently held, for when the window becomes the key window.
+pub fn current_mods() -> ModifiersState {
+    let flags: foundation::NSUInteger = unsafe {
+        msg_send![cocoa::base::class("NSEvent"), modifierFlags]
     };
+    flags_to_mods(flags)
+}
+
+fn flags_to_mods(flags: foundation::NSUInteger) -> ModifiersState {
+    // NX_DEVICE*KEYMASK from IOKit/hidsystem/IOLLEvent.h
+    const NX_DEVICELCTLKEYMASK: foundation::NSUInteger = 0x1;
+    const NX_DEVICELSHIFTKEYMASK: foundation::NSUInteger = 0x2;
+    const NX_DEVICERSHIFTKEYMASK: foundation::NSUInteger = 0x4;
+    const NX_DEVICELCMDKEYMASK: foundation::NSUInteger = 0x8;
+    const NX_DEVICERCMDKEYMASK: foundation::NSUInteger = 0x10;
+    const NX_DEVICELALTKEYMASK: foundation::NSUInteger = 0x20;
+    const NX_DEVICERALTKEYMASK: foundation::NSUInteger = 0x40;
+    const NX_DEVICERCTLKEYMASK: foundation::NSUInteger = 0x2000;
+
+    let has = |mask: foundation::NSUInteger| flags & mask != 0;
     ModifiersState {
-        shift: flags.contains(appkit::NSShiftKeyMask),
-        ctrl: flags.contains(appkit::NSControlKeyMask),
-        alt: flags.contains(appkit::NSAlternateKeyMask),
-        logo: flags.contains(appkit::NSCommandKeyMask),
+        shift: has(appkit::NSShiftKeyMask.bits()),
+        ctrl: has(appkit::NSControlKeyMask.bits()),
+        alt: has(appkit::NSAlternateKeyMask.bits()),
+        logo: has(appkit::NSCommandKeyMask.bits()),
+        alt_graph: false,
+        caps_lock: has(appkit::NSAlphaShiftKeyMask.bits()),
+        num_lock: false,
+        left_shift: has(NX_DEVICELSHIFTKEYMASK),
+        right_shift: has(NX_DEVICERSHIFTKEYMASK),
+        left_ctrl: has(NX_DEVICELCTLKEYMASK),
+        right_ctrl: has(NX_DEVICERCTLKEYMASK),
+        left_alt: has(NX_DEVICELALTKEYMASK),
+        right_alt: has(NX_DEVICERALTKEYMASK),
+        left_logo: has(NX_DEVICELCMDKEYMASK),
+        right_logo: has(NX_DEVICERCMDKEYMASK),
     }
 }
@@ -33,6 +33,7 @@ impl Window2 {
 
 }
 
+mod clipboard;
 mod events_loop;
 mod monitor;
 mod window;
@@ -1,4 +1,5 @@
-use {CreationError, Event, WindowEvent, WindowId, MouseCursor, CursorState};
+use {CreationError, Event, WindowEvent, WindowId, MouseButton, MouseCursor, CursorState, CustomCursor};
+use {ClipboardContents, ClipboardKind};
 use CreationError::OsError;
 use libc;
 
@@ -17,12 +18,29 @@ use cocoa::appkit::{self, NSApplication, NSColor, NSView, NSWindow};
 
 use core_graphics::display::{CGAssociateMouseAndMouseCursorPosition, CGMainDisplayID, CGDisplayPixelsHigh, CGWarpMouseCursorPosition};
 
+// the state of the keys combined from all the keyboards, from CGEventTypes.h
+const K_CG_EVENT_SOURCE_STATE_COMBINED_SESSION_STATE: i32 = 0;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGEventSourceKeyState(state_id: i32, key: u16) -> bool;
+}
+
 use std;
 use std::ops::Deref;
 use std::os::raw::c_void;
+use std::ptr;
+use std::slice;
+use std::sync::Mutex;
 
 use os::macos::WindowExt;
 
+use super::clipboard;
+
+
+// Values of the `NSNormalWindowLevel` and `NSFloatingWindowLevel` constants.
+const NS_NORMAL_WINDOW_LEVEL: libc::c_long = 0;
+const NS_FLOATING_WINDOW_LEVEL: libc::c_long = 3;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Id(pub usize);
@@ -103,6 +121,7 @@ impl WindowDelegate {
                 let state: *mut c_void = *this.get_ivar("winitState");
                 let state = &mut *(state as *mut DelegateState);
                 emit_event(state, WindowEvent::Focused(true));
+                emit_event(state, WindowEvent::ModifiersChanged(super::events_loop::current_mods()));
             }
         }
 
@@ -179,6 +198,8 @@ pub struct Window {
     pub view: IdRef,
     pub window: IdRef,
     pub delegate: WindowDelegate,
+    // the NSCursor created by `set_custom_cursor`, kept alive while it may be displayed
+    custom_cursor: Mutex<Option<IdRef>>,
 }
 
 unsafe impl Send for Window {}
@@ -261,6 +282,16 @@ impl Window {
             if let Some((width, height)) = win_attribs.max_dimensions {
                 nswindow_set_max_dimensions(window.0, width.into(), height.into());
             }
+
+            if win_attribs.always_on_top {
+                let _: () = msg_send![*window, setLevel:NS_FLOATING_WINDOW_LEVEL];
+            } else if win_attribs.always_on_bottom {
+                let _: () = msg_send![*window, setLevel:NS_NORMAL_WINDOW_LEVEL - 1];
+            }
+
+            if win_attribs.maximized {
+                let _: () = msg_send![*window, zoom:nil];
+            }
         }
 
         let ds = DelegateState {
@@ -273,6 +304,7 @@ impl Window {
             view: view,
             window: window,
             delegate: WindowDelegate::new(ds),
+            custom_cursor: Mutex::new(None),
         };
 
         Ok(window)
@@ -339,18 +371,26 @@ impl Window {
                 appkit::NSBorderlessWindowMask as NSUInteger |
                 appkit::NSResizableWindowMask as NSUInteger |
                 appkit::NSTitledWindowMask as NSUInteger
-            } else if attrs.decorations {
-                // Window with a titlebar
-                appkit::NSClosableWindowMask as NSUInteger |
-                appkit::NSMiniaturizableWindowMask as NSUInteger |
-                appkit::NSResizableWindowMask as NSUInteger |
-                appkit::NSTitledWindowMask as NSUInteger
             } else {
-                // Window without a titlebar
-                appkit::NSClosableWindowMask as NSUInteger |
-                appkit::NSMiniaturizableWindowMask as NSUInteger |
-                appkit::NSResizableWindowMask as NSUInteger |
-                appkit::NSFullSizeContentViewWindowMask as NSUInteger
+                let masks = if attrs.decorations {
+                    // Window with a titlebar
+                    appkit::NSClosableWindowMask as NSUInteger |
+                    appkit::NSMiniaturizableWindowMask as NSUInteger |
+                    appkit::NSResizableWindowMask as NSUInteger |
+                    appkit::NSTitledWindowMask as NSUInteger
+                } else {
+                    // Window without a titlebar
+                    appkit::NSClosableWindowMask as NSUInteger |
+                    appkit::NSMiniaturizableWindowMask as NSUInteger |
+                    appkit::NSResizableWindowMask as NSUInteger |
+                    appkit::NSFullSizeContentViewWindowMask as NSUInteger
+                };
+
+                if attrs.resizable {
+                    masks
+                } else {
+                    masks & !(appkit::NSResizableWindowMask as NSUInteger)
+                }
             };
 
             let window = IdRef::new(NSWindow::alloc(nil).initWithContentRect_styleMask_backing_defer_(
@@ -462,6 +502,73 @@ impl Window {
         }
     }
 
+    pub fn set_resizable(&self, resizable: bool) {
+        unsafe {
+            let mut mask: NSUInteger = msg_send![*self.window, styleMask];
+            if resizable {
+                mask |= appkit::NSResizableWindowMask as NSUInteger;
+            } else {
+                mask &= !(appkit::NSResizableWindowMask as NSUInteger);
+            }
+            let _: () = msg_send![*self.window, setStyleMask:mask];
+        }
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        let (width, height) = dimensions.unwrap_or((0, 0));
+        unsafe { nswindow_set_min_dimensions(*self.window, width.into(), height.into()); }
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        let (width, height) = dimensions
+            .map(|(width, height)| (width.into(), height.into()))
+            .unwrap_or((std::f32::MAX as f64, std::f32::MAX as f64));
+        unsafe { nswindow_set_max_dimensions(*self.window, width, height); }
+    }
+
+    pub fn set_maximized(&self, maximized: bool) {
+        // `zoom:` toggles between the user state and the standard (maximized) state
+        if self.is_maximized() != maximized {
+            unsafe { let _: () = msg_send![*self.window, zoom:nil]; }
+        }
+    }
+
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        let zoomed: BOOL = unsafe { msg_send![*self.window, isZoomed] };
+        zoomed == YES
+    }
+
+    pub fn set_minimized(&self, minimized: bool) {
+        unsafe {
+            if minimized {
+                let _: () = msg_send![*self.window, miniaturize:nil];
+            } else {
+                let _: () = msg_send![*self.window, deminiaturize:nil];
+            }
+        }
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        let miniaturized: BOOL = unsafe { msg_send![*self.window, isMiniaturized] };
+        miniaturized == YES
+    }
+
+    #[inline]
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        let level = if always_on_top { NS_FLOATING_WINDOW_LEVEL } else { NS_NORMAL_WINDOW_LEVEL };
+        unsafe { let _: () = msg_send![*self.window, setLevel:level]; }
+    }
+
+    #[inline]
+    pub fn set_always_on_bottom(&self, always_on_bottom: bool) {
+        let level = if always_on_bottom { NS_NORMAL_WINDOW_LEVEL - 1 } else { NS_NORMAL_WINDOW_LEVEL };
+        unsafe { let _: () = msg_send![*self.window, setLevel:level]; }
+    }
+
     #[inline]
     pub fn platform_display(&self) -> *mut libc::c_void {
         unimplemented!()
@@ -472,6 +579,11 @@ impl Window {
         *self.window as *mut libc::c_void
     }
 
+    #[inline]
+    pub fn set_window_icon(&self, _window_icon: Option<::Icon>) {
+        // macOS windows don't have icons, the dock displays the icon of the application
+    }
+
     pub fn set_cursor(&self, cursor: MouseCursor) {
         let cursor_name = match cursor {
             MouseCursor::Arrow | MouseCursor::Default => "arrowCursor",
@@ -510,6 +622,57 @@ impl Window {
         }
     }
 
+    pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+        // cursors are 16 points large, we pick the image for the pixel density of the window
+        let scale = self.hidpi_factor() as f64;
+        let cursor_image = cursor.image_for_size((16.0 * scale).round() as u32);
+        let image = cursor_image.image();
+        let (width, height) = (image.width() as libc::c_long, image.height() as libc::c_long);
+
+        unsafe {
+            let color_space = IdRef::new(NSString::alloc(nil).init_str("NSDeviceRGBColorSpace"));
+            let rep: id = msg_send![Class::get("NSBitmapImageRep").unwrap(), alloc];
+            let rep = IdRef::new(msg_send![rep,
+                initWithBitmapDataPlanes:ptr::null_mut::<*mut u8>()
+                pixelsWide:width
+                pixelsHigh:height
+                bitsPerSample:8 as libc::c_long
+                samplesPerPixel:4 as libc::c_long
+                hasAlpha:YES
+                isPlanar:NO
+                colorSpaceName:*color_space
+                bytesPerRow:width * 4
+                bitsPerPixel:32 as libc::c_long]);
+            if *rep == nil {
+                return;
+            }
+
+            // the bitmap expects premultiplied alpha
+            let data: *mut u8 = msg_send![*rep, bitmapData];
+            let data = slice::from_raw_parts_mut(data, image.rgba().len());
+            for (dst, px) in data.chunks_mut(4).zip(image.rgba().chunks(4)) {
+                let a = px[3] as u32;
+                dst[0] = (px[0] as u32 * a / 255) as u8;
+                dst[1] = (px[1] as u32 * a / 255) as u8;
+                dst[2] = (px[2] as u32 * a / 255) as u8;
+                dst[3] = px[3];
+            }
+
+            let size = NSSize::new(width as f64 / scale, height as f64 / scale);
+            let nsimage: id = msg_send![Class::get("NSImage").unwrap(), alloc];
+            let nsimage = IdRef::new(msg_send![nsimage, initWithSize:size]);
+            let _: () = msg_send![*nsimage, addRepresentation:*rep];
+
+            let (hotspot_x, hotspot_y) = cursor_image.hotspot();
+            let hotspot = NSPoint::new(hotspot_x as f64 / scale, hotspot_y as f64 / scale);
+            let nscursor: id = msg_send![Class::get("NSCursor").unwrap(), alloc];
+            let nscursor = IdRef::new(msg_send![nscursor, initWithImage:*nsimage hotSpot:hotspot]);
+            let _: () = msg_send![*nscursor, set];
+
+            *self.custom_cursor.lock().unwrap() = Some(nscursor);
+        }
+    }
+
     pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
         let cls = Class::get("NSCursor").unwrap();
 
@@ -527,10 +690,61 @@ impl Window {
             CursorState::Grab => {
                 let _: i32 = unsafe { CGAssociateMouseAndMouseCursorPosition(false) };
                 Ok(())
-            }
+            },
+            CursorState::Locked => Err("Cursor locking is not supported on macOS".to_string()),
+            CursorState::Confined => Err("Cursor confinement is not supported on macOS".to_string()),
         }
     }
 
+    #[inline]
+    pub fn set_clipboard(&self, kind: ClipboardKind, contents: ClipboardContents) -> Result<(), String> {
+        clipboard::set_clipboard(kind, contents)
+    }
+
+    #[inline]
+    pub fn clipboard_mime_types(&self, kind: ClipboardKind) -> Vec<String> {
+        clipboard::clipboard_mime_types(kind)
+    }
+
+    #[inline]
+    pub fn get_clipboard(&self, kind: ClipboardKind, mime_type: &str) -> Option<Vec<u8>> {
+        clipboard::get_clipboard(kind, mime_type)
+    }
+
+    #[inline]
+    pub fn set_ime_position(&self, _x: i32, _y: i32) {
+    }
+
+    #[inline]
+    pub fn set_ime_allowed(&self, _allowed: bool) {
+    }
+
+    pub fn get_pressed_keys(&self) -> Vec<::ScanCode> {
+        // the virtual key codes go up to 0x7f
+        (0..0x80u16)
+            .filter(|&code| unsafe {
+                CGEventSourceKeyState(K_CG_EVENT_SOURCE_STATE_COMBINED_SESSION_STATE, code)
+            })
+            .map(|code| code as ::ScanCode)
+            .collect()
+    }
+
+    pub fn get_pressed_mouse_buttons(&self) -> Vec<MouseButton> {
+        let pressed: NSUInteger = unsafe {
+            msg_send![cocoa::base::class("NSEvent"), pressedMouseButtons]
+        };
+        // the bits are the numbers of the buttons
+        (0..std::mem::size_of::<NSUInteger>() * 8)
+            .filter(|&number| pressed & (1 << number) != 0)
+            .map(super::events_loop::button_number_to_mouse_button)
+            .collect()
+    }
+
+    #[inline]
+    pub fn start_drag(&self, _: ::DragData, _: &[::DragAction]) -> Result<(), String> {
+        Err("Dragging data out of a window is not supported yet".to_string())
+    }
+
     #[inline]
     pub fn hidpi_factor(&self) -> f32 {
         unsafe {
@@ -614,7 +828,7 @@ unsafe fn nswindow_set_max_dimensions<V: NSWindow + Copy>(
 pub struct IdRef(id);
 
 impl IdRef {
-    fn new(i: id) -> IdRef {
+    pub fn new(i: id) -> IdRef {
         IdRef(i)
     }
 
@@ -25,7 +25,9 @@ pub struct ThreadLocalData {
     pub win: winapi::HWND,
     pub sender: Sender<Event>,
     pub window_state: Arc<Mutex<WindowState>>,
-    pub mouse_in_window: bool
+    pub mouse_in_window: bool,
+    /// The modifiers last reported to the window, `None` until it gains the focus.
+    pub modifiers: Option<ModifiersState>
 }
 
 /// Equivalent to the windows api [MINMAXINFO](https://msdn.microsoft.com/en-us/library/windows/desktop/ms632605%28v=vs.85%29.aspx)
@@ -59,6 +61,27 @@ fn send_event(input_window: winapi::HWND, event: Event) {
     });
 }
 
+/// Sends a `ModifiersChanged` event if the modifiers changed since they were last reported.
+fn send_modifiers_changed(input_window: winapi::HWND) {
+    use events::WindowEvent::ModifiersChanged;
+    let modifiers = event::get_key_mods();
+    let changed = CONTEXT_STASH.with(|context_stash| {
+        let mut context_stash = context_stash.borrow_mut();
+        if let Some(context_stash) = context_stash.as_mut() {
+            if context_stash.win == input_window && context_stash.modifiers != Some(modifiers) {
+                context_stash.modifiers = Some(modifiers);
+                return true;
+            }
+        }
+
+        false
+    });
+
+    if changed {
+        send_event(input_window, ModifiersChanged(modifiers));
+    }
+}
+
 /// This is the callback that is called by `DispatchMessage` in the events loop.
 ///
 /// Returning 0 tells the Win32 API that the message has been processed.
@@ -189,94 +212,161 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             let value = value as i32;
             let value = value as f32 / winapi::WHEEL_DELTA as f32;
 
-            send_event(window, MouseWheel(LineDelta(0.0, value), TouchPhase::Moved));
+            send_event(window, MouseWheel(LineDelta(0.0, value), TouchPhase::Moved, event::get_key_mods()));
 
             0
         },
 
         winapi::WM_KEYDOWN | winapi::WM_SYSKEYDOWN => {
-            use events::WindowEvent::KeyboardInput;
+            use events::WindowEvent::KeyboardInput as KeyboardInputEvent;
+            use events::KeyboardInput;
             use events::ElementState::Pressed;
             if msg == winapi::WM_SYSKEYDOWN && wparam as i32 == winapi::VK_F4 {
                 user32::DefWindowProcW(window, msg, wparam, lparam)
             } else {
                 let (scancode, vkey) = event::vkeycode_to_element(wparam, lparam);
-                send_event(window, KeyboardInput(Pressed, scancode, vkey, event::get_key_mods()));
+                send_event(window, KeyboardInputEvent(KeyboardInput {
+                    state: Pressed,
+                    scancode: scancode,
+                    physical_key: event::scancode_to_physical_key(scancode),
+                    virtual_keycode: vkey,
+                    logical_key: None,
+                    modifiers: event::get_key_mods(),
+                    // bit 30 is the previous state of the key
+                    is_repeat: (lparam & (1 << 30)) != 0,
+                    is_synthetic: false,
+                }));
+                send_modifiers_changed(window);
                 0
             }
         },
 
         winapi::WM_KEYUP | winapi::WM_SYSKEYUP => {
-            use events::WindowEvent::KeyboardInput;
+            use events::WindowEvent::KeyboardInput as KeyboardInputEvent;
+            use events::KeyboardInput;
             use events::ElementState::Released;
             let (scancode, vkey) = event::vkeycode_to_element(wparam, lparam);
-            send_event(window, KeyboardInput(Released, scancode, vkey, event::get_key_mods()));
+            send_event(window, KeyboardInputEvent(KeyboardInput {
+                state: Released,
+                scancode: scancode,
+                physical_key: event::scancode_to_physical_key(scancode),
+                virtual_keycode: vkey,
+                logical_key: None,
+                modifiers: event::get_key_mods(),
+                is_repeat: false,
+                is_synthetic: false,
+            }));
+            send_modifiers_changed(window);
             0
         },
 
         winapi::WM_LBUTTONDOWN => {
-            use events::WindowEvent::MouseInput;
+            use events::WindowEvent::MouseInput as MouseInputEvent;
+            use events::MouseInput;
             use events::MouseButton::Left;
             use events::ElementState::Pressed;
-            send_event(window, MouseInput(Pressed, Left));
+            send_event(window, MouseInputEvent(MouseInput {
+                state: Pressed,
+                button: Left,
+                modifiers: event::get_key_mods(),
+                is_synthetic: false,
+            }));
             0
         },
 
         winapi::WM_LBUTTONUP => {
-            use events::WindowEvent::MouseInput;
+            use events::WindowEvent::MouseInput as MouseInputEvent;
+            use events::MouseInput;
             use events::MouseButton::Left;
             use events::ElementState::Released;
-            send_event(window, MouseInput(Released, Left));
+            send_event(window, MouseInputEvent(MouseInput {
+                state: Released,
+                button: Left,
+                modifiers: event::get_key_mods(),
+                is_synthetic: false,
+            }));
             0
         },
 
         winapi::WM_RBUTTONDOWN => {
-            use events::WindowEvent::MouseInput;
+            use events::WindowEvent::MouseInput as MouseInputEvent;
+            use events::MouseInput;
             use events::MouseButton::Right;
             use events::ElementState::Pressed;
-            send_event(window, MouseInput(Pressed, Right));
+            send_event(window, MouseInputEvent(MouseInput {
+                state: Pressed,
+                button: Right,
+                modifiers: event::get_key_mods(),
+                is_synthetic: false,
+            }));
             0
         },
 
         winapi::WM_RBUTTONUP => {
-            use events::WindowEvent::MouseInput;
+            use events::WindowEvent::MouseInput as MouseInputEvent;
+            use events::MouseInput;
             use events::MouseButton::Right;
             use events::ElementState::Released;
-            send_event(window, MouseInput(Released, Right));
+            send_event(window, MouseInputEvent(MouseInput {
+                state: Released,
+                button: Right,
+                modifiers: event::get_key_mods(),
+                is_synthetic: false,
+            }));
             0
         },
 
         winapi::WM_MBUTTONDOWN => {
-            use events::WindowEvent::MouseInput;
+            use events::WindowEvent::MouseInput as MouseInputEvent;
+            use events::MouseInput;
             use events::MouseButton::Middle;
             use events::ElementState::Pressed;
-            send_event(window, MouseInput(Pressed, Middle));
+            send_event(window, MouseInputEvent(MouseInput {
+                state: Pressed,
+                button: Middle,
+                modifiers: event::get_key_mods(),
+                is_synthetic: false,
+            }));
             0
         },
 
         winapi::WM_MBUTTONUP => {
-            use events::WindowEvent::MouseInput;
+            use events::WindowEvent::MouseInput as MouseInputEvent;
+            use events::MouseInput;
             use events::MouseButton::Middle;
             use events::ElementState::Released;
-            send_event(window, MouseInput(Released, Middle));
+            send_event(window, MouseInputEvent(MouseInput {
+                state: Released,
+                button: Middle,
+                modifiers: event::get_key_mods(),
+                is_synthetic: false,
+            }));
             0
         },
 
         winapi::WM_XBUTTONDOWN => {
-            use events::WindowEvent::MouseInput;
-            use events::MouseButton::Other;
+            use events::WindowEvent::MouseInput as MouseInputEvent;
+            use events::MouseInput;
             use events::ElementState::Pressed;
-            let xbutton = winapi::HIWORD(wparam as winapi::DWORD) as winapi::c_int; // waiting on PR for winapi to add GET_XBUTTON_WPARAM
-            send_event(window, MouseInput(Pressed, Other(xbutton as u8)));
+            send_event(window, MouseInputEvent(MouseInput {
+                state: Pressed,
+                button: event::xbutton_to_mouse_button(wparam),
+                modifiers: event::get_key_mods(),
+                is_synthetic: false,
+            }));
             0
         },
 
         winapi::WM_XBUTTONUP => {
-            use events::WindowEvent::MouseInput;
-            use events::MouseButton::Other;
+            use events::WindowEvent::MouseInput as MouseInputEvent;
+            use events::MouseInput;
             use events::ElementState::Released;
-            let xbutton = winapi::HIWORD(wparam as winapi::DWORD) as winapi::c_int; 
-            send_event(window, MouseInput(Released, Other(xbutton as u8)));
+            send_event(window, MouseInputEvent(MouseInput {
+                state: Released,
+                button: event::xbutton_to_mouse_button(wparam),
+                modifiers: event::get_key_mods(),
+                is_synthetic: false,
+            }));
             0
         },
 
@@ -303,6 +393,12 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
         winapi::WM_SETFOCUS => {
             use events::WindowEvent::Focused;
             send_event(window, Focused(true));
+            CONTEXT_STASH.with(|context_stash| {
+                if let Some(context_stash) = context_stash.borrow_mut().as_mut() {
+                    context_stash.modifiers = None;
+                }
+            });
+            send_modifiers_changed(window);
             0
         },
 
@@ -320,12 +416,17 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
                     if let Ok(window_state) = cstash.window_state.lock() {
                         if cstash.mouse_in_window {
                             match window_state.cursor_state {
-                                CursorState::Normal => {
-                                    user32::SetCursor(user32::LoadCursorW(
-                                            ptr::null_mut(),
-                                            window_state.cursor));
+                                CursorState::Normal | CursorState::Confined => {
+                                    match window_state.custom_cursor {
+                                        Some(ref cursor) => { user32::SetCursor(cursor.0); },
+                                        None => {
+                                            user32::SetCursor(user32::LoadCursorW(
+                                                    ptr::null_mut(),
+                                                    window_state.cursor));
+                                        },
+                                    }
                                 },
-                                CursorState::Grab | CursorState::Hide => {
+                                CursorState::Grab | CursorState::Hide | CursorState::Locked => {
                                     user32::SetCursor(ptr::null_mut());
                                 }
                             }
@@ -394,6 +495,31 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             0
         },
 
+        winapi::WM_WINDOWPOSCHANGING => {
+            let pos = lparam as *mut winapi::WINDOWPOS;
+
+            let always_on_bottom = CONTEXT_STASH.with(|context_stash| {
+                match context_stash.borrow().as_ref() {
+                    Some(cstash) if cstash.win == window => {
+                        cstash.window_state.lock().unwrap().attributes.always_on_bottom
+                    },
+                    _ => false
+                }
+            });
+
+            // `HWND_BOTTOM` only moves the window once, keep it below the other windows when
+            // it is activated or raised
+            if always_on_bottom {
+                if (*pos).flags & winapi::SWP_NOACTIVATE == 0 {
+                    (*pos).flags |= winapi::SWP_NOZORDER;
+                } else if (*pos).flags & winapi::SWP_NOZORDER == 0 {
+                    (*pos).hwndInsertAfter = super::HWND_BOTTOM;
+                }
+            }
+
+            user32::DefWindowProcW(window, msg, wparam, lparam)
+        },
+
         x if x == *super::WAKEUP_MSG_ID => {
             use events::WindowEvent::Awakened;
             send_event(window, Awakened);
@@ -0,0 +1,219 @@
+use std::{ptr, slice};
+use std::ffi::OsStr;
+use std::os::windows::ffi::OsStrExt;
+
+use {ClipboardContents, TEXT_MIME_TYPE, TEXT_UTF8_MIME_TYPE};
+
+use winapi;
+use user32;
+use kernel32;
+
+/// The MIME type of HTML documents, stored in the `HTML Format` clipboard format.
+const HTML_MIME_TYPE: &'static str = "text/html";
+
+/// The MIME type of PNG images, stored in the `PNG` clipboard format that most applications use.
+const PNG_MIME_TYPE: &'static str = "image/png";
+
+/// The clipboard, opened by a window until this object is dropped.
+struct OpenClipboard;
+
+impl OpenClipboard {
+    fn open(window: winapi::HWND) -> Result<OpenClipboard, String> {
+        // another application may have the clipboard open for a short time
+        for _ in 0 .. 10 {
+            if unsafe { user32::OpenClipboard(window) } != 0 {
+                return Ok(OpenClipboard);
+            }
+            ::std::thread::sleep(::std::time::Duration::from_millis(5));
+        }
+        Err(format!("OpenClipboard failed"))
+    }
+}
+
+impl Drop for OpenClipboard {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { user32::CloseClipboard() };
+    }
+}
+
+fn register_format(name: &str) -> winapi::UINT {
+    let name: Vec<u16> = OsStr::new(name).encode_wide().chain(Some(0)).collect();
+    unsafe { user32::RegisterClipboardFormatW(name.as_ptr()) }
+}
+
+fn format_name(format: winapi::UINT) -> Option<String> {
+    let mut name = [0u16; 256];
+    let len = unsafe { user32::GetClipboardFormatNameW(format, name.as_mut_ptr(), name.len() as i32) };
+    if len <= 0 {
+        return None;
+    }
+    Some(String::from_utf16_lossy(&name[..len as usize]))
+}
+
+/// Returns the clipboard format in which a MIME type is stored.
+fn mime_format(mime_type: &str) -> winapi::UINT {
+    match mime_type {
+        TEXT_UTF8_MIME_TYPE | TEXT_MIME_TYPE => winapi::CF_UNICODETEXT,
+        HTML_MIME_TYPE => register_format("HTML Format"),
+        PNG_MIME_TYPE => register_format("PNG"),
+        _ => register_format(mime_type),
+    }
+}
+
+/// Returns the MIME types stored in a clipboard format.
+fn format_mime_types(format: winapi::UINT) -> Vec<String> {
+    if format == winapi::CF_UNICODETEXT {
+        return vec![TEXT_UTF8_MIME_TYPE.to_owned(), TEXT_MIME_TYPE.to_owned()];
+    }
+    match format_name(format) {
+        Some(ref name) if name == "HTML Format" => vec![HTML_MIME_TYPE.to_owned()],
+        Some(ref name) if name == "PNG" => vec![PNG_MIME_TYPE.to_owned()],
+        Some(ref name) if name.contains('/') => vec![name.clone()],
+        _ => Vec::new(),
+    }
+}
+
+/// Converts data of a MIME type to the representation of its clipboard format.
+fn encode(mime_type: &str, data: &[u8]) -> Vec<u8> {
+    match mime_type {
+        TEXT_UTF8_MIME_TYPE | TEXT_MIME_TYPE => {
+            let text = String::from_utf8_lossy(data);
+            let wide: Vec<u16> = OsStr::new(&text[..]).encode_wide().chain(Some(0)).collect();
+            unsafe { slice::from_raw_parts(wide.as_ptr() as *const u8, wide.len() * 2).to_vec() }
+        },
+        HTML_MIME_TYPE => encode_html(data),
+        _ => data.to_vec(),
+    }
+}
+
+/// Converts data of a clipboard format to the representation of a MIME type.
+fn decode(mime_type: &str, data: &[u8]) -> Vec<u8> {
+    match mime_type {
+        TEXT_UTF8_MIME_TYPE | TEXT_MIME_TYPE => {
+            let wide: &[u16] = unsafe { slice::from_raw_parts(data.as_ptr() as *const u16, data.len() / 2) };
+            let len = wide.iter().position(|&c| c == 0).unwrap_or(wide.len());
+            String::from_utf16_lossy(&wide[..len]).into_bytes()
+        },
+        HTML_MIME_TYPE => decode_html(data),
+        _ => data.to_vec(),
+    }
+}
+
+/// Wraps an HTML fragment in the header of the `HTML Format` clipboard format.
+fn encode_html(html: &[u8]) -> Vec<u8> {
+    let prefix = b"<html><body><!--StartFragment-->";
+    let suffix = b"<!--EndFragment--></body></html>";
+    // the offsets are counted from the start of the header, whose size doesn't depend on them
+    // since they are written with a fixed number of digits
+    let header = |start_html: usize, end_html: usize, start_fragment: usize, end_fragment: usize| {
+        format!("Version:0.9\r\nStartHTML:{:010}\r\nEndHTML:{:010}\r\n\
+                 StartFragment:{:010}\r\nEndFragment:{:010}\r\n",
+                start_html, end_html, start_fragment, end_fragment)
+    };
+
+    let start_html = header(0, 0, 0, 0).len();
+    let start_fragment = start_html + prefix.len();
+    let end_fragment = start_fragment + html.len();
+    let end_html = end_fragment + suffix.len();
+
+    let mut result = header(start_html, end_html, start_fragment, end_fragment).into_bytes();
+    result.extend_from_slice(prefix);
+    result.extend_from_slice(html);
+    result.extend_from_slice(suffix);
+    result.push(0);
+    result
+}
+
+/// Extracts the HTML fragment of data of the `HTML Format` clipboard format.
+fn decode_html(data: &[u8]) -> Vec<u8> {
+    let data = match data.iter().position(|&b| b == 0) {
+        Some(len) => &data[..len],
+        None => data,
+    };
+    let header = String::from_utf8_lossy(data);
+    let offset = |key: &str| -> Option<usize> {
+        header.lines().find(|line| line.starts_with(key))
+              .and_then(|line| line[key.len()..].trim().parse().ok())
+    };
+    match (offset("StartFragment:"), offset("EndFragment:")) {
+        (Some(start), Some(end)) if start <= end && end <= data.len() => data[start .. end].to_vec(),
+        _ => data.to_vec(),
+    }
+}
+
+pub fn set_clipboard(window: winapi::HWND, contents: ClipboardContents) -> Result<(), String> {
+    let _clipboard = try!(OpenClipboard::open(window));
+    unsafe {
+        if user32::EmptyClipboard() == 0 {
+            return Err(format!("EmptyClipboard failed"));
+        }
+
+        for mime_type in contents.mime_types() {
+            let format = mime_format(mime_type);
+            // `text/plain` and `text/plain;charset=utf-8` share the same format
+            if user32::IsClipboardFormatAvailable(format) != 0 {
+                continue;
+            }
+            let data = encode(mime_type, contents.get(mime_type).unwrap());
+
+            // the memory belongs to the system once the data is set
+            let memory = kernel32::GlobalAlloc(winapi::GMEM_MOVEABLE, data.len() as winapi::SIZE_T);
+            if memory.is_null() {
+                return Err(format!("GlobalAlloc failed"));
+            }
+            let locked = kernel32::GlobalLock(memory);
+            ptr::copy_nonoverlapping(data.as_ptr(), locked as *mut u8, data.len());
+            kernel32::GlobalUnlock(memory);
+
+            if user32::SetClipboardData(format, memory as winapi::HANDLE).is_null() {
+                kernel32::GlobalFree(memory);
+                return Err(format!("SetClipboardData failed"));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn clipboard_mime_types(window: winapi::HWND) -> Vec<String> {
+    let _clipboard = match OpenClipboard::open(window) {
+        Ok(clipboard) => clipboard,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut mime_types: Vec<String> = Vec::new();
+    let mut format = 0;
+    loop {
+        format = unsafe { user32::EnumClipboardFormats(format) };
+        if format == 0 {
+            return mime_types;
+        }
+        for mime in format_mime_types(format) {
+            if !mime_types.contains(&mime) {
+                mime_types.push(mime);
+            }
+        }
+    }
+}
+
+pub fn get_clipboard(window: winapi::HWND, mime_type: &str) -> Option<Vec<u8>> {
+    let _clipboard = match OpenClipboard::open(window) {
+        Ok(clipboard) => clipboard,
+        Err(_) => return None,
+    };
+
+    unsafe {
+        let memory = user32::GetClipboardData(mime_format(mime_type));
+        if memory.is_null() {
+            return None;
+        }
+        let locked = kernel32::GlobalLock(memory as winapi::HGLOBAL);
+        if locked.is_null() {
+            return None;
+        }
+        let size = kernel32::GlobalSize(memory as winapi::HGLOBAL) as usize;
+        let data = decode(mime_type, slice::from_raw_parts(locked as *const u8, size));
+        kernel32::GlobalUnlock(memory as winapi::HGLOBAL);
+        Some(data)
+    }
+}
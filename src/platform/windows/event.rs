@@ -1,10 +1,11 @@
-use events::VirtualKeyCode;
+use events::{MouseButton, PhysicalKey, VirtualKeyCode};
 use events::ModifiersState;
 use winapi;
 use user32;
 use ScanCode;
 
 const MAPVK_VSC_TO_VK_EX: u32 = 3;
+const MAPVK_VK_TO_VSC_EX: u32 = 4;
 
 pub fn get_key_mods() -> ModifiersState {
     let mut mods = ModifiersState::default();
@@ -21,19 +22,85 @@ pub fn get_key_mods() -> ModifiersState {
         if (user32::GetKeyState(winapi::VK_LWIN) | user32::GetKeyState(winapi::VK_RWIN)) & (1 << 15) == (1 << 15) {
             mods.logo = true;
         }
+        let is_down = |vk| user32::GetKeyState(vk) & (1 << 15) == (1 << 15);
+        mods.left_shift = is_down(winapi::VK_LSHIFT);
+        mods.right_shift = is_down(winapi::VK_RSHIFT);
+        mods.left_ctrl = is_down(winapi::VK_LCONTROL);
+        mods.right_ctrl = is_down(winapi::VK_RCONTROL);
+        mods.left_alt = is_down(winapi::VK_LMENU);
+        mods.right_alt = is_down(winapi::VK_RMENU);
+        mods.left_logo = is_down(winapi::VK_LWIN);
+        mods.right_logo = is_down(winapi::VK_RWIN);
+        // the layouts with AltGr make the right alt key press the left control key too
+        mods.alt_graph = mods.right_alt && mods.left_ctrl;
+        // the low bit is the toggle state of the lock keys
+        mods.caps_lock = user32::GetKeyState(winapi::VK_CAPITAL) & 1 != 0;
+        mods.num_lock = user32::GetKeyState(winapi::VK_NUMLOCK) & 1 != 0;
     }
     mods
 }
 
+/// Returns the scan codes of the keys held down, as reported by the events.
+pub fn get_pressed_keys() -> Vec<ScanCode> {
+    let mut keys = Vec::new();
+    for vk in 0..256 {
+        match vk {
+            // the mouse buttons, and the modifiers that don't tell their side
+            winapi::VK_LBUTTON | winapi::VK_RBUTTON | winapi::VK_MBUTTON | winapi::VK_XBUTTON1 |
+            winapi::VK_XBUTTON2 | winapi::VK_SHIFT | winapi::VK_CONTROL | winapi::VK_MENU => continue,
+            _ => (),
+        }
+        unsafe {
+            if user32::GetAsyncKeyState(vk) as u16 & (1 << 15) == 0 {
+                continue;
+            }
+            // the extended keys have the 0xE0 prefix in the high byte
+            let scancode = user32::MapVirtualKeyA(vk as u32, MAPVK_VK_TO_VSC_EX);
+            if scancode == 0 {
+                continue;
+            }
+            keys.push(if scancode & 0xff00 != 0 { 0xE000 | (scancode & 0xff) } else { scancode });
+        }
+    }
+    keys
+}
+
+/// Returns the mouse buttons held down.
+pub fn get_pressed_mouse_buttons() -> Vec<MouseButton> {
+    let buttons = [
+        (winapi::VK_LBUTTON, MouseButton::Left),
+        (winapi::VK_RBUTTON, MouseButton::Right),
+        (winapi::VK_MBUTTON, MouseButton::Middle),
+        (winapi::VK_XBUTTON1, MouseButton::Back),
+        (winapi::VK_XBUTTON2, MouseButton::Forward),
+    ];
+    buttons.iter()
+        .filter(|&&(vk, _)| unsafe { user32::GetAsyncKeyState(vk) as u16 & (1 << 15) != 0 })
+        .map(|&(_, button)| button)
+        .collect()
+}
+
+/// Returns the button of a `WM_XBUTTONDOWN` or `WM_XBUTTONUP` message.
+pub fn xbutton_to_mouse_button(wparam: winapi::WPARAM) -> MouseButton {
+    // waiting on PR for winapi to add GET_XBUTTON_WPARAM
+    match winapi::HIWORD(wparam as winapi::DWORD) {
+        1 => MouseButton::Back,
+        2 => MouseButton::Forward,
+        // XBUTTON1 is the eighth button of X11
+        other => MouseButton::Other(other as u16 + 7),
+    }
+}
+
 pub fn vkeycode_to_element(wparam: winapi::WPARAM, lparam: winapi::LPARAM) -> (ScanCode, Option<VirtualKeyCode>) {
-    let scancode = ((lparam >> 16) & 0xff) as u8;
+    let scancode = ((lparam >> 16) & 0xff) as u32;
     let extended = (lparam & 0x01000000) != 0;
     let vk = match wparam as i32 {
-        winapi::VK_SHIFT => unsafe { user32::MapVirtualKeyA(scancode as u32, MAPVK_VSC_TO_VK_EX) as i32 },
+        winapi::VK_SHIFT => unsafe { user32::MapVirtualKeyA(scancode, MAPVK_VSC_TO_VK_EX) as i32 },
         winapi::VK_CONTROL => if extended { winapi::VK_RCONTROL } else { winapi::VK_LCONTROL },
         winapi::VK_MENU => if extended { winapi::VK_RMENU } else { winapi::VK_LMENU },
         other => other
     };
+    let scancode = if extended { 0xE000 | scancode } else { scancode };
     (scancode, match vk {
         //winapi::VK_LBUTTON => Some(VirtualKeyCode::Lbutton),
         //winapi::VK_RBUTTON => Some(VirtualKeyCode::Rbutton),
@@ -208,3 +275,158 @@ pub fn vkeycode_to_element(wparam: winapi::WPARAM, lparam: winapi::LPARAM) -> (S
         _ => None
     })
 }
+
+/// Returns the position of the key with the given scan code, as returned by `vkeycode_to_element`.
+pub fn scancode_to_physical_key(scancode: ScanCode) -> Option<PhysicalKey> {
+    Some(match scancode {
+        0x0001 => PhysicalKey::Escape,
+        0x0002 => PhysicalKey::Digit1,
+        0x0003 => PhysicalKey::Digit2,
+        0x0004 => PhysicalKey::Digit3,
+        0x0005 => PhysicalKey::Digit4,
+        0x0006 => PhysicalKey::Digit5,
+        0x0007 => PhysicalKey::Digit6,
+        0x0008 => PhysicalKey::Digit7,
+        0x0009 => PhysicalKey::Digit8,
+        0x000A => PhysicalKey::Digit9,
+        0x000B => PhysicalKey::Digit0,
+        0x000C => PhysicalKey::Minus,
+        0x000D => PhysicalKey::Equal,
+        0x000E => PhysicalKey::Backspace,
+        0x000F => PhysicalKey::Tab,
+        0x0010 => PhysicalKey::KeyQ,
+        0x0011 => PhysicalKey::KeyW,
+        0x0012 => PhysicalKey::KeyE,
+        0x0013 => PhysicalKey::KeyR,
+        0x0014 => PhysicalKey::KeyT,
+        0x0015 => PhysicalKey::KeyY,
+        0x0016 => PhysicalKey::KeyU,
+        0x0017 => PhysicalKey::KeyI,
+        0x0018 => PhysicalKey::KeyO,
+        0x0019 => PhysicalKey::KeyP,
+        0x001A => PhysicalKey::BracketLeft,
+        0x001B => PhysicalKey::BracketRight,
+        0x001C => PhysicalKey::Enter,
+        0x001D => PhysicalKey::ControlLeft,
+        0x001E => PhysicalKey::KeyA,
+        0x001F => PhysicalKey::KeyS,
+        0x0020 => PhysicalKey::KeyD,
+        0x0021 => PhysicalKey::KeyF,
+        0x0022 => PhysicalKey::KeyG,
+        0x0023 => PhysicalKey::KeyH,
+        0x0024 => PhysicalKey::KeyJ,
+        0x0025 => PhysicalKey::KeyK,
+        0x0026 => PhysicalKey::KeyL,
+        0x0027 => PhysicalKey::Semicolon,
+        0x0028 => PhysicalKey::Quote,
+        0x0029 => PhysicalKey::Backquote,
+        0x002A => PhysicalKey::ShiftLeft,
+        0x002B => PhysicalKey::Backslash,
+        0x002C => PhysicalKey::KeyZ,
+        0x002D => PhysicalKey::KeyX,
+        0x002E => PhysicalKey::KeyC,
+        0x002F => PhysicalKey::KeyV,
+        0x0030 => PhysicalKey::KeyB,
+        0x0031 => PhysicalKey::KeyN,
+        0x0032 => PhysicalKey::KeyM,
+        0x0033 => PhysicalKey::Comma,
+        0x0034 => PhysicalKey::Period,
+        0x0035 => PhysicalKey::Slash,
+        0x0036 => PhysicalKey::ShiftRight,
+        0x0037 => PhysicalKey::NumpadMultiply,
+        0x0038 => PhysicalKey::AltLeft,
+        0x0039 => PhysicalKey::Space,
+        0x003A => PhysicalKey::CapsLock,
+        0x003B => PhysicalKey::F1,
+        0x003C => PhysicalKey::F2,
+        0x003D => PhysicalKey::F3,
+        0x003E => PhysicalKey::F4,
+        0x003F => PhysicalKey::F5,
+        0x0040 => PhysicalKey::F6,
+        0x0041 => PhysicalKey::F7,
+        0x0042 => PhysicalKey::F8,
+        0x0043 => PhysicalKey::F9,
+        0x0044 => PhysicalKey::F10,
+        // the pause key sends the scan code of num lock, which is extended instead
+        0x0045 => PhysicalKey::Pause,
+        0x0046 => PhysicalKey::ScrollLock,
+        0x0047 => PhysicalKey::Numpad7,
+        0x0048 => PhysicalKey::Numpad8,
+        0x0049 => PhysicalKey::Numpad9,
+        0x004A => PhysicalKey::NumpadSubtract,
+        0x004B => PhysicalKey::Numpad4,
+        0x004C => PhysicalKey::Numpad5,
+        0x004D => PhysicalKey::Numpad6,
+        0x004E => PhysicalKey::NumpadAdd,
+        0x004F => PhysicalKey::Numpad1,
+        0x0050 => PhysicalKey::Numpad2,
+        0x0051 => PhysicalKey::Numpad3,
+        0x0052 => PhysicalKey::Numpad0,
+        0x0053 => PhysicalKey::NumpadDecimal,
+        0x0056 => PhysicalKey::IntlBackslash,
+        0x0057 => PhysicalKey::F11,
+        0x0058 => PhysicalKey::F12,
+        0x0059 => PhysicalKey::NumpadEqual,
+        0x0064 => PhysicalKey::F13,
+        0x0065 => PhysicalKey::F14,
+        0x0066 => PhysicalKey::F15,
+        0x0067 => PhysicalKey::F16,
+        0x0068 => PhysicalKey::F17,
+        0x0069 => PhysicalKey::F18,
+        0x006A => PhysicalKey::F19,
+        0x006B => PhysicalKey::F20,
+        0x006C => PhysicalKey::F21,
+        0x006D => PhysicalKey::F22,
+        0x006E => PhysicalKey::F23,
+        0x0070 => PhysicalKey::KanaMode,
+        0x0073 => PhysicalKey::IntlRo,
+        0x0076 => PhysicalKey::F24,
+        0x0079 => PhysicalKey::Convert,
+        0x007B => PhysicalKey::NonConvert,
+        0x007D => PhysicalKey::IntlYen,
+        0x007E => PhysicalKey::NumpadComma,
+        0x00F1 => PhysicalKey::Lang2,
+        0x00F2 => PhysicalKey::Lang1,
+        0xE010 => PhysicalKey::MediaTrackPrevious,
+        0xE019 => PhysicalKey::MediaTrackNext,
+        0xE01C => PhysicalKey::NumpadEnter,
+        0xE01D => PhysicalKey::ControlRight,
+        0xE020 => PhysicalKey::AudioVolumeMute,
+        0xE021 => PhysicalKey::LaunchApp2,
+        0xE022 => PhysicalKey::MediaPlayPause,
+        0xE024 => PhysicalKey::MediaStop,
+        0xE02E => PhysicalKey::AudioVolumeDown,
+        0xE030 => PhysicalKey::AudioVolumeUp,
+        0xE032 => PhysicalKey::BrowserHome,
+        0xE035 => PhysicalKey::NumpadDivide,
+        0xE037 => PhysicalKey::PrintScreen,
+        0xE038 => PhysicalKey::AltRight,
+        0xE045 => PhysicalKey::NumLock,
+        0xE047 => PhysicalKey::Home,
+        0xE048 => PhysicalKey::ArrowUp,
+        0xE049 => PhysicalKey::PageUp,
+        0xE04B => PhysicalKey::ArrowLeft,
+        0xE04D => PhysicalKey::ArrowRight,
+        0xE04F => PhysicalKey::End,
+        0xE050 => PhysicalKey::ArrowDown,
+        0xE051 => PhysicalKey::PageDown,
+        0xE052 => PhysicalKey::Insert,
+        0xE053 => PhysicalKey::Delete,
+        0xE05B => PhysicalKey::SuperLeft,
+        0xE05C => PhysicalKey::SuperRight,
+        0xE05D => PhysicalKey::ContextMenu,
+        0xE05E => PhysicalKey::Power,
+        0xE05F => PhysicalKey::Sleep,
+        0xE063 => PhysicalKey::WakeUp,
+        0xE065 => PhysicalKey::BrowserSearch,
+        0xE066 => PhysicalKey::BrowserFavorites,
+        0xE067 => PhysicalKey::BrowserRefresh,
+        0xE068 => PhysicalKey::BrowserStop,
+        0xE069 => PhysicalKey::BrowserForward,
+        0xE06A => PhysicalKey::BrowserBack,
+        0xE06B => PhysicalKey::LaunchApp1,
+        0xE06C => PhysicalKey::LaunchMail,
+        0xE06D => PhysicalKey::MediaSelect,
+        _ => return None,
+    })
+}
@@ -95,6 +95,10 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pl_attribs: PlatformS
                 winapi::WS_POPUP | winapi::WS_CLIPSIBLINGS | winapi::WS_CLIPCHILDREN
             }
         )
+    } else if !window.resizable {
+        (winapi::WS_EX_APPWINDOW | winapi::WS_EX_WINDOWEDGE,
+            (winapi::WS_OVERLAPPEDWINDOW & !(winapi::WS_THICKFRAME | winapi::WS_MAXIMIZEBOX)) |
+                winapi::WS_CLIPSIBLINGS | winapi::WS_CLIPCHILDREN)
     } else {
         (winapi::WS_EX_APPWINDOW | winapi::WS_EX_WINDOWEDGE,
             winapi::WS_OVERLAPPEDWINDOW | winapi::WS_CLIPSIBLINGS | winapi::WS_CLIPCHILDREN)
@@ -163,6 +167,20 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pl_attribs: PlatformS
         dwmapi::DwmEnableBlurBehindWindow(real_window.0, &bb);
     }
 
+    // changing the z-order of the window if necessary
+    if window.always_on_top {
+        user32::SetWindowPos(real_window.0, super::HWND_TOPMOST, 0, 0, 0, 0,
+                             winapi::SWP_NOMOVE | winapi::SWP_NOSIZE | winapi::SWP_NOACTIVATE);
+    } else if window.always_on_bottom {
+        user32::SetWindowPos(real_window.0, super::HWND_BOTTOM, 0, 0, 0, 0,
+                             winapi::SWP_NOMOVE | winapi::SWP_NOSIZE | winapi::SWP_NOACTIVATE);
+    }
+
+    // maximizing the window if necessary, a hidden window is maximized when it is shown
+    if window.maximized && window.visible && window.monitor.is_none() {
+        user32::ShowWindow(real_window.0, winapi::SW_MAXIMIZE);
+    }
+
     // calling SetForegroundWindow if fullscreen
     if window.monitor.is_some() {
         user32::SetForegroundWindow(real_window.0);
@@ -171,7 +189,9 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pl_attribs: PlatformS
     // Creating a mutex to track the current window state
     let window_state = Arc::new(Mutex::new(WindowState {
         cursor: winapi::IDC_ARROW, // use arrow by default
+        custom_cursor: None,
         cursor_state: CursorState::Normal,
+        maximize_on_show: window.maximized && !window.visible && window.monitor.is_none(),
         attributes: window.clone()
     }));
 
@@ -184,7 +204,8 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pl_attribs: PlatformS
                 win: real_window.0,
                 sender: tx.take().unwrap(),
                 window_state: window_state.clone(),
-                mouse_in_window: false
+                mouse_in_window: false,
+                modifiers: None
             };
             (*context_stash.borrow_mut()) = Some(data);
         });
@@ -192,11 +213,18 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pl_attribs: PlatformS
     };
 
     // building the struct
-    Ok(Window {
+    let win = Window {
         window: real_window,
         events_receiver: events_receiver,
         window_state: window_state,
-    })
+        window_icons: Mutex::new(None),
+    };
+
+    if window.window_icon.is_some() {
+        win.set_window_icon(window.window_icon.clone());
+    }
+
+    Ok(win)
 }
 
 unsafe fn register_window_class() -> Vec<u16> {
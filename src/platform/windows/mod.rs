@@ -11,7 +11,7 @@ use std::sync::{
 };
 use std::sync::mpsc::Receiver;
 use {CreationError, WindowEvent as Event, MouseCursor};
-use CursorState;
+use {ClipboardContents, ClipboardKind, CursorImage, CursorState, CustomCursor, Icon, IconImage};
 
 use WindowAttributes;
 
@@ -31,10 +31,12 @@ pub struct PlatformSpecificHeadlessBuilderAttributes;
 pub use self::monitor::{MonitorId, get_available_monitors, get_primary_monitor};
 
 use winapi;
+use gdi32;
 use user32;
 use kernel32;
 
 mod callback;
+mod clipboard;
 mod event;
 mod init;
 mod monitor;
@@ -43,6 +45,35 @@ lazy_static! {
     static ref WAKEUP_MSG_ID: u32 = unsafe { user32::RegisterWindowMessageA("Glutin::EventID".as_ptr() as *const i8) };
 }
 
+// Special values of the `hWndInsertAfter` parameter of `SetWindowPos`.
+const HWND_BOTTOM: winapi::HWND = 1 as winapi::HWND;
+const HWND_TOPMOST: winapi::HWND = -1isize as winapi::HWND;
+const HWND_NOTOPMOST: winapi::HWND = -2isize as winapi::HWND;
+
+// Parameters of `SystemParametersInfoW` that winapi doesn't declare.
+const SPI_GETKEYBOARDSPEED: winapi::UINT = 0x000A;
+const SPI_GETKEYBOARDDELAY: winapi::UINT = 0x0016;
+
+fn key_repeat_info() -> Option<::KeyRepeatInfo> {
+    let mut speed: winapi::DWORD = 0;
+    let mut delay: c_int = 0;
+    unsafe {
+        if user32::SystemParametersInfoW(SPI_GETKEYBOARDSPEED, 0,
+                                         &mut speed as *mut _ as winapi::PVOID, 0) == 0 ||
+           user32::SystemParametersInfoW(SPI_GETKEYBOARDDELAY, 0,
+                                         &mut delay as *mut _ as winapi::PVOID, 0) == 0
+        {
+            return None;
+        }
+    }
+    // the speed goes from 0 (about 2.5 repeats per second) to 31 (about 30 per second), and the
+    // delay from 0 (about 250ms) to 3 (about 1s)
+    Some(::KeyRepeatInfo {
+        delay: ::std::time::Duration::from_millis(250 * (delay as u64 + 1)),
+        rate: (25 + speed * 275 / 31) / 10,
+    })
+}
+
 /// Cursor
 pub type Cursor = *const winapi::wchar_t;
 
@@ -50,7 +81,12 @@ pub type Cursor = *const winapi::wchar_t;
 #[derive(Clone)]
 pub struct WindowState {
     pub cursor: Cursor,
+    /// If set, replaces `cursor`.
+    pub custom_cursor: Option<Arc<WindowCursor>>,
     pub cursor_state: CursorState,
+    /// Whether the window is maximized when it is shown, as `ShowWindow` would show a hidden
+    /// window to maximize it.
+    pub maximize_on_show: bool,
     pub attributes: WindowAttributes
 }
 
@@ -64,6 +100,9 @@ pub struct Window {
 
     /// The current window state.
     window_state: Arc<Mutex<WindowState>>,
+
+    /// The small and big icons of the window, if any.
+    window_icons: Mutex<Option<(WindowIcon, WindowIcon)>>,
 }
 
 unsafe impl Send for Window {}
@@ -82,6 +121,90 @@ impl Drop for WindowWrapper {
     }
 }
 
+/// A cursor created from a `CursorImage`, destroyed when dropped.
+pub struct WindowCursor(pub winapi::HCURSOR);
+
+unsafe impl Send for WindowCursor {}
+unsafe impl Sync for WindowCursor {}
+
+impl WindowCursor {
+    unsafe fn new(cursor_image: &CursorImage) -> Option<WindowCursor> {
+        let image = cursor_image.image();
+        let (width, height) = (image.width() as c_int, image.height() as c_int);
+
+        let mut bgra = image.rgba().to_vec();
+        for px in bgra.chunks_mut(4) {
+            px.swap(0, 2);
+        }
+        let and_mask = vec![0u8; ((width as usize + 15) / 16 * 2) * height as usize];
+
+        let color = gdi32::CreateBitmap(width, height, 1, 32, bgra.as_ptr() as *const _);
+        let mask = gdi32::CreateBitmap(width, height, 1, 1, and_mask.as_ptr() as *const _);
+        let mut icon_info = winapi::ICONINFO {
+            fIcon: winapi::FALSE,
+            xHotspot: cursor_image.hotspot().0,
+            yHotspot: cursor_image.hotspot().1,
+            hbmMask: mask,
+            hbmColor: color,
+        };
+        let cursor = user32::CreateIconIndirect(&mut icon_info);
+        gdi32::DeleteObject(color as winapi::HGDIOBJ);
+        gdi32::DeleteObject(mask as winapi::HGDIOBJ);
+
+        if cursor.is_null() {
+            None
+        } else {
+            Some(WindowCursor(cursor))
+        }
+    }
+}
+
+impl Drop for WindowCursor {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            user32::DestroyCursor(self.0);
+        }
+    }
+}
+
+// Values of the `wParam` parameter of `WM_SETICON`.
+const ICON_SMALL: winapi::WPARAM = 0;
+const ICON_BIG: winapi::WPARAM = 1;
+
+/// An icon created from an `IconImage`, destroyed when dropped.
+struct WindowIcon(winapi::HICON);
+
+impl WindowIcon {
+    unsafe fn new(image: &IconImage) -> Option<WindowIcon> {
+        let (width, height) = (image.width(), image.height());
+
+        // the color bits are BGRA, the AND mask is ignored for icons with an alpha channel
+        let mut bgra = image.rgba().to_vec();
+        for px in bgra.chunks_mut(4) {
+            px.swap(0, 2);
+        }
+        let and_mask = vec![0u8; ((width as usize + 15) / 16 * 2) * height as usize];
+
+        let icon = user32::CreateIcon(ptr::null_mut(), width as c_int, height as c_int, 1, 32,
+                                      and_mask.as_ptr(), bgra.as_ptr());
+        if icon.is_null() {
+            None
+        } else {
+            Some(WindowIcon(icon))
+        }
+    }
+}
+
+impl Drop for WindowIcon {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            user32::DestroyIcon(self.0);
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct WindowProxy {
     hwnd: winapi::HWND,
@@ -121,8 +244,9 @@ impl Window {
 
     #[inline]
     pub fn show(&self) {
+        let maximize = mem::replace(&mut self.window_state.lock().unwrap().maximize_on_show, false);
         unsafe {
-            user32::ShowWindow(self.window.0, winapi::SW_SHOW);
+            user32::ShowWindow(self.window.0, if maximize { winapi::SW_MAXIMIZE } else { winapi::SW_SHOW });
         }
     }
 
@@ -205,6 +329,59 @@ impl Window {
         }
     }
 
+    pub fn set_resizable(&self, resizable: bool) {
+        {
+            let mut window_state = self.window_state.lock().unwrap();
+            if window_state.attributes.resizable == resizable {
+                return;
+            }
+            window_state.attributes.resizable = resizable;
+        }
+
+        unsafe {
+            let mut style = user32::GetWindowLongW(self.window.0, winapi::GWL_STYLE) as winapi::DWORD;
+
+            // only decorated windows have a sizing border
+            if style & winapi::WS_CAPTION == 0 {
+                return;
+            }
+
+            if resizable {
+                style |= winapi::WS_THICKFRAME | winapi::WS_MAXIMIZEBOX;
+            } else {
+                style &= !(winapi::WS_THICKFRAME | winapi::WS_MAXIMIZEBOX);
+            }
+
+            user32::SetWindowLongW(self.window.0, winapi::GWL_STYLE, style as winapi::LONG);
+            user32::SetWindowPos(self.window.0, ptr::null_mut(), 0, 0, 0, 0,
+                winapi::SWP_NOZORDER | winapi::SWP_NOMOVE | winapi::SWP_NOSIZE |
+                winapi::SWP_NOACTIVATE | winapi::SWP_FRAMECHANGED);
+        }
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.window_state.lock().unwrap().attributes.min_dimensions = dimensions;
+        self.apply_size_constraints();
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.window_state.lock().unwrap().attributes.max_dimensions = dimensions;
+        self.apply_size_constraints();
+    }
+
+    // Resizing the window to its current size makes the system query `WM_GETMINMAXINFO`
+    // again, which clamps the window to the new constraints.
+    fn apply_size_constraints(&self) {
+        if let Some((width, height)) = self.get_outer_size() {
+            unsafe {
+                user32::SetWindowPos(self.window.0, ptr::null_mut(), 0, 0, width as c_int, height as c_int,
+                    winapi::SWP_NOZORDER | winapi::SWP_NOMOVE | winapi::SWP_NOACTIVATE);
+            }
+        }
+    }
+
     #[inline]
     pub fn create_window_proxy(&self) -> WindowProxy {
         WindowProxy { hwnd: self.window.0 }
@@ -243,6 +420,95 @@ impl Window {
     pub fn set_window_resize_callback(&mut self, _: Option<fn(u32, u32)>) {
     }
 
+    #[inline]
+    pub fn set_maximized(&self, maximized: bool) {
+        unsafe {
+            if user32::IsWindowVisible(self.window.0) == 0 {
+                // applied by `show`
+                self.window_state.lock().unwrap().maximize_on_show = maximized;
+                return;
+            }
+            user32::ShowWindow(self.window.0, if maximized { winapi::SW_MAXIMIZE } else { winapi::SW_RESTORE });
+        }
+    }
+
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        self.window_state.lock().unwrap().maximize_on_show ||
+            unsafe { user32::IsZoomed(self.window.0) != 0 }
+    }
+
+    #[inline]
+    pub fn set_minimized(&self, minimized: bool) {
+        unsafe {
+            user32::ShowWindow(self.window.0, if minimized { winapi::SW_MINIMIZE } else { winapi::SW_RESTORE });
+        }
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        unsafe { user32::IsIconic(self.window.0) != 0 }
+    }
+
+    #[inline]
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        {
+            let mut window_state = self.window_state.lock().unwrap();
+            window_state.attributes.always_on_top = always_on_top;
+            if always_on_top {
+                window_state.attributes.always_on_bottom = false;
+            }
+        }
+        let insert_after = if always_on_top { HWND_TOPMOST } else { HWND_NOTOPMOST };
+        self.set_z_order(insert_after);
+    }
+
+    #[inline]
+    pub fn set_always_on_bottom(&self, always_on_bottom: bool) {
+        // the flag is read by the callback to keep the window at the bottom
+        {
+            let mut window_state = self.window_state.lock().unwrap();
+            window_state.attributes.always_on_bottom = always_on_bottom;
+            if always_on_bottom {
+                window_state.attributes.always_on_top = false;
+            }
+        }
+        let insert_after = if always_on_bottom { HWND_BOTTOM } else { HWND_NOTOPMOST };
+        self.set_z_order(insert_after);
+    }
+
+    fn set_z_order(&self, insert_after: winapi::HWND) {
+        unsafe {
+            user32::SetWindowPos(self.window.0, insert_after, 0, 0, 0, 0,
+                                 winapi::SWP_NOMOVE | winapi::SWP_NOSIZE | winapi::SWP_NOACTIVATE);
+        }
+    }
+
+    pub fn set_window_icon(&self, window_icon: Option<Icon>) {
+        let icons = window_icon.and_then(|icon| unsafe {
+            let small = icon.image_for_size(user32::GetSystemMetrics(winapi::SM_CXSMICON) as u32,
+                                            user32::GetSystemMetrics(winapi::SM_CYSMICON) as u32);
+            let big = icon.image_for_size(user32::GetSystemMetrics(winapi::SM_CXICON) as u32,
+                                          user32::GetSystemMetrics(winapi::SM_CYICON) as u32);
+            match (WindowIcon::new(small), WindowIcon::new(big)) {
+                (Some(small), Some(big)) => Some((small, big)),
+                _ => None,
+            }
+        });
+
+        unsafe {
+            let (small, big) = match icons {
+                Some((ref small, ref big)) => (small.0, big.0),
+                None => (ptr::null_mut(), ptr::null_mut()),
+            };
+            user32::SendMessageW(self.window.0, winapi::WM_SETICON, ICON_SMALL, small as winapi::LPARAM);
+            user32::SendMessageW(self.window.0, winapi::WM_SETICON, ICON_BIG, big as winapi::LPARAM);
+        }
+
+        // the previous icons are no longer in use and can be destroyed
+        *self.window_icons.lock().unwrap() = icons;
+    }
+
     #[inline]
     pub fn set_cursor(&self, _cursor: MouseCursor) {
         let cursor_id = match _cursor {
@@ -262,8 +528,30 @@ impl Window {
             _ => winapi::IDC_ARROW, // use arrow for the missing cases.
         };
 
-        let mut cur = self.window_state.lock().unwrap();
-        cur.cursor = cursor_id;
+        {
+            let mut cur = self.window_state.lock().unwrap();
+            cur.cursor = cursor_id;
+            cur.custom_cursor = None;
+        }
+        self.refresh_cursor();
+    }
+
+    pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+        let size = unsafe { user32::GetSystemMetrics(winapi::SM_CXCURSOR) } as u32;
+        let cursor = unsafe { WindowCursor::new(cursor.image_for_size(size)) };
+        self.window_state.lock().unwrap().custom_cursor = cursor.map(Arc::new);
+        self.refresh_cursor();
+    }
+
+    // Windows only asks for the cursor again when the mouse moves, so the window thread is asked
+    // to set it right away. The callback ignores the message when the mouse is not over the
+    // window.
+    fn refresh_cursor(&self) {
+        let lparam = winapi::MAKELONG(winapi::HTCLIENT as winapi::WORD, winapi::WM_MOUSEMOVE as winapi::WORD);
+        unsafe {
+            user32::PostMessageW(self.window.0, winapi::WM_SETCURSOR, self.window.0 as winapi::WPARAM,
+                                 lparam as winapi::LPARAM);
+        }
     }
 
 
@@ -291,32 +579,42 @@ impl Window {
             },
 
             (CursorState::Grab, CursorState::Normal) | (CursorState::Grab, CursorState::Hide) => {
-                unsafe {
-                    let mut rect = mem::uninitialized();
-                    if user32::GetClientRect(self.window.0, &mut rect) == 0 {
-                        return Err(format!("GetWindowRect failed"));
-                    }
-                    user32::ClientToScreen(self.window.0, mem::transmute(&mut rect.left));
-                    user32::ClientToScreen(self.window.0, mem::transmute(&mut rect.right));
-                    if user32::ClipCursor(&rect) == 0 {
-                        return Err(format!("ClipCursor failed"));
-                    }
-                    current_state.cursor_state = CursorState::Grab;
-                    Ok(())
-                }
+                try!(self.clip_cursor());
+                current_state.cursor_state = CursorState::Grab;
+                Ok(())
             },
 
-            (CursorState::Normal, CursorState::Grab) => {
-                unsafe {
-                    if user32::ClipCursor(ptr::null()) == 0 {
-                        return Err(format!("ClipCursor failed"));
-                    }
-                    current_state.cursor_state = CursorState::Normal;
-                    Ok(())
-                }
+            (CursorState::Normal, CursorState::Grab) | (CursorState::Hide, CursorState::Grab) => {
+                try!(unclip_cursor());
+                current_state.cursor_state = state;
+                Ok(())
             },
 
-            _ => unimplemented!(),
+            (CursorState::Confined, CursorState::Confined) => Ok(()),
+
+            (CursorState::Confined, CursorState::Normal) | (CursorState::Confined, CursorState::Hide) => {
+                try!(self.clip_cursor());
+                current_state.cursor_state = CursorState::Confined;
+                Ok(())
+            },
+
+            (CursorState::Normal, CursorState::Confined) | (CursorState::Hide, CursorState::Confined) => {
+                try!(unclip_cursor());
+                current_state.cursor_state = state;
+                Ok(())
+            },
+
+            // the cursor stays clipped to the window, but the rect is taken again in case the
+            // window moved since it was clipped
+            (CursorState::Grab, CursorState::Confined) | (CursorState::Confined, CursorState::Grab) => {
+                try!(self.clip_cursor());
+                current_state.cursor_state = state;
+                Ok(())
+            },
+
+            (CursorState::Locked, _) => Err("Cursor locking is not supported on Windows".to_string()),
+
+            (_, CursorState::Locked) => unreachable!("the cursor is never locked on Windows"),
         };
 
         unsafe { user32::AttachThreadInput(foreground_thread_id, current_thread_id, 0) };
@@ -324,6 +622,66 @@ impl Window {
         res
     }
 
+    // Restricts the cursor to the client area of the window
+    fn clip_cursor(&self) -> Result<(), String> {
+        unsafe {
+            let mut rect = mem::uninitialized();
+            if user32::GetClientRect(self.window.0, &mut rect) == 0 {
+                return Err(format!("GetWindowRect failed"));
+            }
+            user32::ClientToScreen(self.window.0, mem::transmute(&mut rect.left));
+            user32::ClientToScreen(self.window.0, mem::transmute(&mut rect.right));
+            if user32::ClipCursor(&rect) == 0 {
+                return Err(format!("ClipCursor failed"));
+            }
+            Ok(())
+        }
+    }
+
+    pub fn set_clipboard(&self, kind: ClipboardKind, contents: ClipboardContents) -> Result<(), String> {
+        match kind {
+            ClipboardKind::Clipboard => clipboard::set_clipboard(self.window.0, contents),
+            ClipboardKind::Primary => Err("The primary selection doesn't exist on Windows.".to_string()),
+        }
+    }
+
+    pub fn clipboard_mime_types(&self, kind: ClipboardKind) -> Vec<String> {
+        match kind {
+            ClipboardKind::Clipboard => clipboard::clipboard_mime_types(self.window.0),
+            ClipboardKind::Primary => Vec::new(),
+        }
+    }
+
+    pub fn get_clipboard(&self, kind: ClipboardKind, mime_type: &str) -> Option<Vec<u8>> {
+        match kind {
+            ClipboardKind::Clipboard => clipboard::get_clipboard(self.window.0, mime_type),
+            ClipboardKind::Primary => None,
+        }
+    }
+
+    #[inline]
+    pub fn set_ime_position(&self, _x: i32, _y: i32) {
+    }
+
+    #[inline]
+    pub fn set_ime_allowed(&self, _allowed: bool) {
+    }
+
+    #[inline]
+    pub fn get_pressed_keys(&self) -> Vec<::ScanCode> {
+        event::get_pressed_keys()
+    }
+
+    #[inline]
+    pub fn get_pressed_mouse_buttons(&self) -> Vec<::MouseButton> {
+        event::get_pressed_mouse_buttons()
+    }
+
+    #[inline]
+    pub fn start_drag(&self, _: ::DragData, _: &[::DragAction]) -> Result<(), String> {
+        Err("Dragging data out of a window is not supported yet".to_string())
+    }
+
     #[inline]
     pub fn hidpi_factor(&self) -> f32 {
         1.0
@@ -349,6 +707,15 @@ impl Window {
     }
 }
 
+fn unclip_cursor() -> Result<(), String> {
+    unsafe {
+        if user32::ClipCursor(ptr::null()) == 0 {
+            return Err(format!("ClipCursor failed"));
+        }
+        Ok(())
+    }
+}
+
 impl Drop for Window {
     #[inline]
     fn drop(&mut self) {
@@ -1,12 +1,22 @@
 use std::collections::vec_deque::IntoIter as VecDequeIter;
 
+use ClipboardContents;
+use ClipboardKind;
 use CreationError;
 use CursorState;
+use CustomCursor;
+use DragAction;
+use DragData;
 use EventsLoop;
+use Icon;
+use MouseButton;
 use MouseCursor;
+use ScanCode;
 use Window;
 use WindowBuilder;
 use WindowId;
+use TEXT_MIME_TYPE;
+use TEXT_UTF8_MIME_TYPE;
 use native_monitor::NativeMonitorId;
 
 use libc;
@@ -49,6 +59,13 @@ impl WindowBuilder {
         self
     }
 
+    /// Sets whether the window can be resized by the user.
+    #[inline]
+    pub fn with_resizable(mut self, resizable: bool) -> WindowBuilder {
+        self.window.resizable = resizable;
+        self
+    }
+
     /// Requests a specific title for the window.
     #[inline]
     pub fn with_title<T: Into<String>>(mut self, title: T) -> WindowBuilder {
@@ -73,6 +90,39 @@ impl WindowBuilder {
         self
     }
 
+    /// Requests maximized mode.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Ignored on Android and iOS
+    ///
+    #[inline]
+    pub fn with_maximized(mut self, maximized: bool) -> WindowBuilder {
+        self.window.maximized = maximized;
+        self
+    }
+
+    /// Sets whether the window should always stay above other windows.
+    #[inline]
+    pub fn with_always_on_top(mut self, always_on_top: bool) -> WindowBuilder {
+        self.window.always_on_top = always_on_top;
+        self
+    }
+
+    /// Sets whether the window should always stay below other windows.
+    #[inline]
+    pub fn with_always_on_bottom(mut self, always_on_bottom: bool) -> WindowBuilder {
+        self.window.always_on_bottom = always_on_bottom;
+        self
+    }
+
+    /// Sets the icon of the window.
+    #[inline]
+    pub fn with_window_icon(mut self, window_icon: Option<Icon>) -> WindowBuilder {
+        self.window.window_icon = window_icon;
+        self
+    }
+
     /// Sets whether the background of the window should be transparent.
     #[inline]
     pub fn with_transparency(mut self, transparent: bool) -> WindowBuilder {
@@ -88,6 +138,11 @@ impl WindowBuilder {
     }
 
     /// Enables multitouch
+    ///
+    /// ## Platform-specific
+    ///
+    /// On Wayland, the touch events are emitted either way, but a window without multitouch only
+    /// gets the first of the touch points held down on it.
     #[inline]
     pub fn with_multitouch(mut self) -> WindowBuilder {
         self.window.multitouch = true;
@@ -246,6 +301,144 @@ impl Window {
         self.window.set_inner_size(x, y)
     }
 
+    /// Sets whether the window can be resized by the user.
+    ///
+    /// This doesn't prevent the size of the window from being modified with `set_inner_size`.
+    ///
+    /// This is a no-op if the window has already been closed.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Has no effect on Android and iOS
+    ///
+    #[inline]
+    pub fn set_resizable(&self, resizable: bool) {
+        self.window.set_resizable(resizable)
+    }
+
+    /// Sets the minimum dimensions the window can be resized to, or removes the constraint.
+    ///
+    /// Width and height are in pixels. The window is resized if it is currently smaller.
+    ///
+    /// This is a no-op if the window has already been closed.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Has no effect on Android and iOS
+    ///
+    #[inline]
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.window.set_min_dimensions(dimensions)
+    }
+
+    /// Sets the maximum dimensions the window can be resized to, or removes the constraint.
+    ///
+    /// Width and height are in pixels. The window is resized if it is currently larger.
+    ///
+    /// This is a no-op if the window has already been closed.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Has no effect on Android and iOS
+    ///
+    #[inline]
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.window.set_max_dimensions(dimensions)
+    }
+
+    /// Maximizes the window, or restores it to its previous size.
+    ///
+    /// This is a no-op if the window has already been closed.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Has no effect on Android and iOS
+    /// - On Wayland, the window is resized to the size of the first output, as the shell surface
+    ///   used by winit can't be maximized
+    ///
+    #[inline]
+    pub fn set_maximized(&self, maximized: bool) {
+        self.window.set_maximized(maximized)
+    }
+
+    /// Returns `true` if the window is currently maximized.
+    ///
+    /// Returns `false` if the window no longer exists.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Always `false` on Android and iOS
+    ///
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        self.window.is_maximized()
+    }
+
+    /// Minimizes (iconifies) the window, or restores it from its minimized state.
+    ///
+    /// This is a no-op if the window has already been closed.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Has no effect on Android, iOS and Wayland
+    ///
+    #[inline]
+    pub fn set_minimized(&self, minimized: bool) {
+        self.window.set_minimized(minimized)
+    }
+
+    /// Returns `true` if the window is currently minimized.
+    ///
+    /// Returns `false` if the window no longer exists.
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        self.window.is_minimized()
+    }
+
+    /// Sets whether the window should always stay above other windows.
+    ///
+    /// Enabling this disables `always_on_bottom`.
+    ///
+    /// This is a no-op if the window has already been closed.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Has no effect on Android, iOS and Wayland
+    ///
+    #[inline]
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        self.window.set_always_on_top(always_on_top)
+    }
+
+    /// Sets whether the window should always stay below other windows.
+    ///
+    /// Enabling this disables `always_on_top`.
+    ///
+    /// This is a no-op if the window has already been closed.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Has no effect on Android, iOS and Wayland
+    ///
+    #[inline]
+    pub fn set_always_on_bottom(&self, always_on_bottom: bool) {
+        self.window.set_always_on_bottom(always_on_bottom)
+    }
+
+    /// Sets the icon of the window, displayed in the titlebar, the taskbar or the task switcher.
+    /// `None` reverts to the default icon.
+    ///
+    /// This is a no-op if the window has already been closed.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Has no effect on Android, iOS, macOS and Wayland
+    ///
+    #[inline]
+    pub fn set_window_icon(&self, window_icon: Option<Icon>) {
+        self.window.set_window_icon(window_icon)
+    }
+
     /// DEPRECATED. Gets the native platform specific display for this window.
     /// This is typically only required when integrating with
     /// other libraries that need this information.
@@ -270,6 +463,154 @@ impl Window {
         self.window.set_cursor(cursor);
     }
 
+    /// Replaces the mouse cursor of the window with custom images.
+    ///
+    /// The image whose size fits best the cursor size of the system is used, and animated if
+    /// it has several frames. Calling `set_cursor` goes back to the system cursors.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Has no effect on Android and iOS
+    /// - Only the first frame of animated cursors is displayed on Windows and macOS
+    ///
+    #[inline]
+    pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+        self.window.set_custom_cursor(cursor);
+    }
+
+    /// Takes the ownership of a clipboard and fills it with some contents.
+    ///
+    /// Other applications can then paste the contents in any of the formats they hold.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Returns an error on Android and iOS
+    /// - `ClipboardKind::Primary` returns an error on Windows and macOS, and on Wayland when the
+    ///   compositor doesn't support the `zwp_primary_selection_device_manager_v1` protocol
+    /// - On X11 and Wayland, the contents are served by the window, until it is destroyed or
+    ///   another application takes the clipboard
+    /// - On Wayland, the compositor only lets the focused window take the clipboard, in
+    ///   response to an input event
+    ///
+    #[inline]
+    pub fn set_clipboard(&self, kind: ClipboardKind, contents: ClipboardContents) -> Result<(), String> {
+        self.window.set_clipboard(kind, contents)
+    }
+
+    /// Returns the MIME types in which the current contents of a clipboard are available.
+    ///
+    /// Returns an empty list if the clipboard is empty or can't be read.
+    #[inline]
+    pub fn clipboard_mime_types(&self, kind: ClipboardKind) -> Vec<String> {
+        self.window.clipboard_mime_types(kind)
+    }
+
+    /// Reads the current contents of a clipboard in the given format.
+    ///
+    /// Returns `None` if the clipboard is empty, if its contents aren't available in this format
+    /// or if the application owning the clipboard doesn't answer.
+    ///
+    /// This function blocks until the data is transferred.
+    #[inline]
+    pub fn get_clipboard(&self, kind: ClipboardKind, mime_type: &str) -> Option<Vec<u8>> {
+        self.window.get_clipboard(kind, mime_type)
+    }
+
+    /// Reads the current contents of a clipboard as text.
+    ///
+    /// Invalid UTF-8 sequences are replaced with `U+FFFD`.
+    pub fn get_clipboard_text(&self, kind: ClipboardKind) -> Option<String> {
+        self.get_clipboard(kind, TEXT_UTF8_MIME_TYPE)
+            .or_else(|| self.get_clipboard(kind, TEXT_MIME_TYPE))
+            .map(|data| String::from_utf8_lossy(&data).into_owned())
+    }
+
+    /// Sets the position of the candidate window of the input method, in window coordinates.
+    ///
+    /// This is usually the position of the cursor of the text being edited, so that the
+    /// candidates are displayed next to it.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Has no effect on Windows, macOS, Android and iOS
+    ///
+    #[inline]
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        self.window.set_ime_position(x, y)
+    }
+
+    /// Allows or forbids the input method to report the composition of text with
+    /// `WindowEvent::Ime` events.
+    ///
+    /// The input method is not allowed by default, in which case the composition happens in a
+    /// window of the input method and the composed text is received with `ReceivedCharacter`
+    /// events. Once allowed, an `Ime::Enabled` event is emitted if the input method supports
+    /// reporting the composition.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Has no effect on Windows, macOS, Android and iOS
+    /// - On X11, the input method must support the on-the-spot style, which is the case of
+    ///   IBus and Fcitx
+    /// - On Wayland, the compositor must support the `zwp_text_input_v3` protocol. The input
+    ///   method is enabled while the window has the keyboard focus, which also shows the virtual
+    ///   keyboard on touch devices
+    ///
+    #[inline]
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.window.set_ime_allowed(allowed)
+    }
+
+    /// Returns the scan codes of the keys held down, as reported by `KeyboardInput` events.
+    ///
+    /// When the window gains the focus, the keys that are already held down are also reported
+    /// with synthetic `KeyboardInput` presses.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On Wayland, only returns the keys while the window has the keyboard focus
+    /// - Always returns an empty list on Android and iOS
+    ///
+    #[inline]
+    pub fn get_pressed_keys(&self) -> Vec<ScanCode> {
+        self.window.get_pressed_keys()
+    }
+
+    /// Returns the mouse buttons held down.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On Wayland, only returns the buttons pressed while the cursor is over the window
+    /// - Always returns an empty list on Android and iOS
+    ///
+    #[inline]
+    pub fn get_pressed_mouse_buttons(&self) -> Vec<MouseButton> {
+        self.window.get_pressed_mouse_buttons()
+    }
+
+    /// Starts dragging some data out of the window, to drop it in another window or application.
+    ///
+    /// The first of the allowed actions is the one preferred by the window. Once the drag ends,
+    /// a `WindowEvent::DragFinished` event tells whether the data was dropped and with which
+    /// action.
+    ///
+    /// This should be called in response to a mouse button press, and the drag follows the
+    /// pointer until that button is released. Returns an error if no action is allowed.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Returns an error on Windows, macOS, Android and iOS
+    /// - On X11, the drag can be cancelled with the escape key
+    /// - On Wayland, the compositor only starts the drag while a button of the pointer is
+    ///   pressed over the window
+    ///
+    pub fn start_drag(&self, data: DragData, allowed_actions: &[DragAction]) -> Result<(), String> {
+        if allowed_actions.is_empty() {
+            return Err("No action is allowed for the drag".to_string());
+        }
+        self.window.start_drag(data, allowed_actions)
+    }
+
     /// Returns the ratio between the backing framebuffer resolution and the
     /// window size in screen pixels. This is typically one for a normal display
     /// and two for a retina display.
@@ -279,6 +620,12 @@ impl Window {
     }
 
     /// Changes the position of the cursor in window coordinates.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On Wayland, only works while the cursor is locked, and tells where the cursor should
+    ///   appear once it is unlocked
+    ///
     #[inline]
     pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
         self.window.set_cursor_position(x, y)
@@ -286,7 +633,15 @@ impl Window {
 
     /// Sets how glutin handles the cursor. See the documentation of `CursorState` for details.
     ///
-    /// Has no effect on Android.
+    /// ## Platform-specific
+    ///
+    /// - Has no effect on Android
+    /// - `CursorState::Locked` returns an error on Windows and macOS
+    /// - `CursorState::Confined` returns an error on macOS
+    /// - `CursorState::Locked` and `CursorState::Confined` require a compositor supporting the
+    ///   pointer-constraints protocol on Wayland, and the relative-pointer protocol for
+    ///   `CursorState::Locked`
+    ///
     #[inline]
     pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
         self.window.set_cursor_state(state)
@@ -0,0 +1,41 @@
+extern crate winit;
+
+use std::time::{Duration, Instant};
+
+use winit::{ClipboardContents, ClipboardKind};
+
+// The clipboard must be usable from the callback of the events loop, while it dispatches the
+// events of the windows.
+//
+// This needs a display, the test does nothing when there is none.
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd",
+          target_os = "windows"))]
+#[test]
+fn clipboard_from_callback() {
+    if cfg!(unix) && std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        return;
+    }
+
+    let events_loop = winit::EventsLoop::new();
+    let _window = match winit::WindowBuilder::new().with_title("clipboard test").build(&events_loop) {
+        Ok(window) => window,
+        Err(_) => return,
+    };
+
+    let mut called = false;
+    let start = Instant::now();
+    while !called && start.elapsed() < Duration::from_secs(5) {
+        events_loop.poll_events(|_| {
+            if called {
+                return;
+            }
+            called = true;
+            let contents = ClipboardContents::from_text("winit");
+            events_loop.set_clipboard(ClipboardKind::Clipboard, contents).unwrap();
+            events_loop.clipboard_mime_types(ClipboardKind::Clipboard);
+            events_loop.get_clipboard_text(ClipboardKind::Clipboard);
+        });
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert!(called, "the window received no event");
+}